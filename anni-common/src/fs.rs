@@ -1,6 +1,7 @@
 use crate::decode::raw_to_string;
 use log::debug;
 use path_absolutize::*;
+use std::borrow::Cow;
 use std::ffi::OsString;
 pub use std::fs::*;
 use std::path::{Path, PathBuf};
@@ -228,6 +229,63 @@ pub fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result
     return std::os::windows::fs::symlink_dir(link, to);
 }
 
+/// Map characters reserved on any platform in `name` to their full-width forms.
+///
+/// The result is the same on all platforms, so files written on one OS can be found on another
+/// by sanitizing the expected name with the same function:
+///
+/// - `/ \ : * ? " < > |` are replaced by `／ ＼ ： ＊ ？ ＂ ＜ ＞ ｜`
+/// - control characters are removed
+/// - trailing dots are replaced by `．` and trailing spaces are removed, as Windows strips them
+/// - reserved device names on Windows (e.g. `CON`, `COM1`) are suffixed with `_`
+pub fn sanitize_file_name(name: &str) -> Cow<str> {
+    const RESERVED_NAMES: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    let is_reserved_char = |c: char| c.is_control() || "/\\:*?\"<>|".contains(c);
+    // device names are reserved with any extension
+    let reserved_stem = |name: &str| {
+        let stem = name.split('.').next().unwrap_or_default().trim_end();
+        RESERVED_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+            .then_some(stem.len())
+    };
+    if !name.contains(is_reserved_char)
+        && !name.ends_with(['.', ' '])
+        && reserved_stem(name).is_none()
+    {
+        return Cow::Borrowed(name);
+    }
+
+    let mut result: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| match c {
+            '/' => '／',
+            '\\' => '＼',
+            ':' => '：',
+            '*' => '＊',
+            '?' => '？',
+            '"' => '＂',
+            '<' => '＜',
+            '>' => '＞',
+            '|' => '｜',
+            c => c,
+        })
+        .collect();
+    result.truncate(result.trim_end_matches(' ').len());
+    if let Some(stem_len) = reserved_stem(&result) {
+        result.insert(stem_len, '_');
+    }
+    let dots = result.len() - result.trim_end_matches('.').len();
+    result.truncate(result.len() - dots);
+    result.extend(std::iter::repeat('．').take(dots));
+    Cow::Owned(result)
+}
+
 pub fn path_diff<P: AsRef<Path>, Q: AsRef<Path>>(path: P, base: Q) -> io::Result<PathBuf> {
     Ok(pathdiff::diff_paths(path.as_ref().absolutize()?, base.as_ref().absolutize()?).unwrap())
 }
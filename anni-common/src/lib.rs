@@ -5,5 +5,7 @@ pub mod diagnostic;
 pub mod encode;
 pub mod fs;
 pub mod lint;
+pub mod parallel;
+pub mod text;
 pub mod traits;
 pub mod validator;
@@ -0,0 +1,40 @@
+use std::sync::Mutex;
+
+/// Number of worker threads to use, defaults to the number of CPUs.
+pub fn jobs(jobs: Option<usize>) -> usize {
+    jobs.or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// Map `items` with `f` on up to `jobs` threads.
+///
+/// Items are taken from a shared queue by each worker, and results are returned in the order of `items`.
+pub fn map<I, R, F>(items: I, jobs: usize, f: F) -> Vec<R>
+where
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator + Send,
+    R: Send,
+    F: Fn(I::Item) -> R + Sync,
+{
+    let items = items.into_iter();
+    let len = items.len();
+    let queue = Mutex::new(items.enumerate());
+    let results = Mutex::new(Vec::with_capacity(len));
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.max(1).min(len) {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                let Some((index, item)) = next else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
@@ -0,0 +1,26 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalize `title` so that titles differing only in character width or encoding compare equal.
+///
+/// - full-width ASCII forms (`！` to `～`) are replaced by their ASCII counterparts
+/// - ideographic spaces are replaced by ASCII spaces
+/// - leading and trailing whitespace is removed
+/// - the result is in Unicode Normalization Form C (NFC)
+///
+/// The result is only meant for comparison and search. File names should still be derived
+/// from the original title.
+pub fn normalize_title(title: &str) -> String {
+    let normalized: String = title
+        .chars()
+        .map(|c| match c {
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap(),
+            '\u{3000}' => ' ',
+            c => c,
+        })
+        .nfc()
+        .collect();
+    match normalized.trim() {
+        trimmed if trimmed.len() == normalized.len() => normalized,
+        trimmed => trimmed.to_string(),
+    }
+}
@@ -0,0 +1,55 @@
+use anni_common::fs::sanitize_file_name;
+use std::borrow::Cow;
+
+#[test]
+fn sanitize_valid_name() {
+    for name in [
+        "01. 夏凪ぎ.flac",
+        "Track 1",
+        "",
+        "ＣＯＮ",
+        "CONSOLE.flac",
+        ".hidden",
+    ] {
+        assert!(
+            matches!(sanitize_file_name(name), Cow::Borrowed(n) if n == name),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn sanitize_reserved_chars() {
+    assert_eq!(
+        sanitize_file_name("夏凪ぎ/宝物になった日"),
+        "夏凪ぎ／宝物になった日"
+    );
+    assert_eq!(
+        sanitize_file_name(r#"a\b:c*d?e"f<g>h|i"#),
+        "a＼b：c＊d？e＂f＜g＞h｜i"
+    );
+    assert_eq!(sanitize_file_name("tab\there\n"), "tabhere");
+}
+
+#[test]
+fn sanitize_trailing_dots_and_spaces() {
+    assert_eq!(sanitize_file_name("Title..."), "Title．．．");
+    assert_eq!(sanitize_file_name("Title  "), "Title");
+    assert_eq!(sanitize_file_name("Title. "), "Title．");
+}
+
+#[test]
+fn sanitize_reserved_names() {
+    assert_eq!(sanitize_file_name("CON"), "CON_");
+    assert_eq!(sanitize_file_name("nul.flac"), "nul_.flac");
+    assert_eq!(sanitize_file_name("com1 .txt"), "com1_ .txt");
+    assert_eq!(sanitize_file_name("LPT9."), "LPT9_．");
+}
+
+#[test]
+fn sanitize_is_idempotent() {
+    for name in ["a/b", "CON", "Title. ", r#"a\b:c*d?e"f<g>h|i"#] {
+        let sanitized = sanitize_file_name(name).into_owned();
+        assert_eq!(sanitize_file_name(&sanitized), sanitized);
+    }
+}
@@ -0,0 +1,25 @@
+use anni_common::parallel;
+
+#[test]
+fn map_keeps_order() {
+    let items: Vec<u64> = (0..100).collect();
+    let result = parallel::map(items.iter(), 4, |n| {
+        // finish out of order
+        std::thread::sleep(std::time::Duration::from_micros(100 - n));
+        n * 2
+    });
+    assert_eq!(result, (0..100).map(|n| n * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn map_empty() {
+    let result: Vec<u8> = parallel::map(Vec::<u8>::new(), 4, |n| n);
+    assert!(result.is_empty());
+}
+
+#[test]
+fn jobs() {
+    assert_eq!(parallel::jobs(Some(3)), 3);
+    assert_eq!(parallel::jobs(Some(0)), 1);
+    assert!(parallel::jobs(None) >= 1);
+}
@@ -0,0 +1,40 @@
+use anni_common::text::normalize_title;
+
+#[test]
+fn normalize_mixed_width() {
+    assert_eq!(normalize_title("ＡＢＣ１２３"), "ABC123");
+    assert_eq!(normalize_title("Hello！ World？"), "Hello! World?");
+    assert_eq!(
+        normalize_title("夏凪ぎ／宝物になった日"),
+        "夏凪ぎ/宝物になった日"
+    );
+    assert_eq!(
+        normalize_title("夏凪ぎ／宝物になった日"),
+        normalize_title("夏凪ぎ/宝物になった日")
+    );
+    // only ASCII forms are folded
+    assert_eq!(normalize_title("「君の名は。」〜"), "「君の名は。」〜");
+    assert_eq!(
+        normalize_title("Title　(Instrumental)"),
+        "Title (Instrumental)"
+    );
+}
+
+#[test]
+fn normalize_whitespace() {
+    assert_eq!(normalize_title("  Title \t"), "Title");
+    assert_eq!(normalize_title("　Title　"), "Title");
+    assert_eq!(normalize_title(""), "");
+}
+
+#[test]
+fn normalize_combining_characters() {
+    // e + combining acute accent
+    assert_eq!(normalize_title("Pok\u{65}\u{301}mon"), "Pok\u{e9}mon");
+    // か + combining voiced sound mark
+    assert_eq!(normalize_title("\u{304b}\u{3099}"), "\u{304c}");
+    assert_eq!(
+        normalize_title("Caf\u{65}\u{301}"),
+        normalize_title("Caf\u{e9}")
+    );
+}
@@ -0,0 +1,483 @@
+//! Decode audio frames of a FLAC stream into samples.
+use crate::blocks::BlockStreamInfo;
+use crate::error::FlacError;
+use crate::frames::ChannelAssignment;
+use crate::prelude::*;
+use crate::FlacHeader;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom};
+
+impl FlacHeader {
+    /// Open the file of this header and decode its audio frames.
+    pub fn frames(&self) -> Result<FrameReader<BufReader<File>>> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.frame_offset as u64))?;
+        Ok(FrameReader::new(BufReader::new(file), self.stream_info()))
+    }
+}
+
+/// Decoded samples of a frame, one `Vec` per channel.
+pub type Block = Vec<Vec<i32>>;
+
+/// Reads audio frames one by one, from a reader positioned at the first frame.
+pub struct FrameReader<R> {
+    reader: BitReader<R>,
+    bits_per_sample: u8,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R, stream_info: &BlockStreamInfo) -> Self {
+        Self {
+            reader: BitReader::new(reader),
+            bits_per_sample: stream_info.bits_per_sample,
+        }
+    }
+
+    /// Decode the next frame, or return `None` at the end of stream.
+    pub fn next_block(&mut self) -> Result<Option<Block>> {
+        self.reader.crc8 = 0;
+        self.reader.crc16 = 0;
+        let Some(first) = self.reader.read_first_byte()? else {
+            return Ok(None);
+        };
+        let header = self.read_header(first)?;
+
+        let mut channels = Vec::with_capacity(header.channels());
+        for channel in 0..header.channels() {
+            let side = match header.channel_assignment {
+                ChannelAssignment::LeftSide | ChannelAssignment::MidSide => channel == 1,
+                ChannelAssignment::RightSide => channel == 0,
+                _ => false,
+            };
+            let bits = header.bits_per_sample as u32 + side as u32;
+            channels.push(self.read_subframe(header.block_size, bits)?);
+        }
+
+        self.reader.align();
+        let crc = self.reader.crc16;
+        if self.reader.read_bits(16)? as u16 != crc {
+            return Err(FlacError::InvalidFrame("frame crc mismatch"));
+        }
+
+        decorrelate(&header.channel_assignment, &mut channels);
+        Ok(Some(
+            channels
+                .into_iter()
+                .map(|channel| channel.into_iter().map(|s| s as i32).collect())
+                .collect(),
+        ))
+    }
+
+    fn read_header(&mut self, first: u8) -> Result<FrameHeader> {
+        let second = self.reader.read_bits(8)?;
+        // 14 bits sync code and 1 reserved bit
+        if first != 0xff || second >> 1 != 0b1111100 {
+            return Err(FlacError::InvalidFrame("invalid sync code"));
+        }
+
+        let block_size_code = self.reader.read_bits(4)?;
+        let sample_rate_code = self.reader.read_bits(4)?;
+        let channel_assignment = match self.reader.read_bits(4)? {
+            n @ 0..=7 => ChannelAssignment::Independent(n as u8 + 1),
+            8 => ChannelAssignment::LeftSide,
+            9 => ChannelAssignment::RightSide,
+            10 => ChannelAssignment::MidSide,
+            _ => return Err(FlacError::InvalidFrame("reserved channel assignment")),
+        };
+        let bits_per_sample = match self.reader.read_bits(3)? {
+            0 => self.bits_per_sample,
+            1 => 8,
+            2 => 12,
+            4 => 16,
+            5 => 20,
+            6 => 24,
+            7 => 32,
+            _ => return Err(FlacError::InvalidFrame("reserved sample size")),
+        };
+        if self.reader.read_bits(1)? != 0 {
+            return Err(FlacError::InvalidFrame("reserved bit is set"));
+        }
+        self.reader.skip_coded_number()?;
+
+        let block_size = match block_size_code {
+            0 => return Err(FlacError::InvalidFrame("reserved block size")),
+            1 => 192,
+            n @ 2..=5 => 576 << (n - 2),
+            6 => self.reader.read_bits(8)? as usize + 1,
+            7 => self.reader.read_bits(16)? as usize + 1,
+            n => 256 << (n - 8),
+        };
+        // sample rate is not needed for decoding, but has to be read to reach the crc
+        match sample_rate_code {
+            12 => {
+                self.reader.read_bits(8)?;
+            }
+            13 | 14 => {
+                self.reader.read_bits(16)?;
+            }
+            15 => return Err(FlacError::InvalidFrame("invalid sample rate")),
+            _ => {}
+        }
+
+        let crc = self.reader.crc8;
+        if self.reader.read_bits(8)? as u8 != crc {
+            return Err(FlacError::InvalidFrame("header crc mismatch"));
+        }
+
+        Ok(FrameHeader {
+            block_size,
+            channel_assignment,
+            bits_per_sample,
+        })
+    }
+
+    fn read_subframe(&mut self, block_size: usize, bits: u32) -> Result<Vec<i64>> {
+        if self.reader.read_bits(1)? != 0 {
+            return Err(FlacError::InvalidFrame("invalid subframe padding"));
+        }
+        let kind = self.reader.read_bits(6)?;
+        let wasted = if self.reader.read_bits(1)? == 1 {
+            self.reader.read_unary(bits)? + 1
+        } else {
+            0
+        };
+        let bits = bits
+            .checked_sub(wasted)
+            .ok_or(FlacError::InvalidFrame("too many wasted bits"))?;
+
+        let mut samples = match kind {
+            0 => vec![self.reader.read_signed(bits)?; block_size],
+            1 => (0..block_size)
+                .map(|_| self.reader.read_signed(bits))
+                .collect::<Result<_>>()?,
+            8..=12 => {
+                let order = (kind - 8) as usize;
+                let mut samples = self.read_warm_up(block_size, order, bits)?;
+                self.read_residual(block_size, order, &mut samples)?;
+                const COEFFICIENTS: [&[i64]; 5] =
+                    [&[], &[1], &[2, -1], &[3, -3, 1], &[4, -6, 4, -1]];
+                predict(&mut samples, COEFFICIENTS[order], 0)?;
+                samples
+            }
+            32..=63 => {
+                let order = (kind - 31) as usize;
+                let mut samples = self.read_warm_up(block_size, order, bits)?;
+                let precision = self.reader.read_bits(4)? + 1;
+                if precision == 16 {
+                    return Err(FlacError::InvalidFrame("invalid lpc precision"));
+                }
+                let shift = self.reader.read_signed(5)?;
+                if shift < 0 {
+                    return Err(FlacError::InvalidFrame("negative lpc shift"));
+                }
+                let coefficients = (0..order)
+                    .map(|_| self.reader.read_signed(precision))
+                    .collect::<Result<Vec<_>>>()?;
+                self.read_residual(block_size, order, &mut samples)?;
+                predict(&mut samples, &coefficients, shift as u32)?;
+                samples
+            }
+            _ => return Err(FlacError::InvalidFrame("reserved subframe type")),
+        };
+
+        if wasted > 0 {
+            samples.iter_mut().for_each(|sample| *sample <<= wasted);
+        }
+        Ok(samples)
+    }
+
+    fn read_warm_up(&mut self, block_size: usize, order: usize, bits: u32) -> Result<Vec<i64>> {
+        if order > block_size {
+            return Err(FlacError::InvalidFrame(
+                "predictor order exceeds block size",
+            ));
+        }
+        let mut samples = Vec::with_capacity(block_size);
+        for _ in 0..order {
+            samples.push(self.reader.read_signed(bits)?);
+        }
+        Ok(samples)
+    }
+
+    /// Read residual after warm up samples into `samples`.
+    fn read_residual(
+        &mut self,
+        block_size: usize,
+        order: usize,
+        samples: &mut Vec<i64>,
+    ) -> Result<()> {
+        let parameter_bits = match self.reader.read_bits(2)? {
+            0 => 4,
+            1 => 5,
+            _ => return Err(FlacError::InvalidFrame("reserved residual coding method")),
+        };
+        let escape = (1 << parameter_bits) - 1;
+        let partition_order = self.reader.read_bits(4)?;
+        let partition_size = block_size >> partition_order;
+        if partition_size << partition_order != block_size || partition_size < order {
+            return Err(FlacError::InvalidFrame("invalid partition order"));
+        }
+
+        for partition in 0..1usize << partition_order {
+            let count = if partition == 0 {
+                partition_size - order
+            } else {
+                partition_size
+            };
+            let parameter = self.reader.read_bits(parameter_bits)?;
+            if parameter == escape {
+                let bits = self.reader.read_bits(5)?;
+                for _ in 0..count {
+                    samples.push(self.reader.read_signed(bits)?);
+                }
+            } else {
+                for _ in 0..count {
+                    // folded residual fits in 32 bits
+                    let quotient = self.reader.read_unary(u32::MAX >> parameter)? as u64;
+                    let value = (quotient << parameter) | self.reader.read_bits(parameter)? as u64;
+                    samples.push((value >> 1) as i64 ^ -((value & 1) as i64));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct FrameHeader {
+    block_size: usize,
+    channel_assignment: ChannelAssignment,
+    bits_per_sample: u8,
+}
+
+impl FrameHeader {
+    fn channels(&self) -> usize {
+        match self.channel_assignment {
+            ChannelAssignment::Independent(channels) => channels as usize,
+            _ => 2,
+        }
+    }
+}
+
+/// Restore samples from residual in `samples` after `coefficients.len()` warm up samples.
+///
+/// Frames whose samples overflow are rejected, as they can only come from corrupted data.
+fn predict(samples: &mut [i64], coefficients: &[i64], shift: u32) -> Result<()> {
+    for i in coefficients.len()..samples.len() {
+        let prediction = coefficients
+            .iter()
+            .enumerate()
+            .try_fold(0i64, |sum, (j, coefficient)| {
+                sum.checked_add(coefficient.checked_mul(samples[i - 1 - j])?)
+            });
+        samples[i] = prediction
+            .and_then(|prediction| samples[i].checked_add(prediction >> shift))
+            .ok_or(FlacError::InvalidFrame("sample overflow"))?;
+    }
+    Ok(())
+}
+
+fn decorrelate(assignment: &ChannelAssignment, channels: &mut [Vec<i64>]) {
+    let [a, b] = channels else {
+        return;
+    };
+    match assignment {
+        ChannelAssignment::LeftSide => b.iter_mut().zip(a.iter()).for_each(|(s, l)| *s = l - *s),
+        ChannelAssignment::RightSide => a.iter_mut().zip(b.iter()).for_each(|(s, r)| *s += r),
+        ChannelAssignment::MidSide => a.iter_mut().zip(b.iter_mut()).for_each(|(m, s)| {
+            let mid = (*m << 1) | (*s & 1);
+            (*m, *s) = ((mid + *s) >> 1, (mid - *s) >> 1);
+        }),
+        _ => {}
+    }
+}
+
+/// Reads bits MSB first, keeping CRC-8 and CRC-16 of all bytes read.
+struct BitReader<R> {
+    reader: R,
+    byte: u8,
+    /// Unread bits in `byte`
+    left: u32,
+    crc8: u8,
+    crc16: u16,
+}
+
+impl<R: Read> BitReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            byte: 0,
+            left: 0,
+            crc8: 0,
+            crc16: 0,
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        let mut byte = [0u8];
+        self.reader.read_exact(&mut byte)?;
+        let byte = byte[0];
+        self.crc8 = CRC8_TABLE[(self.crc8 ^ byte) as usize];
+        self.crc16 = (self.crc16 << 8) ^ CRC16_TABLE[((self.crc16 >> 8) as u8 ^ byte) as usize];
+        Ok(byte)
+    }
+
+    /// Read the first byte of a frame, or `None` at the end of stream.
+    fn read_first_byte(&mut self) -> Result<Option<u8>> {
+        match self.read_byte() {
+            Ok(byte) => Ok(Some(byte)),
+            Err(FlacError::IO(e)) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read `n` bits, `n` <= 32.
+    fn read_bits(&mut self, mut n: u32) -> Result<u32> {
+        let mut result = 0u64;
+        while n > 0 {
+            if self.left == 0 {
+                self.byte = self.read_byte()?;
+                self.left = 8;
+            }
+            let take = n.min(self.left);
+            let bits = (self.byte as u64 >> (self.left - take)) & ((1 << take) - 1);
+            result = (result << take) | bits;
+            self.left -= take;
+            n -= take;
+        }
+        Ok(result as u32)
+    }
+
+    /// Read a signed two's complement integer of `n` bits, `n` <= 33.
+    fn read_signed(&mut self, n: u32) -> Result<i64> {
+        if n == 0 {
+            return Ok(0);
+        }
+        let value = if n > 32 {
+            ((self.read_bits(n - 32)? as u64) << 32) | self.read_bits(32)? as u64
+        } else {
+            self.read_bits(n)? as u64
+        };
+        let shift = 64 - n;
+        Ok(((value << shift) as i64) >> shift)
+    }
+
+    /// Count zero bits before the next one bit, which must not be more than `max`.
+    fn read_unary(&mut self, max: u32) -> Result<u32> {
+        let mut count = 0;
+        while self.read_bits(1)? == 0 {
+            if count == max {
+                return Err(FlacError::InvalidFrame("unary code too long"));
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Skip "UTF-8" coded frame or sample number.
+    fn skip_coded_number(&mut self) -> Result<()> {
+        let first = self.read_bits(8)? as u8;
+        let continuation = match first.leading_ones() {
+            0 => 0,
+            n @ 2..=7 => n - 1,
+            _ => return Err(FlacError::InvalidFrame("invalid coded number")),
+        };
+        for _ in 0..continuation {
+            if self.read_bits(8)? >> 6 != 0b10 {
+                return Err(FlacError::InvalidFrame("invalid coded number"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Skip remaining bits of the current byte.
+    fn align(&mut self) {
+        self.left = 0;
+    }
+}
+
+const CRC8_TABLE: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+const CRC16_TABLE: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decorrelate() {
+        let (left, right) = (vec![10, -4, 0], vec![3, 5, -1]);
+        let side: Vec<_> = left.iter().zip(right.iter()).map(|(l, r)| l - r).collect();
+        let mid: Vec<_> = left
+            .iter()
+            .zip(right.iter())
+            .map(|(l, r)| (l + r) >> 1)
+            .collect();
+
+        for (assignment, channels) in [
+            (ChannelAssignment::LeftSide, [left.clone(), side.clone()]),
+            (ChannelAssignment::RightSide, [side.clone(), right.clone()]),
+            (ChannelAssignment::MidSide, [mid, side]),
+        ] {
+            let mut channels = channels;
+            decorrelate(&assignment, &mut channels);
+            assert_eq!(channels, [left.clone(), right.clone()]);
+        }
+    }
+
+    #[test]
+    fn test_read_signed() {
+        let mut reader = BitReader::new(&[0b1110_0101, 0b0000_0001][..]);
+        assert_eq!(reader.read_signed(3).unwrap(), -1);
+        assert_eq!(reader.read_signed(5).unwrap(), 5);
+        assert_eq!(reader.read_unary(7).unwrap(), 7);
+
+        let mut reader = BitReader::new(&[0b0000_0001][..]);
+        assert!(reader.read_unary(6).is_err());
+    }
+
+    #[test]
+    fn test_predict() {
+        let mut samples = vec![1, 2, 1, -1];
+        predict(&mut samples, &[2, -1], 0).unwrap();
+        assert_eq!(samples, [1, 2, 4, 5]);
+
+        let mut samples = vec![i64::MAX / 2, i64::MAX / 2, 0];
+        assert!(predict(&mut samples, &[4, -1], 0).is_err());
+    }
+}
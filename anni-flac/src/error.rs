@@ -13,6 +13,8 @@ pub enum FlacError {
     InvalidSeekTableSize,
     #[error("invalid picture type")]
     InvalidPictureType,
+    #[error("invalid frame: {0}")]
+    InvalidFrame(&'static str),
     #[error(transparent)]
     InvalidString(#[from] FromUtf8Error),
     #[error(transparent)]
@@ -11,7 +11,7 @@ use std::path::{Path, PathBuf};
 pub struct FlacHeader {
     pub blocks: Vec<MetadataBlock>,
     pub path: PathBuf,
-    frame_offset: usize,
+    pub(crate) frame_offset: usize,
 }
 
 impl FlacHeader {
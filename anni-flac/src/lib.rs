@@ -7,6 +7,7 @@ mod utils;
 pub use header::*;
 
 pub mod blocks;
+pub mod decode;
 pub mod error;
 pub mod frames;
 pub mod prelude;
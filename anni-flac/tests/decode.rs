@@ -0,0 +1,47 @@
+use anni_flac::error::FlacError;
+use anni_flac::FlacHeader;
+use md5::{Digest, Md5};
+
+/// MD5 of decoded samples, in the same way as `md5_signature` in STREAMINFO.
+fn decoded_md5(header: &FlacHeader) -> anni_flac::prelude::Result<([u8; 16], usize)> {
+    let bytes = (header.stream_info().bits_per_sample as usize).div_ceil(8);
+    let mut frames = header.frames()?;
+    let mut md5 = Md5::new();
+    let mut samples = 0;
+    while let Some(block) = frames.next_block()? {
+        samples += block[0].len();
+        for i in 0..block[0].len() {
+            for channel in block.iter() {
+                md5.update(&channel[i].to_le_bytes()[..bytes]);
+            }
+        }
+    }
+    Ok((md5.finalize().into(), samples))
+}
+
+#[test]
+fn decode_matches_md5_signature() {
+    for file in ["../assets/1s.flac", "../assets/1s-full.flac"] {
+        let header = FlacHeader::from_file(file).unwrap();
+        let (md5, samples) = decoded_md5(&header).unwrap();
+        let info = header.stream_info();
+        assert_eq!(md5, info.md5_signature);
+        assert_eq!(samples as u64, info.total_samples);
+    }
+}
+
+#[test]
+fn decode_corrupted_frame() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("corrupted.flac");
+    let mut data = std::fs::read("../assets/1s.flac").unwrap();
+    let len = data.len();
+    data[len - 100] ^= 0xff;
+    std::fs::write(&path, data).unwrap();
+
+    let header = FlacHeader::from_file(&path).unwrap();
+    assert!(matches!(
+        decoded_md5(&header),
+        Err(FlacError::InvalidFrame(_))
+    ));
+}
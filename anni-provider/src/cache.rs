@@ -1,19 +1,26 @@
-use crate::{AnniProvider, AudioInfo, AudioResourceReader, ProviderError, Range, ResourceReader};
+use crate::{
+    AnniProvider, AudioInfo, AudioResourceReader, Capabilities, ProviderError, Range,
+    ResourceReader,
+};
 use async_trait::async_trait;
 use dashmap::DashMap;
 use lru::LruCache;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::future::Future;
+use std::io::SeekFrom;
 use std::num::NonZeroU8;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::fs::File;
-use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
-use tokio::sync::Mutex;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, ReadBuf};
+use tokio::sync::{Mutex, Notify, Semaphore};
 use tokio::time::Duration;
 
 pub struct Cache {
@@ -27,11 +34,38 @@ impl Cache {
     }
 
     pub fn invalidate(&self, album_id: &str, disc_id: u8, track_id: u8) {
-        self.pool.remove(&do_hash(format!(
-            "{}/{:02}/{:02}",
-            album_id, disc_id, track_id
-        )));
+        self.pool.remove(&audio_key(album_id, disc_id, track_id));
     }
+
+    /// Info of audio which is fully cached, whose size is the actual number of bytes cached.
+    ///
+    /// Returns `None` if the audio is not cached, still being filled or expired.
+    pub fn cached_audio_info(
+        &self,
+        album_id: &str,
+        disc_id: NonZeroU8,
+        track_id: NonZeroU8,
+    ) -> Option<AudioInfo> {
+        self.pool
+            .cached_info(&audio_key(album_id, disc_id.get(), track_id.get()))
+    }
+
+    /// Drop cached cover of an album, or of a disc if `disc_id` is given.
+    pub fn invalidate_cover(&self, album_id: &str, disc_id: Option<NonZeroU8>) {
+        self.pool.remove(&cover_key(album_id, disc_id));
+    }
+}
+
+fn audio_key(album_id: &str, disc_id: u8, track_id: u8) -> String {
+    do_hash(format!("{}/{:02}/{:02}", album_id, disc_id, track_id))
+}
+
+fn cover_key(album_id: &str, disc_id: Option<NonZeroU8>) -> String {
+    do_hash(format!(
+        "cover/{}/{}",
+        album_id,
+        disc_id.map(NonZeroU8::get).unwrap_or(0)
+    ))
 }
 
 #[async_trait]
@@ -47,7 +81,11 @@ impl AnniProvider for Cache {
         disc_id: NonZeroU8,
         track_id: NonZeroU8,
     ) -> Result<AudioInfo, ProviderError> {
-        // audio info request are passed to the inner provider directly
+        // size reported by the inner provider may be an estimate, while cached size is exact
+        if let Some(info) = self.cached_audio_info(album_id, disc_id, track_id) {
+            return Ok(info);
+        }
+        // otherwise audio info request are passed to the inner provider directly
         self.inner.get_audio_info(album_id, disc_id, track_id).await
     }
 
@@ -58,9 +96,20 @@ impl AnniProvider for Cache {
         track_id: NonZeroU8,
         range: Range,
     ) -> Result<AudioResourceReader, ProviderError> {
+        let key = audio_key(album_id, disc_id.get(), track_id.get());
+        // sparse cache relies on the inner provider to fetch missing ranges
+        if self.pool.mode == CacheMode::Sparse && self.inner.capabilities().supports_range {
+            return self
+                .pool
+                .fetch_sparse(key, range, |range| {
+                    self.inner.get_audio(album_id, disc_id, track_id, range)
+                })
+                .await;
+        }
+
         self.pool
             .fetch(
-                do_hash(format!("{}/{:02}/{:02}", album_id, disc_id, track_id)),
+                key,
                 range,
                 self.inner.get_audio(
                     album_id,
@@ -77,112 +126,1005 @@ impl AnniProvider for Cache {
         album_id: &str,
         disc_id: Option<NonZeroU8>,
     ) -> Result<ResourceReader, ProviderError> {
-        // TODO: cache cover
-        self.inner.get_cover(album_id, disc_id).await
+        let cover = self
+            .pool
+            .fetch(cover_key(album_id, disc_id), Range::FULL, async {
+                // covers are small, so they are read at once to know their size
+                let mut reader = self.inner.get_cover(album_id, disc_id).await?;
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data).await?;
+                Ok(AudioResourceReader {
+                    info: AudioInfo {
+                        // providers serve covers from `cover.jpg`
+                        extension: "jpg".to_string(),
+                        size: data.len(),
+                        duration: 0,
+                    },
+                    range: Range::FULL,
+                    reader: Box::pin(std::io::Cursor::new(data)),
+                })
+            })
+            .await?;
+        Ok(cover.reader)
     }
 
     async fn reload(&mut self) -> Result<(), ProviderError> {
         // reload the inner provider
         self.inner.reload().await
     }
+
+    /// Ranges are always supported as cache serves ranges by itself.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_range: true,
+            ..self.inner.capabilities()
+        }
+    }
+}
+
+/// Where cached audio data is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheBackend {
+    /// Store cached data as files in cache root
+    #[default]
+    Disk,
+    /// Keep cached data in memory, which would be lost on restart
+    Memory,
+}
+
+/// How much of an audio file is cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheMode {
+    /// Cache the whole file on the first request
+    #[default]
+    Full,
+    /// Cache only requested byte ranges, and fetch missing ranges from the inner provider
+    ///
+    /// Providers without range support are always cached in full mode.
+    Sparse,
+}
+
+/// Which item is removed first when a [CachePool] is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// Remove the least recently used item
+    #[default]
+    Lru,
+    /// Remove the least frequently used item
+    Lfu,
+    /// Remove the item with the largest size per access, so that large but rarely used files go first
+    SizeWeighted,
+}
+
+/// A cached item which may be evicted.
+struct EvictionCandidate<'a> {
+    key: &'a str,
+    /// Number of requests to the item
+    accesses: u64,
+    /// Bytes used by the item
+    size: usize,
+}
+
+impl EvictionPolicy {
+    /// Choose an item from `candidates`, which are ordered from the least recently used one.
+    fn victim<'a>(
+        &self,
+        mut candidates: impl Iterator<Item = EvictionCandidate<'a>>,
+    ) -> Option<&'a str> {
+        let victim = match self {
+            EvictionPolicy::Lru => candidates.next(),
+            // the first one wins on tie, which is less recently used
+            EvictionPolicy::Lfu => candidates.min_by_key(|c| c.accesses),
+            EvictionPolicy::SizeWeighted => candidates.reduce(|victim, c| {
+                let weight = |c: &EvictionCandidate| c.size as u64 / c.accesses.max(1);
+                if weight(&c) > weight(&victim) {
+                    c
+                } else {
+                    victim
+                }
+            }),
+        };
+        victim.map(|c| c.key)
+    }
 }
 
 pub struct CachePool {
     /// Root of cache folder
     root: PathBuf,
+    /// Storage backend of cached data
+    backend: CacheBackend,
+    /// Whether whole files or requested ranges are cached
+    mode: CacheMode,
+    /// How to choose items to remove when space is full
+    eviction: EvictionPolicy,
+    /// Time after which cached items are fetched again
+    /// None means items never expire
+    ttl: Option<Duration>,
     /// Maximum space used by cache
     /// 0 means unlimited
     max_size: usize,
     cache: DashMap<String, Arc<CacheItem>>,
+    /// Items cached in sparse mode
+    sparse: DashMap<String, Arc<SparseItem>>,
     // https://github.com/xacrimon/dashmap/issues/189
     // FIXME: this structure acts like Mutex for now, since there's no reader at all
     last_used: RwLock<LruCache<String, Arc<Mutex<u8>>>>,
+    /// Number of requests to each item, used by eviction policies
+    accesses: DashMap<String, u64>,
+    /// Number of requests served by cached items
+    hits: AtomicU64,
+    /// Number of requests which created new items
+    misses: AtomicU64,
+    /// Number of items removed to free space
+    evictions: AtomicU64,
+    /// Writes index file in background
+    index: Arc<IndexWriter>,
+    /// Limits number of items being filled at the same time
+    fills: Arc<Semaphore>,
+    /// Number of spawned fill tasks which have not finished
+    running_fills: AtomicUsize,
+    /// Notified when a fill task finishes
+    fill_finished: Notify,
+}
+
+/// Default number of items which can be filled at the same time.
+pub const DEFAULT_FILL_LIMIT: usize = 4;
+
+/// Maximum bytes reserved for a memory item before it's filled, as reported sizes may be wrong.
+const MAX_MEMORY_RESERVE: usize = 16 * 1024 * 1024;
+
+/// Request of an item which is not cached yet.
+///
+/// The item is forgotten if the request fails or is cancelled before the item is added,
+/// so that later requests would fetch it again instead of waiting for it.
+struct PendingItem<'a> {
+    pool: &'a CachePool,
+    key: &'a str,
+    added: bool,
+}
+
+impl Drop for PendingItem<'_> {
+    fn drop(&mut self) {
+        if !self.added {
+            self.pool.last_used.write().pop(self.key);
+            self.pool.accesses.remove(self.key);
+        }
+    }
+}
+
+/// A spawned fill task, which is counted by [CachePool::wait_fills] until dropped.
+struct RunningFill(Arc<CachePool>);
+
+impl RunningFill {
+    fn new(pool: Arc<CachePool>) -> Self {
+        pool.running_fills.fetch_add(1, Ordering::SeqCst);
+        Self(pool)
+    }
+}
+
+impl Drop for RunningFill {
+    fn drop(&mut self) {
+        self.0.running_fills.fetch_sub(1, Ordering::SeqCst);
+        self.0.fill_finished.notify_waiters();
+    }
+}
+
+/// Name of the file in cache root which records cached items, so that they can be served after restart.
+const INDEX_FILE: &str = "index.json";
+
+/// Delay of writing index file after a change, changes made in the delay are written at once.
+const INDEX_SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// Writer of index file, which keeps the latest entries until they are written.
+#[derive(Default)]
+struct IndexWriter {
+    /// Entries not written yet, a write has been scheduled if it's `Some`
+    pending: parking_lot::Mutex<Option<Vec<IndexEntry>>>,
+    /// Serializes writes of index file
+    lock: parking_lot::Mutex<()>,
+}
+
+impl IndexWriter {
+    /// Write pending entries to index file in `root`, which blocks.
+    ///
+    /// Index is written to a temporary file first, and then renamed to replace the old one.
+    fn write(&self, root: &Path) {
+        let _lock = self.lock.lock();
+        let Some(entries) = self.pending.lock().take() else {
+            return;
+        };
+
+        let path = root.join(INDEX_FILE);
+        let temp = root.join(format!("{INDEX_FILE}.tmp"));
+        let result = serde_json::to_vec(&entries)
+            .map_err(std::io::Error::from)
+            .and_then(|data| std::fs::write(&temp, data))
+            .and_then(|_| std::fs::rename(&temp, &path));
+        if let Err(e) = result {
+            log::error!("Failed to save cache index: {}", e);
+        }
+    }
+}
+
+/// A cached item recorded in index file.
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    key: String,
+    ext: String,
+    size: usize,
+    duration: u64,
+    /// Start and exclusive end of cached chunks, only for items cached in sparse mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunks: Option<Vec<(u64, u64)>>,
+    /// Unix timestamp in seconds when the item was fetched, only for items cached in full mode
+    ///
+    /// Items of previous indexes without it are treated as fetched on restoring.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<u64>,
+}
+
+/// Statistics of a [CachePool].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of cached items
+    pub entries: usize,
+    /// Bytes used by cached items
+    pub space_used: usize,
+    /// Maximum bytes used by cache, 0 means unlimited
+    pub max_size: usize,
+    /// Number of items still being filled
+    pub filling: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
 }
 
 impl CachePool {
     pub fn new<P: AsRef<Path>>(root: P, max_size: usize) -> Self {
-        Self {
+        Self::with_backend(root, max_size, CacheBackend::Disk)
+    }
+
+    /// Create a cache pool which keeps cached data in memory.
+    pub fn new_memory(max_size: usize) -> Self {
+        Self::with_backend(PathBuf::new(), max_size, CacheBackend::Memory)
+    }
+
+    /// Create a cache pool with the given backend.
+    ///
+    /// Disk pools restore items recorded in index file of `root`, so cached files can be served after restart.
+    pub fn with_backend<P: AsRef<Path>>(root: P, max_size: usize, backend: CacheBackend) -> Self {
+        let pool = Self {
             root: PathBuf::from(root.as_ref()),
+            backend,
+            mode: CacheMode::Full,
+            eviction: EvictionPolicy::Lru,
+            ttl: None,
             max_size: if max_size == 0 { usize::MAX } else { max_size },
             cache: Default::default(),
+            sparse: Default::default(),
             last_used: RwLock::new(LruCache::unbounded()),
+            accesses: Default::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            index: Default::default(),
+            fills: Arc::new(Semaphore::new(DEFAULT_FILL_LIMIT)),
+            running_fills: AtomicUsize::new(0),
+            fill_finished: Notify::new(),
+        };
+        if pool.backend == CacheBackend::Disk {
+            pool.load_index();
         }
+        pool
+    }
+
+    /// Set cache mode of the pool.
+    ///
+    /// Sparse items restored from index are removed if the pool is set to full mode, as they would never be used.
+    pub fn with_mode(mut self, mode: CacheMode) -> Self {
+        self.mode = mode;
+        if mode == CacheMode::Full && !self.sparse.is_empty() {
+            let keys: Vec<_> = self.sparse.iter().map(|item| item.key().clone()).collect();
+            for key in keys {
+                self.remove_item(&key);
+            }
+            self.save_index();
+        }
+        self
+    }
+
+    pub fn mode(&self) -> CacheMode {
+        self.mode
+    }
+
+    /// Set eviction policy of the pool.
+    pub fn with_eviction(mut self, eviction: EvictionPolicy) -> Self {
+        self.eviction = eviction;
+        self
+    }
+
+    pub fn eviction(&self) -> EvictionPolicy {
+        self.eviction
+    }
+
+    /// Set maximum number of items filled at the same time, 0 means unlimited.
+    ///
+    /// Requests of uncached items wait for other items to be filled when the limit is reached.
+    /// Defaults to [DEFAULT_FILL_LIMIT].
+    pub fn with_fill_limit(mut self, limit: usize) -> Self {
+        let limit = if limit == 0 {
+            Semaphore::MAX_PERMITS
+        } else {
+            limit
+        };
+        self.fills = Arc::new(Semaphore::new(limit));
+        self
+    }
+
+    /// Set time to live of cached items, items older than `ttl` are fetched again on request.
+    ///
+    /// Requests reading an expired item keep reading the old data until they finish,
+    /// while new requests are served by the item being fetched.
+    /// Only items cached in full mode expire.
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.cache.len() + self.sparse.len(),
+            space_used: self.space_used(),
+            max_size: if self.max_size == usize::MAX {
+                0
+            } else {
+                self.max_size
+            },
+            filling: self.cache.iter().filter(|item| !item.cached()).count(),
+            hits: self.hits(),
+            misses: self.misses(),
+            evictions: self.evictions(),
+        }
+    }
+
+    /// Wait for items being filled to be fully cached, for at most `timeout`, and write pending changes of index file.
+    ///
+    /// Returns `false` if some items are still being filled after `timeout`.
+    /// It should be called before exiting, as unfinished items are removed when the runtime shuts down.
+    pub async fn wait_fills(&self, timeout: Duration) -> bool {
+        let wait = async {
+            loop {
+                // notified future receives notifications once created
+                let finished = self.fill_finished.notified();
+                if self.running_fills.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+                finished.await;
+            }
+        };
+        let finished = tokio::time::timeout(timeout, wait).await.is_ok();
+        self.flush_index().await;
+        finished
+    }
+
+    /// Number of requests served by cached items.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests which were not cached before.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Number of items removed to free space.
+    pub fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
     }
 
     async fn fetch(
-        &self,
+        self: &Arc<Self>,
         key: String,
         range: Range,
         on_miss: impl Future<Output = Result<AudioResourceReader, ProviderError>>,
     ) -> Result<AudioResourceReader, ProviderError> {
-        let item = if !self.has_cache(&key) {
-            // on miss, set state to cached first
-            let mutex = Arc::new(Mutex::new(0));
-            let handle = mutex.clone().lock_owned().await;
-            self.last_used.write().put(key.clone(), mutex);
+        if self.has_cache(&key) {
+            if let Some(reader) = self.cached(&key, range).await? {
+                return Ok(reader);
+            }
+        }
+
+        // on miss, set state to cached first
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let mutex = Arc::new(Mutex::new(0));
+        let handle = mutex.clone().lock_owned().await;
+        self.last_used.write().put(key.clone(), mutex);
+        self.record_access(&key);
+        let mut pending = PendingItem {
+            pool: self,
+            key: &key,
+            added: false,
+        };
 
-            // get data, return directly if it's a partial request
-            let result = on_miss.await?;
+        // wait for other items to be filled before opening a new stream
+        let permit = self.fills.clone().acquire_owned().await.unwrap();
 
-            // prepare for new item
-            let path = self.root.join(&key);
-            let mut file = tokio::fs::File::create(&path).await?;
-
-            let AudioResourceReader {
-                info, mut reader, ..
-            } = result;
-            let item = Arc::new(CacheItem::new(path, info, false));
-
-            // remove old item if space is full
-            if self.space_used() > self.max_size {
-                // get the first item of BTreeMap
-                let mut write = self.last_used.write();
-                let key = write.pop_lru().unwrap();
-                // remove it from cache map
-                // drop would do the removal
-                self.remove(&key.0);
+        // get data, return directly if it's a partial request
+        let result = on_miss.await?;
+
+        // prepare for new item
+        let AudioResourceReader {
+            info, mut reader, ..
+        } = result;
+        let (item, file) = match self.backend {
+            CacheBackend::Disk => {
+                let path = self.root.join(&key);
+                let file = tokio::fs::File::create(&path).await?;
+                let item = CacheItem::new(CacheData::Disk(path), info, false);
+                (Arc::new(item), Some(file))
             }
+            CacheBackend::Memory => {
+                let reserve = info.size.min(MAX_MEMORY_RESERVE);
+                let data = CacheData::Memory(RwLock::new(Vec::with_capacity(reserve)));
+                (Arc::new(CacheItem::new(data, info, false)), None)
+            }
+        };
 
-            // write to map
-            self.cache.insert(key.clone(), item.clone());
-            // item is set to cached, release lock
-            drop(handle);
-
-            // cache
-            let item_spawn = item.clone();
-            tokio::spawn(async move {
-                let actual_size = tokio::io::copy(&mut reader, &mut file).await.unwrap() as usize;
-                if item_spawn.size() != actual_size {
-                    item_spawn.set_size(actual_size);
-                }
-                item_spawn.set_cached(true);
+        // remove old items if space is full
+        // drop would do the removal
+        self.evict();
+
+        // write to map
+        self.cache.insert(key.clone(), item.clone());
+        pending.added = true;
+        drop(pending);
+        // item is set to cached, release lock
+        drop(handle);
+
+        // cache
+        let pool = self.clone();
+        let item_spawn = item.clone();
+        let running = RunningFill::new(self.clone());
+        tokio::spawn(async move {
+            let result = match file {
+                Some(mut file) => tokio::io::copy(&mut reader, &mut file)
+                    .await
+                    .map(|size| size as usize),
+                None => item_spawn.fill_memory(&mut reader).await,
+            };
+            // stream ended before the size reported by provider, which is usually a broken
+            // connection, while reported size can be smaller than the actual one
+            let reported_size = item_spawn.size();
+            let result = result.and_then(|actual_size| match actual_size {
+                size if size < reported_size => Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!("expected {reported_size} bytes, got {actual_size}"),
+                )),
+                size => Ok(size),
             });
-            item
-        } else {
-            // resource requested, but not added to cache map yet
-            if !self.cache.contains_key(&key) {
-                // await cache mutex
-                let mutex = {
-                    let mut map = self.last_used.write();
-                    map.get(&key).unwrap().clone()
-                };
+            match result {
+                Ok(actual_size) => {
+                    let size_changed = reported_size != actual_size;
+                    if size_changed {
+                        item_spawn.set_size(actual_size);
+                    }
+                    item_spawn.set_cached(true);
+                    // size reported by provider may be smaller than the actual one
+                    if size_changed {
+                        pool.evict();
+                    }
+                    pool.save_index();
+                }
+                Err(e) => {
+                    log::error!("Failed to cache {key}: {e}");
+                    // readers of the item get the error, and later requests fetch it again
+                    item_spawn.set_failed();
+                    pool.discard(&key, &item_spawn);
+                }
+            }
+            drop(permit);
+            drop(running);
+        });
+
+        Ok(item.to_audio_resource_reader(range).await?)
+    }
+
+    /// Read `range` of cached item of `key`, waiting for it if it's being requested.
+    ///
+    /// Returns `None` if the item should be fetched, which happens when the pending request
+    /// failed, or the cached item is expired or broken.
+    async fn cached(
+        &self,
+        key: &str,
+        range: Range,
+    ) -> Result<Option<AudioResourceReader>, ProviderError> {
+        // resource requested, but not added to cache map yet
+        if !self.cache.contains_key(key) {
+            // await cache mutex
+            let mutex = self.last_used.write().get(key).cloned();
+            if let Some(mutex) = mutex {
                 let _ = mutex.lock().await;
             }
-            // update last_used time
-            self.last_used.write().get(&key).unwrap();
-            self.cache.get(&key).unwrap().clone()
+        }
+        let Some(item) = self.cache.get(key).map(|item| item.clone()) else {
+            return Ok(None);
         };
+        // expired items are treated as missing
+        if item.cached() && self.ttl.is_some_and(|ttl| item.age() >= ttl) {
+            self.discard(key, &item);
+            return Ok(None);
+        }
+        // cached data may be truncated or removed out-of-band,
+        // which is checked when opening the file
+        let reader = match item.to_audio_resource_reader(range).await {
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::InvalidData | std::io::ErrorKind::NotFound
+                ) =>
+            {
+                log::warn!("Cached data of {key} does not match its size, fetching again");
+                self.discard(key, &item);
+                return Ok(None);
+            }
+            reader => reader?,
+        };
+        // update last_used time
+        self.last_used.write().get(key);
+        self.record_access(key);
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Ok(Some(reader))
+    }
 
-        Ok(item
-            .to_audio_resource_reader(tokio::fs::File::open(&item.path).await?, range)
-            .await)
+    /// Serve `range` from cached chunks, and fetch missing parts with `fetch`.
+    ///
+    /// Fetched parts are saved as new chunks after being read.
+    async fn fetch_sparse<F, Fut>(
+        self: &Arc<Self>,
+        key: String,
+        range: Range,
+        fetch: F,
+    ) -> Result<AudioResourceReader, ProviderError>
+    where
+        F: Fn(Range) -> Fut,
+        Fut: Future<Output = Result<AudioResourceReader, ProviderError>>,
+    {
+        self.evict();
+
+        let item = self.sparse.get(&key).map(|item| item.clone());
+        let Some(item) = item else {
+            // size of audio is unknown before the first request, so the whole range is fetched
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            let AudioResourceReader { info, reader, .. } = fetch(range).await?;
+            let prefix = match self.backend {
+                CacheBackend::Disk => Some(self.root.join(&key)),
+                CacheBackend::Memory => None,
+            };
+            let item = self
+                .sparse
+                .entry(key.clone())
+                .or_insert_with(|| Arc::new(SparseItem::new(prefix, info)))
+                .clone();
+            self.record_access(&key);
+            self.last_used.write().put(key, Arc::new(Mutex::new(0)));
+
+            let reader = SparseFillReader::new(self.clone(), item.clone(), range.start, reader);
+            return Ok(item.to_audio_resource(range, Box::pin(reader)));
+        };
+        // update last_used time
+        self.last_used.write().get(&key);
+        self.record_access(&key);
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        let size = item.info.size as u64;
+        let end = range.end.map_or(size, |end| (end + 1).min(size));
+        let mut reader: ResourceReader = Box::pin(tokio::io::empty());
+        for segment in item.plan(range.start, end) {
+            let part: ResourceReader = match segment {
+                Segment::Memory { data, offset, len } => {
+                    Box::pin(std::io::Cursor::new(ChunkSlice {
+                        data,
+                        range: offset..offset + len,
+                    }))
+                }
+                Segment::Disk {
+                    path,
+                    offset,
+                    start,
+                    len,
+                } => match open_chunk(&path, offset, len).await {
+                    Ok(reader) => reader,
+                    // chunk may be replaced by a larger one after planning
+                    Err(_) => fill_gap(self, &item, start, start + len, &fetch).await?,
+                },
+                Segment::Gap { start, end } => fill_gap(self, &item, start, end, &fetch).await?,
+            };
+            reader = Box::pin(reader.chain(part));
+        }
+        Ok(item.to_audio_resource(range, reader))
+    }
+
+    /// Save `data` starting at `start` as a new chunk of `item`, and evict items if space is full.
+    fn commit_chunk(self: &Arc<Self>, item: &Arc<SparseItem>, start: u64, data: Vec<u8>) {
+        let len = data.len() as u64;
+        if len == 0 || SparseItem::covers(&item.chunks.read(), start, start + len) {
+            return;
+        }
+
+        let Some(path) = item.chunk_path(start, start + len) else {
+            let chunk = SparseChunk {
+                data: ChunkData::Memory(Arc::new(data)),
+                len,
+            };
+            item.insert(start, chunk);
+            self.evict();
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let pool = self.clone();
+        let item = item.clone();
+        let running = RunningFill::new(self.clone());
+        handle.spawn(async move {
+            // chunks are limited and waited like other fills
+            let _permit = pool.fills.clone().acquire_owned().await.unwrap();
+            // chunk is visible after the whole file is written
+            match tokio::fs::write(&path, &data).await {
+                Ok(_) => {
+                    let chunk = SparseChunk {
+                        data: ChunkData::Disk(path),
+                        len,
+                    };
+                    item.insert(start, chunk);
+                    pool.evict();
+                    pool.save_index();
+                }
+                Err(e) => log::error!("Failed to write cache chunk: {}", e),
+            }
+            drop(running);
+        });
+    }
+
+    /// Remove items chosen by eviction policy until space used is under limit.
+    fn evict(&self) {
+        let mut evicted = false;
+        while self.space_used() > self.max_size {
+            let Some(key) = self.victim() else {
+                break;
+            };
+            self.remove_item(&key);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            evicted = true;
+        }
+        if evicted {
+            self.save_index();
+        }
+    }
+
+    /// Choose an item to evict, items still being requested or filled are not chosen.
+    fn victim(&self) -> Option<String> {
+        let last_used = self.last_used.read();
+        let candidates = last_used.iter().rev().filter_map(|(key, _)| {
+            let size = match self.cache.get(key) {
+                Some(item) if item.cached() => item.size(),
+                Some(_) => return None,
+                None => self.sparse.get(key)?.size(),
+            };
+            Some(EvictionCandidate {
+                key,
+                accesses: self.accesses.get(key).map_or(0, |count| *count),
+                size,
+            })
+        });
+        self.eviction.victim(candidates).map(str::to_string)
+    }
+
+    /// Check sizes of all cached items, and remove items whose data does not match.
+    ///
+    /// Returns the number of removed items.
+    pub async fn verify(&self) -> usize {
+        let items: Vec<_> = self
+            .cache
+            .iter()
+            .filter(|item| item.cached())
+            .map(|item| (item.key().clone(), item.value().clone()))
+            .collect();
+
+        let mut removed = 0;
+        for (key, item) in items {
+            if !item.verify().await {
+                log::warn!("Cached data of {key} does not match its size, removing it");
+                self.discard(&key, &item);
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            self.save_index();
+        }
+        removed
+    }
+
+    /// Remove an expired, broken or failed item, without interrupting requests which are still reading it.
+    fn discard(&self, key: &str, item: &Arc<CacheItem>) {
+        // another request may have replaced the item already
+        if self
+            .cache
+            .remove_if(key, |_, cached| Arc::ptr_eq(cached, item))
+            .is_none()
+        {
+            return;
+        }
+        item.remove_file();
+        self.last_used.write().pop(key);
+        self.accesses.remove(key);
+    }
+
+    fn record_access(&self, key: &str) {
+        *self.accesses.entry(key.to_string()).or_insert(0) += 1;
     }
 
     fn remove(&self, key: &str) {
-        self.cache.remove(key).map(|r| r.1.set_cached(false));
+        self.remove_item(key);
+        self.save_index();
+    }
+
+    fn remove_item(&self, key: &str) {
+        if let Some((_, item)) = self.cache.remove(key) {
+            item.remove_file();
+        }
+        if let Some((_, item)) = self.sparse.remove(key) {
+            item.remove();
+        }
+        self.accesses.remove(key);
         self.last_used.write().pop(key);
     }
 
+    /// Read entries of index file, returns an empty list if it does not exist or is broken.
+    fn read_index(&self) -> Vec<IndexEntry> {
+        let data = match std::fs::read(self.root.join(INDEX_FILE)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+            Err(e) => {
+                log::warn!("Failed to read cache index: {}", e);
+                return Vec::new();
+            }
+        };
+        serde_json::from_slice(&data).unwrap_or_else(|e| {
+            log::warn!("Failed to parse cache index: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Restore items recorded in index file, and remove cached files which are not recorded.
+    ///
+    /// Items whose file is missing or has a different size are skipped.
+    /// Files of previous key schemes are renamed first, see [CachePool::migrate_files].
+    fn load_index(&self) {
+        let migrated_files = self.migrate_files();
+        let mut migrated = !migrated_files.is_empty();
+        let entries = self.read_index();
+        let mut last_used = self.last_used.write();
+        // files cached before index was introduced are the least recently used ones
+        for key in migrated_files {
+            let path = self.root.join(&key);
+            let Some(info) = legacy_info(&path) else {
+                continue;
+            };
+            let item = CacheItem::new(CacheData::Disk(path), info, true);
+            self.cache.insert(key.clone(), Arc::new(item));
+            last_used.put(key, Arc::new(Mutex::new(0)));
+        }
+        for mut entry in entries {
+            // keys of previous schemes are dropped if they can not be migrated
+            let Some(key) = KeyScheme::migrate(&entry.key) else {
+                continue;
+            };
+            if key != entry.key {
+                entry.key = key;
+                migrated = true;
+            }
+
+            let path = self.root.join(&entry.key);
+            let info = AudioInfo {
+                extension: entry.ext,
+                size: entry.size,
+                duration: entry.duration,
+            };
+            if let Some(chunks) = entry.chunks {
+                let item = SparseItem::new(Some(path), info);
+                for (start, end) in chunks {
+                    let path = item.chunk_path(start, end).unwrap();
+                    let size_matches =
+                        std::fs::metadata(&path).map_or(false, |meta| meta.len() == end - start);
+                    if size_matches {
+                        let chunk = SparseChunk {
+                            data: ChunkData::Disk(path),
+                            len: end - start,
+                        };
+                        item.insert(start, chunk);
+                    }
+                }
+                if item.size() == 0 {
+                    continue;
+                }
+                self.sparse.insert(entry.key.clone(), Arc::new(item));
+            } else {
+                let size_matches =
+                    std::fs::metadata(&path).map_or(false, |meta| meta.len() == entry.size as u64);
+                if !size_matches {
+                    continue;
+                }
+                let mut item = CacheItem::new(CacheData::Disk(path), info, true);
+                if let Some(created_at) = entry.created_at {
+                    item.created_at = UNIX_EPOCH + Duration::from_secs(created_at);
+                }
+                self.cache.insert(entry.key.clone(), Arc::new(item));
+            }
+            last_used.put(entry.key, Arc::new(Mutex::new(0)));
+        }
+        drop(last_used);
+
+        self.sweep();
+        if migrated {
+            self.save_index();
+        }
+    }
+
+    /// Rename cached files of previous key schemes in root, returns keys of renamed files.
+    ///
+    /// Files are scanned instead of read from index, as caches of previous versions may have no index file.
+    fn migrate_files(&self) -> Vec<String> {
+        let Ok(files) = std::fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+        let mut migrated = Vec::new();
+        for file in files.flatten() {
+            let name = file.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            let Some(key) = KeyScheme::migrate(name).filter(|key| key != name) else {
+                continue;
+            };
+            match std::fs::rename(file.path(), self.root.join(&key)) {
+                Ok(_) => migrated.push(key),
+                Err(e) => log::warn!("Failed to migrate cache key {}: {}", name, e),
+            }
+        }
+        migrated
+    }
+
+    /// Remove cached files in root which do not belong to any item, like files of interrupted fills.
+    fn sweep(&self) {
+        let files = match std::fs::read_dir(&self.root) {
+            Ok(files) => files,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                log::warn!("Failed to read cache folder: {}", e);
+                return;
+            }
+        };
+        let mut used: HashSet<PathBuf> = self
+            .cache
+            .iter()
+            .filter_map(|item| match &item.data {
+                CacheData::Disk(path) => Some(path.clone()),
+                CacheData::Memory(_) => None,
+            })
+            .collect();
+        for item in self.sparse.iter() {
+            used.extend(
+                item.chunks
+                    .read()
+                    .values()
+                    .filter_map(|chunk| match &chunk.data {
+                        ChunkData::Disk(path) => Some(path.clone()),
+                        ChunkData::Memory(_) => None,
+                    }),
+            );
+        }
+
+        for file in files.flatten() {
+            let name = file.file_name();
+            // chunk files are named by key of their item with a range suffix
+            let key = name.to_string_lossy();
+            let key = key.split('.').next().unwrap_or_default();
+            if KeyScheme::of(key).is_none() || used.contains(&file.path()) {
+                continue;
+            }
+            if let Err(e) = std::fs::remove_file(file.path()) {
+                log::warn!("Failed to remove unused cache file: {}", e);
+            }
+        }
+    }
+
+    /// Write cached items of a disk pool to index file, from the least recently used one.
+    ///
+    /// Index is written in background after [INDEX_SAVE_DELAY], or at once if there's no runtime.
+    fn save_index(&self) {
+        if self.backend != CacheBackend::Disk {
+            return;
+        }
+
+        let entries: Vec<_> = self
+            .last_used
+            .read()
+            .iter()
+            .rev()
+            .filter_map(|(key, _)| {
+                if let Some(item) = self.sparse.get(key) {
+                    let chunks: Vec<_> = item
+                        .chunks
+                        .read()
+                        .iter()
+                        .map(|(start, chunk)| (*start, start + chunk.len))
+                        .collect();
+                    return (!chunks.is_empty()).then(|| IndexEntry {
+                        key: key.clone(),
+                        ext: item.info.extension.clone(),
+                        size: item.info.size,
+                        duration: item.info.duration,
+                        chunks: Some(chunks),
+                        created_at: None,
+                    });
+                }
+                let item = self.cache.get(key)?;
+                item.cached().then(|| IndexEntry {
+                    key: key.clone(),
+                    ext: item.ext.clone(),
+                    size: item.size(),
+                    duration: item.duration,
+                    chunks: None,
+                    created_at: item
+                        .created_at
+                        .duration_since(UNIX_EPOCH)
+                        .ok()
+                        .map(|time| time.as_secs()),
+                })
+            })
+            .collect();
+
+        // a scheduled write would pick the latest entries
+        if self.index.pending.lock().replace(entries).is_some() {
+            return;
+        }
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            self.index.write(&self.root);
+            return;
+        };
+        let index = self.index.clone();
+        let root = self.root.clone();
+        handle.spawn(async move {
+            tokio::time::sleep(INDEX_SAVE_DELAY).await;
+            let _ = tokio::task::spawn_blocking(move || index.write(&root)).await;
+        });
+    }
+
+    /// Write pending changes of index file at once.
+    async fn flush_index(&self) {
+        let index = self.index.clone();
+        let root = self.root.clone();
+        let _ = tokio::task::spawn_blocking(move || index.write(&root)).await;
+    }
+
+    /// Info of a fully cached item in full mode, without counting as a request.
+    fn cached_info(&self, key: &str) -> Option<AudioInfo> {
+        let item = self.cache.get(key)?;
+        if !item.cached() || self.ttl.is_some_and(|ttl| item.age() >= ttl) {
+            return None;
+        }
+        Some(AudioInfo {
+            extension: item.ext.clone(),
+            size: item.size(),
+            duration: item.duration,
+        })
+    }
+
     fn has_cache(&self, key: &str) -> bool {
         self.last_used.read().contains(key)
     }
@@ -191,43 +1133,142 @@ impl CachePool {
         self.cache
             .iter()
             .map(|i| i.size())
+            .chain(self.sparse.iter().map(|i| i.size()))
             .reduce(|a, b| a + b)
             .unwrap_or(0)
     }
 }
 
+/// Info of a file cached before index was introduced, which is either FLAC audio or a cover.
+///
+/// Returns `None` if the file can not be read.
+fn legacy_info(path: &Path) -> Option<AudioInfo> {
+    use anni_flac::blocks::BlockStreamInfo;
+    use anni_flac::prelude::Decode;
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path).ok()?;
+    let size = file.metadata().ok()?.len() as usize;
+    // magic number and header of STREAMINFO block
+    let mut header = [0; 8];
+    let is_flac = file.read_exact(&mut header).is_ok() && header.starts_with(b"fLaC");
+    let stream_info = is_flac
+        .then(|| BlockStreamInfo::from_reader(&mut file).ok())
+        .flatten()
+        .filter(|info| info.sample_rate > 0);
+    let (extension, duration) = match stream_info {
+        Some(info) => ("flac", info.total_samples * 1000 / info.sample_rate as u64),
+        None => ("", 0),
+    };
+    Some(AudioInfo {
+        extension: extension.to_string(),
+        size,
+        duration,
+    })
+}
+
+impl Drop for CachePool {
+    fn drop(&mut self) {
+        // write changes which are still delayed
+        self.index.write(&self.root);
+    }
+}
+
 fn do_hash(key: String) -> String {
-    use sha2::{Digest, Sha256};
-    let mut hasher = Sha256::new();
-    Sha256::update(&mut hasher, key);
-    let result = hasher.finalize();
-    hex::encode(result)
+    KeyScheme::CURRENT.key(&key)
+}
+
+/// How cache keys, which are also file names of cached items, are derived from resources.
+///
+/// Keys start with a prefix of their scheme, so that keys of previous schemes can be recognized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyScheme {
+    /// Hex of SHA-256 digest without prefix, used before schemes were introduced
+    Legacy,
+    /// Hex of SHA-256 digest, prefixed by `sha256-`
+    Sha256,
+}
+
+impl KeyScheme {
+    /// Scheme of newly created keys.
+    pub const CURRENT: KeyScheme = KeyScheme::Sha256;
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            KeyScheme::Legacy => "",
+            KeyScheme::Sha256 => "sha256-",
+        }
+    }
+
+    /// Derive cache key of `resource`.
+    pub fn key(&self, resource: &str) -> String {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::digest(resource);
+        format!("{}{}", self.prefix(), hex::encode(digest))
+    }
+
+    /// Recognize scheme of `key`, returns `None` if `key` is not a cache key.
+    pub fn of(key: &str) -> Option<KeyScheme> {
+        let is_sha256_hex =
+            |hex: &str| hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit());
+        match key.strip_prefix(KeyScheme::Sha256.prefix()) {
+            Some(hex) if is_sha256_hex(hex) => Some(KeyScheme::Sha256),
+            _ if is_sha256_hex(key) => Some(KeyScheme::Legacy),
+            _ => None,
+        }
+    }
+
+    /// Convert `key` to the current scheme.
+    ///
+    /// Returns `None` if `key` is not a cache key, or its digest can not be reused by the current scheme.
+    pub fn migrate(key: &str) -> Option<String> {
+        match KeyScheme::of(key)? {
+            KeyScheme::Legacy => Some(format!("{}{}", KeyScheme::CURRENT.prefix(), key)),
+            KeyScheme::Sha256 => Some(key.to_string()),
+        }
+    }
+}
+
+enum CacheData {
+    Disk(PathBuf),
+    Memory(RwLock<Vec<u8>>),
 }
 
 struct CacheItem {
     ext: String,
-    path: PathBuf,
+    data: CacheData,
     size: RwLock<usize>,
     duration: u64,
     cached: RwLock<bool>,
+    /// Whether filling the item failed, in which case it's never cached
+    failed: AtomicBool,
+    /// When the item was fetched from the inner provider, which is kept in index file
+    created_at: SystemTime,
 }
 
 impl CacheItem {
-    fn new(path: PathBuf, info: AudioInfo, cached: bool) -> Self {
+    fn new(data: CacheData, info: AudioInfo, cached: bool) -> Self {
         let AudioInfo {
             extension: ext,
             duration,
             size,
         } = info;
         CacheItem {
-            path,
+            data,
             ext,
             size: RwLock::new(size),
             duration,
             cached: RwLock::new(cached),
+            failed: AtomicBool::new(false),
+            created_at: SystemTime::now(),
         }
     }
 
+    /// Time since the item was fetched, which is zero if system time goes backwards.
+    fn age(&self) -> Duration {
+        self.created_at.elapsed().unwrap_or_default()
+    }
+
     fn size(&self) -> usize {
         *self.size.read()
     }
@@ -243,42 +1284,133 @@ impl CacheItem {
     fn set_cached(&self, cached: bool) {
         *self.cached.write() = cached
     }
+
+    fn failed(&self) -> bool {
+        self.failed.load(Ordering::SeqCst)
+    }
+
+    fn set_failed(&self) {
+        self.failed.store(true, Ordering::SeqCst)
+    }
+
+    /// Remove cached file of the item, without interrupting requests which are still reading it.
+    ///
+    /// Item is kept as cached or failed so that dropping it would not remove the new file at the
+    /// same path, while opened files can still be read after being removed.
+    fn remove_file(&self) {
+        if let CacheData::Disk(path) = &self.data {
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    log::warn!("Failed to remove cache file: {}", e);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether size of cached data matches the recorded size.
+    async fn verify(&self) -> bool {
+        match &self.data {
+            CacheData::Disk(path) => tokio::fs::metadata(path)
+                .await
+                .map_or(false, |meta| meta.len() == self.size() as u64),
+            CacheData::Memory(data) => data.read().len() == self.size(),
+        }
+    }
+
+    /// Read all data from `reader` into memory, returns the total size read.
+    async fn fill_memory(&self, reader: &mut ResourceReader) -> std::io::Result<usize> {
+        let data = match &self.data {
+            CacheData::Memory(data) => data,
+            CacheData::Disk(_) => unreachable!("fill_memory called on disk cache item"),
+        };
+
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut total = 0;
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            data.write().extend_from_slice(&buf[..n]);
+            total += n;
+        }
+        Ok(total)
+    }
 }
 
 #[async_trait::async_trait]
 trait CacheReader {
-    fn to_reader(&self, file: tokio::fs::File) -> CacheItemReader;
+    fn to_reader(&self, file: tokio::fs::File, filled: usize) -> CacheItemReader;
 
-    async fn to_audio_resource_reader(
-        &self,
-        file: tokio::fs::File,
-        range: Range,
-    ) -> AudioResourceReader;
+    async fn to_audio_resource_reader(&self, range: Range) -> std::io::Result<AudioResourceReader>;
 }
 
 #[async_trait::async_trait]
 impl CacheReader for Arc<CacheItem> {
-    fn to_reader(&self, file: tokio::fs::File) -> CacheItemReader {
+    fn to_reader(&self, file: tokio::fs::File, filled: usize) -> CacheItemReader {
         CacheItemReader {
             item: self.clone(),
             file: Box::pin(file),
-            filled: 0,
+            filled,
+            reached_eof: false,
             timer: None,
         }
     }
 
-    async fn to_audio_resource_reader(&self, file: File, range: Range) -> AudioResourceReader {
-        let mut reader = self.to_reader(file);
-        if range.start > 0 {
-            let reader = &mut reader;
-            let _ = tokio::io::copy(&mut reader.take(range.start), &mut tokio::io::sink()).await;
+    async fn to_audio_resource_reader(&self, range: Range) -> std::io::Result<AudioResourceReader> {
+        let mut file = match &self.data {
+            CacheData::Disk(path) => File::open(path).await?,
+            CacheData::Memory(_) => {
+                let reader = MemoryItemReader {
+                    item: self.clone(),
+                    position: range.start as usize,
+                    timer: None,
+                };
+                let reader: ResourceReader = match range.length() {
+                    Some(length) => Box::pin(reader.take(length)),
+                    None => Box::pin(reader),
+                };
+                return Ok(self.to_audio_resource(range, reader));
+            }
+        };
+
+        // read the state before metadata, as filling may finish in between
+        let cached = self.cached();
+        let written = file.metadata().await?.len();
+        if cached && written != self.size() as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "cached file does not match its size",
+            ));
         }
+
+        // seek directly if the requested part has already been written to disk
+        let seekable = range.start > 0 && written >= range.start;
+        let reader = if seekable && file.seek(SeekFrom::Start(range.start)).await.is_ok() {
+            self.to_reader(file, range.start as usize)
+        } else {
+            let mut reader = self.to_reader(file, 0);
+            if range.start > 0 {
+                // the file is still being cached, wait for data by reading and discarding it
+                let reader = &mut reader;
+                let _ =
+                    tokio::io::copy(&mut reader.take(range.start), &mut tokio::io::sink()).await;
+            }
+            reader
+        };
         let length = range.length();
         let reader: ResourceReader = match length {
             Some(length) => Box::pin(reader.take(length)),
             None => Box::pin(reader),
         };
 
+        Ok(self.to_audio_resource(range, reader))
+    }
+}
+
+impl CacheItem {
+    fn to_audio_resource(&self, range: Range, reader: ResourceReader) -> AudioResourceReader {
         AudioResourceReader {
             info: AudioInfo {
                 extension: self.ext.clone(),
@@ -293,12 +1425,13 @@ impl CacheReader for Arc<CacheItem> {
 
 impl Drop for CacheItem {
     fn drop(&mut self) {
-        // not cached, means:
-        // a. file not fully cached and program reaches program termination
-        // b. manually set cached to false
-        if !self.cached() {
-            if let Err(e) = std::fs::remove_file(&self.path) {
-                log::error!("Failed to drop CacheItem: {}", e);
+        // not cached, means file not fully cached and program reaches program termination
+        // files of removed or failed items have been removed already
+        if !self.cached() && !self.failed() {
+            if let CacheData::Disk(path) = &self.data {
+                if let Err(e) = std::fs::remove_file(path) {
+                    log::error!("Failed to drop CacheItem: {}", e);
+                }
             }
         }
     }
@@ -308,6 +1441,8 @@ struct CacheItemReader {
     item: Arc<CacheItem>,
     file: Pin<Box<tokio::fs::File>>,
     filled: usize,
+    /// Whether EOF has been reached once after caching finished.
+    reached_eof: bool,
 
     timer: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
 }
@@ -345,14 +1480,24 @@ impl AsyncRead for CacheItemReader {
                             Poll::Ready(Ok(()))
                         } else if self.item.cached() {
                             if self.filled != self.item.size() {
+                                if self.reached_eof {
+                                    // file is truncated, there's nothing more to read
+                                    return Poll::Ready(Err(std::io::Error::new(
+                                        std::io::ErrorKind::UnexpectedEof,
+                                        "cached file is truncated",
+                                    )));
+                                }
                                 // caching finished just now
                                 // wake immediately to finish the last part
+                                self.reached_eof = true;
                                 cx.waker().wake_by_ref();
                                 Poll::Pending
                             } else {
                                 // EOF
                                 Poll::Ready(Ok(()))
                             }
+                        } else if self.item.failed() {
+                            Poll::Ready(Err(fill_failed()))
                         } else {
                             // not done, wait for more data
                             // set up timer to wait
@@ -372,3 +1517,349 @@ impl AsyncRead for CacheItemReader {
         }
     }
 }
+
+fn fill_failed() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "failed to read audio from provider",
+    )
+}
+
+/// Reader of memory cache item.
+///
+/// Like [CacheItemReader], it waits for more data if the item is still being cached.
+struct MemoryItemReader {
+    item: Arc<CacheItem>,
+    position: usize,
+
+    timer: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+}
+
+impl AsyncRead for MemoryItemReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        // Wait mode
+        if let Some(timer) = self.timer.as_mut() {
+            match timer.as_mut().poll(cx) {
+                Poll::Ready(_) => self.timer = None,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        // Read mode
+        // check cached state before reading, so that no data would be missed
+        let cached = self.item.cached();
+        let failed = self.item.failed();
+        let read = match &self.item.data {
+            CacheData::Memory(data) => {
+                let data = data.read();
+                let start = self.position.min(data.len());
+                let len = buf.remaining().min(data.len() - start);
+                buf.put_slice(&data[start..start + len]);
+                len
+            }
+            CacheData::Disk(_) => unreachable!("MemoryItemReader used on disk cache item"),
+        };
+
+        if read > 0 {
+            self.position += read;
+            Poll::Ready(Ok(()))
+        } else if cached {
+            // EOF
+            Poll::Ready(Ok(()))
+        } else if failed {
+            Poll::Ready(Err(fill_failed()))
+        } else {
+            // not done, wait for more data
+            self.timer = Some(Box::pin(tokio::time::sleep(Duration::from_millis(100))));
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// Fetch `[start, end)` of audio and save it to `item` after being read.
+async fn fill_gap<F, Fut>(
+    pool: &Arc<CachePool>,
+    item: &Arc<SparseItem>,
+    start: u64,
+    end: u64,
+    fetch: &F,
+) -> Result<ResourceReader, ProviderError>
+where
+    F: Fn(Range) -> Fut,
+    Fut: Future<Output = Result<AudioResourceReader, ProviderError>>,
+{
+    let audio = fetch(Range::new(start, Some(end - 1))).await?;
+    let reader = Box::pin(audio.reader.take(end - start));
+    Ok(Box::pin(SparseFillReader::new(
+        pool.clone(),
+        item.clone(),
+        start,
+        reader,
+    )))
+}
+
+async fn open_chunk(path: &Path, offset: u64, len: u64) -> std::io::Result<ResourceReader> {
+    let mut file = File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    Ok(Box::pin(file.take(len)))
+}
+
+/// Audio cached in sparse mode, which consists of chunks of requested byte ranges.
+struct SparseItem {
+    /// Path prefix of chunk files, `None` for memory backend
+    prefix: Option<PathBuf>,
+    info: AudioInfo,
+    /// Cached chunks, keyed by start offset
+    ///
+    /// Chunks never contain each other, but they may overlap.
+    chunks: RwLock<BTreeMap<u64, SparseChunk>>,
+    /// Whether the item has been removed from pool, chunks inserted after removal are dropped
+    removed: AtomicBool,
+}
+
+struct SparseChunk {
+    data: ChunkData,
+    len: u64,
+}
+
+enum ChunkData {
+    Disk(PathBuf),
+    Memory(Arc<Vec<u8>>),
+}
+
+impl SparseChunk {
+    fn remove_file(&self) {
+        if let ChunkData::Disk(path) = &self.data {
+            if let Err(e) = std::fs::remove_file(path) {
+                log::error!("Failed to remove cache chunk: {}", e);
+            }
+        }
+    }
+}
+
+/// Part of a requested range.
+enum Segment {
+    Memory {
+        data: Arc<Vec<u8>>,
+        offset: usize,
+        len: usize,
+    },
+    Disk {
+        path: PathBuf,
+        /// Offset in chunk file
+        offset: u64,
+        /// Offset in audio
+        start: u64,
+        len: u64,
+    },
+    /// Range not cached, with exclusive end
+    Gap { start: u64, end: u64 },
+}
+
+impl SparseItem {
+    fn new(prefix: Option<PathBuf>, info: AudioInfo) -> Self {
+        Self {
+            prefix,
+            info,
+            chunks: Default::default(),
+            removed: AtomicBool::new(false),
+        }
+    }
+
+    /// Path of chunk file of `[start, end)`, `None` for memory backend.
+    fn chunk_path(&self, start: u64, end: u64) -> Option<PathBuf> {
+        let mut path = self.prefix.clone()?.into_os_string();
+        path.push(format!(".{}-{}", start, end));
+        Some(PathBuf::from(path))
+    }
+
+    /// Remove all chunks, requests which opened chunk files can still read them.
+    fn remove(&self) {
+        let mut chunks = self.chunks.write();
+        self.removed.store(true, Ordering::SeqCst);
+        for chunk in std::mem::take(&mut *chunks).into_values() {
+            chunk.remove_file();
+        }
+    }
+
+    /// Total size of cached chunks.
+    fn size(&self) -> usize {
+        self.chunks.read().values().map(|c| c.len as usize).sum()
+    }
+
+    fn covers(chunks: &BTreeMap<u64, SparseChunk>, start: u64, end: u64) -> bool {
+        chunks
+            .iter()
+            .any(|(s, chunk)| *s <= start && s + chunk.len >= end)
+    }
+
+    /// Split `[start, end)` into cached segments and gaps.
+    fn plan(&self, start: u64, end: u64) -> Vec<Segment> {
+        let chunks = self.chunks.read();
+        let mut segments = Vec::new();
+        let mut cursor = start;
+        while cursor < end {
+            let covering = chunks
+                .range(..=cursor)
+                .filter(|(s, chunk)| *s + chunk.len > cursor)
+                .max_by_key(|(s, chunk)| *s + chunk.len);
+            match covering {
+                Some((s, chunk)) => {
+                    let stop = (s + chunk.len).min(end);
+                    let offset = cursor - s;
+                    let len = stop - cursor;
+                    segments.push(match &chunk.data {
+                        ChunkData::Disk(path) => Segment::Disk {
+                            path: path.clone(),
+                            offset,
+                            start: cursor,
+                            len,
+                        },
+                        ChunkData::Memory(data) => Segment::Memory {
+                            data: data.clone(),
+                            offset: offset as usize,
+                            len: len as usize,
+                        },
+                    });
+                    cursor = stop;
+                }
+                None => {
+                    let stop = chunks
+                        .range(cursor + 1..end)
+                        .next()
+                        .map_or(end, |(s, _)| *s);
+                    segments.push(Segment::Gap {
+                        start: cursor,
+                        end: stop,
+                    });
+                    cursor = stop;
+                }
+            }
+        }
+        segments
+    }
+
+    fn insert(&self, start: u64, chunk: SparseChunk) {
+        let end = start + chunk.len;
+        let mut chunks = self.chunks.write();
+        if self.removed.load(Ordering::SeqCst) {
+            chunk.remove_file();
+            return;
+        }
+        if Self::covers(&chunks, start, end) {
+            // the same range has been cached by another request
+            // chunk with exactly the same range shares the file, which should be kept
+            if chunks.get(&start).map_or(true, |c| c.len != chunk.len) {
+                chunk.remove_file();
+            }
+            return;
+        }
+
+        // remove chunks contained by the new one
+        let contained: Vec<_> = chunks
+            .range(start..end)
+            .filter(|(s, c)| *s + c.len <= end)
+            .map(|(s, _)| *s)
+            .collect();
+        for s in contained {
+            if let Some(c) = chunks.remove(&s) {
+                c.remove_file();
+            }
+        }
+        chunks.insert(start, chunk);
+    }
+
+    fn to_audio_resource(&self, range: Range, reader: ResourceReader) -> AudioResourceReader {
+        AudioResourceReader {
+            info: self.info.clone(),
+            range,
+            reader,
+        }
+    }
+}
+
+/// Part of a memory chunk.
+struct ChunkSlice {
+    data: Arc<Vec<u8>>,
+    range: std::ops::Range<usize>,
+}
+
+impl AsRef<[u8]> for ChunkSlice {
+    fn as_ref(&self) -> &[u8] {
+        &self.data[self.range.clone()]
+    }
+}
+
+/// Maximum size of a chunk saved by [SparseFillReader], which also limits memory used by buffer.
+const SPARSE_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Reader which saves data read from `reader` to a [SparseItem].
+///
+/// Data is saved every [SPARSE_CHUNK_SIZE] bytes, on EOF, or when the reader is dropped,
+/// so partially read ranges are cached as well.
+struct SparseFillReader {
+    pool: Arc<CachePool>,
+    item: Arc<SparseItem>,
+    start: u64,
+    buf: Vec<u8>,
+    reader: ResourceReader,
+}
+
+impl SparseFillReader {
+    fn new(
+        pool: Arc<CachePool>,
+        item: Arc<SparseItem>,
+        start: u64,
+        reader: ResourceReader,
+    ) -> Self {
+        Self {
+            pool,
+            item,
+            start,
+            buf: Vec::new(),
+            reader,
+        }
+    }
+
+    fn commit(&mut self) {
+        let data = std::mem::take(&mut self.buf);
+        let start = self.start;
+        self.start += data.len() as u64;
+        self.pool.commit_chunk(&self.item, start, data);
+    }
+}
+
+impl AsyncRead for SparseFillReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let result = self.reader.as_mut().poll_read(cx, buf);
+        if let Poll::Ready(Ok(_)) = result {
+            let now = buf.filled().len();
+            if before == now {
+                // EOF
+                self.commit();
+            } else {
+                self.buf.extend_from_slice(&buf.filled()[before..now]);
+                if self.buf.len() >= SPARSE_CHUNK_SIZE {
+                    self.commit();
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Drop for SparseFillReader {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use serde::Serialize;
 use std::borrow::Cow;
 use std::collections::HashSet;
 use std::num::NonZeroU8;
@@ -15,12 +16,19 @@ pub type ResourceReader = Pin<Box<dyn AsyncRead + Send>>;
 pub struct AudioInfo {
     /// File extension of the file
     pub extension: String,
-    /// File size of the file
+    /// File size of the file, `0` if the size is unknown(e.g. for streams)
     pub size: usize,
     /// Audio duration of the file, in milliseconds
     pub duration: u64,
 }
 
+impl AudioInfo {
+    /// File size of the file, or `None` if the size is unknown.
+    pub fn known_size(&self) -> Option<usize> {
+        (self.size > 0).then_some(self.size)
+    }
+}
+
 /// AudioResourceReader abstracts the file result a provider returns with extra information of audio
 pub struct AudioResourceReader {
     /// Audio info
@@ -31,7 +39,7 @@ pub struct AudioResourceReader {
     pub reader: ResourceReader,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Range {
     pub start: u64,
     pub end: Option<u64>,
@@ -51,6 +59,13 @@ impl Range {
         total: None,
     };
 
+    /// Range large enough to read duration of non-FLAC audio files
+    pub const AUDIO_HEADER: Range = Range {
+        start: 0,
+        end: Some(crate::format::AUDIO_HEADER_SIZE - 1),
+        total: None,
+    };
+
     /// create a new range with given start and end offset
     pub fn new(start: u64, end: Option<u64>) -> Self {
         Self {
@@ -72,7 +87,8 @@ impl Range {
             Some(end) => std::cmp::min(end, limit),
             None => limit,
         };
-        end - self.start + 1
+        // ranges starting beyond the limit are empty
+        end.checked_sub(self.start).map_or(0, |length| length + 1)
     }
 
     /// return a new Range with updated end property
@@ -129,6 +145,35 @@ impl Range {
     }
 }
 
+/// Features supported by a provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    /// Whether [AnniProvider::get_audio] respects the requested range.
+    pub supports_range: bool,
+    /// Whether covers can be provided by [AnniProvider::get_cover].
+    pub has_covers: bool,
+    /// Whether lyrics can be provided.
+    pub has_lyrics: bool,
+    /// Whether [AnniProvider::albums] lists all available albums.
+    pub listable: bool,
+}
+
+impl Capabilities {
+    /// Conservative capabilities, which only assumes albums can be listed.
+    pub const CONSERVATIVE: Capabilities = Capabilities {
+        supports_range: false,
+        has_covers: false,
+        has_lyrics: false,
+        listable: true,
+    };
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::CONSERVATIVE
+    }
+}
+
 /// AnniProvider is a common trait for anni resource providers.
 /// It provides functions to get cover, audio, album list and reload.
 #[async_trait]
@@ -154,10 +199,19 @@ pub trait AnniProvider: Sync {
         disc_id: NonZeroU8,
         track_id: NonZeroU8,
     ) -> Result<AudioInfo> {
-        Ok(self
+        let info = self
             .get_audio(album_id, disc_id, track_id, Range::FLAC_HEADER)
             .await?
-            .info)
+            .info;
+        if info.extension.eq_ignore_ascii_case("flac") {
+            Ok(info)
+        } else {
+            // headers of other formats may not fit in FLAC_HEADER, request again with a larger range
+            Ok(self
+                .get_audio(album_id, disc_id, track_id, Range::AUDIO_HEADER)
+                .await?
+                .info)
+        }
     }
 
     /// Returns a reader implements AsyncRead for content reading
@@ -170,11 +224,37 @@ pub trait AnniProvider: Sync {
     ) -> Result<AudioResourceReader>;
 
     /// Returns a cover of corresponding album
+    ///
+    /// If `disc_id` is given but the disc does not have its own cover, cover of the album is returned.
     async fn get_cover(&self, album_id: &str, disc_id: Option<NonZeroU8>)
         -> Result<ResourceReader>;
 
     /// Reloads the provider for new albums
     async fn reload(&mut self) -> Result<()>;
+
+    /// Features supported by the provider.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::CONSERVATIVE
+    }
+
+    /// Features supported when serving album `album_id`.
+    ///
+    /// Combined providers may serve albums by inner providers with different capabilities.
+    async fn album_capabilities(&self, _album_id: &str) -> Capabilities {
+        self.capabilities()
+    }
+
+    /// Get an inner provider by its name, if the provider combines named providers.
+    fn named_provider(&self, _name: &str) -> Option<&(dyn AnniProvider + Send + Sync)> {
+        None
+    }
+
+    /// Inner providers in order with their names, if the provider combines multiple providers.
+    ///
+    /// Returns an empty list by default, which means the provider is not combined.
+    fn inner_providers(&self) -> Vec<(&str, &(dyn AnniProvider + Send + Sync))> {
+        Vec::new()
+    }
 }
 
 #[derive(Clone)]
@@ -204,7 +284,7 @@ pub trait FileSystemProvider: Sync {
     async fn get_audio_file(&self, path: &PathBuf, range: Range) -> Result<AudioResourceReader> {
         let reader = self.get_file(path, range).await?;
         let metadata = self.get_audio_info(path).await?;
-        let (duration, reader) = crate::utils::read_duration(reader, range).await?;
+        let (duration, reader) = crate::utils::read_duration(reader, range, &metadata.0).await?;
         Ok(AudioResourceReader {
             info: AudioInfo {
                 extension: metadata.0,
@@ -232,6 +312,12 @@ pub enum ProviderError {
     #[error("file not found")]
     FileNotFound,
 
+    #[error("provider {provider} timed out after {}ms", timeout.as_millis())]
+    Timeout {
+        provider: String,
+        timeout: std::time::Duration,
+    },
+
     #[error(transparent)]
     IOError(#[from] std::io::Error),
 
@@ -254,10 +340,25 @@ pub enum ProviderError {
     #[error(transparent)]
     FlacError(#[from] anni_flac::error::FlacError),
 
+    /// Metadata database can not be opened or read, until the provider is reloaded successfully.
+    #[error("metadata database is unavailable: {0}")]
+    DatabaseUnavailable(String),
+
     #[error("an error occurred")]
     GeneralError,
 }
 
+impl ProviderError {
+    /// Whether the error means the requested file does not exist.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            ProviderError::FileNotFound => true,
+            ProviderError::IOError(e) => e.kind() == std::io::ErrorKind::NotFound,
+            _ => false,
+        }
+    }
+}
+
 pub fn strict_album_path(root: &PathBuf, album_id: &str, layer: usize) -> PathBuf {
     let mut res = root.clone();
     for i in 0..layer {
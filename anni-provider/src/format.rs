@@ -0,0 +1,263 @@
+//! Format specific helpers for audio files other than FLAC.
+use std::borrow::Cow;
+
+/// Bytes to read from the beginning of a non-FLAC audio file to find its duration.
+pub const AUDIO_HEADER_SIZE: u64 = 64 * 1024;
+
+/// Get MIME type of an audio file by its extension.
+///
+/// Unknown extensions fall back to `audio/{extension}`.
+pub fn content_type(extension: &str) -> Cow<'static, str> {
+    match known_content_type(extension) {
+        Some(content_type) => Cow::Borrowed(content_type),
+        None => Cow::Owned(format!("audio/{extension}")),
+    }
+}
+
+/// Get MIME type of an audio file by its extension, or `None` if the extension is unknown.
+pub fn known_content_type(extension: &str) -> Option<&'static str> {
+    match extension.to_ascii_lowercase().as_str() {
+        "flac" => Some("audio/flac"),
+        "wav" | "wave" => Some("audio/wav"),
+        "m4a" | "mp4" | "alac" => Some("audio/mp4"),
+        "mp3" => Some("audio/mpeg"),
+        "aac" => Some("audio/aac"),
+        "ogg" | "opus" => Some("audio/ogg"),
+        _ => None,
+    }
+}
+
+/// Get file extension from a MIME type returned by [content_type].
+pub fn extension(content_type: &str) -> Option<&str> {
+    let subtype = content_type
+        .split(';')
+        .next()?
+        .trim()
+        .strip_prefix("audio/")?;
+    Some(match subtype {
+        "wav" | "wave" | "x-wav" => "wav",
+        "mp4" | "x-m4a" => "m4a",
+        "mpeg" => "mp3",
+        _ => subtype,
+    })
+}
+
+/// Read duration in milliseconds from the beginning of an audio file.
+///
+/// `header` may be truncated, in which case `None` is returned if the needed information is not included.
+pub(crate) fn duration(extension: &str, header: &[u8]) -> Option<u64> {
+    match extension.to_ascii_lowercase().as_str() {
+        "wav" | "wave" => wav_duration(header),
+        "m4a" | "mp4" | "alac" => mp4_duration(header),
+        _ => None,
+    }
+}
+
+/// Audio format read from `fmt ` chunk of a RIFF WAVE file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavFormat {
+    pub sample_rate: u32,
+    /// Bytes per second
+    pub byte_rate: u32,
+    pub bits_per_sample: u16,
+}
+
+impl WavFormat {
+    fn parse(fmt: &[u8]) -> Option<Self> {
+        let fmt = fmt.get(0..16)?;
+        Some(WavFormat {
+            sample_rate: u32::from_le_bytes(fmt[4..8].try_into().unwrap()),
+            byte_rate: u32::from_le_bytes(fmt[8..12].try_into().unwrap()),
+            bits_per_sample: u16::from_le_bytes(fmt[14..16].try_into().unwrap()),
+        })
+    }
+}
+
+/// Chunks of a RIFF WAVE file in `header`, as `(id, size, body)`.
+///
+/// `body` is truncated if the chunk does not end in `header`.
+fn wav_chunks(header: &[u8]) -> Option<impl Iterator<Item = (&[u8], usize, &[u8])>> {
+    if header.get(0..4)? != b"RIFF" || header.get(8..12)? != b"WAVE" {
+        return None;
+    }
+
+    let mut offset = Some(12usize);
+    Some(std::iter::from_fn(move || {
+        let chunk = header.get(offset?..offset? + 8)?;
+        let size = u32::from_le_bytes(chunk[4..8].try_into().unwrap()) as usize;
+        let body = offset? + 8;
+        // chunks are padded to even size
+        offset = body.checked_add(size + (size & 1));
+        let end = body.saturating_add(size).min(header.len());
+        Some((&chunk[0..4], size, &header[body..end]))
+    }))
+}
+
+/// Read format of a RIFF WAVE file from its `fmt ` chunk at the beginning of the file.
+pub fn wav_format(header: &[u8]) -> Option<WavFormat> {
+    wav_chunks(header)?
+        .find(|(id, _, _)| *id == b"fmt ")
+        .and_then(|(_, _, fmt)| WavFormat::parse(fmt))
+}
+
+/// Read duration of a RIFF WAVE file from its `fmt ` and `data` chunks.
+fn wav_duration(header: &[u8]) -> Option<u64> {
+    let mut byte_rate = None;
+    for (id, size, body) in wav_chunks(header)? {
+        match id {
+            b"fmt " => byte_rate = Some(WavFormat::parse(body)?.byte_rate as u64),
+            b"data" => {
+                let byte_rate = byte_rate.filter(|rate| *rate > 0)?;
+                return Some(size as u64 * 1000 / byte_rate);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Find a box named `name` in `data`, and return its (maybe truncated) body.
+fn find_atom<'a>(data: &'a [u8], name: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut offset = 0usize;
+    while let Some(header) = data.get(offset..offset + 8) {
+        let (header_size, size) = match u32::from_be_bytes(header[0..4].try_into().unwrap()) {
+            // box extends to the end of file
+            0 => (8, (data.len() - offset) as u64),
+            // 64-bit box size
+            1 => {
+                let size = data.get(offset + 8..offset + 16)?;
+                (16, u64::from_be_bytes(size.try_into().unwrap()))
+            }
+            size => (8, size as u64),
+        };
+        if size < header_size {
+            return None;
+        }
+
+        let end = (offset as u64).saturating_add(size);
+        if &header[4..8] == name {
+            let end = end.min(data.len() as u64) as usize;
+            return data.get(offset + header_size as usize..end);
+        }
+        offset = usize::try_from(end).ok()?;
+    }
+    None
+}
+
+/// Read duration of an MPEG-4 file from `moov/mvhd`.
+///
+/// Only files with `moov` placed before `mdat`(fast start) can be handled.
+fn mp4_duration(header: &[u8]) -> Option<u64> {
+    let moov = find_atom(header, b"moov")?;
+    let mvhd = find_atom(moov, b"mvhd")?;
+    let (timescale, duration) = match mvhd.first()? {
+        0 => (
+            u32::from_be_bytes(mvhd.get(12..16)?.try_into().unwrap()),
+            u32::from_be_bytes(mvhd.get(16..20)?.try_into().unwrap()) as u64,
+        ),
+        1 => (
+            u32::from_be_bytes(mvhd.get(20..24)?.try_into().unwrap()),
+            u64::from_be_bytes(mvhd.get(24..32)?.try_into().unwrap()),
+        ),
+        _ => return None,
+    };
+    if timescale == 0 {
+        return None;
+    }
+    Some((duration as u128 * 1000 / timescale as u128) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(extra_chunk: bool, data_size: u32) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.extend_from_slice(b"RIFF");
+        result.extend_from_slice(&0u32.to_le_bytes());
+        result.extend_from_slice(b"WAVE");
+        result.extend_from_slice(b"fmt ");
+        result.extend_from_slice(&16u32.to_le_bytes());
+        result.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        result.extend_from_slice(&2u16.to_le_bytes()); // channels
+        result.extend_from_slice(&44100u32.to_le_bytes()); // sample rate
+        result.extend_from_slice(&(44100u32 * 4).to_le_bytes()); // byte rate
+        result.extend_from_slice(&4u16.to_le_bytes()); // block align
+        result.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+        if extra_chunk {
+            result.extend_from_slice(b"LIST");
+            result.extend_from_slice(&3u32.to_le_bytes());
+            result.extend_from_slice(&[0, 0, 0, 0]); // with padding
+        }
+        result.extend_from_slice(b"data");
+        result.extend_from_slice(&data_size.to_le_bytes());
+        result
+    }
+
+    fn atom(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+        result.extend_from_slice(name);
+        result.extend_from_slice(body);
+        result
+    }
+
+    fn mvhd_v0(timescale: u32, duration: u32) -> Vec<u8> {
+        let mut body = vec![0u8; 12];
+        body.extend_from_slice(&timescale.to_be_bytes());
+        body.extend_from_slice(&duration.to_be_bytes());
+        body.extend_from_slice(&[0u8; 80]);
+        atom(b"mvhd", &body)
+    }
+
+    #[test]
+    fn test_wav_duration() {
+        assert_eq!(duration("wav", &wav(false, 44100 * 4 * 3)), Some(3000));
+        assert_eq!(duration("wav", &wav(true, 44100 * 2)), Some(500));
+        // truncated before data chunk
+        assert_eq!(duration("wav", &wav(false, 0)[..40]), None);
+        assert_eq!(duration("wav", b"fLaC"), None);
+    }
+
+    #[test]
+    fn test_wav_format() {
+        let format = WavFormat {
+            sample_rate: 44100,
+            byte_rate: 44100 * 4,
+            bits_per_sample: 16,
+        };
+        assert_eq!(wav_format(&wav(false, 0)), Some(format));
+        assert_eq!(wav_format(&wav(true, 0)), Some(format));
+        // truncated in fmt chunk
+        assert_eq!(wav_format(&wav(false, 0)[..30]), None);
+        assert_eq!(wav_format(b"fLaC"), None);
+    }
+
+    #[test]
+    fn test_mp4_duration() {
+        let moov = atom(b"moov", &mvhd_v0(44100, 44100 * 61));
+        let mut file = atom(b"ftyp", b"M4A \0\0\0\0");
+        file.extend_from_slice(&moov);
+        file.extend_from_slice(&atom(b"mdat", &[0u8; 16]));
+        assert_eq!(duration("m4a", &file), Some(61000));
+
+        // moov after mdat can not be found in truncated header
+        let mut file = atom(b"ftyp", b"M4A \0\0\0\0");
+        file.extend_from_slice(&(1_000_000u32).to_be_bytes());
+        file.extend_from_slice(b"mdat");
+        file.extend_from_slice(&[0u8; 64]);
+        assert_eq!(duration("m4a", &file), None);
+    }
+
+    #[test]
+    fn test_content_type() {
+        for ext in ["flac", "wav", "m4a", "mp3", "ogg"] {
+            assert_eq!(extension(&content_type(ext)), Some(ext));
+        }
+        assert_eq!(content_type("alac"), "audio/mp4");
+        assert_eq!(content_type("ape"), "audio/ape");
+        assert_eq!(known_content_type("ape"), None);
+        assert_eq!(extension("audio/x-wav"), Some("wav"));
+        assert_eq!(extension("image/jpeg"), None);
+    }
+}
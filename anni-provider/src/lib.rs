@@ -2,6 +2,7 @@ pub use common::*;
 
 pub mod cache;
 mod common;
+pub mod format;
 pub mod fs;
 pub mod providers;
 mod utils;
@@ -1,6 +1,7 @@
+use super::database::DatabaseState;
 use crate::{
-    AnniProvider, AudioResourceReader, FileEntry, FileSystemProvider, ProviderError, Range,
-    ResourceReader, Result,
+    AnniProvider, AudioResourceReader, Capabilities, FileEntry, FileSystemProvider, ProviderError,
+    Range, ResourceReader, Result,
 };
 use anni_repo::db::RepoDatabaseRead;
 use anni_repo::library::{AlbumFolderInfo, DiscFolderInfo};
@@ -17,6 +18,7 @@ pub struct CommonConventionProvider {
     root: PathBuf,
     fs: Box<dyn FileSystemProvider + Send + Sync>,
     repo: Mutex<RepoDatabaseRead>,
+    database: DatabaseState,
 
     pub albums: HashMap<String, FileEntry>,
     pub discs: HashMap<String, Vec<FileEntry>>,
@@ -32,6 +34,7 @@ impl CommonConventionProvider {
             root,
             fs,
             repo: Mutex::new(repo),
+            database: Default::default(),
 
             albums: HashMap::new(),
             discs: HashMap::new(),
@@ -58,6 +61,7 @@ impl AnniProvider for CommonConventionProvider {
         track_id: NonZeroU8,
         range: Range,
     ) -> Result<AudioResourceReader> {
+        self.database.check()?;
         let disc = self.get_disc(album_id, disc_id)?;
         let file = self
             .fs
@@ -71,6 +75,7 @@ impl AnniProvider for CommonConventionProvider {
         album_id: &str,
         disc_id: Option<NonZeroU8>,
     ) -> Result<ResourceReader> {
+        self.database.check()?;
         let folder = match disc_id {
             Some(disc_id) => self.get_disc(album_id, disc_id)?,
             _ => self
@@ -78,16 +83,39 @@ impl AnniProvider for CommonConventionProvider {
                 .get(album_id)
                 .ok_or(ProviderError::FileNotFound)?,
         };
-        self.fs
+        match self
+            .fs
             .get_file(&folder.path.join("cover.jpg"), Range::FULL)
             .await
+        {
+            // fallback to album cover
+            Err(e) if disc_id.is_some() && e.is_not_found() => self.get_cover(album_id, None).await,
+            result => result,
+        }
     }
 
     async fn reload(&mut self) -> Result<()> {
         self.fs.reload().await?;
-        self.repo.lock().reload()?;
-        self.reload_albums().await?;
-        Ok(())
+        if let Err(e) = self.repo.lock().reload() {
+            return Err(self.database.fail(e));
+        }
+        match self.reload_albums().await {
+            Ok(()) => {
+                self.database.recover();
+                Ok(())
+            }
+            Err(ProviderError::RepoError(e)) => Err(self.database.fail(e)),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_range: true,
+            has_covers: true,
+            has_lyrics: false,
+            listable: true,
+        }
     }
 }
 
@@ -106,13 +134,18 @@ impl CommonConventionProvider {
         }
     }
 
+    /// Scan albums under root, previous albums are kept if scanning failed.
     pub async fn reload_albums(&mut self) -> Result<()> {
-        self.albums.clear();
-        self.discs.clear();
+        let albums = std::mem::take(&mut self.albums);
+        let discs = std::mem::take(&mut self.discs);
 
         let mut to_visit = vec![self.root.clone()];
         while let Some(dir) = to_visit.pop() {
-            self.walk_dir_impl(dir, &mut to_visit).await?;
+            if let Err(e) = self.walk_dir_impl(dir, &mut to_visit).await {
+                self.albums = albums;
+                self.discs = discs;
+                return Err(e);
+            }
         }
 
         Ok(())
@@ -0,0 +1,47 @@
+use crate::ProviderError;
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+
+/// Minimum interval between logs of requests failed by an unavailable database.
+const LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Availability of metadata database used by a provider.
+///
+/// Once the database fails to be opened or read, requests fail with
+/// [ProviderError::DatabaseUnavailable] until the next successful reload.
+#[derive(Default)]
+pub(crate) struct DatabaseState {
+    error: Mutex<Option<String>>,
+    last_log: Mutex<Option<Instant>>,
+}
+
+impl DatabaseState {
+    /// Mark the database as unavailable because of `error`.
+    pub(crate) fn fail(&self, error: anni_repo::error::Error) -> ProviderError {
+        let message = error.to_string();
+        log::error!("Metadata database is unavailable: {message}");
+        *self.last_log.lock() = Some(Instant::now());
+        *self.error.lock() = Some(message.clone());
+        ProviderError::DatabaseUnavailable(message)
+    }
+
+    /// Mark the database as available after a successful reload.
+    pub(crate) fn recover(&self) {
+        if self.error.lock().take().is_some() {
+            log::info!("Metadata database is available again");
+        }
+    }
+
+    /// Fail if the database is unavailable, logging at most once in [LOG_INTERVAL].
+    pub(crate) fn check(&self) -> Result<(), ProviderError> {
+        let Some(message) = self.error.lock().clone() else {
+            return Ok(());
+        };
+        let mut last_log = self.last_log.lock();
+        if last_log.map_or(true, |time| time.elapsed() >= LOG_INTERVAL) {
+            log::error!("Request failed as metadata database is unavailable: {message}");
+            *last_log = Some(Instant::now());
+        }
+        Err(ProviderError::DatabaseUnavailable(message))
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    common::content_range_to_range, AnniProvider, AudioInfo, AudioResourceReader, ProviderError,
-    Range, ResourceReader,
+    common::content_range_to_range, AnniProvider, AudioInfo, AudioResourceReader, Capabilities,
+    ProviderError, Range, ResourceReader,
 };
 use anni_google_drive3::{
     hyper, hyper::client::HttpConnector, hyper_rustls::HttpsConnector, oauth2, DriveHub,
@@ -8,11 +8,13 @@ use anni_google_drive3::{
 use async_trait::async_trait;
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::num::NonZeroU8;
 use std::path::PathBuf;
 
 use self::oauth2::authenticator::Authenticator;
 use self::oauth2::authenticator_delegate::DefaultInstalledFlowDelegate;
+use super::database::DatabaseState;
 use crate::utils::read_duration;
 use anni_google_drive3::api::{FileList, FileListCall};
 use anni_google_drive3::hyper_rustls::HttpsConnectorBuilder;
@@ -22,8 +24,11 @@ use dashmap::DashMap;
 use futures::TryStreamExt;
 use parking_lot::Mutex;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use tokio::sync::Semaphore;
 
+#[derive(Clone)]
 pub enum DriveAuth {
     InstalledFlow {
         client_id: String,
@@ -109,15 +114,105 @@ impl From<PathBuf> for TokenStorage {
 pub struct DriveProviderSettings {
     pub corpora: String,
     pub drive_id: Option<String>,
+    /// Retry policy of failed requests
+    pub retry: RetryPolicy,
 }
 
 impl DriveProviderSettings {
     pub fn new(corpora: String, drive_id: Option<String>) -> Self {
-        Self { corpora, drive_id }
+        Self {
+            corpora,
+            drive_id,
+            retry: Default::default(),
+        }
+    }
+}
+
+/// Retry failed requests with exponential backoff.
+///
+/// Server errors(5xx), rate limit errors(429, and 403 with rate limit reasons) and connection
+/// errors are retried. `Retry-After` header is respected if present, but never waited longer
+/// than `max_delay`. Other errors fail immediately.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Maximum retries after the first failure
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each retry after it
+    pub backoff: Duration,
+    /// Upper bound of delay before a retry
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 4,
+            backoff: Duration::from_millis(500),
+            max_delay: Duration::from_secs(32),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the retry after `retry` retries, or `retry_after` required by the server.
+    fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after
+            .unwrap_or_else(|| self.backoff.saturating_mul(2u32.saturating_pow(retry)))
+            .min(self.max_delay)
     }
 }
+
+/// Whether a failed request can be retried, and how long to wait as the server requires.
+fn retryable(err: &anni_google_drive3::Error) -> Option<Option<Duration>> {
+    use anni_google_drive3::Error;
+
+    let is_retryable_status = |status: u16| status >= 500 || status == 429;
+    match err {
+        Error::HttpError(_) | Error::Io(_) => Some(None),
+        // body of failures is not json, so reasons of 403 errors are unknown
+        // and they are treated as permission errors
+        Error::Failure(response) if is_retryable_status(response.status().as_u16()) => {
+            let retry_after = response
+                .headers()
+                .get("Retry-After")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok())
+                .map(Duration::from_secs);
+            Some(retry_after)
+        }
+        Error::BadRequest(value) => {
+            let error = value.get("error")?;
+            let status = error.get("code")?.as_u64()? as u16;
+            if status == 403 {
+                // only rate limit errors are retried, other 403 errors are permission errors
+                let rate_limited = error
+                    .get("errors")
+                    .and_then(|e| e.as_array())
+                    .map(|errors| {
+                        errors.iter().any(|e| {
+                            matches!(
+                                e.get("reason").and_then(|r| r.as_str()),
+                                Some("rateLimitExceeded" | "userRateLimitExceeded")
+                            )
+                        })
+                    })
+                    .unwrap_or(false);
+                rate_limited.then_some(None)
+            } else if is_retryable_status(status) {
+                Some(None)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
 pub struct DriveClient {
-    hub: Box<DriveHub<HttpsConnector<HttpConnector>>>,
+    /// Hubs of each token, used in turn
+    hubs: Vec<DriveHub<HttpsConnector<HttpConnector>>>,
+    /// Index of hub to be used by the next request
+    next_hub: AtomicUsize,
     settings: DriveProviderSettings,
     /// Semaphore for rate limiting
     semaphore: Semaphore,
@@ -132,34 +227,105 @@ impl DriveClient {
         settings: DriveProviderSettings,
         token_storage: impl Into<TokenStorage>,
     ) -> Result<Self, ProviderError> {
-        let auth = auth.build(token_storage.into()).await?;
-        auth.token(&[
-            "https://www.googleapis.com/auth/drive.metadata.readonly",
-            "https://www.googleapis.com/auth/drive.readonly",
-        ])
-        .await?;
-        let hub = DriveHub::new(
-            hyper::Client::builder().build(
-                HttpsConnectorBuilder::new()
-                    .with_native_roots()
-                    .https_or_http()
-                    .enable_http1()
-                    .enable_http2()
-                    .build(),
-            ),
-            auth,
-        );
+        Self::with_token_storages(auth, settings, vec![token_storage.into()]).await
+    }
+
+    /// Create a client with multiple tokens, which are used in turn for each request.
+    ///
+    /// Retries of failed requests use the next token, so requests exceeding quota of one
+    /// token are retried with another. `token_storages` must not be empty.
+    pub async fn with_token_storages(
+        auth: DriveAuth,
+        settings: DriveProviderSettings,
+        token_storages: Vec<TokenStorage>,
+    ) -> Result<Self, ProviderError> {
+        if token_storages.is_empty() {
+            return Err(ProviderError::GeneralError);
+        }
+
+        let mut hubs = Vec::with_capacity(token_storages.len());
+        for token_storage in token_storages {
+            let auth = auth.clone().build(token_storage).await?;
+            auth.token(&[
+                "https://www.googleapis.com/auth/drive.metadata.readonly",
+                "https://www.googleapis.com/auth/drive.readonly",
+            ])
+            .await?;
+            hubs.push(DriveHub::new(
+                hyper::Client::builder().build(
+                    HttpsConnectorBuilder::new()
+                        .with_native_roots()
+                        .https_or_http()
+                        .enable_http1()
+                        .enable_http2()
+                        .build(),
+                ),
+                auth,
+            ));
+        }
         Ok(Self {
-            hub: Box::new(hub),
+            hubs,
+            next_hub: AtomicUsize::new(0),
             settings,
             covers: DashMap::new(),
             semaphore: Semaphore::new(200),
         })
     }
 
+    /// Hub for the next request, in round-robin order.
+    fn hub(&self) -> &DriveHub<HttpsConnector<HttpConnector>> {
+        let index = self.next_hub.fetch_add(1, Ordering::Relaxed) % self.hubs.len();
+        &self.hubs[index]
+    }
+
+    /// Run request built by `request` with retry.
+    ///
+    /// Only idempotent requests(GET) should be sent by this method.
+    async fn retry<T, F, Fut>(&self, mut request: F) -> Result<T, ProviderError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anni_google_drive3::Result<T>>,
+    {
+        let policy = &self.settings.retry;
+        let mut retry = 0;
+        loop {
+            let permit = self.semaphore.acquire().await.unwrap();
+            let result = request().await;
+            drop(permit);
+
+            match result {
+                Ok(result) => {
+                    if retry > 0 {
+                        log::debug!("Drive request succeeded after {retry} retries");
+                    }
+                    return Ok(result);
+                }
+                Err(e) if retry < policy.retries => match retryable(&e) {
+                    Some(retry_after) => {
+                        let delay = policy.delay(retry, retry_after);
+                        retry += 1;
+                        log::debug!(
+                            "Drive request failed, retrying in {delay:?} ({retry}/{}): {e}",
+                            policy.retries
+                        );
+                        drop(e);
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => return Err(e.into()),
+                },
+                Err(e) => {
+                    if retry > 0 {
+                        log::debug!("Drive request failed after {retry} retries: {e}");
+                    }
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
     fn prepare_list(&self) -> FileListCall<HttpsConnector<HttpConnector>> {
         let result = self
-            .hub
+            .hub()
             .files()
             .list()
             .corpora(&self.settings.corpora)
@@ -173,12 +339,15 @@ impl DriveClient {
     }
 
     async fn list_folder(&self, parent_id: &str) -> Result<FileList, ProviderError> {
-        let permit = self.semaphore.acquire().await.unwrap();
-        let (_, list) = self.prepare_list()
-            .q(&format!("mimeType = 'application/vnd.google-apps.folder' and trashed = false and '{parent_id}' in parents"))
-            .param("fields", "nextPageToken, files(id,name)")
-            .doit().await?;
-        drop(permit);
+        let q = format!("mimeType = 'application/vnd.google-apps.folder' and trashed = false and '{parent_id}' in parents");
+        let (_, list) = self
+            .retry(|| {
+                self.prepare_list()
+                    .q(&q)
+                    .param("fields", "nextPageToken, files(id,name)")
+                    .doit()
+            })
+            .await?;
         Ok(list)
     }
 
@@ -187,18 +356,18 @@ impl DriveClient {
         file_id: &str,
         range: &Range,
     ) -> Result<(ResourceReader, Range), ProviderError> {
-        let permit = self.semaphore.acquire().await.unwrap();
         let (resp, _) = self
-            .hub
-            .files()
-            .get(file_id)
-            .supports_all_drives(true)
-            .acknowledge_abuse(true)
-            .param("alt", "media")
-            .range(range.to_range_header())
-            .doit()
+            .retry(|| {
+                self.hub()
+                    .files()
+                    .get(file_id)
+                    .supports_all_drives(true)
+                    .acknowledge_abuse(true)
+                    .param("alt", "media")
+                    .range(range.to_range_header())
+                    .doit()
+            })
             .await?;
-        drop(permit);
         let content_range = resp
             .headers()
             .get("Content-Range")
@@ -223,12 +392,15 @@ impl DriveClient {
                 .ok_or(ProviderError::FileNotFound);
         }
 
-        let permit = self.semaphore.acquire().await.unwrap();
-        let (_, list) = self.prepare_list()
-            .q(&format!("trashed = false and mimeType = 'image/jpeg' and name = 'cover.jpg' and '{}' in parents", parent_id))
-            .param("fields", "nextPageToken, files(id,name)")
-            .doit().await?;
-        drop(permit);
+        let q = format!("trashed = false and mimeType = 'image/jpeg' and name = 'cover.jpg' and '{}' in parents", parent_id);
+        let (_, list) = self
+            .retry(|| {
+                self.prepare_list()
+                    .q(&q)
+                    .param("fields", "nextPageToken, files(id,name)")
+                    .doit()
+            })
+            .await?;
 
         let files = list.files.unwrap();
         let file = files.get(0).ok_or(ProviderError::FileNotFound)?;
@@ -259,6 +431,7 @@ pub struct DriveProvider {
     // properties
     strict: bool,
     repo: Mutex<Option<RepoDatabaseRead>>,
+    database: DatabaseState,
 }
 
 impl DriveProvider {
@@ -267,15 +440,28 @@ impl DriveProvider {
         settings: DriveProviderSettings,
         repo: Option<RepoDatabaseRead>,
         token_storage: impl Into<TokenStorage>,
+    ) -> Result<Self, ProviderError> {
+        Self::with_token_storages(auth, settings, repo, vec![token_storage.into()]).await
+    }
+
+    /// Create a provider with multiple tokens, which are rotated across requests.
+    ///
+    /// See [DriveClient::with_token_storages].
+    pub async fn with_token_storages(
+        auth: DriveAuth,
+        settings: DriveProviderSettings,
+        repo: Option<RepoDatabaseRead>,
+        token_storages: Vec<TokenStorage>,
     ) -> Result<Self, ProviderError> {
         let mut this = Self {
-            client: DriveClient::new(auth, settings, token_storage).await?,
+            client: DriveClient::with_token_storages(auth, settings, token_storages).await?,
             folders: Default::default(),
             discs: Default::default(),
             files: Default::default(),
             audios: Default::default(),
             strict: repo.is_none(),
             repo: Mutex::new(repo),
+            database: Default::default(),
         };
         this.reload().await?;
         Ok(this)
@@ -348,6 +534,7 @@ impl AnniProvider for DriveProvider {
         track_id: NonZeroU8,
         range: Range,
     ) -> Result<AudioResourceReader, ProviderError> {
+        self.database.check()?;
         // catalog not found
         if !self.folders.contains_key(album_id) {
             return Err(ProviderError::FileNotFound);
@@ -360,7 +547,6 @@ impl AnniProvider for DriveProvider {
             let folder_id = self.get_parent_folder(album_id, Some(disc_id));
 
             // get audio file id
-            let permit = self.client.semaphore.acquire().await.unwrap();
             let q = if self.strict {
                 format!("trashed = false and name = '{track_id}.flac' and '{folder_id}' in parents")
             } else {
@@ -368,12 +554,14 @@ impl AnniProvider for DriveProvider {
             };
             let (_, list) = self
                 .client
-                .prepare_list()
-                .q(&q)
-                .param("fields", "nextPageToken, files(id,name,fileExtension,size)")
-                .doit()
+                .retry(|| {
+                    self.client
+                        .prepare_list()
+                        .q(&q)
+                        .param("fields", "nextPageToken, files(id,name,fileExtension,size)")
+                        .doit()
+                })
                 .await?;
-            drop(permit);
 
             let files = list.files.unwrap();
             let id = if self.strict {
@@ -413,7 +601,7 @@ impl AnniProvider for DriveProvider {
                 let metadata = self.audios.get(&file_id).unwrap().value().clone(); // drop lock inline
 
                 let (reader, range) = self.client.get_file(&file_id, &range).await?;
-                let (duration, reader) = read_duration(reader, range).await?;
+                let (duration, reader) = read_duration(reader, range, &metadata.0).await?;
                 Ok(AudioResourceReader {
                     info: AudioInfo {
                         extension: metadata.0,
@@ -433,6 +621,7 @@ impl AnniProvider for DriveProvider {
         album_id: &str,
         disc_id: Option<NonZeroU8>,
     ) -> Result<ResourceReader, ProviderError> {
+        self.database.check()?;
         // album_id not found
         if !self.folders.contains_key(album_id) ||
             // disc not found
@@ -453,7 +642,14 @@ impl AnniProvider for DriveProvider {
                 let folder_id = self.get_parent_folder(album_id, disc_id);
 
                 // get cover file id
-                self.client.get_cover_id_in(&folder_id).await?
+                match self.client.get_cover_id_in(&folder_id).await {
+                    Ok(id) => id,
+                    // fallback to album cover
+                    Err(e) if disc_id.is_some() && e.is_not_found() => {
+                        return self.get_cover(album_id, None).await;
+                    }
+                    Err(e) => return Err(e),
+                }
             }
         };
 
@@ -467,26 +663,29 @@ impl AnniProvider for DriveProvider {
         self.audios.clear();
 
         if let Some(repo) = &mut *self.repo.lock() {
-            repo.reload()?;
+            if let Err(e) = repo.reload() {
+                return Err(self.database.fail(e));
+            }
         }
 
         let mut page_token = String::new();
         loop {
-            let permit = self.client.semaphore.acquire().await.unwrap();
             let (_, list) = self
                 .client
-                .prepare_list()
-                .page_token(&page_token)
-                .q(if self.strict {
-                    "mimeType = 'application/vnd.google-apps.folder' and name != '0' and name != '1' and name != '2' and name != '3' and name != '4' and name != '5' and name != '6' and name != '7' and name != '8' and name != '9' and trashed = false"
-                } else {
-                    "mimeType = 'application/vnd.google-apps.folder' and trashed = false"
+                .retry(|| {
+                    self.client
+                        .prepare_list()
+                        .page_token(&page_token)
+                        .q(if self.strict {
+                            "mimeType = 'application/vnd.google-apps.folder' and name != '0' and name != '1' and name != '2' and name != '3' and name != '4' and name != '5' and name != '6' and name != '7' and name != '8' and name != '9' and trashed = false"
+                        } else {
+                            "mimeType = 'application/vnd.google-apps.folder' and trashed = false"
+                        })
+                        .param("fields", "nextPageToken, files(id,name)")
+                        .page_size(1000)
+                        .doit()
                 })
-                .param("fields", "nextPageToken, files(id,name)")
-                .page_size(1000)
-                .doit()
                 .await?;
-            drop(permit);
             for file in list.files.unwrap() {
                 let name = file.name.unwrap();
                 if self.strict {
@@ -510,7 +709,8 @@ impl AnniProvider for DriveProvider {
                             disc_count as u8,
                             &title,
                             edition.as_deref(),
-                        )?;
+                        );
+                        let album_id = album_id.map_err(|e| self.database.fail(e))?;
                         match album_id {
                             Some(album_id) => {
                                 self.folders.insert(album_id.to_string(), file.id.unwrap());
@@ -531,6 +731,86 @@ impl AnniProvider for DriveProvider {
                 page_token = list.next_page_token.unwrap();
             }
         }
+        self.database.recover();
         Ok(())
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_range: true,
+            has_covers: true,
+            has_lyrics: false,
+            listable: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anni_google_drive3::Error;
+
+    fn failure(status: u16, retry_after: Option<&str>) -> Error {
+        let mut response = hyper::Response::builder().status(status);
+        if let Some(retry_after) = retry_after {
+            response = response.header("Retry-After", retry_after);
+        }
+        Error::Failure(response.body(hyper::Body::empty()).unwrap())
+    }
+
+    fn bad_request(code: u16, reason: &str) -> Error {
+        Error::BadRequest(serde_json::json!({
+            "error": {
+                "code": code,
+                "errors": [{ "reason": reason }],
+            }
+        }))
+    }
+
+    #[test]
+    fn test_retryable() {
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        assert_eq!(retryable(&Error::Io(io)), Some(None));
+
+        assert_eq!(retryable(&failure(503, None)), Some(None));
+        assert_eq!(
+            retryable(&failure(429, Some("7"))),
+            Some(Some(Duration::from_secs(7)))
+        );
+        assert_eq!(retryable(&failure(403, None)), None);
+        assert_eq!(retryable(&failure(404, None)), None);
+
+        assert_eq!(
+            retryable(&bad_request(403, "userRateLimitExceeded")),
+            Some(None)
+        );
+        assert_eq!(
+            retryable(&bad_request(403, "insufficientFilePermissions")),
+            None
+        );
+        assert_eq!(retryable(&bad_request(500, "backendError")), Some(None));
+        assert_eq!(retryable(&bad_request(404, "notFound")), None);
+        assert_eq!(retryable(&Error::BadRequest(serde_json::json!({}))), None);
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            retries: 10,
+            backoff: Duration::from_millis(500),
+            max_delay: Duration::from_secs(4),
+        };
+        assert_eq!(policy.delay(0, None), Duration::from_millis(500));
+        assert_eq!(policy.delay(1, None), Duration::from_secs(1));
+        assert_eq!(policy.delay(3, None), Duration::from_secs(4));
+        assert_eq!(policy.delay(40, None), Duration::from_secs(4));
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(2))),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(3600))),
+            Duration::from_secs(4)
+        );
+    }
 }
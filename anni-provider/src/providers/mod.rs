@@ -2,20 +2,38 @@
 pub use convention::CommonConventionProvider;
 #[cfg(feature = "drive")]
 pub use drive::DriveProvider;
-pub use multiple::MultipleProviders;
+pub use multiple::{MultipleProviders, Routing};
 pub use no_cache::NoCacheStrictLocalProvider;
 #[cfg(feature = "proxy")]
 pub use proxy::ProxyBackend;
+#[cfg(feature = "s3")]
+pub use s3::{S3Provider, S3Settings};
 #[cfg(feature = "strict")]
 pub use strict::CommonStrictProvider;
+pub use timeout::TimeoutProvider;
+#[cfg(feature = "webdav")]
+pub use webdav::{WebDavProvider, WebDavSettings};
+#[cfg(feature = "zip")]
+pub use zip::ZipProvider;
 
 #[cfg(feature = "convention")]
 mod convention;
+#[cfg(feature = "repo")]
+mod database;
 #[cfg(feature = "drive")]
 pub mod drive;
 mod multiple;
 mod no_cache;
 #[cfg(feature = "proxy")]
 mod proxy;
+#[cfg(any(feature = "s3", feature = "webdav"))]
+mod remote;
+#[cfg(feature = "s3")]
+mod s3;
 #[cfg(feature = "strict")]
 mod strict;
+mod timeout;
+#[cfg(feature = "webdav")]
+mod webdav;
+#[cfg(feature = "zip")]
+mod zip;
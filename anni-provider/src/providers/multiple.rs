@@ -1,15 +1,197 @@
-use crate::{AnniProvider, AudioInfo, AudioResourceReader, ProviderError, Range, ResourceReader};
+use crate::{
+    AnniProvider, AudioInfo, AudioResourceReader, Capabilities, ProviderError, Range,
+    ResourceReader,
+};
 use async_trait::async_trait;
+use futures::future::BoxFuture;
+use lru::LruCache;
+use parking_lot::Mutex;
 use std::borrow::Cow;
 use std::collections::HashSet;
-use std::num::NonZeroU8;
+use std::num::{NonZeroU8, NonZeroUsize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Time for a failed provider to be excluded from selection.
+const UNHEALTHY_DURATION: Duration = Duration::from_secs(30);
+
+/// Number of tracks whose provider is remembered.
+const PINNED_TRACKS: usize = 1024;
+
+type TrackKey = (String, NonZeroU8, NonZeroU8);
+
+/// How to choose a provider when multiple providers have the same album.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Routing {
+    /// Always use the first provider which has the album.
+    #[default]
+    Priority,
+    /// Take turns to use providers which have the album.
+    ///
+    /// A track keeps being served by the provider which served it last time while the
+    /// provider is healthy, so that audio info and ranges of the same track stay consistent.
+    RoundRobin,
+}
 
 /// [MultipleProviders] combines multiple anni providers as a whole.
-pub struct MultipleProviders(Vec<Box<dyn AnniProvider + Send + Sync>>);
+///
+/// A provider is marked as unhealthy for a while if it fails with errors other than
+/// [ProviderError::FileNotFound], and the request is retried by the next provider.
+/// Unhealthy providers are not used until [UNHEALTHY_DURATION] passes.
+pub struct MultipleProviders {
+    providers: Vec<Box<dyn AnniProvider + Send + Sync>>,
+    /// Names of providers, used to select a provider explicitly
+    names: Vec<String>,
+    routing: Routing,
+    /// Time until which each provider is considered unhealthy
+    unhealthy_until: Vec<Mutex<Option<Instant>>>,
+    next: AtomicUsize,
+    /// Index of provider which served each track last time
+    pinned: Mutex<LruCache<TrackKey, usize>>,
+}
 
 impl MultipleProviders {
     pub fn new(providers: Vec<Box<dyn AnniProvider + Send + Sync>>) -> Self {
-        Self(providers)
+        let unhealthy_until = providers.iter().map(|_| Mutex::new(None)).collect();
+        let names = (0..providers.len()).map(|i| i.to_string()).collect();
+        Self {
+            providers,
+            names,
+            routing: Routing::Priority,
+            unhealthy_until,
+            next: AtomicUsize::new(0),
+            pinned: Mutex::new(LruCache::new(NonZeroUsize::new(PINNED_TRACKS).unwrap())),
+        }
+    }
+
+    pub fn with_routing(mut self, routing: Routing) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Name providers in order, which defaults to their indexes.
+    pub fn with_names(mut self, names: Vec<String>) -> Self {
+        assert_eq!(
+            names.len(),
+            self.providers.len(),
+            "Number of names does not match number of providers"
+        );
+        self.names = names;
+        self
+    }
+
+    /// Add a provider named `name` after existing ones, which is healthy initially.
+    pub fn push(&mut self, name: String, provider: Box<dyn AnniProvider + Send + Sync>) {
+        self.providers.push(provider);
+        self.names.push(name);
+        self.unhealthy_until.push(Mutex::new(None));
+    }
+
+    /// Number of inner providers.
+    pub fn len(&self) -> usize {
+        self.providers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    /// Inner providers in order, with their names and whether they are in rotation.
+    ///
+    /// Providers marked as unhealthy are out of rotation until [UNHEALTHY_DURATION] passes.
+    pub fn providers(
+        &self,
+    ) -> impl Iterator<Item = (&str, &(dyn AnniProvider + Send + Sync), bool)> + '_ {
+        self.providers.iter().enumerate().map(|(index, provider)| {
+            (
+                self.names[index].as_str(),
+                provider.as_ref(),
+                self.is_healthy(index),
+            )
+        })
+    }
+
+    fn is_healthy(&self, index: usize) -> bool {
+        let mut until = self.unhealthy_until[index].lock();
+        match *until {
+            Some(time) if time > Instant::now() => false,
+            Some(_) => {
+                *until = None;
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// Send `request` to healthy providers which have the album in order, until one of them
+    /// succeeds or fails with [ProviderError::FileNotFound].
+    ///
+    /// If `track` is given, the provider which served it last time is tried first.
+    async fn route<'a, T>(
+        &'a self,
+        album_id: &'a str,
+        track: Option<TrackKey>,
+        request: impl Fn(&'a (dyn AnniProvider + Send + Sync)) -> BoxFuture<'a, crate::Result<T>>,
+    ) -> crate::Result<T> {
+        let mut candidates: Vec<usize> = (0..self.providers.len())
+            .filter(|&index| self.is_healthy(index))
+            .collect();
+        if self.routing == Routing::RoundRobin {
+            // providers without the album must not take a turn
+            let mut having = Vec::with_capacity(candidates.len());
+            for index in candidates {
+                if self.providers[index].has_album(album_id).await {
+                    having.push(index);
+                }
+            }
+            candidates = having;
+            if candidates.len() > 1 {
+                let start = self.next.fetch_add(1, Ordering::Relaxed) % candidates.len();
+                candidates.rotate_left(start);
+            }
+        }
+        let pinned = track
+            .as_ref()
+            .and_then(|track| self.pinned.lock().get(track).copied());
+        if let Some(position) =
+            pinned.and_then(|pinned| candidates.iter().position(|&i| i == pinned))
+        {
+            candidates[..=position].rotate_right(1);
+        }
+
+        let mut result = Err(ProviderError::FileNotFound);
+        for index in candidates {
+            let provider = self.providers[index].as_ref();
+            // albums are checked lazily in priority mode, as later providers are rarely used
+            if self.routing == Routing::Priority && !provider.has_album(album_id).await {
+                continue;
+            }
+            result = request(provider).await;
+            if !self.should_retry(index, &result) {
+                if let (Some(track), Ok(_)) = (track, &result) {
+                    self.pinned.lock().put(track, index);
+                }
+                break;
+            }
+        }
+        result
+    }
+
+    /// Update health of provider by the result of a request.
+    ///
+    /// Returns whether the request should be retried by other providers.
+    fn should_retry<T>(&self, index: usize, result: &crate::Result<T>) -> bool {
+        match result {
+            Ok(_) | Err(ProviderError::FileNotFound) => {
+                *self.unhealthy_until[index].lock() = None;
+                false
+            }
+            Err(e) => {
+                log::warn!("Provider {} is marked as unhealthy: {e}", self.names[index]);
+                *self.unhealthy_until[index].lock() = Some(Instant::now() + UNHEALTHY_DURATION);
+                true
+            }
+        }
     }
 }
 
@@ -17,14 +199,14 @@ impl MultipleProviders {
 impl AnniProvider for MultipleProviders {
     async fn albums(&self) -> crate::Result<HashSet<Cow<str>>> {
         let mut albums: HashSet<Cow<str>> = HashSet::new();
-        for provider in self.0.iter() {
+        for provider in self.providers.iter() {
             albums.extend(provider.albums().await?);
         }
         Ok(albums)
     }
 
     async fn has_album(&self, album_id: &str) -> bool {
-        for provider in self.0.iter() {
+        for provider in self.providers.iter() {
             if provider.has_album(album_id).await {
                 return true;
             }
@@ -39,13 +221,11 @@ impl AnniProvider for MultipleProviders {
         disc_id: NonZeroU8,
         track_id: NonZeroU8,
     ) -> crate::Result<AudioInfo> {
-        for provider in self.0.iter() {
-            if provider.has_album(album_id).await {
-                return provider.get_audio_info(album_id, disc_id, track_id).await;
-            }
-        }
-
-        Err(ProviderError::FileNotFound)
+        let track = (album_id.to_string(), disc_id, track_id);
+        self.route(album_id, Some(track), |provider| {
+            provider.get_audio_info(album_id, disc_id, track_id)
+        })
+        .await
     }
 
     async fn get_audio(
@@ -55,13 +235,11 @@ impl AnniProvider for MultipleProviders {
         track_id: NonZeroU8,
         range: Range,
     ) -> crate::Result<AudioResourceReader> {
-        for provider in self.0.iter() {
-            if provider.has_album(album_id).await {
-                return provider.get_audio(album_id, disc_id, track_id, range).await;
-            }
-        }
-
-        Err(ProviderError::FileNotFound)
+        let track = (album_id.to_string(), disc_id, track_id);
+        self.route(album_id, Some(track), |provider| {
+            provider.get_audio(album_id, disc_id, track_id, range)
+        })
+        .await
     }
 
     async fn get_cover(
@@ -69,20 +247,69 @@ impl AnniProvider for MultipleProviders {
         album_id: &str,
         disc_id: Option<NonZeroU8>,
     ) -> crate::Result<ResourceReader> {
-        for provider in self.0.iter() {
-            if provider.has_album(album_id).await {
-                return provider.get_cover(album_id, disc_id).await;
-            }
-        }
-
-        Err(ProviderError::FileNotFound)
+        self.route(album_id, None, |provider| {
+            provider.get_cover(album_id, disc_id)
+        })
+        .await
     }
 
     async fn reload(&mut self) -> crate::Result<()> {
-        for provider in self.0.iter_mut() {
+        for provider in self.providers.iter_mut() {
             provider.reload().await?;
         }
+        for until in self.unhealthy_until.iter() {
+            *until.lock() = None;
+        }
+        self.pinned.lock().clear();
 
         Ok(())
     }
+
+    /// Ranges are supported only if all providers support them,
+    /// while covers and lyrics are available if any provider has them.
+    fn capabilities(&self) -> Capabilities {
+        let capabilities: Vec<_> = self.providers.iter().map(|p| p.capabilities()).collect();
+        combine_capabilities(&capabilities)
+    }
+
+    /// Capabilities of healthy providers which may serve the album, combined like [Self::capabilities].
+    async fn album_capabilities(&self, album_id: &str) -> Capabilities {
+        let mut capabilities = Vec::new();
+        for (index, provider) in self.providers.iter().enumerate() {
+            if self.is_healthy(index) && provider.has_album(album_id).await {
+                capabilities.push(provider.album_capabilities(album_id).await);
+                // only the first one is used in priority mode
+                if self.routing == Routing::Priority {
+                    break;
+                }
+            }
+        }
+        combine_capabilities(&capabilities)
+    }
+
+    fn named_provider(&self, name: &str) -> Option<&(dyn AnniProvider + Send + Sync)> {
+        let index = self.names.iter().position(|n| n == name)?;
+        Some(self.providers[index].as_ref())
+    }
+
+    fn inner_providers(&self) -> Vec<(&str, &(dyn AnniProvider + Send + Sync))> {
+        self.names
+            .iter()
+            .map(String::as_str)
+            .zip(self.providers.iter().map(|p| p.as_ref()))
+            .collect()
+    }
+}
+
+/// Combine capabilities of providers, which are conservative if there's no provider.
+fn combine_capabilities(capabilities: &[Capabilities]) -> Capabilities {
+    if capabilities.is_empty() {
+        return Capabilities::CONSERVATIVE;
+    }
+    Capabilities {
+        supports_range: capabilities.iter().all(|c| c.supports_range),
+        has_covers: capabilities.iter().any(|c| c.has_covers),
+        has_lyrics: capabilities.iter().any(|c| c.has_lyrics),
+        listable: capabilities.iter().all(|c| c.listable),
+    }
 }
@@ -1,5 +1,6 @@
 use crate::{
-    strict_album_path, AnniProvider, AudioInfo, AudioResourceReader, Range, ResourceReader,
+    strict_album_path, AnniProvider, AudioInfo, AudioResourceReader, Capabilities, Range,
+    ResourceReader,
 };
 use std::borrow::Cow;
 use std::collections::{HashSet, VecDeque};
@@ -80,7 +81,7 @@ impl AnniProvider for NoCacheStrictLocalProvider {
         file.seek(SeekFrom::Start(range.start)).await?;
         let file = file.take(range.length_limit(file_size));
         let reader = Box::pin(file);
-        let (duration, reader) = crate::utils::read_duration(reader, range).await?;
+        let (duration, reader) = crate::utils::read_duration(reader, range, "flac").await?;
 
         Ok(AudioResourceReader {
             info: AudioInfo {
@@ -102,11 +103,15 @@ impl AnniProvider for NoCacheStrictLocalProvider {
         album_id: &str,
         disc_id: Option<NonZeroU8>,
     ) -> crate::Result<ResourceReader> {
-        let mut cover = strict_album_path(&self.root, album_id, self.layer);
-        if let Some(disc_id) = disc_id {
-            cover.push(disc_id.get().to_string());
+        let album = strict_album_path(&self.root, album_id, self.layer);
+        let mut cover = match disc_id {
+            Some(disc_id) => album.join(disc_id.get().to_string()).join("cover.jpg"),
+            None => album.join("cover.jpg"),
+        };
+        if !cover.exists() && disc_id.is_some() && cover.parent().is_some_and(|p| p.is_dir()) {
+            // disc exists but has no cover, fallback to album cover
+            cover = album.join("cover.jpg");
         }
-        cover.push(format!("cover.jpg"));
 
         if !cover.exists() {
             return Err(crate::ProviderError::FileNotFound);
@@ -119,4 +124,13 @@ impl AnniProvider for NoCacheStrictLocalProvider {
     async fn reload(&mut self) -> crate::Result<()> {
         Ok(())
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_range: true,
+            has_covers: true,
+            has_lyrics: false,
+            listable: true,
+        }
+    }
 }
@@ -1,5 +1,8 @@
 use crate::common::content_range_to_range;
-use crate::{AnniProvider, AudioInfo, AudioResourceReader, ProviderError, Range, ResourceReader};
+use crate::{
+    AnniProvider, AudioInfo, AudioResourceReader, Capabilities, ProviderError, Range,
+    ResourceReader,
+};
 use async_trait::async_trait;
 use futures::TryStreamExt;
 use reqwest::Response;
@@ -128,6 +131,15 @@ impl AnniProvider for ProxyBackend {
         // proxy provider does not need to be reloaded
         Ok(())
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_range: true,
+            has_covers: true,
+            has_lyrics: false,
+            listable: true,
+        }
+    }
 }
 
 fn audio_info_from_response(response: &Response) -> Result<AudioInfo, ProviderError> {
@@ -141,16 +153,13 @@ fn audio_info_from_response(response: &Response) -> Result<AudioInfo, ProviderEr
         None => "0",
     }
     .to_string();
-    let extension = match response.headers().get("Content-Type") {
-        Some(content_type) => {
-            let content_type = content_type.to_str().unwrap_or("audio/flac");
-            content_type
-                .strip_prefix("audio/")
-                .unwrap_or("flac")
-                .to_string()
-        }
-        None => "flac".to_string(),
-    };
+    let extension = response
+        .headers()
+        .get("Content-Type")
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(crate::format::extension)
+        .unwrap_or("flac")
+        .to_string();
     Ok(AudioInfo {
         extension,
         size: original_size
@@ -0,0 +1,80 @@
+//! Helpers shared by providers of file systems accessed over HTTP, like WebDAV and S3.
+
+use crate::{FileEntry, ProviderError};
+use futures::TryStreamExt;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::path::Path;
+use std::pin::Pin;
+use tokio::io::AsyncRead;
+use tokio_stream::{self as stream, Stream};
+
+/// Entry in a folder of remote file system.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct RemoteEntry {
+    /// Name of the entry, without parent path and trailing `/`
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Folders in `entries` as children of `parent`.
+pub(super) fn children(
+    parent: &Path,
+    entries: Vec<RemoteEntry>,
+) -> Pin<Box<dyn Stream<Item = FileEntry> + Send>> {
+    let parent = parent.to_path_buf();
+    Box::pin(stream::iter(entries.into_iter().filter_map(move |entry| {
+        if entry.is_dir {
+            Some(FileEntry {
+                path: parent.join(&entry.name),
+                name: entry.name,
+            })
+        } else {
+            None
+        }
+    })))
+}
+
+/// The first entry in `entries` whose name starts with `prefix`.
+pub(super) fn entry_by_prefix(
+    parent: &Path,
+    entries: Vec<RemoteEntry>,
+    prefix: &str,
+) -> crate::Result<FileEntry> {
+    entries
+        .into_iter()
+        .find(|entry| entry.name.starts_with(prefix))
+        .map(|entry| FileEntry {
+            path: parent.join(&entry.name),
+            name: entry.name,
+        })
+        .ok_or(ProviderError::FileNotFound)
+}
+
+/// Extension and size of audio at `path`, read from response of `HEAD` request `head`.
+pub(super) async fn audio_info(
+    path: &Path,
+    head: RequestBuilder,
+) -> crate::Result<(String, usize)> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let response = head.send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(ProviderError::FileNotFound);
+    }
+    let size = response
+        .error_for_status()?
+        .content_length()
+        .ok_or(ProviderError::GeneralError)?;
+    Ok((extension, size as usize))
+}
+
+/// Read body of `response` as it arrives, so that files are never buffered as a whole.
+pub(super) fn body_reader(response: Response) -> impl AsyncRead + Send + Unpin {
+    let body = response
+        .bytes_stream()
+        .map_err(|e| std::io::Error::other(e.to_string()))
+        .into_async_read();
+    tokio_util::compat::FuturesAsyncReadCompatExt::compat(body)
+}
@@ -0,0 +1,509 @@
+use super::remote::{self, RemoteEntry};
+use crate::{FileEntry, FileSystemProvider, ProviderError, Range, ResourceReader};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use quick_xml::events::Event;
+use reqwest::{Method, RequestBuilder, StatusCode};
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use tokio_stream::Stream;
+
+/// Characters to be escaped in uri and query string, all except unreserved characters
+const URI_ENCODE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Payload of requests made by provider is always empty, so it's not signed
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+pub struct S3Settings {
+    /// Endpoint of object storage, e.g. `https://s3.us-east-1.amazonaws.com` or `http://localhost:9000`
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Prefix of object keys, paths are resolved relative to it
+    pub prefix: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+/// File system on S3 compatible object storage.
+///
+/// Objects are accessed by path-style urls, and folders are common prefixes delimited by `/`.
+/// Use it with [CommonStrictProvider](super::CommonStrictProvider) or
+/// [CommonConventionProvider](super::CommonConventionProvider) to serve audio files.
+pub struct S3Provider {
+    endpoint: String,
+    host: String,
+    region: String,
+    bucket: String,
+    prefix: String,
+    access_key_id: String,
+    secret_access_key: String,
+    client: reqwest::Client,
+}
+
+impl S3Provider {
+    pub fn new(settings: S3Settings) -> Self {
+        let endpoint = settings.endpoint.trim_end_matches('/').to_string();
+        let host = endpoint
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .unwrap_or(&endpoint)
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let prefix = settings.prefix.trim_matches('/');
+        let prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{prefix}/")
+        };
+
+        Self {
+            endpoint,
+            host,
+            region: settings.region,
+            bucket: settings.bucket,
+            prefix,
+            access_key_id: settings.access_key_id,
+            secret_access_key: settings.secret_access_key,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Object key of `path`, without trailing `/`.
+    fn key_of(&self, path: &Path) -> String {
+        let segments: Vec<_> = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(segment) => Some(segment.to_string_lossy()),
+                _ => None,
+            })
+            .collect();
+        format!("{}{}", self.prefix, segments.join("/"))
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    /// Build a signed request with AWS Signature Version 4.
+    ///
+    /// `key` is an object key, or `None` for the bucket itself.
+    fn request(&self, method: Method, key: Option<&str>, query: &[(&str, &str)]) -> RequestBuilder {
+        let mut uri = format!("/{}", encode(&self.bucket));
+        if let Some(key) = key {
+            uri.push('/');
+            uri += &key.split('/').map(encode).collect::<Vec<_>>().join("/");
+        }
+        let query = canonical_query(query);
+
+        let datetime = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let headers = [
+            ("host", self.host.as_str()),
+            ("x-amz-content-sha256", UNSIGNED_PAYLOAD),
+            ("x-amz-date", datetime.as_str()),
+        ];
+        let signed_headers = signed_headers(&headers);
+        let canonical_request =
+            canonical_request(method.as_str(), &uri, &query, &headers, UNSIGNED_PAYLOAD);
+        let scope = scope(&datetime, &self.region);
+        let signature = signature(
+            &self.secret_access_key,
+            &self.region,
+            &datetime,
+            &canonical_request,
+        );
+
+        let url = if query.is_empty() {
+            format!("{}{uri}", self.endpoint)
+        } else {
+            format!("{}{uri}?{query}", self.endpoint)
+        };
+        self.client
+            .request(method, url)
+            .header("x-amz-content-sha256", UNSIGNED_PAYLOAD)
+            .header("x-amz-date", datetime)
+            .header(
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                    self.access_key_id
+                ),
+            )
+    }
+
+    /// List objects and common prefixes under `path` with ListObjectsV2.
+    async fn list(&self, path: &Path) -> crate::Result<Vec<RemoteEntry>> {
+        let mut prefix = self.key_of(path);
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+
+        let mut entries = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("list-type", "2"),
+                ("prefix", prefix.as_str()),
+                ("delimiter", "/"),
+            ];
+            if let Some(token) = &continuation_token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let response = self.request(Method::GET, None, &query).send().await?;
+            let body = response.error_for_status()?.text().await?;
+            let result = parse_list_result(&body)?;
+
+            entries.extend(result.entries.into_iter().filter_map(|mut entry| {
+                entry.name = entry
+                    .name
+                    .strip_prefix(&prefix)?
+                    .trim_end_matches('/')
+                    .to_string();
+                if entry.name.is_empty() {
+                    None
+                } else {
+                    Some(entry)
+                }
+            }));
+            match result.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[async_trait]
+impl FileSystemProvider for S3Provider {
+    async fn children(
+        &self,
+        path: &PathBuf,
+    ) -> crate::Result<Pin<Box<dyn Stream<Item = FileEntry> + Send>>> {
+        Ok(remote::children(path, self.list(path).await?))
+    }
+
+    async fn get_file_entry_by_prefix(
+        &self,
+        parent: &PathBuf,
+        prefix: &str,
+    ) -> crate::Result<FileEntry> {
+        remote::entry_by_prefix(parent, self.list(parent).await?, prefix)
+    }
+
+    async fn get_file(&self, path: &PathBuf, range: Range) -> crate::Result<ResourceReader> {
+        let key = self.key_of(path);
+        let mut req = self.request(Method::GET, Some(&key), &[]);
+        if let Some(range) = range.to_range_header() {
+            req = req.header("Range", range);
+        }
+        let response = req.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(ProviderError::FileNotFound);
+        }
+
+        Ok(Box::pin(remote::body_reader(response.error_for_status()?)))
+    }
+
+    async fn get_audio_info(&self, path: &PathBuf) -> crate::Result<(String, usize)> {
+        let key = self.key_of(path);
+        remote::audio_info(path, self.request(Method::HEAD, Some(&key), &[])).await
+    }
+
+    async fn reload(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+fn encode(input: &str) -> String {
+    utf8_percent_encode(input, URI_ENCODE).to_string()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Canonical query string of AWS Signature Version 4, sorted by encoded names and values.
+fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut query: Vec<_> = query
+        .iter()
+        .map(|(key, value)| (encode(key), encode(value)))
+        .collect();
+    query.sort();
+    query
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Names of `headers` joined by `;`.
+fn signed_headers(headers: &[(&str, &str)]) -> String {
+    headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Canonical request of AWS Signature Version 4.
+///
+/// `headers` are signed headers with lowercase names, sorted by names.
+fn canonical_request(
+    method: &str,
+    uri: &str,
+    query: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+) -> String {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    format!(
+        "{method}\n{uri}\n{query}\n{canonical_headers}\n{}\n{payload_hash}",
+        signed_headers(headers)
+    )
+}
+
+/// Credential scope of requests signed at `datetime`, which is formatted as `%Y%m%dT%H%M%SZ`.
+fn scope(datetime: &str, region: &str) -> String {
+    format!("{}/{region}/s3/aws4_request", &datetime[..8])
+}
+
+/// Signature of `canonical_request` signed at `datetime`, in hex.
+fn signature(
+    secret_access_key: &str,
+    region: &str,
+    datetime: &str,
+    canonical_request: &str,
+) -> String {
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{datetime}\n{}\n{}",
+        scope(datetime, region),
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let signing_key = hmac_sha256(
+        format!("AWS4{secret_access_key}").as_bytes(),
+        &datetime.as_bytes()[..8],
+    );
+    let signing_key = hmac_sha256(&signing_key, region.as_bytes());
+    let signing_key = hmac_sha256(&signing_key, b"s3");
+    let signing_key = hmac_sha256(&signing_key, b"aws4_request");
+    hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()))
+}
+
+#[derive(Debug, Default)]
+struct ListResult {
+    /// Objects and common prefixes, named by their full keys
+    entries: Vec<RemoteEntry>,
+    next_continuation_token: Option<String>,
+}
+
+/// Parse `ListBucketResult` response of ListObjectsV2 request.
+fn parse_list_result(body: &str) -> crate::Result<ListResult> {
+    let invalid = |e: quick_xml::Error| {
+        ProviderError::IOError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            e.to_string(),
+        ))
+    };
+
+    let mut reader = quick_xml::Reader::from_str(body);
+    reader.trim_text(true);
+
+    let mut result = ListResult::default();
+    let mut truncated = false;
+    // name of the element whose text is being read, and whether it's in `CommonPrefixes`
+    let mut element = Vec::new();
+    let mut in_common_prefixes = false;
+    loop {
+        match reader.read_event().map_err(&invalid)? {
+            Event::Start(e) => {
+                element = e.local_name().as_ref().to_vec();
+                if element == b"CommonPrefixes" {
+                    in_common_prefixes = true;
+                }
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(&invalid)?.to_string();
+                match element.as_slice() {
+                    b"Key" => result.entries.push(RemoteEntry {
+                        name: text,
+                        is_dir: false,
+                    }),
+                    b"Prefix" if in_common_prefixes => result.entries.push(RemoteEntry {
+                        name: text,
+                        is_dir: true,
+                    }),
+                    b"IsTruncated" => truncated = text == "true",
+                    b"NextContinuationToken" => result.next_continuation_token = Some(text),
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                if e.local_name().as_ref() == b"CommonPrefixes" {
+                    in_common_prefixes = false;
+                }
+                element.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !truncated {
+        result.next_continuation_token = None;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Example secret access key in AWS documentation.
+    const SECRET_ACCESS_KEY: &str = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+    const EMPTY_PAYLOAD_HASH: &str =
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    // test vectors from https://docs.aws.amazon.com/AmazonS3/latest/API/sig-v4-header-based-auth.html
+    #[test]
+    fn test_sign_get_object() {
+        let headers = [
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("range", "bytes=0-9"),
+            ("x-amz-content-sha256", EMPTY_PAYLOAD_HASH),
+            ("x-amz-date", "20130524T000000Z"),
+        ];
+        let canonical_request =
+            canonical_request("GET", "/test.txt", "", &headers, EMPTY_PAYLOAD_HASH);
+        assert_eq!(
+            canonical_request,
+            "GET
+/test.txt
+
+host:examplebucket.s3.amazonaws.com
+range:bytes=0-9
+x-amz-content-sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855
+x-amz-date:20130524T000000Z
+
+host;range;x-amz-content-sha256;x-amz-date
+e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            signature(
+                SECRET_ACCESS_KEY,
+                "us-east-1",
+                "20130524T000000Z",
+                &canonical_request
+            ),
+            "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41"
+        );
+    }
+
+    #[test]
+    fn test_sign_list_objects() {
+        let headers = [
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("x-amz-content-sha256", EMPTY_PAYLOAD_HASH),
+            ("x-amz-date", "20130524T000000Z"),
+        ];
+        let query = canonical_query(&[("prefix", "J"), ("max-keys", "2")]);
+        assert_eq!(query, "max-keys=2&prefix=J");
+        let canonical_request = canonical_request("GET", "/", &query, &headers, EMPTY_PAYLOAD_HASH);
+        assert_eq!(
+            signature(
+                SECRET_ACCESS_KEY,
+                "us-east-1",
+                "20130524T000000Z",
+                &canonical_request
+            ),
+            "34b48302e7b5fa45bde8084f4b7868a86f0a534bc59db6670ed5711ef69dc6f7"
+        );
+    }
+
+    #[test]
+    fn test_canonical_query() {
+        // names are sorted before values, even if a name is a prefix of another
+        assert_eq!(
+            canonical_query(&[("prefix", "a b/"), ("list-type", "2"), ("prefix-x", "")]),
+            "list-type=2&prefix=a%20b%2F&prefix-x="
+        );
+    }
+
+    #[test]
+    fn test_parse_list_result() {
+        let result = parse_list_result(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>bucket</Name>
+  <Prefix>music/</Prefix>
+  <KeyCount>3</KeyCount>
+  <MaxKeys>1000</MaxKeys>
+  <Delimiter>/</Delimiter>
+  <IsTruncated>true</IsTruncated>
+  <NextContinuationToken>1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=</NextContinuationToken>
+  <Contents>
+    <Key>music/cover &amp; back.jpg</Key>
+    <Size>1024</Size>
+  </Contents>
+  <CommonPrefixes>
+    <Prefix>music/[2023-01-01] Album/</Prefix>
+  </CommonPrefixes>
+  <CommonPrefixes>
+    <Prefix>music/Another/</Prefix>
+  </CommonPrefixes>
+</ListBucketResult>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result.entries,
+            [
+                RemoteEntry {
+                    name: "music/cover & back.jpg".to_string(),
+                    is_dir: false,
+                },
+                RemoteEntry {
+                    name: "music/[2023-01-01] Album/".to_string(),
+                    is_dir: true,
+                },
+                RemoteEntry {
+                    name: "music/Another/".to_string(),
+                    is_dir: true,
+                },
+            ]
+        );
+        assert_eq!(
+            result.next_continuation_token.as_deref(),
+            Some("1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=")
+        );
+    }
+
+    #[test]
+    fn test_parse_list_result_not_truncated() {
+        let result = parse_list_result(
+            r#"<ListBucketResult>
+  <Prefix></Prefix>
+  <IsTruncated>false</IsTruncated>
+  <NextContinuationToken>token</NextContinuationToken>
+</ListBucketResult>"#,
+        )
+        .unwrap();
+        assert!(result.entries.is_empty());
+        assert_eq!(result.next_continuation_token, None);
+    }
+
+    #[test]
+    fn test_parse_list_result_invalid() {
+        assert!(parse_list_result("<ListBucketResult><Key>a</Prefix>").is_err());
+    }
+}
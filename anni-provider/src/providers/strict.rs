@@ -1,6 +1,6 @@
 use crate::{
-    AnniProvider, AudioResourceReader, FileEntry, FileSystemProvider, ProviderError, Range,
-    ResourceReader, Result,
+    AnniProvider, AudioResourceReader, Capabilities, FileEntry, FileSystemProvider, ProviderError,
+    Range, ResourceReader, Result,
 };
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -67,11 +67,16 @@ impl AnniProvider for CommonStrictProvider {
         match disc_id {
             Some(disc_id) => {
                 let disc = self.get_disc(album_id, disc_id).await?;
-                let cover = self
+                match self
                     .fs
                     .get_file_entry_by_prefix(&disc.path, "cover.jpg")
-                    .await?;
-                self.fs.get_file(&cover.path, Range::FULL).await
+                    .await
+                {
+                    Ok(cover) => self.fs.get_file(&cover.path, Range::FULL).await,
+                    // fallback to album cover
+                    Err(e) if e.is_not_found() => self.get_cover(album_id, None).await,
+                    Err(e) => Err(e),
+                }
             }
             None => {
                 let album = self
@@ -92,6 +97,15 @@ impl AnniProvider for CommonStrictProvider {
         self.reload_albums().await?;
         Ok(())
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_range: true,
+            has_covers: true,
+            has_lyrics: false,
+            listable: true,
+        }
+    }
 }
 
 impl CommonStrictProvider {
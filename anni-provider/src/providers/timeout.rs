@@ -0,0 +1,104 @@
+use crate::{
+    AnniProvider, AudioInfo, AudioResourceReader, Capabilities, ProviderError, Range,
+    ResourceReader,
+};
+use async_trait::async_trait;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::future::Future;
+use std::num::NonZeroU8;
+use std::time::Duration;
+
+/// [TimeoutProvider] limits the time the inner provider takes to fetch audio, audio info or covers.
+///
+/// Only the time to get a reader is limited, while reading from the returned reader is not.
+/// Timed out requests fail with [ProviderError::Timeout].
+pub struct TimeoutProvider {
+    inner: Box<dyn AnniProvider + Send + Sync>,
+    /// Name of the inner provider, used in logs and errors
+    name: String,
+    timeout: Duration,
+}
+
+impl TimeoutProvider {
+    pub fn new(
+        inner: Box<dyn AnniProvider + Send + Sync>,
+        name: String,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            name,
+            timeout,
+        }
+    }
+
+    async fn run<T>(&self, future: impl Future<Output = crate::Result<T>>) -> crate::Result<T> {
+        match tokio::time::timeout(self.timeout, future).await {
+            Ok(result) => result,
+            Err(_) => {
+                log::warn!(
+                    "Provider {} timed out after {}ms",
+                    self.name,
+                    self.timeout.as_millis()
+                );
+                Err(ProviderError::Timeout {
+                    provider: self.name.clone(),
+                    timeout: self.timeout,
+                })
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AnniProvider for TimeoutProvider {
+    async fn albums(&self) -> crate::Result<HashSet<Cow<str>>> {
+        self.inner.albums().await
+    }
+
+    async fn has_album(&self, album_id: &str) -> bool {
+        self.inner.has_album(album_id).await
+    }
+
+    async fn get_audio_info(
+        &self,
+        album_id: &str,
+        disc_id: NonZeroU8,
+        track_id: NonZeroU8,
+    ) -> crate::Result<AudioInfo> {
+        self.run(self.inner.get_audio_info(album_id, disc_id, track_id))
+            .await
+    }
+
+    async fn get_audio(
+        &self,
+        album_id: &str,
+        disc_id: NonZeroU8,
+        track_id: NonZeroU8,
+        range: Range,
+    ) -> crate::Result<AudioResourceReader> {
+        self.run(self.inner.get_audio(album_id, disc_id, track_id, range))
+            .await
+    }
+
+    async fn get_cover(
+        &self,
+        album_id: &str,
+        disc_id: Option<NonZeroU8>,
+    ) -> crate::Result<ResourceReader> {
+        self.run(self.inner.get_cover(album_id, disc_id)).await
+    }
+
+    async fn reload(&mut self) -> crate::Result<()> {
+        self.inner.reload().await
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn album_capabilities(&self, album_id: &str) -> Capabilities {
+        self.inner.album_capabilities(album_id).await
+    }
+}
@@ -0,0 +1,329 @@
+use super::remote::{self, RemoteEntry};
+use crate::{FileEntry, FileSystemProvider, ProviderError, Range, ResourceReader};
+use async_trait::async_trait;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use quick_xml::events::Event;
+use reqwest::{Method, RequestBuilder, StatusCode};
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use tokio::io::AsyncReadExt;
+use tokio_stream::Stream;
+
+/// Characters to be escaped in a path segment of WebDAV url
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b']')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:propfind xmlns:D="DAV:"><D:prop><D:resourcetype/><D:getcontentlength/></D:prop></D:propfind>"#;
+
+pub struct WebDavSettings {
+    /// Base url of WebDAV server, paths are resolved relative to it
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// File system on a WebDAV server.
+///
+/// Paths are absolute paths relative to the configured url, e.g. `/Album/01. Track.flac`.
+/// Use it with [CommonStrictProvider](super::CommonStrictProvider) or
+/// [CommonConventionProvider](super::CommonConventionProvider) to serve audio files.
+pub struct WebDavProvider {
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    client: reqwest::Client,
+}
+
+impl WebDavProvider {
+    pub fn new(settings: WebDavSettings) -> Self {
+        Self {
+            url: settings.url.trim_end_matches('/').to_string(),
+            username: settings.username,
+            password: settings.password,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn request(&self, method: Method, path: &Path) -> RequestBuilder {
+        let req = self.client.request(method, self.url_of(path));
+        match &self.username {
+            Some(username) => req.basic_auth(username, self.password.as_ref()),
+            None => req,
+        }
+    }
+
+    fn url_of(&self, path: &Path) -> String {
+        let mut url = self.url.clone();
+        for component in path.components() {
+            if let Component::Normal(segment) = component {
+                url.push('/');
+                url.extend(utf8_percent_encode(
+                    &segment.to_string_lossy(),
+                    PATH_SEGMENT,
+                ));
+            }
+        }
+        url
+    }
+
+    /// List entries in `path`, excluding itself.
+    async fn list(&self, path: &Path) -> crate::Result<Vec<RemoteEntry>> {
+        let response = self
+            .request(Method::from_bytes(b"PROPFIND").unwrap(), path)
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml")
+            .body(PROPFIND_BODY)
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(ProviderError::FileNotFound);
+        }
+        let body = response.error_for_status()?.text().await?;
+
+        // responses contain the requested collection itself
+        let this = href_path(&self.url_of(path));
+        let this = this.trim_end_matches('/');
+        let entries = parse_multistatus(&body)?
+            .into_iter()
+            .filter(|entry| !entry.name.is_empty() && entry.href.trim_end_matches('/') != this)
+            .map(|entry| RemoteEntry {
+                name: entry.name,
+                is_dir: entry.is_dir,
+            })
+            .collect();
+        Ok(entries)
+    }
+}
+
+#[async_trait]
+impl FileSystemProvider for WebDavProvider {
+    async fn children(
+        &self,
+        path: &PathBuf,
+    ) -> crate::Result<Pin<Box<dyn Stream<Item = FileEntry> + Send>>> {
+        Ok(remote::children(path, self.list(path).await?))
+    }
+
+    async fn get_file_entry_by_prefix(
+        &self,
+        parent: &PathBuf,
+        prefix: &str,
+    ) -> crate::Result<FileEntry> {
+        remote::entry_by_prefix(parent, self.list(parent).await?, prefix)
+    }
+
+    async fn get_file(&self, path: &PathBuf, range: Range) -> crate::Result<ResourceReader> {
+        let mut req = self.request(Method::GET, path);
+        if let Some(range) = range.to_range_header() {
+            req = req.header("Range", range);
+        }
+        let response = req.send().await?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Err(ProviderError::FileNotFound);
+        }
+        let response = response.error_for_status()?;
+        let size = response.content_length();
+
+        let mut body = remote::body_reader(response);
+        if status == StatusCode::PARTIAL_CONTENT || range.is_full() {
+            return Ok(Box::pin(body));
+        }
+
+        // server ignored Range header, skip and limit the full body by ourselves
+        tokio::io::copy(&mut (&mut body).take(range.start), &mut tokio::io::sink()).await?;
+        Ok(Box::pin(body.take(fallback_limit(range, size))))
+    }
+
+    async fn get_audio_info(&self, path: &PathBuf) -> crate::Result<(String, usize)> {
+        remote::audio_info(path, self.request(Method::HEAD, path)).await
+    }
+
+    async fn reload(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+/// Length of `range` to read from full body of `size` bytes, after skipping bytes before it.
+///
+/// Ranges beyond the body, including any range of an empty body, are empty.
+fn fallback_limit(range: Range, size: Option<u64>) -> u64 {
+    match (range.end, size) {
+        (Some(_), Some(size)) => size
+            .checked_sub(1)
+            .map_or(0, |last| range.length_limit(last)),
+        (Some(end), None) => end.checked_sub(range.start).map_or(0, |length| length + 1),
+        (None, _) => u64::MAX,
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct DavEntry {
+    /// Decoded href of the entry, without scheme and host
+    href: String,
+    /// Last segment of href
+    name: String,
+    is_dir: bool,
+}
+
+/// Decoded path of `href`, which may be an absolute url.
+fn href_path(href: &str) -> String {
+    let path = match href.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|index| &rest[index..]).unwrap_or("/"),
+        None => href,
+    };
+    percent_decode_str(path).decode_utf8_lossy().to_string()
+}
+
+/// Parse `multistatus` response of PROPFIND request.
+fn parse_multistatus(body: &str) -> crate::Result<Vec<DavEntry>> {
+    let invalid = |e: quick_xml::Error| {
+        ProviderError::IOError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            e.to_string(),
+        ))
+    };
+
+    let mut reader = quick_xml::Reader::from_str(body);
+    reader.trim_text(true);
+
+    let mut entries = Vec::new();
+    let mut current: Option<DavEntry> = None;
+    let mut in_href = false;
+    loop {
+        match reader.read_event().map_err(&invalid)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"response" => current = Some(DavEntry::default()),
+                b"href" => in_href = true,
+                b"collection" => {
+                    if let Some(entry) = current.as_mut() {
+                        entry.is_dir = true;
+                    }
+                }
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"collection" => {
+                if let Some(entry) = current.as_mut() {
+                    entry.is_dir = true;
+                }
+            }
+            Event::Text(text) if in_href => {
+                if let Some(entry) = current.as_mut() {
+                    let href = text.unescape().map_err(&invalid)?;
+                    let href = href_path(&href);
+                    entry.name = href
+                        .trim_end_matches('/')
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or_default()
+                        .to_string();
+                    entry.href = href;
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"href" => in_href = false,
+                b"response" => entries.extend(current.take()),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_href_path() {
+        assert_eq!(href_path("/dav/Album%20A/"), "/dav/Album A/");
+        assert_eq!(
+            href_path("https://example.com/dav/%5B2023%5D%20Album/01.flac"),
+            "/dav/[2023] Album/01.flac"
+        );
+        assert_eq!(href_path("https://example.com"), "/");
+    }
+
+    #[test]
+    fn test_parse_multistatus() {
+        let entries = parse_multistatus(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<D:multistatus xmlns:D="DAV:">
+  <D:response>
+    <D:href>/dav/</D:href>
+    <D:propstat>
+      <D:prop><D:resourcetype><D:collection/></D:resourcetype></D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+  </D:response>
+  <D:response>
+    <D:href>https://example.com/dav/Album%20A/</D:href>
+    <D:propstat>
+      <D:prop><D:resourcetype><D:collection></D:collection></D:resourcetype></D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+  </D:response>
+  <D:response>
+    <D:href>/dav/cover%20&amp;%20back.jpg</D:href>
+    <D:propstat>
+      <D:prop><D:resourcetype/><D:getcontentlength>1024</D:getcontentlength></D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+  </D:response>
+</D:multistatus>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            entries,
+            [
+                DavEntry {
+                    href: "/dav/".to_string(),
+                    name: "dav".to_string(),
+                    is_dir: true,
+                },
+                DavEntry {
+                    href: "/dav/Album A/".to_string(),
+                    name: "Album A".to_string(),
+                    is_dir: true,
+                },
+                DavEntry {
+                    href: "/dav/cover & back.jpg".to_string(),
+                    name: "cover & back.jpg".to_string(),
+                    is_dir: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_multistatus_invalid() {
+        assert!(parse_multistatus("<D:multistatus><D:href>/</D:response>").is_err());
+    }
+
+    #[test]
+    fn test_fallback_limit() {
+        let range = Range::new(10, Some(19));
+        assert_eq!(fallback_limit(range, Some(100)), 10);
+        assert_eq!(fallback_limit(range, Some(15)), 5);
+        assert_eq!(fallback_limit(range, Some(5)), 0);
+        assert_eq!(fallback_limit(range, Some(0)), 0);
+        assert_eq!(fallback_limit(range, None), 10);
+        assert_eq!(fallback_limit(Range::new(10, None), Some(100)), u64::MAX);
+        assert_eq!(fallback_limit(Range::new(10, Some(5)), None), 0);
+    }
+}
@@ -0,0 +1,468 @@
+use crate::{
+    AnniProvider, AudioInfo, AudioResourceReader, Capabilities, ProviderError, Range,
+    ResourceReader,
+};
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::num::{NonZeroU8, NonZeroUsize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::StreamReader;
+use uuid::Uuid;
+
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014b50;
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+/// Header id of ZIP64 extended information extra field
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+/// Size of ZIP64 end of central directory record, without extensible data
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE: u64 = 56;
+/// Size of ZIP64 end of central directory locator
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE: u64 = 20;
+/// Size of end of central directory record, without comment
+const END_OF_CENTRAL_DIRECTORY_SIZE: u64 = 22;
+/// Max size of end of central directory record, with the longest comment
+const END_OF_CENTRAL_DIRECTORY_MAX_SIZE: u64 = END_OF_CENTRAL_DIRECTORY_SIZE + u16::MAX as u64;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// Max number of archives whose central directories are kept in memory
+const MAX_CACHED_ARCHIVES: usize = 64;
+/// Size of chunks sent from the thread decompressing deflated entries
+const INFLATE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// `ZipProvider` serves albums packed in ZIP archives, without unpacking them.
+///
+/// Each album is an archive named `{album_id}.zip` in `root`. Entries in the archive
+/// follow the strict layout: `{disc_id}/{track_id}.flac` for tracks, `cover.jpg` for album cover
+/// and `{disc_id}/cover.jpg` for disc covers.
+///
+/// Archives are indexed when they are requested for the first time, and indexes of
+/// recently used archives are kept. Stored entries are read from their offset directly,
+/// while deflated entries have to be decompressed from the beginning, which is slow
+/// for ranges far from the start.
+pub struct ZipProvider {
+    root: PathBuf,
+    /// album_id <-> path of archive
+    albums: HashMap<String, PathBuf>,
+    /// album_id <-> entries of recently used archives
+    archives: Mutex<LruCache<String, Arc<ZipArchive>>>,
+}
+
+impl ZipProvider {
+    pub async fn new(root: PathBuf) -> crate::Result<Self> {
+        let mut me = Self {
+            root,
+            albums: HashMap::new(),
+            archives: Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_CACHED_ARCHIVES).unwrap(),
+            )),
+        };
+        me.reload().await?;
+        Ok(me)
+    }
+
+    async fn archive(&self, album_id: &str) -> crate::Result<Arc<ZipArchive>> {
+        if let Some(archive) = self.archives.lock().get(album_id) {
+            return Ok(archive.clone());
+        }
+
+        let path = self
+            .albums
+            .get(album_id)
+            .ok_or(ProviderError::FileNotFound)?;
+        let archive = Arc::new(ZipArchive::open(path.clone()).await?);
+        self.archives
+            .lock()
+            .put(album_id.to_string(), archive.clone());
+        Ok(archive)
+    }
+}
+
+#[async_trait::async_trait]
+impl AnniProvider for ZipProvider {
+    async fn albums(&self) -> crate::Result<HashSet<Cow<str>>> {
+        Ok(self
+            .albums
+            .keys()
+            .map(|album_id| Cow::Borrowed(album_id.as_str()))
+            .collect())
+    }
+
+    async fn get_audio(
+        &self,
+        album_id: &str,
+        disc_id: NonZeroU8,
+        track_id: NonZeroU8,
+        range: Range,
+    ) -> crate::Result<AudioResourceReader> {
+        let archive = self.archive(album_id).await?;
+        let (name, entry) = archive
+            .track(disc_id, track_id)
+            .ok_or(ProviderError::FileNotFound)?;
+        let extension = name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_string())
+            .unwrap_or_default();
+
+        let size = entry.size;
+        let (start, end) = match size.checked_sub(1) {
+            Some(last) if range.start <= last => {
+                (range.start, range.end.map_or(last, |end| end.min(last)))
+            }
+            // empty entry, or range beyond the end of entry
+            _ => (range.start, range.start.saturating_sub(1)),
+        };
+        let reader = archive.read(entry, start, end).await?;
+        let (duration, reader) = crate::utils::read_duration(reader, range, &extension).await?;
+
+        Ok(AudioResourceReader {
+            info: AudioInfo {
+                extension,
+                size: size as usize,
+                duration,
+            },
+            range: Range {
+                start,
+                end: Some(end),
+                total: Some(size),
+            },
+            reader,
+        })
+    }
+
+    async fn get_cover(
+        &self,
+        album_id: &str,
+        disc_id: Option<NonZeroU8>,
+    ) -> crate::Result<ResourceReader> {
+        let archive = self.archive(album_id).await?;
+        let entry = match disc_id {
+            Some(disc_id) => match archive.entries.get(&format!("{disc_id}/cover.jpg")) {
+                Some(entry) => entry,
+                // disc exists but has no cover, fallback to album cover
+                None if archive.has_disc(disc_id) => archive
+                    .entries
+                    .get("cover.jpg")
+                    .ok_or(ProviderError::FileNotFound)?,
+                None => return Err(ProviderError::FileNotFound),
+            },
+            None => archive
+                .entries
+                .get("cover.jpg")
+                .ok_or(ProviderError::FileNotFound)?,
+        };
+
+        match entry.size.checked_sub(1) {
+            Some(last) => archive.read(entry, 0, last).await,
+            None => Ok(Box::pin(tokio::io::empty())),
+        }
+    }
+
+    async fn reload(&mut self) -> crate::Result<()> {
+        self.albums.clear();
+        self.archives.lock().clear();
+
+        let mut dir = tokio::fs::read_dir(&self.root).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            if !path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+            {
+                continue;
+            }
+
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            match Uuid::parse_str(&stem) {
+                Ok(album_id) => {
+                    self.albums.insert(album_id.to_string(), path);
+                }
+                Err(_) => log::warn!("Unexpected archive: {path:?}"),
+            }
+        }
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_range: true,
+            has_covers: true,
+            has_lyrics: false,
+            listable: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ZipEntry {
+    method: u16,
+    compressed_size: u64,
+    /// Uncompressed size
+    size: u64,
+    /// Offset of local file header
+    header_offset: u64,
+}
+
+/// Central directory of a ZIP archive.
+struct ZipArchive {
+    path: PathBuf,
+    /// name <-> entry, directories are not included
+    entries: HashMap<String, ZipEntry>,
+}
+
+impl ZipArchive {
+    /// Read central directory of archive at `path`.
+    async fn open(path: PathBuf) -> crate::Result<Self> {
+        let mut file = tokio::fs::File::open(&path).await?;
+        let file_size = file.metadata().await?.len();
+        if file_size < END_OF_CENTRAL_DIRECTORY_SIZE {
+            return Err(invalid_archive("archive is too small"));
+        }
+
+        // find end of central directory record from the end of file
+        let tail_size = file_size.min(END_OF_CENTRAL_DIRECTORY_MAX_SIZE);
+        let mut tail = vec![0; tail_size as usize];
+        file.seek(SeekFrom::Start(file_size - tail_size)).await?;
+        file.read_exact(&mut tail).await?;
+        let eocd = (0..=tail.len() - END_OF_CENTRAL_DIRECTORY_SIZE as usize)
+            .rev()
+            .find(|&i| read_u32(&tail, i) == END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+            .ok_or_else(|| invalid_archive("end of central directory not found"))?;
+        let mut directory_size = read_u32(&tail, eocd + 12) as u64;
+        let mut directory_offset = read_u32(&tail, eocd + 16) as u64;
+        if directory_offset == u32::MAX as u64 || directory_size == u32::MAX as u64 {
+            let eocd_offset = file_size - tail_size + eocd as u64;
+            (directory_size, directory_offset) = read_zip64_eocd(&mut file, eocd_offset).await?;
+        }
+        // sizes in headers are untrusted, do not allocate more than the file
+        if directory_offset
+            .checked_add(directory_size)
+            .is_none_or(|end| end > file_size)
+        {
+            return Err(invalid_archive("central directory is out of archive"));
+        }
+
+        let mut directory = vec![0; directory_size as usize];
+        file.seek(SeekFrom::Start(directory_offset)).await?;
+        file.read_exact(&mut directory).await?;
+
+        let mut entries = HashMap::new();
+        let mut offset = 0;
+        while offset + 46 <= directory.len() {
+            if read_u32(&directory, offset) != CENTRAL_DIRECTORY_SIGNATURE {
+                return Err(invalid_archive("invalid central directory header"));
+            }
+            let name_length = read_u16(&directory, offset + 28) as usize;
+            let extra_length = read_u16(&directory, offset + 30) as usize;
+            let comment_length = read_u16(&directory, offset + 32) as usize;
+            let name = directory
+                .get(offset + 46..offset + 46 + name_length)
+                .ok_or_else(|| invalid_archive("invalid central directory header"))?;
+            let name = String::from_utf8_lossy(name).replace('\\', "/");
+
+            if !name.ends_with('/') {
+                let mut entry = ZipEntry {
+                    method: read_u16(&directory, offset + 10),
+                    compressed_size: read_u32(&directory, offset + 20) as u64,
+                    size: read_u32(&directory, offset + 24) as u64,
+                    header_offset: read_u32(&directory, offset + 42) as u64,
+                };
+                let extra_offset = offset + 46 + name_length;
+                let extra = directory
+                    .get(extra_offset..extra_offset + extra_length)
+                    .ok_or_else(|| invalid_archive("invalid central directory header"))?;
+                entry.read_zip64_extra(extra)?;
+                entries.insert(name, entry);
+            }
+            offset += 46 + name_length + extra_length + comment_length;
+        }
+
+        Ok(Self { path, entries })
+    }
+
+    /// Find track entry `{disc_id}/{track_id}.*`, preferring FLAC.
+    fn track(&self, disc_id: NonZeroU8, track_id: NonZeroU8) -> Option<(&str, &ZipEntry)> {
+        let prefix = format!("{disc_id}/{track_id}.");
+        self.entries
+            .get_key_value(&format!("{prefix}flac"))
+            .or_else(|| {
+                self.entries.iter().find(|(name, _)| {
+                    name.strip_prefix(&prefix)
+                        .is_some_and(|extension| !extension.contains('/'))
+                })
+            })
+            .map(|(name, entry)| (name.as_str(), entry))
+    }
+
+    fn has_disc(&self, disc_id: NonZeroU8) -> bool {
+        let prefix = format!("{disc_id}/");
+        self.entries.keys().any(|name| name.starts_with(&prefix))
+    }
+
+    /// Read bytes `start..=end` of uncompressed data of `entry`.
+    ///
+    /// Reads nothing if `end < start`.
+    async fn read(&self, entry: &ZipEntry, start: u64, end: u64) -> crate::Result<ResourceReader> {
+        let length = (end + 1).saturating_sub(start);
+
+        let mut file = tokio::fs::File::open(&self.path).await?;
+        let mut header = [0; 30];
+        file.seek(SeekFrom::Start(entry.header_offset)).await?;
+        file.read_exact(&mut header).await?;
+        if read_u32(&header, 0) != LOCAL_FILE_HEADER_SIGNATURE {
+            return Err(invalid_archive("invalid local file header"));
+        }
+        let data_offset =
+            entry.header_offset + 30 + read_u16(&header, 26) as u64 + read_u16(&header, 28) as u64;
+
+        match entry.method {
+            METHOD_STORED => {
+                file.seek(SeekFrom::Start(data_offset + start)).await?;
+                Ok(Box::pin(file.take(length)))
+            }
+            METHOD_DEFLATED => {
+                // deflate stream can not be seeked, so decompress and skip bytes before `start`,
+                // and send the range in chunks so that the entry is never buffered as a whole
+                let file = file.into_std().await;
+                let compressed_size = entry.compressed_size;
+                let (sender, receiver) = tokio::sync::mpsc::channel(2);
+                tokio::task::spawn_blocking(move || {
+                    let inflate = || -> std::io::Result<()> {
+                        let mut file = file;
+                        file.seek(SeekFrom::Start(data_offset))?;
+                        let mut decoder =
+                            flate2::read::DeflateDecoder::new(file.take(compressed_size));
+                        std::io::copy(&mut (&mut decoder).take(start), &mut std::io::sink())?;
+
+                        let mut decoder = decoder.take(length);
+                        loop {
+                            let mut chunk = vec![0; INFLATE_CHUNK_SIZE];
+                            let size = decoder.read(&mut chunk)?;
+                            if size == 0 {
+                                return Ok(());
+                            }
+                            chunk.truncate(size);
+                            if sender.blocking_send(Ok(Cursor::new(chunk))).is_err() {
+                                // reader is dropped
+                                return Ok(());
+                            }
+                        }
+                    };
+                    if let Err(e) = inflate() {
+                        let _ = sender.blocking_send(Err(e));
+                    }
+                });
+                Ok(Box::pin(StreamReader::new(ReceiverStream::new(receiver))))
+            }
+            method => Err(ProviderError::IOError(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("unsupported compression method {method}"),
+            ))),
+        }
+    }
+}
+
+impl ZipEntry {
+    /// Read sizes and offset saturated in central directory header from ZIP64 extra field.
+    fn read_zip64_extra(&mut self, mut extra: &[u8]) -> crate::Result<()> {
+        while extra.len() >= 4 {
+            let id = read_u16(extra, 0);
+            let size = read_u16(extra, 2) as usize;
+            let data = extra
+                .get(4..4 + size)
+                .ok_or_else(|| invalid_archive("invalid extra field"))?;
+            if id == ZIP64_EXTRA_ID {
+                // only saturated fields are present, in this order
+                let mut data = data;
+                for field in [
+                    &mut self.size,
+                    &mut self.compressed_size,
+                    &mut self.header_offset,
+                ] {
+                    if *field != u32::MAX as u64 {
+                        continue;
+                    }
+                    if data.len() < 8 {
+                        return Err(invalid_archive("invalid ZIP64 extra field"));
+                    }
+                    *field = read_u64(data, 0);
+                    data = &data[8..];
+                }
+                return Ok(());
+            }
+            extra = &extra[4 + size..];
+        }
+        Ok(())
+    }
+}
+
+/// Read size and offset of central directory from ZIP64 end of central directory record,
+/// whose locator is right before end of central directory record at `eocd_offset`.
+async fn read_zip64_eocd(
+    file: &mut tokio::fs::File,
+    eocd_offset: u64,
+) -> crate::Result<(u64, u64)> {
+    let locator_offset = eocd_offset
+        .checked_sub(ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE)
+        .ok_or_else(|| invalid_archive("ZIP64 end of central directory locator not found"))?;
+    let mut locator = [0; ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE as usize];
+    file.seek(SeekFrom::Start(locator_offset)).await?;
+    file.read_exact(&mut locator).await?;
+    if read_u32(&locator, 0) != ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE {
+        return Err(invalid_archive(
+            "ZIP64 end of central directory locator not found",
+        ));
+    }
+
+    let record_offset = read_u64(&locator, 8);
+    if record_offset.saturating_add(ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE) > locator_offset {
+        return Err(invalid_archive(
+            "invalid ZIP64 end of central directory locator",
+        ));
+    }
+    let mut record = [0; ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE as usize];
+    file.seek(SeekFrom::Start(record_offset)).await?;
+    file.read_exact(&mut record).await?;
+    if read_u32(&record, 0) != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+        return Err(invalid_archive(
+            "invalid ZIP64 end of central directory record",
+        ));
+    }
+    Ok((read_u64(&record, 40), read_u64(&record, 48)))
+}
+
+fn invalid_archive(message: &str) -> ProviderError {
+    ProviderError::IOError(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message.to_string(),
+    ))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
@@ -1,3 +1,4 @@
+use crate::format::AUDIO_HEADER_SIZE;
 use crate::{Range, ResourceReader};
 use anni_flac::blocks::BlockStreamInfo;
 use anni_flac::prelude::{AsyncDecode, Encode, Result};
@@ -24,7 +25,12 @@ where
 pub(crate) async fn read_duration(
     reader: ResourceReader,
     range: Range,
+    extension: &str,
 ) -> Result<(u64, ResourceReader)> {
+    if !extension.eq_ignore_ascii_case("flac") {
+        return read_other_duration(reader, range, extension).await;
+    }
+
     if !range.contains_flac_header() {
         return Ok((0, reader));
     }
@@ -33,3 +39,24 @@ pub(crate) async fn read_duration(
     let duration = info.total_samples * 1000 / info.sample_rate as u64;
     Ok((duration, Box::pin(reader)))
 }
+
+/// Read duration of non-FLAC audio from the first [AUDIO_HEADER_SIZE] bytes.
+///
+/// Duration is reported as 0 if it can not be found in the header.
+async fn read_other_duration(
+    mut reader: ResourceReader,
+    range: Range,
+    extension: &str,
+) -> Result<(u64, ResourceReader)> {
+    if range.start != 0 {
+        return Ok((0, reader));
+    }
+
+    let mut header = Vec::new();
+    (&mut reader)
+        .take(AUDIO_HEADER_SIZE)
+        .read_to_end(&mut header)
+        .await?;
+    let duration = crate::format::duration(extension, &header).unwrap_or(0);
+    Ok((duration, Box::pin(Cursor::new(header).chain(reader))))
+}
@@ -0,0 +1,694 @@
+mod common;
+
+use anni_provider::cache::{Cache, CacheMode, CachePool, CacheStats, EvictionPolicy, KeyScheme};
+use anni_provider::{AnniProvider, Range, Result};
+use common::{Failure, StubProvider, ALBUM_ID, SIZE};
+use parking_lot::Mutex;
+use std::num::NonZeroU8;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+async fn read(cache: &Cache, start: u64, end: u64) -> Vec<u8> {
+    let audio = cache
+        .get_audio(
+            ALBUM_ID,
+            NonZeroU8::MIN,
+            NonZeroU8::MIN,
+            Range::new(start, Some(end)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(audio.info.size, SIZE);
+    let mut data = Vec::new();
+    let mut reader = audio.reader;
+    reader.read_to_end(&mut data).await.unwrap();
+    data
+}
+
+#[tokio::test]
+async fn sparse_cache() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let pool = CachePool::new_memory(0).with_mode(CacheMode::Sparse);
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            audio: Some((0..SIZE as u8).collect()),
+            supports_range: true,
+            ranges: requests.clone(),
+            ..Default::default()
+        }),
+        Arc::new(pool),
+    );
+
+    assert_eq!(read(&cache, 10, 19).await, (10..20).collect::<Vec<u8>>());
+    assert_eq!(*requests.lock(), [(10, Some(19))]);
+
+    // only the missing part is fetched
+    assert_eq!(read(&cache, 15, 29).await, (15..30).collect::<Vec<u8>>());
+    assert_eq!(*requests.lock(), [(10, Some(19)), (20, Some(29))]);
+
+    // served from cached chunks
+    assert_eq!(read(&cache, 12, 27).await, (12..28).collect::<Vec<u8>>());
+    assert_eq!(requests.lock().len(), 2);
+
+    // gaps between and after chunks are fetched
+    assert_eq!(read(&cache, 0, 39).await, (0..40).collect::<Vec<u8>>());
+    assert_eq!(requests.lock()[2..], [(0, Some(9)), (30, Some(39))]);
+}
+
+#[tokio::test]
+async fn sparse_cache_index() {
+    let root = tempfile::tempdir().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let cache = |pool: &Arc<CachePool>| {
+        Cache::new(
+            Box::new(StubProvider {
+                audio: Some((0..SIZE as u8).collect()),
+                supports_range: true,
+                ranges: requests.clone(),
+                ..Default::default()
+            }),
+            pool.clone(),
+        )
+    };
+
+    let pool = Arc::new(CachePool::new(root.path(), 0).with_mode(CacheMode::Sparse));
+    assert_eq!(
+        read(&cache(&pool), 10, 19).await,
+        (10..20).collect::<Vec<u8>>()
+    );
+    assert!(pool.wait_fills(Duration::from_secs(1)).await);
+    drop(pool);
+
+    // chunks are restored after restart
+    let pool = Arc::new(CachePool::new(root.path(), 0).with_mode(CacheMode::Sparse));
+    assert_eq!(pool.stats().space_used, 10);
+    assert_eq!(
+        read(&cache(&pool), 12, 17).await,
+        (12..18).collect::<Vec<u8>>()
+    );
+    assert_eq!(requests.lock().len(), 1);
+    drop(pool);
+
+    // and removed with the index entry if the pool is switched to full mode
+    let pool = CachePool::new(root.path(), 0).with_mode(CacheMode::Full);
+    assert_eq!(pool.stats().entries, 0);
+    let files = std::fs::read_dir(root.path()).unwrap();
+    assert!(files
+        .map(|file| file.unwrap().file_name())
+        .all(|name| name == "index.json"));
+}
+
+#[tokio::test]
+async fn sparse_cache_evict_on_insert() {
+    let pool = Arc::new(CachePool::new_memory(15).with_mode(CacheMode::Sparse));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            audio: Some((0..SIZE as u8).collect()),
+            supports_range: true,
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+
+    read(&cache, 0, 9).await;
+    assert_eq!(pool.evictions(), 0);
+    // chunks are over the limit once saved
+    read(&cache, 20, 29).await;
+    assert_eq!(pool.evictions(), 1);
+    assert_eq!(pool.stats().space_used, 0);
+}
+
+async fn read_cover(cache: &Cache, album_id: &str, disc_id: Option<NonZeroU8>) -> Result<String> {
+    let mut reader = cache.get_cover(album_id, disc_id).await?;
+    let mut data = String::new();
+    reader.read_to_string(&mut data).await.unwrap();
+    Ok(data)
+}
+
+#[tokio::test]
+async fn cover_cache() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let pool = Arc::new(CachePool::new_memory(0));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    // size of cover is known before it's filled
+    assert_eq!(pool.stats().space_used, "cover 0".len());
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // disc covers are cached separately
+    let disc = NonZeroU8::new(2);
+    assert_eq!(read_cover(&cache, ALBUM_ID, disc).await.unwrap(), "cover 2");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    cache.invalidate_cover(ALBUM_ID, None);
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    // failed requests are not cached
+    let missing = "00000000-0000-0000-0000-000000000000";
+    assert!(read_cover(&cache, missing, None).await.is_err());
+    assert!(read_cover(&cache, missing, None).await.is_err());
+    assert_eq!(requests.load(Ordering::SeqCst), 5);
+
+    assert_eq!(
+        pool.stats(),
+        CacheStats {
+            entries: 2,
+            space_used: "cover 0".len() + "cover 2".len(),
+            max_size: 0,
+            filling: 0,
+            hits: 1,
+            misses: 5,
+            evictions: 0,
+        }
+    );
+}
+
+#[tokio::test]
+async fn cache_evictions() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    // previous covers are evicted when a new cover is requested
+    let pool = Arc::new(CachePool::new_memory(1));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+
+    for disc_id in [1, 2, 1] {
+        let disc = NonZeroU8::new(disc_id);
+        assert_eq!(
+            read_cover(&cache, ALBUM_ID, disc).await.unwrap(),
+            format!("cover {disc_id}")
+        );
+    }
+    assert_eq!(pool.misses(), 3);
+    assert_eq!(pool.hits(), 0);
+    // cover in the pool is evicted when the next one is added
+    assert_eq!(pool.evictions(), 2);
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn lfu_eviction() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    // only one cover fits in the pool
+    let pool = Arc::new(CachePool::new_memory("cover 0".len()).with_eviction(EvictionPolicy::Lfu));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+
+    // cover of disc 2 is used less than disc 1, although it's used more recently
+    for disc_id in [1, 1, 2, 3, 1] {
+        let disc = NonZeroU8::new(disc_id);
+        assert_eq!(
+            read_cover(&cache, ALBUM_ID, disc).await.unwrap(),
+            format!("cover {disc_id}")
+        );
+    }
+    // disc 2 is evicted when disc 3 is added
+    assert_eq!(pool.evictions(), 1);
+    assert_eq!(pool.hits(), 2);
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    assert_eq!(
+        read_cover(&cache, ALBUM_ID, NonZeroU8::new(2))
+            .await
+            .unwrap(),
+        "cover 2"
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn cache_trim_after_actual_size() {
+    // two audios fit in the pool by reported size, but only one by actual size
+    let pool = Arc::new(CachePool::new_memory(SIZE * 3 / 2));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            size: Some(SIZE / 2),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+
+    for track_id in 1..=2 {
+        let mut audio = cache
+            .get_audio(
+                ALBUM_ID,
+                NonZeroU8::MIN,
+                NonZeroU8::new(track_id).unwrap(),
+                Range::FULL,
+            )
+            .await
+            .unwrap();
+        let mut data = Vec::new();
+        audio.reader.read_to_end(&mut data).await.unwrap();
+        assert_eq!(data, vec![track_id; SIZE]);
+    }
+
+    let stats = pool.stats();
+    assert!(stats.space_used <= stats.max_size);
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.evictions, 1);
+}
+
+#[tokio::test]
+async fn cache_audio_info_actual_size() {
+    let pool = Arc::new(CachePool::new_memory(0));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            size: Some(SIZE / 2),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+    let info = || cache.get_audio_info(ALBUM_ID, NonZeroU8::MIN, NonZeroU8::MIN);
+
+    // size reported by provider is used before the audio is cached
+    assert_eq!(info().await.unwrap().size, SIZE / 2);
+
+    let mut audio = cache
+        .get_audio(ALBUM_ID, NonZeroU8::MIN, NonZeroU8::MIN, Range::FULL)
+        .await
+        .unwrap();
+    let mut data = Vec::new();
+    audio.reader.read_to_end(&mut data).await.unwrap();
+    assert!(pool.wait_fills(Duration::from_secs(1)).await);
+
+    let info = info().await.unwrap();
+    assert_eq!(info.size, SIZE);
+    assert_eq!(info.extension, "flac");
+}
+
+#[tokio::test]
+async fn cache_ttl() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let pool = Arc::new(CachePool::new_memory(0).with_ttl(Some(Duration::from_millis(100))));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+
+    let mut old = cache.get_cover(ALBUM_ID, None).await.unwrap();
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // expired item is fetched again
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+    assert_eq!(pool.stats().entries, 1);
+
+    // readers of the expired item can still read it
+    let mut data = String::new();
+    old.read_to_string(&mut data).await.unwrap();
+    assert_eq!(data, "cover 0");
+}
+
+#[tokio::test]
+async fn cache_ttl_restored() {
+    let root = tempfile::tempdir().unwrap();
+    let key = KeyScheme::CURRENT.key(&format!("cover/{ALBUM_ID}/0"));
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    std::fs::write(root.path().join(&key), "cover 0").unwrap();
+    std::fs::write(
+        root.path().join("index.json"),
+        serde_json::json!([{ "key": key, "ext": "", "size": 7, "duration": 0, "created_at": now - 7200 }])
+            .to_string(),
+    )
+    .unwrap();
+
+    // age of restored items counts time before restart
+    let requests = Arc::new(AtomicUsize::new(0));
+    let pool = Arc::new(CachePool::new(root.path(), 0).with_ttl(Some(Duration::from_secs(3600))));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+    assert_eq!(pool.stats().entries, 1);
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // and the time of fetching again is recorded
+    assert!(pool.wait_fills(Duration::from_secs(1)).await);
+    let index: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(root.path().join("index.json")).unwrap()).unwrap();
+    assert!(index[0]["created_at"].as_u64().unwrap() >= now);
+}
+
+#[tokio::test]
+async fn cache_index() {
+    let root = tempfile::tempdir().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let pool = Arc::new(CachePool::new(root.path(), 0));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    // index is written in background
+    assert!(!root.path().join("index.json").exists());
+    assert!(pool.wait_fills(Duration::from_secs(1)).await);
+    assert!(root.path().join("index.json").exists());
+    drop(cache);
+    drop(pool);
+
+    // cached items are restored from index, like after a restart
+    let pool = Arc::new(CachePool::new(root.path(), 0));
+    assert_eq!(pool.stats().entries, 1);
+    assert_eq!(pool.stats().space_used, "cover 0".len());
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    assert_eq!(pool.hits(), 1);
+
+    // removed items are removed from index as well
+    cache.invalidate_cover(ALBUM_ID, None);
+    assert!(pool.wait_fills(Duration::from_secs(1)).await);
+    assert_eq!(CachePool::new(root.path(), 0).stats().entries, 0);
+}
+
+#[test]
+fn cache_sweep() {
+    let root = tempfile::tempdir().unwrap();
+    let key = KeyScheme::CURRENT.key("cover/unknown/0");
+    let files = [key.clone(), format!("{key}.0-10"), "other".to_string()];
+    for file in &files {
+        std::fs::write(root.path().join(file), "data").unwrap();
+    }
+
+    // files of cache keys not recorded in index are removed
+    let pool = CachePool::new(root.path(), 0);
+    assert_eq!(pool.stats().entries, 0);
+    assert!(!root.path().join(&files[0]).exists());
+    assert!(!root.path().join(&files[1]).exists());
+    assert!(root.path().join(&files[2]).exists());
+}
+
+#[test]
+fn key_scheme() {
+    let key = KeyScheme::CURRENT.key("cover/album/0");
+    assert!(key.starts_with("sha256-"));
+    assert_eq!(KeyScheme::of(&key), Some(KeyScheme::Sha256));
+
+    let legacy = KeyScheme::Legacy.key("cover/album/0");
+    assert_eq!(KeyScheme::of(&legacy), Some(KeyScheme::Legacy));
+    assert_eq!(KeyScheme::migrate(&legacy), Some(key));
+
+    assert_eq!(KeyScheme::of("index.json"), None);
+    assert_eq!(KeyScheme::migrate("index.json"), None);
+}
+
+#[tokio::test]
+async fn cache_index_migration() {
+    let root = tempfile::tempdir().unwrap();
+    let resource = format!("cover/{ALBUM_ID}/0");
+    let legacy = KeyScheme::Legacy.key(&resource);
+    std::fs::write(root.path().join(&legacy), "cover 0").unwrap();
+    std::fs::write(
+        root.path().join("index.json"),
+        serde_json::json!([{ "key": legacy, "ext": "", "size": 7, "duration": 0 }]).to_string(),
+    )
+    .unwrap();
+
+    // cached file of legacy key is renamed and served
+    let requests = Arc::new(AtomicUsize::new(0));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        Arc::new(CachePool::new(root.path(), 0)),
+    );
+    assert!(!root.path().join(&legacy).exists());
+    assert!(root.path().join(KeyScheme::CURRENT.key(&resource)).exists());
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    assert_eq!(requests.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn cache_legacy_files() {
+    let root = tempfile::tempdir().unwrap();
+    // FLAC header with STREAMINFO of 3 seconds of 44.1kHz audio
+    let mut audio = b"fLaC\x80\0\0\x22".to_vec();
+    audio.extend_from_slice(&[0; 10]);
+    let samples: u64 = (44100 << 44) | (1 << 41) | (15 << 36) | (44100 * 3);
+    audio.extend_from_slice(&samples.to_be_bytes());
+    audio.extend_from_slice(&[0; 16]);
+    let audio_key = KeyScheme::Legacy.key(&format!("{ALBUM_ID}/01/01"));
+    let cover_key = KeyScheme::Legacy.key(&format!("cover/{ALBUM_ID}/0"));
+    std::fs::write(root.path().join(&audio_key), &audio).unwrap();
+    std::fs::write(root.path().join(&cover_key), "cover 0").unwrap();
+
+    // files of previous versions without index are migrated as well
+    let requests = Arc::new(AtomicUsize::new(0));
+    let pool = Arc::new(CachePool::new(root.path(), 0));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+    assert_eq!(pool.stats().entries, 2);
+    assert!(!root.path().join(&audio_key).exists());
+    assert!(!root.path().join(&cover_key).exists());
+
+    let info = cache
+        .cached_audio_info(ALBUM_ID, NonZeroU8::MIN, NonZeroU8::MIN)
+        .unwrap();
+    assert_eq!(info.extension, "flac");
+    assert_eq!(info.size, audio.len());
+    assert_eq!(info.duration, 3000);
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    assert_eq!(requests.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn cache_integrity() {
+    let root = tempfile::tempdir().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let pool = Arc::new(CachePool::new(root.path(), 0));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            requests: requests.clone(),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+    let path = root
+        .path()
+        .join(KeyScheme::CURRENT.key(&format!("cover/{ALBUM_ID}/0")));
+
+    // truncated file is fetched again
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    std::fs::write(&path, "cov").unwrap();
+    assert_eq!(read_cover(&cache, ALBUM_ID, None).await.unwrap(), "cover 0");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // broken items are removed by verification
+    assert_eq!(pool.verify().await, 0);
+    std::fs::write(&path, "cov").unwrap();
+    assert_eq!(pool.verify().await, 1);
+    assert_eq!(pool.stats().entries, 0);
+}
+
+#[tokio::test]
+async fn cache_fill_limit() {
+    let (mut writer, stream) = tokio::io::duplex(SIZE);
+    let cache = Arc::new(Cache::new(
+        Box::new(StubProvider {
+            stream: Mutex::new(Some(stream)),
+            ..Default::default()
+        }),
+        Arc::new(CachePool::new_memory(0).with_fill_limit(1)),
+    ));
+    let get_audio = |track_id: u8| {
+        let cache = cache.clone();
+        tokio::spawn(async move {
+            let mut audio = cache
+                .get_audio(
+                    ALBUM_ID,
+                    NonZeroU8::MIN,
+                    NonZeroU8::new(track_id).unwrap(),
+                    Range::FULL,
+                )
+                .await
+                .unwrap();
+            let mut data = Vec::new();
+            audio.reader.read_to_end(&mut data).await.unwrap();
+            data
+        })
+    };
+
+    // the first track is still being filled
+    let first = get_audio(1);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // so the second one waits for it
+    let second = get_audio(2);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!second.is_finished());
+
+    writer.write_all(&[1; SIZE]).await.unwrap();
+    drop(writer);
+    assert_eq!(first.await.unwrap(), vec![1; SIZE]);
+    assert_eq!(second.await.unwrap(), vec![2; SIZE]);
+}
+
+#[tokio::test]
+async fn cache_wait_fills() {
+    let (mut writer, stream) = tokio::io::duplex(SIZE);
+    let pool = Arc::new(CachePool::new_memory(0));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            stream: Mutex::new(Some(stream)),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+    assert!(pool.wait_fills(Duration::ZERO).await);
+
+    let _audio = cache
+        .get_audio(ALBUM_ID, NonZeroU8::MIN, NonZeroU8::MIN, Range::FULL)
+        .await
+        .unwrap();
+    assert!(!pool.wait_fills(Duration::from_millis(50)).await);
+
+    let wait = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.wait_fills(Duration::from_secs(5)).await }
+    });
+    writer.write_all(&[1; SIZE]).await.unwrap();
+    drop(writer);
+    assert!(wait.await.unwrap());
+    assert_eq!(pool.stats().filling, 0);
+}
+
+#[tokio::test]
+async fn filling_items_not_evicted() {
+    let (mut writer, stream) = tokio::io::duplex(SIZE);
+    let disk = tempfile::tempdir().unwrap();
+    // only one track fits in the pool
+    let pool = Arc::new(CachePool::new(disk.path(), SIZE));
+    let cache = Cache::new(
+        Box::new(StubProvider {
+            stream: Mutex::new(Some(stream)),
+            ..Default::default()
+        }),
+        pool.clone(),
+    );
+    let read = |track_id: u8| {
+        let cache = &cache;
+        async move {
+            let mut audio = cache
+                .get_audio(
+                    ALBUM_ID,
+                    NonZeroU8::MIN,
+                    NonZeroU8::new(track_id).unwrap(),
+                    Range::FULL,
+                )
+                .await
+                .unwrap();
+            let mut data = Vec::new();
+            audio.reader.read_to_end(&mut data).await.unwrap();
+            data
+        }
+    };
+
+    // the first track is still being filled, so the second one is evicted instead
+    let mut first = cache
+        .get_audio(ALBUM_ID, NonZeroU8::MIN, NonZeroU8::MIN, Range::FULL)
+        .await
+        .unwrap();
+    assert_eq!(read(2).await, vec![2; SIZE]);
+    assert_eq!(read(3).await, vec![3; SIZE]);
+    assert_eq!(pool.evictions(), 1);
+
+    writer.write_all(&[1; SIZE]).await.unwrap();
+    drop(writer);
+    let mut data = Vec::new();
+    first.reader.read_to_end(&mut data).await.unwrap();
+    assert_eq!(data, vec![1; SIZE]);
+    assert!(pool.wait_fills(Duration::from_secs(1)).await);
+
+    // and the first track is still cached
+    assert_eq!(read(1).await, vec![1; SIZE]);
+    assert_eq!(pool.hits(), 1);
+    assert_eq!(pool.stats().entries, 2);
+}
+
+#[tokio::test]
+async fn cache_fill_failure() {
+    let disk = tempfile::tempdir().unwrap();
+    for (pool, failure) in [
+        (CachePool::new_memory(0), Failure::Broken),
+        (CachePool::new(disk.path(), 0), Failure::Broken),
+        (CachePool::new_memory(0), Failure::Truncated),
+        (CachePool::new(disk.path(), 0), Failure::Truncated),
+    ] {
+        let pool = Arc::new(pool);
+        let cache = Cache::new(
+            Box::new(StubProvider {
+                failures: AtomicUsize::new(1),
+                failure,
+                ..Default::default()
+            }),
+            pool.clone(),
+        );
+        let read = || async {
+            let mut audio = cache
+                .get_audio(ALBUM_ID, NonZeroU8::MIN, NonZeroU8::MIN, Range::FULL)
+                .await
+                .ok()?;
+            let mut data = Vec::new();
+            audio.reader.read_to_end(&mut data).await.ok()?;
+            Some(data)
+        };
+
+        // requests get the error instead of waiting forever,
+        // which is returned on opening if the file has been removed
+        assert!(read().await.is_none());
+        assert!(pool.wait_fills(Duration::from_secs(1)).await);
+        assert_eq!(pool.stats().entries, 0);
+
+        // failed item is fetched again
+        assert_eq!(read().await.unwrap(), vec![1; SIZE]);
+        assert_eq!(pool.misses(), 2);
+    }
+}
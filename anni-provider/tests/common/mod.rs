@@ -0,0 +1,173 @@
+#![allow(dead_code)]
+
+use anni_provider::{
+    AnniProvider, AudioInfo, AudioResourceReader, Capabilities, ProviderError, Range,
+    ResourceReader, Result,
+};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::num::NonZeroU8;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, DuplexStream, ReadBuf};
+
+pub const ALBUM_ID: &str = "b8fdf8c6-1f27-4ab4-a3c7-4c4a3ff4d6e5";
+pub const SIZE: usize = 100;
+
+/// Requested ranges of audio, as `(start, end)`.
+pub type Ranges = Arc<Mutex<Vec<(u64, Option<u64>)>>>;
+
+/// How failing requests to [StubProvider] fail.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Failure {
+    /// Return [ProviderError::GeneralError] for audio and cover.
+    #[default]
+    Error,
+    /// Return the first half of audio, then fail with a connection reset.
+    Broken,
+    /// Return the first half of audio, then end the stream cleanly.
+    Truncated,
+}
+
+/// Provider which has only [ALBUM_ID], whose tracks are [SIZE] bytes of their track id
+/// and covers are `cover {disc_id}`. Tests override fields with struct update syntax.
+#[derive(Default)]
+pub struct StubProvider {
+    /// Content of every track, instead of the track id repeated.
+    pub audio: Option<Vec<u8>>,
+    /// Size reported in audio info, instead of the actual size.
+    pub size: Option<usize>,
+    /// Serve the requested range of audio, instead of the whole file.
+    pub supports_range: bool,
+    /// Time taken by every request of [ALBUM_ID].
+    pub delay: Duration,
+    /// Number of requests which fail in `failure` mode.
+    pub failures: AtomicUsize,
+    pub failure: Failure,
+    /// Stream of the first track, which is used once.
+    pub stream: Mutex<Option<DuplexStream>>,
+    /// Number of audio and cover requests received.
+    pub requests: Arc<AtomicUsize>,
+    /// Ranges of audio requests received.
+    pub ranges: Ranges,
+}
+
+impl StubProvider {
+    /// Provider which fails every request with `failure`.
+    pub fn failing(failure: Failure) -> Self {
+        Self {
+            failures: AtomicUsize::new(usize::MAX),
+            failure,
+            ..Default::default()
+        }
+    }
+
+    async fn request(&self, album_id: &str) -> Result<bool> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        if album_id != ALBUM_ID {
+            return Err(ProviderError::FileNotFound);
+        }
+        tokio::time::sleep(self.delay).await;
+        let failed = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        match failed {
+            true if self.failure == Failure::Error => Err(ProviderError::GeneralError),
+            failed => Ok(failed),
+        }
+    }
+}
+
+#[async_trait]
+impl AnniProvider for StubProvider {
+    async fn albums(&self) -> Result<HashSet<Cow<str>>> {
+        Ok(HashSet::from([Cow::Borrowed(ALBUM_ID)]))
+    }
+
+    async fn get_audio(
+        &self,
+        album_id: &str,
+        _disc_id: NonZeroU8,
+        track_id: NonZeroU8,
+        range: Range,
+    ) -> Result<AudioResourceReader> {
+        self.ranges.lock().push((range.start, range.end));
+        let failed = self.request(album_id).await?;
+
+        let mut data = match &self.audio {
+            Some(audio) => audio.clone(),
+            None => vec![track_id.get(); SIZE],
+        };
+        let size = self.size.unwrap_or(data.len());
+        if self.supports_range {
+            let end = range.end.map_or(data.len(), |end| end as usize + 1);
+            data = data[range.start as usize..end].to_vec();
+        }
+        let reader: ResourceReader = match self.stream.lock().take() {
+            Some(stream) if track_id == NonZeroU8::MIN => Box::pin(stream),
+            _ if failed => {
+                data.truncate(data.len() / 2);
+                match self.failure {
+                    Failure::Broken => Box::pin(BrokenReader(Cursor::new(data))),
+                    _ => Box::pin(Cursor::new(data)),
+                }
+            }
+            _ => Box::pin(Cursor::new(data)),
+        };
+        Ok(AudioResourceReader {
+            info: AudioInfo {
+                extension: "flac".to_string(),
+                size,
+                duration: 0,
+            },
+            range,
+            reader,
+        })
+    }
+
+    async fn get_cover(
+        &self,
+        album_id: &str,
+        disc_id: Option<NonZeroU8>,
+    ) -> Result<ResourceReader> {
+        self.request(album_id).await?;
+        let cover = format!("cover {}", disc_id.map_or(0, NonZeroU8::get));
+        Ok(Box::pin(Cursor::new(cover.into_bytes())))
+    }
+
+    async fn reload(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_range: self.supports_range,
+            has_covers: true,
+            ..Capabilities::CONSERVATIVE
+        }
+    }
+}
+
+/// Reader which fails with a connection reset after all data is read.
+struct BrokenReader(Cursor<Vec<u8>>);
+
+impl AsyncRead for BrokenReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if self.0.position() as usize == self.0.get_ref().len() {
+            let error = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+            return Poll::Ready(Err(error));
+        }
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
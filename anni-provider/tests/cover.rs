@@ -0,0 +1,69 @@
+mod common;
+
+use anni_provider::fs::LocalFileSystemProvider;
+use anni_provider::providers::{CommonStrictProvider, NoCacheStrictLocalProvider};
+use anni_provider::AnniProvider;
+use common::ALBUM_ID;
+use std::num::NonZeroU8;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+/// Create a two-disc album, where only disc 2 has its own cover.
+fn create_album(root: &Path) {
+    let album = root.join(ALBUM_ID);
+    std::fs::create_dir_all(album.join("1")).unwrap();
+    std::fs::create_dir_all(album.join("2")).unwrap();
+    std::fs::write(album.join("1").join("1.flac"), "").unwrap();
+    std::fs::write(album.join("2").join("1.flac"), "").unwrap();
+    std::fs::write(album.join("cover.jpg"), "album").unwrap();
+    std::fs::write(album.join("2").join("cover.jpg"), "disc 2").unwrap();
+}
+
+async fn read_cover(provider: &impl AnniProvider, disc_id: Option<u8>) -> String {
+    let mut reader = provider
+        .get_cover(ALBUM_ID, disc_id.and_then(NonZeroU8::new))
+        .await
+        .unwrap();
+    let mut cover = String::new();
+    reader.read_to_string(&mut cover).await.unwrap();
+    cover
+}
+
+#[tokio::test]
+async fn strict_disc_cover() {
+    let root = tempfile::tempdir().unwrap();
+    create_album(root.path());
+
+    let provider = CommonStrictProvider::new(
+        root.path().to_path_buf(),
+        0,
+        Box::new(LocalFileSystemProvider),
+    )
+    .await
+    .unwrap();
+    assert_eq!(read_cover(&provider, None).await, "album");
+    assert_eq!(read_cover(&provider, Some(1)).await, "album");
+    assert_eq!(read_cover(&provider, Some(2)).await, "disc 2");
+    assert!(provider
+        .get_cover(ALBUM_ID, NonZeroU8::new(3))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn no_cache_disc_cover() {
+    let root = tempfile::tempdir().unwrap();
+    create_album(root.path());
+
+    let provider = NoCacheStrictLocalProvider {
+        root: root.path().to_path_buf(),
+        layer: 0,
+    };
+    assert_eq!(read_cover(&provider, None).await, "album");
+    assert_eq!(read_cover(&provider, Some(1)).await, "album");
+    assert_eq!(read_cover(&provider, Some(2)).await, "disc 2");
+    assert!(provider
+        .get_cover(ALBUM_ID, NonZeroU8::new(3))
+        .await
+        .is_err());
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use anni_provider::providers::{MultipleProviders, NoCacheStrictLocalProvider, Routing};
+use anni_provider::{AnniProvider, Capabilities, Range};
+use common::{Failure, StubProvider, ALBUM_ID};
+use std::collections::HashSet;
+use std::num::NonZeroU8;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use tokio::io::AsyncReadExt;
+
+/// Create a local provider whose album cover is `cover`.
+fn local_provider(root: &Path, cover: &str) -> Box<dyn AnniProvider + Send + Sync> {
+    let album = root.join(ALBUM_ID);
+    std::fs::create_dir_all(album.join("1")).unwrap();
+    std::fs::write(album.join("cover.jpg"), cover).unwrap();
+    Box::new(NoCacheStrictLocalProvider {
+        root: root.to_path_buf(),
+        layer: 0,
+    })
+}
+
+async fn read_cover(provider: &impl AnniProvider) -> String {
+    let mut reader = provider.get_cover(ALBUM_ID, None).await.unwrap();
+    let mut cover = String::new();
+    reader.read_to_string(&mut cover).await.unwrap();
+    cover
+}
+
+#[tokio::test]
+async fn priority_routing() {
+    let a = tempfile::tempdir().unwrap();
+    let b = tempfile::tempdir().unwrap();
+    let provider = MultipleProviders::new(vec![
+        local_provider(a.path(), "a"),
+        local_provider(b.path(), "b"),
+    ]);
+    for _ in 0..3 {
+        assert_eq!(read_cover(&provider).await, "a");
+    }
+}
+
+#[tokio::test]
+async fn round_robin_routing() {
+    let a = tempfile::tempdir().unwrap();
+    let b = tempfile::tempdir().unwrap();
+    let provider = MultipleProviders::new(vec![
+        local_provider(a.path(), "a"),
+        local_provider(b.path(), "b"),
+    ])
+    .with_routing(Routing::RoundRobin);
+    let mut covers = Vec::new();
+    for _ in 0..4 {
+        covers.push(read_cover(&provider).await);
+    }
+    assert_eq!(covers, ["a", "b", "a", "b"]);
+}
+
+#[tokio::test]
+async fn unhealthy_provider_skipped() {
+    for routing in [Routing::Priority, Routing::RoundRobin] {
+        let a = tempfile::tempdir().unwrap();
+        let broken = StubProvider::failing(Failure::Error);
+        let requests = broken.requests.clone();
+        let provider =
+            MultipleProviders::new(vec![Box::new(broken), local_provider(a.path(), "a")])
+                .with_routing(routing);
+
+        // failed request is retried by the next provider, and the failed one is excluded afterwards
+        for _ in 0..4 {
+            assert_eq!(read_cover(&provider).await, "a");
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1, "{routing:?}");
+        assert!(provider.providers().any(|(_, _, healthy)| !healthy));
+    }
+}
+
+#[tokio::test]
+async fn track_pinned_to_provider() {
+    let provider = MultipleProviders::new(vec![
+        Box::new(StubProvider {
+            size: Some(1),
+            ..Default::default()
+        }),
+        Box::new(StubProvider {
+            size: Some(2),
+            ..Default::default()
+        }),
+    ])
+    .with_routing(Routing::RoundRobin);
+    let disc = NonZeroU8::new(1).unwrap();
+    let track = NonZeroU8::new(1).unwrap();
+
+    let info = provider
+        .get_audio_info(ALBUM_ID, disc, track)
+        .await
+        .unwrap();
+    for _ in 0..4 {
+        let audio = provider
+            .get_audio(ALBUM_ID, disc, track, Range::FULL)
+            .await
+            .unwrap();
+        assert_eq!(audio.info.size, info.size);
+    }
+
+    // other tracks still take turns
+    let mut sizes = HashSet::new();
+    for track in 2..4 {
+        let track = NonZeroU8::new(track).unwrap();
+        let info = provider
+            .get_audio_info(ALBUM_ID, disc, track)
+            .await
+            .unwrap();
+        sizes.insert(info.size);
+    }
+    assert_eq!(sizes.len(), 2);
+}
+
+#[tokio::test]
+async fn named_provider() {
+    let a = tempfile::tempdir().unwrap();
+    let b = tempfile::tempdir().unwrap();
+    let provider = MultipleProviders::new(vec![
+        local_provider(a.path(), "a"),
+        local_provider(b.path(), "b"),
+    ])
+    .with_names(vec!["a".to_string(), "b".to_string()]);
+
+    let b = provider.named_provider("b").unwrap();
+    let mut reader = b.get_cover(ALBUM_ID, None).await.unwrap();
+    let mut cover = String::new();
+    reader.read_to_string(&mut cover).await.unwrap();
+    assert_eq!(cover, "b");
+
+    assert!(provider.named_provider("c").is_none());
+}
+
+#[tokio::test]
+async fn push_provider() {
+    let a = tempfile::tempdir().unwrap();
+    let b = tempfile::tempdir().unwrap();
+    let mut provider = MultipleProviders::new(vec![local_provider(a.path(), "a")])
+        .with_names(vec!["a".to_string()])
+        .with_routing(Routing::RoundRobin);
+    provider.push("b".to_string(), local_provider(b.path(), "b"));
+    assert_eq!(provider.len(), 2);
+    assert!(provider.named_provider("b").is_some());
+
+    let mut covers = Vec::new();
+    for _ in 0..4 {
+        covers.push(read_cover(&provider).await);
+    }
+    assert_eq!(covers, ["a", "b", "a", "b"]);
+}
+
+#[tokio::test]
+async fn album_capabilities() {
+    let a = tempfile::tempdir().unwrap();
+    let local = || local_provider(a.path(), "a");
+    let stub = || -> Box<dyn AnniProvider + Send + Sync> { Box::<StubProvider>::default() };
+
+    // only the first provider serves the album in priority mode
+    let provider = MultipleProviders::new(vec![local(), stub()]);
+    assert_eq!(
+        provider.album_capabilities(ALBUM_ID).await,
+        local().capabilities()
+    );
+
+    // any provider may serve the album in round-robin mode
+    let provider = MultipleProviders::new(vec![local(), stub()]).with_routing(Routing::RoundRobin);
+    let capabilities = provider.album_capabilities(ALBUM_ID).await;
+    assert!(!capabilities.supports_range);
+    assert!(capabilities.has_covers);
+}
+
+#[tokio::test]
+async fn no_provider_capabilities() {
+    // nothing is assumed without any provider
+    let provider = MultipleProviders::new(Vec::new());
+    assert_eq!(provider.capabilities(), Capabilities::CONSERVATIVE);
+
+    // neither if no provider has the album
+    let provider = MultipleProviders::new(vec![Box::<StubProvider>::default()]);
+    let missing = "00000000-0000-0000-0000-000000000000";
+    assert_eq!(
+        provider.album_capabilities(missing).await,
+        Capabilities::CONSERVATIVE
+    );
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use anni_provider::providers::TimeoutProvider;
+use anni_provider::{AnniProvider, ProviderError, Range};
+use common::{StubProvider, ALBUM_ID};
+use std::num::NonZeroU8;
+use std::time::Duration;
+
+fn provider(delay: Duration) -> TimeoutProvider {
+    TimeoutProvider::new(
+        Box::new(StubProvider {
+            delay,
+            ..Default::default()
+        }),
+        "slow".to_string(),
+        Duration::from_millis(100),
+    )
+}
+
+#[tokio::test]
+async fn timeout_provider() {
+    let fast = provider(Duration::ZERO);
+    assert!(fast.get_cover(ALBUM_ID, None).await.is_ok());
+    assert!(fast.has_album(ALBUM_ID).await);
+
+    let slow = provider(Duration::from_secs(60));
+    match slow.get_cover(ALBUM_ID, None).await {
+        Err(ProviderError::Timeout { provider, timeout }) => {
+            assert_eq!(provider, "slow");
+            assert_eq!(timeout, Duration::from_millis(100));
+        }
+        _ => panic!("request should time out"),
+    }
+
+    // errors of inner provider are passed through
+    let missing = "00000000-0000-0000-0000-000000000000";
+    assert!(matches!(
+        slow.get_audio(missing, NonZeroU8::MIN, NonZeroU8::MIN, Range::FULL)
+            .await,
+        Err(ProviderError::FileNotFound)
+    ));
+}
@@ -0,0 +1,242 @@
+mod common;
+
+use anni_provider::providers::ZipProvider;
+use anni_provider::{AnniProvider, Range};
+use common::ALBUM_ID;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::Write;
+use std::num::NonZeroU8;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+/// Write a ZIP archive with given entries, which are deflated if the flag is set.
+///
+/// Sizes and offsets are written in ZIP64 records if `zip64` is set.
+/// CRC-32 is not calculated as it's not checked by the provider.
+fn write_zip(path: &Path, entries: &[(&str, &str, bool)], zip64: bool) {
+    let mut data = Vec::new();
+    let mut directory = Vec::new();
+    for (name, content, deflated) in entries {
+        let (method, compressed): (u16, Vec<u8>) = if *deflated {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(content.as_bytes()).unwrap();
+            (8, encoder.finish().unwrap())
+        } else {
+            (0, content.as_bytes().to_vec())
+        };
+        let offset = data.len() as u32;
+
+        data.extend(0x04034b50u32.to_le_bytes());
+        data.extend([20, 0, 0, 0]); // version, flags
+        data.extend(method.to_le_bytes());
+        data.extend([0; 8]); // time, date, crc
+        data.extend((compressed.len() as u32).to_le_bytes());
+        data.extend((content.len() as u32).to_le_bytes());
+        data.extend((name.len() as u16).to_le_bytes());
+        data.extend([0, 0]); // extra length
+        data.extend(name.as_bytes());
+        data.extend(&compressed);
+
+        directory.extend(0x02014b50u32.to_le_bytes());
+        directory.extend([20, 0, 20, 0, 0, 0]); // version made by, version, flags
+        directory.extend(method.to_le_bytes());
+        directory.extend([0; 8]); // time, date, crc
+        if zip64 {
+            directory.extend([0xff; 8]); // sizes
+        } else {
+            directory.extend((compressed.len() as u32).to_le_bytes());
+            directory.extend((content.len() as u32).to_le_bytes());
+        }
+        directory.extend((name.len() as u16).to_le_bytes());
+        directory.extend(if zip64 { [28, 0] } else { [0, 0] }); // extra length
+        directory.extend([0; 10]); // comment, disk, internal and external attributes
+        if zip64 {
+            directory.extend([0xff; 4]);
+        } else {
+            directory.extend(offset.to_le_bytes());
+        }
+        directory.extend(name.as_bytes());
+        if zip64 {
+            directory.extend([1, 0, 24, 0]); // ZIP64 extra field
+            directory.extend((content.len() as u64).to_le_bytes());
+            directory.extend((compressed.len() as u64).to_le_bytes());
+            directory.extend((offset as u64).to_le_bytes());
+        }
+    }
+
+    let directory_offset = data.len() as u32;
+    data.extend(&directory);
+    if zip64 {
+        let record_offset = data.len() as u64;
+        data.extend(0x06064b50u32.to_le_bytes());
+        data.extend(44u64.to_le_bytes());
+        data.extend([45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]); // versions, disk numbers
+        data.extend((entries.len() as u64).to_le_bytes());
+        data.extend((entries.len() as u64).to_le_bytes());
+        data.extend((directory.len() as u64).to_le_bytes());
+        data.extend((directory_offset as u64).to_le_bytes());
+
+        data.extend(0x07064b50u32.to_le_bytes());
+        data.extend([0; 4]); // disk number
+        data.extend(record_offset.to_le_bytes());
+        data.extend(1u32.to_le_bytes()); // number of disks
+    }
+    data.extend(0x06054b50u32.to_le_bytes());
+    data.extend([0; 4]); // disk numbers
+    if zip64 {
+        data.extend([0xff; 12]); // entries, directory size and offset
+    } else {
+        data.extend((entries.len() as u16).to_le_bytes());
+        data.extend((entries.len() as u16).to_le_bytes());
+        data.extend((directory.len() as u32).to_le_bytes());
+        data.extend(directory_offset.to_le_bytes());
+    }
+    data.extend([0, 0]); // comment length
+    std::fs::write(path, data).unwrap();
+}
+
+async fn create_provider(root: &Path, zip64: bool) -> ZipProvider {
+    write_zip(
+        &root.join(format!("{ALBUM_ID}.zip")),
+        &[
+            ("1/1.flac", "0123456789", false),
+            ("2/1.flac", "abcdefghij", true),
+            ("cover.jpg", "album", true),
+            ("2/cover.jpg", "disc 2", false),
+        ],
+        zip64,
+    );
+    std::fs::write(root.join("not-an-album.zip"), "").unwrap();
+    ZipProvider::new(root.to_path_buf()).await.unwrap()
+}
+
+async fn read_audio(provider: &ZipProvider, disc_id: u8, range: Range) -> (String, Range) {
+    let mut audio = provider
+        .get_audio(
+            ALBUM_ID,
+            NonZeroU8::new(disc_id).unwrap(),
+            NonZeroU8::new(1).unwrap(),
+            range,
+        )
+        .await
+        .unwrap();
+    let mut content = String::new();
+    audio.reader.read_to_string(&mut content).await.unwrap();
+    (content, audio.range)
+}
+
+#[tokio::test]
+async fn zip_albums() {
+    let root = tempfile::tempdir().unwrap();
+    let provider = create_provider(root.path(), false).await;
+
+    let albums = provider.albums().await.unwrap();
+    assert_eq!(albums.len(), 1);
+    assert!(provider.has_album(ALBUM_ID).await);
+}
+
+#[tokio::test]
+async fn zip_audio_range() {
+    let root = tempfile::tempdir().unwrap();
+    let provider = create_provider(root.path(), false).await;
+
+    // stored entry
+    let (content, range) = read_audio(&provider, 1, Range::new(2, Some(5))).await;
+    assert_eq!(content, "2345");
+    assert_eq!(range.end, Some(5));
+    assert_eq!(range.total, Some(10));
+    let (content, _) = read_audio(&provider, 1, Range::new(7, Some(100))).await;
+    assert_eq!(content, "789");
+
+    // deflated entry
+    let (content, _) = read_audio(&provider, 2, Range::new(4, None)).await;
+    assert_eq!(content, "efghij");
+    let (content, range) = read_audio(&provider, 2, Range::new(1, Some(2))).await;
+    assert_eq!(content, "bc");
+    assert_eq!(range.total, Some(10));
+
+    assert!(provider
+        .get_audio(
+            ALBUM_ID,
+            NonZeroU8::new(3).unwrap(),
+            NonZeroU8::new(1).unwrap(),
+            Range::FULL,
+        )
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn zip_cover() {
+    let root = tempfile::tempdir().unwrap();
+    let provider = create_provider(root.path(), false).await;
+
+    for (disc_id, expected) in [(None, "album"), (Some(1), "album"), (Some(2), "disc 2")] {
+        let mut reader = provider
+            .get_cover(ALBUM_ID, disc_id.and_then(NonZeroU8::new))
+            .await
+            .unwrap();
+        let mut cover = String::new();
+        reader.read_to_string(&mut cover).await.unwrap();
+        assert_eq!(cover, expected);
+    }
+    assert!(provider
+        .get_cover(ALBUM_ID, NonZeroU8::new(3))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn zip64_archive() {
+    let root = tempfile::tempdir().unwrap();
+    let provider = create_provider(root.path(), true).await;
+
+    let (content, range) = read_audio(&provider, 1, Range::new(2, Some(5))).await;
+    assert_eq!(content, "2345");
+    assert_eq!(range.total, Some(10));
+    let (content, _) = read_audio(&provider, 2, Range::new(4, None)).await;
+    assert_eq!(content, "efghij");
+}
+
+#[tokio::test]
+async fn zip_large_deflated_entry() {
+    let root = tempfile::tempdir().unwrap();
+    let content: String = (0..200_000)
+        .map(|i| char::from(b'a' + (i % 26) as u8))
+        .collect();
+    write_zip(
+        &root.path().join(format!("{ALBUM_ID}.zip")),
+        &[("1/1.flac", &content, true)],
+        false,
+    );
+    let provider = ZipProvider::new(root.path().to_path_buf()).await.unwrap();
+
+    let (read, _) = read_audio(&provider, 1, Range::FULL).await;
+    assert_eq!(read, content);
+    let (read, _) = read_audio(&provider, 1, Range::new(65_530, Some(131_080))).await;
+    assert_eq!(read, content[65_530..=131_080]);
+}
+
+#[tokio::test]
+async fn zip_invalid_directory() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join(format!("{ALBUM_ID}.zip"));
+    write_zip(&path, &[("1/1.flac", "0123456789", false)], false);
+    // directory size in end of central directory record is beyond the archive
+    let mut data = std::fs::read(&path).unwrap();
+    let size = data.len();
+    data[size - 10..size - 6].copy_from_slice(&0x7fff_ffffu32.to_le_bytes());
+    std::fs::write(&path, data).unwrap();
+    let provider = ZipProvider::new(root.path().to_path_buf()).await.unwrap();
+
+    assert!(provider
+        .get_audio(
+            ALBUM_ID,
+            NonZeroU8::new(1).unwrap(),
+            NonZeroU8::new(1).unwrap(),
+            Range::FULL,
+        )
+        .await
+        .is_err());
+}
@@ -1,12 +1,12 @@
 mod rows;
 
-pub const DB_VERSION: &str = "1.1";
+pub const DB_VERSION: &str = "1.2";
 
 #[cfg(feature = "db-read")]
 mod read;
 
 #[cfg(feature = "db-read")]
-pub use read::RepoDatabaseRead;
+pub use read::{ArtistEntry, RepoDatabaseRead};
 
 #[cfg(feature = "db-write")]
 mod write;
@@ -1,7 +1,9 @@
 use crate::db::rows;
 use crate::models::{Album, AnniDate, Disc, DiscInfo, TagString, TagType, Track, TrackType};
 use crate::prelude::{AlbumInfo, RepoResult};
-use rusqlite::{params, Connection, OpenFlags, Params};
+use anni_common::fs::sanitize_file_name;
+use anni_common::text::normalize_title;
+use rusqlite::{params, Connection, DatabaseName, OpenFlags, Params};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_rusqlite::from_rows;
@@ -13,6 +15,8 @@ use uuid::Uuid;
 pub struct RepoDatabaseRead {
     uri: PathBuf,
     conn: Connection,
+    /// Whether the database is copied into memory
+    in_memory: bool,
 }
 
 #[derive(Serialize)]
@@ -21,6 +25,13 @@ pub struct TagEntry {
     pub children: Vec<TagString>,
 }
 
+/// Album artist with the number of albums.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ArtistEntry {
+    pub artist: String,
+    pub albums: usize,
+}
+
 impl RepoDatabaseRead {
     pub fn new<P>(path: P) -> RepoResult<RepoDatabaseRead>
     where
@@ -29,14 +40,45 @@ impl RepoDatabaseRead {
         Ok(Self {
             uri: path.as_ref().to_path_buf(),
             conn: Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?,
+            in_memory: false,
+        })
+    }
+
+    /// Copy database at `path` into an in-memory database, so that queries do not touch the disk.
+    ///
+    /// The copy costs memory about the size of the database file, and is rebuilt on [Self::reload].
+    pub fn new_in_memory<P>(path: P) -> RepoResult<RepoDatabaseRead>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self {
+            uri: path.as_ref().to_path_buf(),
+            conn: Self::load_in_memory(path.as_ref())?,
+            in_memory: true,
         })
     }
 
+    fn load_in_memory(path: &Path) -> RepoResult<Connection> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.restore(
+            DatabaseName::Main,
+            path,
+            None::<fn(rusqlite::backup::Progress)>,
+        )?;
+        Ok(conn)
+    }
+
+    /// Whether the database is copied into memory.
+    pub fn is_in_memory(&self) -> bool {
+        self.in_memory
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub fn new_with_vfs(path: &str, vfs: &str) -> RepoResult<RepoDatabaseRead> {
         Ok(Self {
             uri: path.as_ref().to_path_buf(),
             conn: Connection::open_with_flags_and_vfs(path, OpenFlags::SQLITE_OPEN_READ_ONLY, vfs)?,
+            in_memory: false,
         })
     }
 
@@ -62,6 +104,25 @@ impl RepoDatabaseRead {
             albums.push(album?);
         }
 
+        if albums.is_empty() {
+            // catalog from file name may have been sanitized
+            let mut stmt = self.conn.prepare(
+                "SELECT album_id, catalog, title FROM repo_album
+  WHERE release_date = ? AND disc_count = ?;",
+            )?;
+            let albums_iter = stmt
+                .query_map(params![release_date.to_string(), disc_count], |row| {
+                    Ok((row.get(0)?, row.get::<_, String>(1)?, row.get(2)?))
+                })?;
+            let catalog = sanitize_file_name(catalog);
+            for album in albums_iter {
+                let (album_id, album_catalog, title) = album?;
+                if sanitize_file_name(&album_catalog) == catalog {
+                    albums.push((album_id, title));
+                }
+            }
+        }
+
         if albums.is_empty() {
             Ok(None)
         } else if albums.len() == 1 {
@@ -69,7 +130,11 @@ impl RepoDatabaseRead {
         } else {
             let filtered: Vec<_> = albums
                 .iter()
-                .filter(|(_, title)| title == album_title)
+                .filter(|(_, title)| {
+                    title == album_title
+                        || sanitize_file_name(title) == sanitize_file_name(album_title)
+                        || normalize_title(title) == normalize_title(album_title)
+                })
                 .collect();
             if filtered.is_empty() {
                 Ok(None)
@@ -131,6 +196,7 @@ impl RepoDatabaseRead {
             release_date: AnniDate::from_str(&album_row.release_date)?,
             album_type: TrackType::from_str(&album_row.album_type)?,
             catalog: album_row.catalog,
+            series: album_row.series,
             tags: album_tags,
         };
 
@@ -174,6 +240,15 @@ impl RepoDatabaseRead {
         self.query_optional("SELECT * FROM repo_album WHERE album_id = ?", [album_id])
     }
 
+    /// Get ids of albums with the given catalog.
+    pub fn get_album_ids_by_catalog(&self, catalog: &str) -> RepoResult<Vec<Uuid>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT album_id FROM repo_album WHERE catalog = ? ORDER BY album_id")?;
+        let album_ids = stmt.query_map(params![catalog], |row| row.get(0))?;
+        Ok(album_ids.collect::<Result<_, _>>()?)
+    }
+
     pub fn get_disc(&self, album_id: Uuid, disc_id: u8) -> RepoResult<Option<rows::DiscRow>> {
         self.query_optional(
             "SELECT * FROM repo_disc WHERE album_id = ? AND disc_id = ?",
@@ -330,8 +405,21 @@ SELECT * FROM repo_album WHERE album_id IN (
         }
     }
 
+    /// Get distinct album artists with the number of albums, sorted by number of albums.
+    pub fn album_artists(&self) -> RepoResult<Vec<ArtistEntry>> {
+        self.query_list(
+            "SELECT artist, COUNT(*) AS albums FROM repo_album
+  GROUP BY artist ORDER BY albums DESC, artist;",
+            params![],
+        )
+    }
+
     pub fn reload(&mut self) -> RepoResult<()> {
-        self.conn = Connection::open(&self.uri)?;
+        self.conn = if self.in_memory {
+            Self::load_in_memory(&self.uri)?
+        } else {
+            Connection::open(&self.uri)?
+        };
         Ok(())
     }
 }
@@ -33,6 +33,8 @@ pub struct AlbumRow {
     pub release_date: String,
     #[serde(rename(serialize = "type"))]
     pub album_type: String,
+    #[serde(default)]
+    pub series: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -83,6 +85,7 @@ interface AlbumRow {
     artist: string;
     release_date: string;
     type: TrackType;
+    series?: string;
 }
 
 type AlbumRowArray = AlbumRow[];
@@ -31,7 +31,8 @@ CREATE TABLE IF NOT EXISTS "repo_album" (
   "artist"         TEXT NOT NULL,
   "release_date"   TEXT NOT NULL,
   "disc_count"     INTEGER NOT NULL,
-  "album_type"     TEXT NOT NULL DEFAULT 'normal' CHECK("album_type" IN ('normal', 'instrumental', 'absolute', 'drama', 'radio', 'vocal'))
+  "album_type"     TEXT NOT NULL DEFAULT 'normal' CHECK("album_type" IN ('normal', 'instrumental', 'absolute', 'drama', 'radio', 'vocal')),
+  "series"         TEXT
 );
 
 CREATE TABLE IF NOT EXISTS "repo_disc" (
@@ -105,6 +106,22 @@ CREATE UNIQUE INDEX "repo_album_index" ON "repo_album" (
   "album_id"
 );
 
+CREATE INDEX IF NOT EXISTS "repo_album_series_index" ON "repo_album" (
+  "series"
+);
+
+CREATE INDEX IF NOT EXISTS "repo_album_catalog_index" ON "repo_album" (
+  "catalog"
+);
+
+CREATE INDEX IF NOT EXISTS "repo_album_title_index" ON "repo_album" (
+  "title"
+);
+
+CREATE INDEX IF NOT EXISTS "repo_album_artist_index" ON "repo_album" (
+  "artist"
+);
+
 CREATE UNIQUE INDEX IF NOT EXISTS "repo_disc_index" ON "repo_disc" (
   "album_id",
   "disc_id"
@@ -122,6 +139,22 @@ CREATE INDEX IF NOT EXISTS "repo_tag_detail_index" ON "repo_tag_detail" (
   "track_id"
 );
 
+CREATE INDEX IF NOT EXISTS "repo_tag_detail_tag_index" ON "repo_tag_detail" (
+  "tag_id"
+);
+
+CREATE INDEX IF NOT EXISTS "repo_tag_i18n_index" ON "repo_tag_i18n" (
+  "tag_id"
+);
+
+CREATE INDEX IF NOT EXISTS "repo_tag_relation_index" ON "repo_tag_relation" (
+  "tag_id"
+);
+
+CREATE INDEX IF NOT EXISTS "repo_tag_relation_parent_index" ON "repo_tag_relation" (
+  "parent_id"
+);
+
 COMMIT;
 "#,
         )?;
@@ -129,12 +162,20 @@ COMMIT;
         Ok(())
     }
 
+    /// Collect statistics for the query planner and compact the database file.
+    ///
+    /// This should be called after all data and indexes are written.
+    pub fn optimize(&self) -> RepoResult<()> {
+        self.conn.execute_batch("ANALYZE; VACUUM;")?;
+        Ok(())
+    }
+
     pub fn add_album(&self, album: &crate::models::Album) -> RepoResult<()> {
         let album_id = album.album_id();
 
         // add album info
         self.conn.execute(
-            "INSERT INTO repo_album (album_id, title, edition, catalog, artist, release_date, disc_count, album_type) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO repo_album (album_id, title, edition, catalog, artist, release_date, disc_count, album_type, series) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 album_id,
                 album.title_raw(),
@@ -144,6 +185,7 @@ COMMIT;
                 album.release_date().to_string(),
                 album.discs_len(),
                 album.track_type().as_ref(),
+                album.series(),
             ],
         )?;
 
@@ -0,0 +1,204 @@
+//! In-place edits of album toml, which keep comments and order of the original file.
+
+use crate::prelude::*;
+use toml_edit::{Array, Document, Item, Table, Value};
+
+/// Add `tag` to album tags of album toml `input`.
+///
+/// Returns `None` if the album already has the tag.
+pub fn add_album_tag(input: &str, tag: &TagRef) -> RepoResult<Option<String>> {
+    let mut document: Document = input.parse()?;
+    let Some(album) = document.get_mut("album").and_then(Item::as_table_mut) else {
+        return Ok(None);
+    };
+
+    let tags = album
+        .entry("tags")
+        .or_insert_with(|| toml_edit::value(multiline_array()));
+    let Some(tags) = tags.as_array_mut() else {
+        return Ok(None);
+    };
+    if tags.iter().any(|t| matches_tag(t, tag)) {
+        return Ok(None);
+    }
+
+    // follow decoration of existing tags
+    let decor = tags.iter().last().map(|t| t.decor().clone());
+    tags.push(tag.to_string());
+    if let Some(decor) = decor {
+        *tags.get_mut(tags.len() - 1).unwrap().decor_mut() = decor;
+    } else {
+        tags.get_mut(0).unwrap().decor_mut().set_prefix("\n    ");
+    }
+    Ok(Some(document.to_string()))
+}
+
+/// Remove `tag` from album tags of album toml `input`.
+///
+/// Returns `None` if the album does not have the tag.
+pub fn remove_album_tag(input: &str, tag: &TagRef) -> RepoResult<Option<String>> {
+    let mut document: Document = input.parse()?;
+    let Some(tags) = document
+        .get_mut("album")
+        .and_then(Item::as_table_mut)
+        .and_then(|album| album.get_mut("tags"))
+        .and_then(Item::as_array_mut)
+    else {
+        return Ok(None);
+    };
+
+    let len = tags.len();
+    tags.retain(|t| !matches_tag(t, tag));
+    Ok((tags.len() != len).then(|| document.to_string()))
+}
+
+/// Rename artist `old` to `new` in album, discs and tracks of album toml `input`.
+///
+/// Returns the modified toml with the number of renamed entries, or `None` if no artist matches.
+pub fn rename_artist(input: &str, old: &str, new: &str) -> RepoResult<Option<(String, usize)>> {
+    let mut document: Document = input.parse()?;
+    let mut count = 0;
+    if let Some(album) = document.get_mut("album").and_then(Item::as_table_mut) {
+        count += rename_table_artist(album, old, new);
+    }
+    if let Some(discs) = document
+        .get_mut("discs")
+        .and_then(Item::as_array_of_tables_mut)
+    {
+        for disc in discs.iter_mut() {
+            count += rename_table_artist(disc, old, new);
+            if let Some(tracks) = disc
+                .get_mut("tracks")
+                .and_then(Item::as_array_of_tables_mut)
+            {
+                for track in tracks.iter_mut() {
+                    count += rename_table_artist(track, old, new);
+                }
+            }
+        }
+    }
+    Ok((count > 0).then(|| (document.to_string(), count)))
+}
+
+/// Rename `artist` field of `table`, and keep its decoration.
+fn rename_table_artist(table: &mut Table, old: &str, new: &str) -> usize {
+    let Some(artist) = table.get_mut("artist").and_then(Item::as_value_mut) else {
+        return 0;
+    };
+    if artist.as_str() != Some(old) {
+        return 0;
+    }
+
+    let decor = artist.decor().clone();
+    *artist = Value::from(new);
+    *artist.decor_mut() = decor;
+    1
+}
+
+/// Tags without type match tags of any type with the same name.
+fn matches_tag(value: &Value, tag: &TagRef) -> bool {
+    let Some(value) = value.as_str() else {
+        return false;
+    };
+    let value = TagRef::from_cow_str(value);
+    value.name() == tag.name()
+        && (value.tag_type() == tag.tag_type()
+            || *value.tag_type() == TagType::Unknown
+            || *tag.tag_type() == TagType::Unknown)
+}
+
+fn multiline_array() -> Array {
+    let mut array = Array::new();
+    array.set_trailing("\n");
+    array.set_trailing_comma(true);
+    array
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = r#"[album]
+title = "Title"
+# tags of the album
+tags = [
+    "tag1",
+    "artist:tag2",
+]
+"#;
+
+    #[test]
+    fn test_add_album_tag() {
+        let output = add_album_tag(INPUT, &TagRef::from_cow_str("tag3"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            output,
+            r#"[album]
+title = "Title"
+# tags of the album
+tags = [
+    "tag1",
+    "artist:tag2",
+    "tag3",
+]
+"#
+        );
+        assert!(add_album_tag(&output, &TagRef::from_cow_str("artist:tag1"))
+            .unwrap()
+            .is_none());
+
+        let output = add_album_tag(
+            "[album]\ntitle = \"Title\"\n",
+            &TagRef::from_cow_str("tag1"),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            output,
+            "[album]\ntitle = \"Title\"\ntags = [\n    \"tag1\",\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_album_tag() {
+        let output = remove_album_tag(INPUT, &TagRef::from_cow_str("tag2"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            output,
+            r#"[album]
+title = "Title"
+# tags of the album
+tags = [
+    "tag1",
+]
+"#
+        );
+        assert!(remove_album_tag(&output, &TagRef::from_cow_str("tag2"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_rename_artist() {
+        let input = r#"[album]
+title = "Title"
+artist = "Old" # album artist
+
+[[discs]]
+artist = "Other"
+
+[[discs.tracks]]
+title = "Track 1"
+artist = "Old"
+
+[[discs.tracks]]
+title = "Track 2"
+"#;
+        let (output, count) = rename_artist(input, "Old", "New").unwrap().unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(output, input.replace("\"Old\"", "\"New\""));
+        assert!(rename_artist(&output, "Old", "New").unwrap().is_none());
+    }
+}
@@ -11,6 +11,9 @@ pub enum Error {
         err: toml::de::Error,
     },
 
+    #[error(transparent)]
+    TomlEditError(#[from] toml_edit::TomlError),
+
     #[error("album with the same catalog already exists: {0}")]
     RepoAlbumExists(String),
 
@@ -41,9 +44,29 @@ pub enum Error {
     #[error("invalid date: {0}")]
     InvalidDate(String),
 
+    #[error("{field} of {target} is required")]
+    BuilderMissingField {
+        target: &'static str,
+        field: &'static str,
+    },
+
+    #[error("too many {target}: {actual}, limit is {limit}")]
+    AlbumLimitExceeded {
+        target: &'static str,
+        limit: usize,
+        actual: usize,
+    },
+
+    #[error("invalid lyric tag [{tag}] at line {line}")]
+    InvalidLyricTag { line: usize, tag: String },
+
     #[error(transparent)]
     IOError(#[from] std::io::Error),
 
+    #[cfg(feature = "json")]
+    #[error(transparent)]
+    JsonParseError(#[from] serde_json::Error),
+
     #[cfg(any(feature = "db-read", feature = "db-write"))]
     #[error(transparent)]
     SqliteError(#[from] rusqlite::Error),
@@ -1,6 +1,8 @@
+pub mod edit;
 pub mod error;
 pub mod library;
 mod manager;
+pub mod migrate;
 pub mod models;
 
 #[cfg(feature = "search")]
@@ -15,8 +17,10 @@ pub mod prelude {
 
 pub mod db;
 pub(crate) mod utils;
+pub mod validate;
 
+pub use anni_common::text::normalize_title;
 pub use manager::{OwnedRepositoryManager, RepositoryManager};
 
 #[cfg(feature = "git")]
-pub use utils::git::setup_git2;
+pub use utils::git::{setup_git2, RetryPolicy};
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use crate::validate::{validate_album, validate_album_lyrics, ValidateDiagnostic};
 use anni_common::fs;
 use indexmap::IndexSet;
 use std::collections::{HashMap, HashSet};
@@ -48,6 +49,30 @@ impl RepositoryManager {
         Self::new(root.as_ref())
     }
 
+    /// Clone a repository like [Self::clone], retrying on network errors.
+    ///
+    /// A partially cloned repository at `root` is resumed instead of cloned again.
+    #[cfg(feature = "git")]
+    pub fn clone_with_retry<P>(url: &str, root: P, policy: crate::RetryPolicy) -> RepoResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        crate::utils::git::setup_git2_internal();
+        policy.run(|| crate::utils::git::clone(url, root.as_ref()))?;
+        Self::new(root.as_ref())
+    }
+
+    /// Pull a repository like [Self::pull], retrying on network errors.
+    #[cfg(feature = "git")]
+    pub fn pull_with_retry<P>(root: P, branch: &str, policy: crate::RetryPolicy) -> RepoResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        crate::utils::git::setup_git2_internal();
+        policy.run(|| crate::utils::git::pull(root.as_ref(), branch))?;
+        Self::new(root.as_ref())
+    }
+
     pub fn name(&self) -> &str {
         self.repo.name()
     }
@@ -56,6 +81,36 @@ impl RepositoryManager {
         self.repo.edition()
     }
 
+    /// Root directory of album covers, if cover asset is enabled.
+    pub fn cover_root(&self) -> Option<PathBuf> {
+        self.repo.cover().map(|cover| self.root.join(&cover.root))
+    }
+
+    /// Path of the cover of album with given `album_id`, if cover asset is enabled.
+    ///
+    /// Covers are stored as `{cover_root}/{album_id}.jpg`.
+    pub fn cover_path(&self, album_id: &Uuid) -> Option<PathBuf> {
+        self.cover_root()
+            .map(|root| root.join(format!("{album_id}.jpg")))
+    }
+
+    /// Root directory of lyrics, if lyric asset is enabled.
+    pub fn lyric_root(&self) -> Option<PathBuf> {
+        self.repo.lyric().map(|lyric| self.root.join(&lyric.root))
+    }
+
+    /// Path of the LRC lyric of a track, if lyric asset is enabled.
+    ///
+    /// Lyrics are stored as `{lyric_root}/{album_id}/{disc_id}/{track_id}.lrc`.
+    /// Plain text lyrics use the same path with `.txt` extension.
+    pub fn lyric_path(&self, album_id: &Uuid, disc_id: usize, track_id: usize) -> Option<PathBuf> {
+        self.lyric_root().map(|root| {
+            root.join(album_id.to_string())
+                .join(disc_id.to_string())
+                .join(format!("{track_id}.lrc"))
+        })
+    }
+
     // Get all album roots.
     fn album_roots(&self) -> Vec<PathBuf> {
         self.repo
@@ -107,6 +162,18 @@ impl RepositoryManager {
         Ok(paths)
     }
 
+    /// Load all albums one by one with their paths.
+    ///
+    /// Unlike [OwnedRepositoryManager], albums are not kept in memory.
+    pub fn iter_albums(
+        &self,
+    ) -> RepoResult<impl Iterator<Item = (PathBuf, RepoResult<Album>)> + '_> {
+        Ok(self.all_album_paths()?.into_iter().map(move |path| {
+            let album = self.load_album(&path);
+            (path, album)
+        }))
+    }
+
     /// Get album paths with given catalog.
     pub fn album_paths(&self, catalog: &str) -> RepoResult<Vec<PathBuf>> {
         let mut paths = Vec::new();
@@ -158,6 +225,39 @@ impl RepositoryManager {
             .collect())
     }
 
+    /// Load album(s) with given catalog, which can also be a catalog range like `KSLA-0178~0180`.
+    ///
+    /// Albums with ranged catalog may be stored with catalog of a disc. If no album is found by
+    /// the catalog itself, albums of catalogs in the range are loaded, and those with the same
+    /// catalog range or disc catalogs are returned.
+    pub fn resolve_albums(&self, catalog: &str) -> RepoResult<Vec<Album>> {
+        let albums = self.load_albums(catalog)?;
+        if !albums.is_empty() {
+            return Ok(albums);
+        }
+        let Some(catalogs) = expand_catalog_range(catalog, AlbumLimits::DEFAULT.max_discs) else {
+            return Ok(albums);
+        };
+
+        let mut result: Vec<Album> = Vec::new();
+        for candidate in catalogs.iter() {
+            for album in self.load_albums(candidate)? {
+                let matches = album.catalog() == catalog
+                    || expand_catalog_range(album.catalog(), AlbumLimits::DEFAULT.max_discs)
+                        .as_ref()
+                        == Some(&catalogs)
+                    || album
+                        .iter()
+                        .map(|disc| disc.catalog().to_string())
+                        .eq(catalogs.iter().cloned());
+                if matches && !result.iter().any(|a| a.album_id() == album.album_id()) {
+                    result.push(album);
+                }
+            }
+        }
+        Ok(result)
+    }
+
     /// Add new album to the repository.
     pub fn add_album(&self, mut album: Album, allow_duplicate: bool) -> RepoResult<()> {
         let catalog = album.catalog();
@@ -203,6 +303,35 @@ impl RepositoryManager {
     }
 }
 
+/// Expand catalog range like `KSLA-0178~0180` or `KSLA-0178~80` to catalogs in the range.
+///
+/// Returns `None` if `catalog` is not a range, or the range contains more than `max` catalogs.
+pub(crate) fn expand_catalog_range(catalog: &str, max: usize) -> Option<Vec<String>> {
+    let (first, last) = catalog.split_once('~')?;
+    let digits = first.bytes().rev().take_while(u8::is_ascii_digit).count();
+    let (prefix, start) = first.split_at(first.len() - digits);
+    let last = last.trim();
+    if start.is_empty()
+        || last.is_empty()
+        || last.len() > start.len()
+        || !last.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    // the last catalog may omit leading digits shared with the first one
+    let end = format!("{}{last}", &start[..start.len() - last.len()]);
+    let (start_number, end_number): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+    if start_number > end_number || end_number - start_number >= max as u64 {
+        return None;
+    }
+    Some(
+        (start_number..=end_number)
+            .map(|n| format!("{prefix}{n:0width$}", width = start.len()))
+            .collect(),
+    )
+}
+
 /// A repository manager which own full copy of a repo.
 ///
 /// This is helpful when you need to perform a full-repo operation,
@@ -226,7 +355,26 @@ pub struct OwnedRepositoryManager {
 
 impl OwnedRepositoryManager {
     pub fn new(repo: RepositoryManager) -> RepoResult<Self> {
-        let mut repo = Self {
+        let mut repo = Self::lock(repo)?;
+        repo.load_tags()?;
+        repo.load_albums()?;
+
+        Ok(repo)
+    }
+
+    /// Load tags of the repository only, while albums are left unloaded.
+    ///
+    /// Albums loaded one by one with [RepositoryManager::iter_albums] can be resolved by
+    /// [resolve_album_tags](Self::resolve_album_tags) without keeping all of them in memory.
+    pub fn new_tags_only(repo: RepositoryManager) -> RepoResult<Self> {
+        let mut repo = Self::lock(repo)?;
+        repo.load_tags()?;
+
+        Ok(repo)
+    }
+
+    fn lock(repo: RepositoryManager) -> RepoResult<Self> {
+        let repo = Self {
             repo,
             tags: Default::default(),
             tags_relation: Default::default(),
@@ -243,9 +391,6 @@ impl OwnedRepositoryManager {
         }
 
         fs::write(lock_file, "")?;
-        repo.load_tags()?;
-        repo.load_albums()?;
-
         Ok(repo)
     }
 
@@ -275,6 +420,11 @@ impl OwnedRepositoryManager {
             .and_then(|tags| tags.get(tag.tag_type()))
     }
 
+    /// Resolve tags of an album which is not loaded by this manager.
+    pub fn resolve_album_tags(&self, album: &mut Album) -> RepoResult<()> {
+        album.resolve_tags(&self.tags)
+    }
+
     pub fn tags_iter(&self) -> impl Iterator<Item = &Tag> {
         self.tags.values().flat_map(|m| m.values())
     }
@@ -299,6 +449,161 @@ impl OwnedRepositoryManager {
         self.album_tags.get(tag)
     }
 
+    /// Get all albums in the given series, sorted by catalog.
+    pub fn albums_in_series(&self, series: &str) -> Vec<&Album> {
+        let mut albums: Vec<_> = self
+            .albums
+            .values()
+            .filter(|album| album.series() == Some(series))
+            .collect();
+        albums.sort_by(|a, b| a.catalog().cmp(b.catalog()));
+        albums
+    }
+
+    /// Get all series in the repository, with the number of albums in each series.
+    pub fn series(&self) -> HashMap<&str, usize> {
+        let mut series = HashMap::new();
+        for album in self.albums.values() {
+            if let Some(name) = album.series() {
+                *series.entry(name).or_insert(0) += 1;
+            }
+        }
+        series
+    }
+
+    /// Search albums by artist, title and tag, sorted by catalog.
+    ///
+    /// `artist` and `title` are matched as case-insensitive substrings, and `tag` is matched
+    /// by [albums_tagged_by](Self::albums_tagged_by). Filters which are `None` are ignored.
+    pub fn search_albums(
+        &self,
+        artist: Option<&str>,
+        title: Option<&str>,
+        tag: Option<&TagRef<'_>>,
+    ) -> Vec<&Album> {
+        fn contains(haystack: &str, needle: &str) -> bool {
+            haystack.to_lowercase().contains(&needle.to_lowercase())
+        }
+
+        let tagged = tag.map(|tag| {
+            self.albums_tagged_by(tag).map_or(HashSet::new(), |albums| {
+                albums.iter().collect::<HashSet<_>>()
+            })
+        });
+        let mut albums: Vec<_> = self
+            .albums
+            .values()
+            .filter(|album| {
+                artist.map_or(true, |artist| contains(album.artist(), artist))
+                    && title.map_or(true, |title| contains(&album.full_title(), title))
+                    && tagged
+                        .as_ref()
+                        .map_or(true, |tagged| tagged.contains(&album.album_id()))
+            })
+            .collect();
+        albums.sort_by(|a, b| a.catalog().cmp(b.catalog()));
+        albums
+    }
+
+    /// Get catalogs shared by albums in different places, sorted by catalog.
+    ///
+    /// Albums in the same `{catalog}/{catalog}.N.toml` folder are different editions of
+    /// the same catalog, so they are not duplicates of each other.
+    /// Album ids of each catalog are sorted by album path.
+    pub fn duplicate_catalogs(&self) -> Vec<(String, Vec<Uuid>)> {
+        let mut catalogs: HashMap<&str, Vec<&Album>> = HashMap::new();
+        for album in self.albums.values() {
+            catalogs.entry(album.catalog()).or_default().push(album);
+        }
+
+        let mut duplicates: Vec<_> = catalogs
+            .into_iter()
+            .filter(|(_, albums)| {
+                let places: HashSet<_> = albums
+                    .iter()
+                    .filter_map(|album| self.album_path(&album.album_id()))
+                    .map(album_place)
+                    .collect();
+                places.len() > 1
+            })
+            .map(|(catalog, mut albums)| {
+                albums.sort_by_key(|album| self.album_path(&album.album_id()));
+                let album_ids = albums.iter().map(|album| album.album_id()).collect();
+                (catalog.to_string(), album_ids)
+            })
+            .collect();
+        duplicates.sort();
+        duplicates
+    }
+
+    /// Validate all albums with [validate_album] and [validate_album_lyrics].
+    ///
+    /// Returns albums with at least one problem and their paths, sorted by path.
+    pub fn validate(&self, today: &AnniDate) -> Vec<(&Path, &Album, Vec<ValidateDiagnostic>)> {
+        let mut result: Vec<_> = self
+            .albums
+            .values()
+            .filter_map(|album| {
+                let mut diagnostics = validate_album(album, today);
+                diagnostics.extend(validate_album_lyrics(album, &self.repo));
+                if diagnostics.is_empty() {
+                    return None;
+                }
+                let path = self.album_path(&album.album_id()).unwrap();
+                Some((path, album, diagnostics))
+            })
+            .collect();
+        result.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+        result
+    }
+
+    /// Get lyric files in lyric root which are not referenced by any track, sorted by path.
+    ///
+    /// Hidden files are ignored. Returns an empty list if lyric asset is not enabled.
+    pub fn orphan_lyrics(&self) -> RepoResult<Vec<PathBuf>> {
+        let Some(root) = self.repo.lyric_root() else {
+            return Ok(Vec::new());
+        };
+        if !root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut referenced = HashSet::new();
+        for album in self.albums.values() {
+            for (disc_index, disc) in album.iter().enumerate() {
+                for track_index in 0..disc.iter().count() {
+                    let path = self
+                        .repo
+                        .lyric_path(&album.album_id(), disc_index + 1, track_index + 1)
+                        .unwrap();
+                    // plain text lyrics
+                    referenced.insert(path.with_extension("txt"));
+                    referenced.insert(path);
+                }
+            }
+        }
+
+        let mut orphans = Vec::new();
+        let mut dirs = vec![root];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+
+                let path = entry.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if !referenced.contains(&path) {
+                    orphans.push(path);
+                }
+            }
+        }
+        orphans.sort();
+        Ok(orphans)
+    }
+
     fn add_tag(&mut self, tag: Tag, tag_relative_path: PathBuf) -> Result<(), Error> {
         // fully duplicated tags are not allowed
         if let Some(tag) = self.tag(tag.as_ref()) {
@@ -435,69 +740,92 @@ impl OwnedRepositoryManager {
         }
     }
 
+    /// Find a cycle in tag relations, like the first one of [Self::validate_tag_graph].
     pub fn check_tags_loop<'me, 'tag>(&'me self) -> Option<Vec<&'me TagRef<'tag>>>
     where
         'me: 'tag,
     {
-        fn dfs<'tag, 'func>(
-            tag: &'tag TagRef<'tag>,
-            tags_relation: &'tag HashMap<TagRef<'static>, IndexSet<TagRef<'static>>>,
-            current: &'func mut HashMap<&'tag TagRef<'tag>, bool>,
-            visited: &'func mut HashMap<&'tag TagRef<'tag>, bool>,
-            mut path: Vec<&'tag TagRef<'tag>>,
-        ) -> (bool, Vec<&'tag TagRef<'tag>>) {
-            visited.insert(tag, true);
-            current.insert(tag, true);
-            path.push(tag);
+        self.tag_cycles().into_iter().next()
+    }
 
-            if let Some(children) = tags_relation.get(tag) {
-                for child in children {
-                    if let Some(true) = current.get(child) {
-                        path.push(child);
-                        return (true, path);
-                    }
-                    // if !visited[child]
-                    if !visited.get(child).map_or(false, |x| *x) {
-                        let (loop_detected, loop_path) =
-                            dfs(child, tags_relation, current, visited, path);
-                        if loop_detected {
-                            return (true, loop_path);
-                        } else {
-                            path = loop_path;
-                        }
-                    }
+    /// Find cycles in tag relations by back edges of DFS.
+    ///
+    /// Each cycle starts and ends with the same tag, e.g. `[A, B, A]` if A includes B and B includes A.
+    /// One cycle is found for each back edge, so cycles sharing tags with a found one may be
+    /// missing, but relations are acyclic if and only if no cycle is found.
+    pub fn validate_tag_graph(&self) -> Result<(), Vec<Vec<TagRef<'static>>>> {
+        let cycles = self.tag_cycles();
+        if cycles.is_empty() {
+            Ok(())
+        } else {
+            Err(cycles
+                .into_iter()
+                .map(|cycle| cycle.into_iter().cloned().collect())
+                .collect())
+        }
+    }
+
+    fn tag_cycles(&self) -> Vec<Vec<&TagRef<'static>>> {
+        fn dfs<'a>(
+            tag: &'a TagRef<'static>,
+            tags_relation: &'a HashMap<TagRef<'static>, IndexSet<TagRef<'static>>>,
+            visited: &mut HashSet<&'a TagRef<'static>>,
+            path: &mut Vec<&'a TagRef<'static>>,
+            cycles: &mut Vec<Vec<&'a TagRef<'static>>>,
+        ) {
+            visited.insert(tag);
+            path.push(tag);
+            for child in tags_relation.get(tag).into_iter().flatten() {
+                if let Some(start) = path.iter().position(|t| *t == child) {
+                    let mut cycle = path[start..].to_vec();
+                    cycle.push(child);
+                    cycles.push(cycle);
+                } else if !visited.contains(child) {
+                    dfs(child, tags_relation, visited, path, cycles);
                 }
             }
-
-            current.insert(tag, false);
             path.pop();
-            (false, path)
         }
 
-        let mut visited: HashMap<&TagRef, bool> = Default::default();
-        let mut current: HashMap<&TagRef, bool> = Default::default();
-        let tags: Vec<_> = self.tags_iter().map(|t| t.as_ref()).collect();
-        for tag in tags.into_iter() {
-            // if !visited[tag]
-            if !visited.get(&tag).map_or(false, |x| *x) {
-                let (loop_detected, path) = dfs(
+        // start from tags in order, so that the same cycles are found each time
+        let mut tags: Vec<_> = self.tags_relation.keys().collect();
+        tags.sort_by_cached_key(|tag| tag.to_string());
+
+        let mut visited = HashSet::new();
+        let mut cycles = Vec::new();
+        for tag in tags {
+            if !visited.contains(tag) {
+                dfs(
                     tag,
                     &self.tags_relation,
-                    &mut current,
                     &mut visited,
-                    Default::default(),
+                    &mut Vec::new(),
+                    &mut cycles,
                 );
-                if loop_detected {
-                    return Some(path);
-                }
             }
         }
-
-        None
+        cycles
     }
 
     #[cfg(feature = "db-write")]
     pub fn to_database<P>(&self, database_path: P) -> RepoResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.write_database(database_path, false)
+    }
+
+    /// Write database like [Self::to_database], and optimize it for querying and distribution.
+    #[cfg(feature = "db-write")]
+    pub fn to_optimized_database<P>(&self, database_path: P) -> RepoResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.write_database(database_path, true)
+    }
+
+    #[cfg(feature = "db-write")]
+    fn write_database<P>(&self, database_path: P, optimize: bool) -> RepoResult<()>
     where
         P: AsRef<Path>,
     {
@@ -521,6 +849,9 @@ impl OwnedRepositoryManager {
 
         // Create Index
         db.create_index()?;
+        if optimize {
+            db.optimize()?;
+        }
 
         // Creation time
         fs::write(
@@ -577,3 +908,16 @@ impl Drop for OwnedRepositoryManager {
         }
     }
 }
+
+/// Place of album at `path`, which is the folder for albums in `{catalog}/{catalog}.N.toml`
+/// layout, or the file itself otherwise.
+fn album_place(path: &Path) -> &Path {
+    let folder = path.parent().filter(|parent| {
+        let (Some(folder), Some(file)) = (parent.file_name(), path.file_name()) else {
+            return false;
+        };
+        file.to_string_lossy()
+            .starts_with(&format!("{}.", folder.to_string_lossy()))
+    });
+    folder.unwrap_or(path)
+}
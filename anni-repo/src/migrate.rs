@@ -0,0 +1,153 @@
+//! Migrate metadata files between repository editions.
+//!
+//! Each [Migration] is a named step that brings album files to a newer edition.
+//! Steps work on the raw toml document so that comments and formatting are preserved,
+//! and are idempotent so that running a migration twice is harmless.
+use crate::prelude::*;
+use std::cmp::Ordering;
+use toml_edit::{Document, Item, Table};
+
+pub struct Migration {
+    /// Edition this step migrates to
+    pub edition: &'static str,
+    /// Name of this step
+    pub name: &'static str,
+    /// Apply this step on an album document, returns whether the document was changed
+    apply: fn(&mut Table) -> bool,
+}
+
+/// Latest repository edition.
+pub const LATEST_EDITION: &str = "1.0+alpha.1.5.1";
+
+/// All migration steps, sorted by edition.
+///
+/// A step is added here only when [LATEST_EDITION] is bumped with a format change.
+/// No edition before [LATEST_EDITION] has been released, so there are no steps yet.
+pub const MIGRATIONS: &[Migration] = &[];
+
+/// Compare editions by their numeric parts. Build metadata after `+` is ignored.
+fn edition_key(edition: &str) -> Vec<u64> {
+    edition
+        .split('+')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Compare two repository editions.
+pub fn compare_editions(a: &str, b: &str) -> Ordering {
+    edition_key(a).cmp(&edition_key(b))
+}
+
+/// Get migration steps needed to migrate from edition `from` to edition `to`.
+pub fn migrations_between(from: &str, to: &str) -> Vec<&'static Migration> {
+    steps_between(MIGRATIONS, from, to)
+}
+
+fn steps_between<'a>(migrations: &'a [Migration], from: &str, to: &str) -> Vec<&'a Migration> {
+    let from = edition_key(from);
+    let to = edition_key(to);
+    migrations
+        .iter()
+        .filter(|m| {
+            let edition = edition_key(m.edition);
+            from < edition && edition <= to
+        })
+        .collect()
+}
+
+/// Apply migration `steps` on album toml `input`.
+///
+/// Returns `None` if nothing was changed.
+pub fn migrate_album(input: &str, steps: &[&Migration]) -> RepoResult<Option<String>> {
+    let mut document: Document = input.parse()?;
+    let album = match document.get_mut("album").and_then(Item::as_table_mut) {
+        Some(album) => album,
+        None => return Ok(None),
+    };
+
+    let mut changed = false;
+    for step in steps {
+        if (step.apply)(album) {
+            log::debug!("Applied migration step {}", step.name);
+            changed = true;
+        }
+    }
+    Ok(changed.then(|| document.to_string()))
+}
+
+/// Set edition of repository in `repo.toml`.
+pub fn set_repo_edition(input: &str, edition: &str) -> RepoResult<String> {
+    let mut document: Document = input.parse()?;
+    document["repo"]["edition"] = toml_edit::value(edition);
+    Ok(document.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename_release_group(album: &mut Table) -> bool {
+        match album.remove("release_group") {
+            Some(series) => {
+                if !album.contains_key("series") {
+                    album.insert("series", series);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    const STEPS: &[Migration] = &[
+        Migration {
+            edition: "1.1",
+            name: "rename-release-group",
+            apply: rename_release_group,
+        },
+        Migration {
+            edition: "1.2",
+            name: "noop",
+            apply: |_| false,
+        },
+    ];
+
+    #[test]
+    fn test_migrations_between() {
+        let names = |from, to| {
+            steps_between(STEPS, from, to)
+                .into_iter()
+                .map(|m| m.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(LATEST_EDITION, "1.2"),
+            vec!["rename-release-group", "noop"]
+        );
+        assert_eq!(names("1.1", "1.2"), vec!["noop"]);
+        assert!(names("1.2", "1.2").is_empty());
+        assert!(migrations_between(LATEST_EDITION, LATEST_EDITION).is_empty());
+    }
+
+    #[test]
+    fn test_migrate_album_idempotent() {
+        let input = r#"[album]
+album_id = "15006392-e2ae-4204-b7db-e59211f3cdcf"
+title = "Title"
+release_group = "Series"
+"#;
+        let steps = steps_between(STEPS, LATEST_EDITION, "1.2");
+        let output = migrate_album(input, &steps).unwrap().unwrap();
+        assert_eq!(
+            output,
+            r#"[album]
+album_id = "15006392-e2ae-4204-b7db-e59211f3cdcf"
+title = "Title"
+series = "Series"
+"#
+        );
+        assert!(migrate_album(&output, &steps).unwrap().is_none());
+    }
+}
@@ -3,8 +3,10 @@ use indexmap::IndexSet;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
+use std::time::Duration;
 use uuid::Uuid;
 
 pub const UNKNOWN_ARTIST: &'static str = "[Unknown Artist]";
@@ -48,18 +50,104 @@ impl Album {
     }
 }
 
+/// Upper bounds of album size, to reject malformed or malicious album documents early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlbumLimits {
+    /// Maximum length of album document in bytes, which is checked before parsing
+    pub max_size: usize,
+    /// Maximum number of discs in an album
+    pub max_discs: usize,
+    /// Maximum number of tracks in a disc
+    pub max_tracks_per_disc: usize,
+}
+
+impl AlbumLimits {
+    /// Default limits, which are generous enough for real releases.
+    pub const DEFAULT: AlbumLimits = AlbumLimits {
+        max_size: 1024 * 1024,
+        max_discs: 100,
+        max_tracks_per_disc: 1000,
+    };
+
+    pub const UNLIMITED: AlbumLimits = AlbumLimits {
+        max_size: usize::MAX,
+        max_discs: usize::MAX,
+        max_tracks_per_disc: usize::MAX,
+    };
+
+    /// Make sure album document `input` is not too long to be parsed.
+    pub fn check_size(&self, input: &str) -> RepoResult<()> {
+        if input.len() > self.max_size {
+            return Err(Error::AlbumLimitExceeded {
+                target: "bytes",
+                limit: self.max_size,
+                actual: input.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Make sure `discs` of an album do not exceed the limits.
+    pub fn check_discs(&self, discs: &[Disc]) -> RepoResult<()> {
+        if discs.len() > self.max_discs {
+            return Err(Error::AlbumLimitExceeded {
+                target: "discs",
+                limit: self.max_discs,
+                actual: discs.len(),
+            });
+        }
+        for disc in discs.iter() {
+            if disc.tracks.len() > self.max_tracks_per_disc {
+                return Err(Error::AlbumLimitExceeded {
+                    target: "tracks in a disc",
+                    limit: self.max_tracks_per_disc,
+                    actual: disc.tracks.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for AlbumLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 impl FromStr for Album {
     type Err = Error;
 
+    /// Parse album toml with [AlbumLimits::DEFAULT].
     fn from_str(toml_str: &str) -> Result<Self, Self::Err> {
+        Album::from_str_with_limits(toml_str, &AlbumLimits::DEFAULT)
+    }
+}
+
+impl Album {
+    /// Parse album toml, and make sure the album does not exceed `limits`.
+    pub fn from_str_with_limits(toml_str: &str, limits: &AlbumLimits) -> RepoResult<Self> {
+        limits.check_size(toml_str)?;
         let album: Album = toml::from_str(toml_str).map_err(|e| Error::TomlParseError {
             target: "Album",
             input: toml_str.to_string(),
             err: e,
         })?;
+        album.check_limits(limits)?;
 
         Ok(album)
     }
+
+    pub fn check_limits(&self, limits: &AlbumLimits) -> RepoResult<()> {
+        limits.check_discs(&self.discs)
+    }
+}
+
+impl Display for Album {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let toml = toml::to_string_pretty(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&toml)
+    }
 }
 
 impl Deref for Album {
@@ -98,8 +186,23 @@ impl Album {
         self.info.edition.as_deref()
     }
 
+    /// Album artist for display.
+    ///
+    /// If `artist` is not set, names in `artists` are joined.
     pub fn artist(&self) -> &str {
-        self.info.artist.as_ref()
+        self.info.artist()
+    }
+
+    /// Album artist names.
+    ///
+    /// Names in `artists` are used if it's a list, otherwise `artist` is returned as the only one.
+    pub fn artists(&self) -> Vec<&str> {
+        self.info.artists()
+    }
+
+    /// Album artists with their roles
+    pub fn detailed_artists(&self) -> Option<&HashMap<String, String>> {
+        self.info.artists.as_ref().and_then(Artists::detailed)
     }
 
     pub fn release_date(&self) -> &AnniDate {
@@ -114,6 +217,10 @@ impl Album {
         self.info.catalog.as_ref()
     }
 
+    pub fn series(&self) -> Option<&str> {
+        self.info.series.as_deref()
+    }
+
     pub fn tags<'me, 'tag>(&'me self) -> Vec<&'me TagRef<'tag>>
     where
         'tag: 'me,
@@ -168,17 +275,21 @@ impl Album {
             .iter()
             .map(|disc| disc.artist().to_string())
             .collect::<HashSet<_>>();
+        let album_artist = self.info.artist().to_string();
         if disc_artist.len() == 1
-            && (self.artist == UNKNOWN_ARTIST || &self.artist == disc_artist.iter().next().unwrap())
+            && (self.artist == UNKNOWN_ARTIST
+                || &album_artist == disc_artist.iter().next().unwrap())
         {
             // all artists of the discs are the same, set all artists of discs to None
             for disc in self.discs.iter_mut() {
                 disc.artist = None;
             }
-            self.artist = disc_artist.into_iter().next().unwrap();
+            // keep album artist empty if it's joined from artist list
+            if album_artist != disc_artist.iter().next().unwrap().as_str() {
+                self.artist = disc_artist.into_iter().next().unwrap();
+            }
         } else {
             // not the same, set part of them to None
-            let album_artist = self.artist.to_string();
             for disc in self.discs.iter_mut() {
                 if disc.artist.as_deref() == Some(&album_artist) {
                     disc.artist = None;
@@ -456,11 +567,15 @@ pub struct AlbumInfo {
     #[serde(deserialize_with = "anni_common::decode::non_empty_str")]
     pub edition: Option<String>,
     /// Album artist
+    ///
+    /// Can be omitted if `artists` is a list of artist names.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub artist: String,
     /// Album artists
     #[serde(default)]
     #[serde(skip_serializing_if = "is_artists_empty")]
-    pub artists: Option<HashMap<String, String>>,
+    pub artists: Option<Artists>,
     /// Album release date
     #[serde(rename = "date")]
     pub release_date: AnniDate,
@@ -469,12 +584,37 @@ pub struct AlbumInfo {
     pub album_type: TrackType,
     /// Album catalog
     pub catalog: String,
+    /// Album series, or release group
+    ///
+    /// Albums with the same series are related to each other, such as discs of a box set.
+    #[serde(default, alias = "release_group")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "anni_common::decode::non_empty_str")]
+    pub series: Option<String>,
     /// Album tags
     #[serde(default)]
     // TODO: use IndexSet
     pub tags: Vec<TagString>,
 }
 
+impl AlbumInfo {
+    pub(crate) fn artist(&self) -> &str {
+        if self.artist.is_empty() {
+            joined_artists(&self.artists).unwrap_or_default()
+        } else {
+            self.artist.as_str()
+        }
+    }
+
+    pub(crate) fn artists(&self) -> Vec<&str> {
+        match self.artists.as_ref().and_then(Artists::list) {
+            Some(list) => list.names().collect(),
+            None if self.artist.is_empty() => Vec::new(),
+            None => vec![self.artist.as_str()],
+        }
+    }
+}
+
 impl Default for AlbumInfo {
     fn default() -> Self {
         Self {
@@ -482,10 +622,11 @@ impl Default for AlbumInfo {
             title: "UnknownTitle".to_string(),
             edition: None,
             artist: UNKNOWN_ARTIST.to_string(),
-            artists: HashMap::new().into(),
+            artists: None,
             release_date: AnniDate::new(2021, 1, 1),
             album_type: TrackType::Normal,
             catalog: "@TEMP".to_string(),
+            series: None,
             tags: Default::default(),
         }
     }
@@ -547,7 +688,7 @@ pub struct DiscInfo {
     pub artist: Option<String>,
     /// Disc artists
     #[serde(skip_serializing_if = "is_artists_empty")]
-    pub artists: Option<HashMap<String, String>>,
+    pub artists: Option<Artists>,
     /// Disc type
     #[serde(rename = "type")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -559,11 +700,27 @@ pub struct DiscInfo {
 }
 
 impl DiscInfo {
+    /// Disc artist for display, which inherits from `album` if not set.
+    pub(crate) fn artist<'a>(&'a self, album: &'a AlbumInfo) -> &'a str {
+        self.artist
+            .as_deref()
+            .or_else(|| joined_artists(&self.artists))
+            .unwrap_or_else(|| album.artist())
+    }
+
+    pub(crate) fn artists<'a>(&'a self, album: &'a AlbumInfo) -> Vec<&'a str> {
+        match (self.artists.as_ref().and_then(Artists::list), &self.artist) {
+            (Some(list), _) => list.names().collect(),
+            (None, Some(artist)) => vec![artist.as_str()],
+            (None, None) => album.artists(),
+        }
+    }
+
     pub fn new(
         catalog: String,
         title: Option<String>,
         artist: Option<String>,
-        artists: Option<HashMap<String, String>>,
+        artists: Option<Artists>,
         disc_type: Option<TrackType>,
         tags: Vec<TagString>,
     ) -> Self {
@@ -598,10 +755,7 @@ impl<'album> DiscRef<'album> {
     }
 
     pub fn artist(&self) -> &str {
-        self.disc
-            .artist
-            .as_deref()
-            .unwrap_or(self.album.artist.as_str())
+        self.disc.artist(self.album)
     }
 
     /// Get raw disc artist without inherit
@@ -609,8 +763,14 @@ impl<'album> DiscRef<'album> {
         self.disc.artist.as_deref()
     }
 
-    pub fn artists(&self) -> Option<&HashMap<String, String>> {
-        self.disc.artists.as_ref()
+    /// Disc artist names, which inherits from album if not set.
+    pub fn artists(&self) -> Vec<&str> {
+        self.disc.artists(self.album)
+    }
+
+    /// Disc artists with their roles
+    pub fn detailed_artists(&self) -> Option<&HashMap<String, String>> {
+        self.disc.artists.as_ref().and_then(Artists::detailed)
     }
 
     pub fn catalog(&self) -> &str {
@@ -660,10 +820,7 @@ impl<'album> DiscRefMut<'album> {
     }
 
     pub fn artist(&self) -> &str {
-        self.disc
-            .artist
-            .as_deref()
-            .unwrap_or(self.album.artist.as_str())
+        self.disc.artist(self.album)
     }
 
     pub fn catalog(&self) -> &str {
@@ -712,7 +869,10 @@ impl<'album> DiscRefMut<'album> {
             for mut track in self.iter_mut() {
                 track.artist = None;
             }
-            self.disc.artist = Some(track_artist.into_iter().next().unwrap());
+            let track_artist = track_artist.into_iter().next().unwrap();
+            if track_artist != self.artist() {
+                self.disc.artist = Some(track_artist);
+            }
         } else {
             // not the same, ignore extraction
         }
@@ -755,11 +915,20 @@ pub struct Track {
     pub artist: Option<String>,
     /// Track artists
     #[serde(skip_serializing_if = "is_artists_empty")]
-    pub artists: Option<HashMap<String, String>>,
+    pub artists: Option<Artists>,
     /// Track type
     #[serde(rename = "type")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub track_type: Option<TrackType>,
+    /// Track duration, written as `mm:ss` or seconds
+    #[serde(default, with = "track_duration")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<Duration>,
+    /// International Standard Recording Code of the track
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "anni_common::decode::non_empty_str")]
+    pub isrc: Option<String>,
     /// Track tags
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -770,7 +939,7 @@ impl Track {
     pub fn new(
         title: String,
         artist: Option<String>,
-        artists: Option<HashMap<String, String>>,
+        artists: Option<Artists>,
         track_type: Option<TrackType>,
         tags: Vec<TagString>,
     ) -> Self {
@@ -779,6 +948,8 @@ impl Track {
             artist,
             artists,
             track_type,
+            duration: None,
+            isrc: None,
             tags,
         }
     }
@@ -787,6 +958,12 @@ impl Track {
         Track::new(String::new(), None, None, None, Default::default())
     }
 
+    /// Track title normalized by [normalize_title](anni_common::text::normalize_title),
+    /// which should only be used to search and compare titles.
+    pub fn normalize_title(&self) -> String {
+        anni_common::text::normalize_title(&self.title)
+    }
+
     pub(crate) fn resolve_tags(
         &mut self,
         tags: &HashMap<String, HashMap<TagType, Tag>>,
@@ -815,19 +992,32 @@ where
     }
 
     pub fn artist(&self) -> &'disc str {
-        self.track.artist.as_deref().unwrap_or_else(|| {
-            self.disc
-                .artist
-                .as_deref()
-                .unwrap_or(self.album.artist.as_str())
-        })
+        self.track
+            .artist
+            .as_deref()
+            .or_else(|| joined_artists(&self.track.artists))
+            .unwrap_or_else(|| self.disc.artist(self.album))
+    }
+
+    /// Track artist names, which inherits from disc and album if not set.
+    pub fn artists(&self) -> Vec<&'disc str> {
+        match (
+            self.track.artists.as_ref().and_then(Artists::list),
+            &self.track.artist,
+        ) {
+            (Some(list), _) => list.names().collect(),
+            (None, Some(artist)) => vec![artist.as_str()],
+            (None, None) => self.disc.artists(self.album),
+        }
     }
 
-    pub fn artists(&self) -> Option<&'disc HashMap<String, String>> {
-        self.track
-            .artists
-            .as_ref()
-            .or_else(|| self.disc.artists.as_ref().or(self.album.artists.as_ref()))
+    /// Track artists with their roles, which inherits from disc and album if not set.
+    pub fn detailed_artists(&self) -> Option<&'disc HashMap<String, String>> {
+        let detailed =
+            |artists: &'disc Option<Artists>| artists.as_ref().and_then(Artists::detailed);
+        detailed(&self.track.artists)
+            .or_else(|| detailed(&self.disc.artists))
+            .or_else(|| detailed(&self.album.artists))
     }
 
     pub fn track_type(&self) -> &'disc TrackType {
@@ -839,6 +1029,14 @@ where
         })
     }
 
+    pub fn duration(&self) -> Option<Duration> {
+        self.track.duration
+    }
+
+    pub fn isrc(&self) -> Option<&'disc str> {
+        self.track.isrc.as_deref()
+    }
+
     pub fn tags_iter<'me, 'tag>(&'me self) -> impl Iterator<Item = &'me TagRef<'tag>>
     where
         'tag: 'me,
@@ -891,10 +1089,14 @@ where
         self.inner().artist()
     }
 
-    pub fn artists(&self) -> Option<&HashMap<String, String>> {
+    pub fn artists(&self) -> Vec<&str> {
         self.inner().artists()
     }
 
+    pub fn detailed_artists(&self) -> Option<&HashMap<String, String>> {
+        self.inner().detailed_artists()
+    }
+
     pub fn track_type(&self) -> &TrackType {
         self.inner().track_type()
     }
@@ -910,10 +1112,7 @@ where
         if let Some(artist) = artist {
             let artist_str = artist.as_str();
             let current_artist_str = self.track.artist.as_deref().unwrap_or_else(|| {
-                self.disc
-                    .artist
-                    .as_deref()
-                    .unwrap_or(self.album.artist.as_str())
+                joined_artists(&self.track.artists).unwrap_or_else(|| self.disc.artist(self.album))
             });
 
             if artist_str == current_artist_str {
@@ -990,13 +1189,75 @@ impl TrackType {
     }
 }
 
-pub(crate) fn is_artists_empty(artists: &Option<HashMap<String, String>>) -> bool {
+/// (De)serialize track duration as `mm:ss`, while seconds are also accepted when deserializing.
+///
+/// Fractional seconds are dropped.
+mod track_duration {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match duration {
+            Some(duration) => {
+                let seconds = duration.as_secs();
+                serializer.serialize_str(&format!("{:02}:{:02}", seconds / 60, seconds % 60))
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawDuration {
+            Seconds(u64),
+            Text(String),
+        }
+
+        let seconds = match Option::<RawDuration>::deserialize(deserializer)? {
+            Some(RawDuration::Seconds(seconds)) => seconds,
+            Some(RawDuration::Text(text)) => parse(&text)
+                .ok_or_else(|| de::Error::custom(format!("Invalid duration: {text}")))?,
+            None => return Ok(None),
+        };
+        Ok(Some(Duration::from_secs(seconds)))
+    }
+
+    /// Parse `mm:ss` or seconds to seconds.
+    fn parse(text: &str) -> Option<u64> {
+        match text.trim().split_once(':') {
+            Some((minutes, seconds)) => {
+                let seconds: u64 = seconds.parse().ok()?;
+                if seconds >= 60 {
+                    return None;
+                }
+                Some(minutes.parse::<u64>().ok()? * 60 + seconds)
+            }
+            None => text.trim().parse().ok(),
+        }
+    }
+}
+
+pub(crate) fn is_artists_empty(artists: &Option<Artists>) -> bool {
     match artists {
         Some(artists) => artists.is_empty(),
         None => true,
     }
 }
 
+fn joined_artists(artists: &Option<Artists>) -> Option<&str> {
+    artists
+        .as_ref()
+        .and_then(Artists::list)
+        .map(ArtistList::joined)
+}
+
 #[cfg(feature = "flac")]
 impl From<anni_flac::FlacHeader> for Track {
     fn from(stream: anni_flac::FlacHeader) -> Self {
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+
+/// Separator used to join multiple artists into a display string.
+pub const ARTIST_SEPARATOR: &str = "、";
+
+/// Artists of an album, disc or track.
+///
+/// In toml, `artists` can be written as an array of artist names:
+///
+/// ```toml
+/// artists = ["Artist A", "Artist B"]
+/// ```
+///
+/// or a table which maps roles to artists, which is kept for compatibility.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Artists {
+    /// Artist names, in order
+    List(ArtistList),
+    /// Artists with their roles
+    Detailed(HashMap<String, String>),
+}
+
+impl Artists {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Artists::List(list) => list.names.is_empty(),
+            Artists::Detailed(artists) => artists.is_empty(),
+        }
+    }
+
+    /// Get artist list. Returns `None` for detailed artists or empty list.
+    pub fn list(&self) -> Option<&ArtistList> {
+        match self {
+            Artists::List(list) if !list.names.is_empty() => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Get detailed artists. Returns `None` for artist list.
+    pub fn detailed(&self) -> Option<&HashMap<String, String>> {
+        match self {
+            Artists::Detailed(artists) => Some(artists),
+            Artists::List(_) => None,
+        }
+    }
+}
+
+impl From<Vec<String>> for Artists {
+    fn from(names: Vec<String>) -> Self {
+        Artists::List(ArtistList::new(names))
+    }
+}
+
+impl From<HashMap<String, String>> for Artists {
+    fn from(artists: HashMap<String, String>) -> Self {
+        Artists::Detailed(artists)
+    }
+}
+
+impl Serialize for Artists {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Artists::List(list) => list.names.serialize(serializer),
+            Artists::Detailed(artists) => artists.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Artists {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawArtists {
+            List(Vec<String>),
+            Detailed(HashMap<String, String>),
+        }
+
+        Ok(match RawArtists::deserialize(deserializer)? {
+            RawArtists::List(names) => names.into(),
+            RawArtists::Detailed(artists) => artists.into(),
+        })
+    }
+}
+
+/// Ordered artist names, with a joined string for display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtistList {
+    names: Vec<String>,
+    joined: String,
+}
+
+impl ArtistList {
+    pub fn new(names: Vec<String>) -> Self {
+        let joined = names.join(ARTIST_SEPARATOR);
+        Self { names, joined }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// Artist names joined by [ARTIST_SEPARATOR]
+    pub fn joined(&self) -> &str {
+        &self.joined
+    }
+}
@@ -0,0 +1,295 @@
+use crate::prelude::*;
+use std::time::Duration;
+use uuid::Uuid;
+
+impl Album {
+    /// Build an album without writing toml.
+    pub fn builder() -> AlbumBuilder {
+        AlbumBuilder::default()
+    }
+}
+
+impl Disc {
+    pub fn builder() -> DiscBuilder {
+        DiscBuilder::default()
+    }
+}
+
+impl Track {
+    pub fn builder() -> TrackBuilder {
+        TrackBuilder::default()
+    }
+}
+
+/// Builder of [Album].
+///
+/// `title`, `artist`, `release_date`, `catalog` and at least one disc are required,
+/// while `artist` can be omitted if `artists` is a list of names.
+/// Missing `album_id` would be generated, and `album_type` defaults to [TrackType::Normal].
+#[derive(Debug, Clone, Default)]
+pub struct AlbumBuilder {
+    album_id: Option<Uuid>,
+    title: Option<String>,
+    edition: Option<String>,
+    artist: Option<String>,
+    artists: Option<Artists>,
+    release_date: Option<AnniDate>,
+    album_type: Option<TrackType>,
+    catalog: Option<String>,
+    series: Option<String>,
+    tags: Vec<TagString>,
+    discs: Vec<DiscBuilder>,
+}
+
+impl AlbumBuilder {
+    pub fn album_id(mut self, album_id: Uuid) -> Self {
+        self.album_id = Some(album_id);
+        self
+    }
+
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn edition<S: Into<String>>(mut self, edition: S) -> Self {
+        self.edition = Some(edition.into());
+        self
+    }
+
+    pub fn artist<S: Into<String>>(mut self, artist: S) -> Self {
+        self.artist = Some(artist.into());
+        self
+    }
+
+    /// Set artists, which can be a list of names or detailed artists.
+    pub fn artists<A: Into<Artists>>(mut self, artists: A) -> Self {
+        self.artists = Some(artists.into());
+        self
+    }
+
+    pub fn release_date(mut self, release_date: AnniDate) -> Self {
+        self.release_date = Some(release_date);
+        self
+    }
+
+    pub fn album_type(mut self, album_type: TrackType) -> Self {
+        self.album_type = Some(album_type);
+        self
+    }
+
+    pub fn catalog<S: Into<String>>(mut self, catalog: S) -> Self {
+        self.catalog = Some(catalog.into());
+        self
+    }
+
+    pub fn series<S: Into<String>>(mut self, series: S) -> Self {
+        self.series = Some(series.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: TagString) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    /// Add a disc. Disc catalog would be inherited from album if not set.
+    pub fn add_disc(mut self, disc: DiscBuilder) -> Self {
+        self.discs.push(disc);
+        self
+    }
+
+    /// Validate fields and build the album. The result is formatted by [Album::format].
+    pub fn build(self) -> RepoResult<Album> {
+        let title = required("Album", "title", self.title)?;
+        // album artist can be joined from artist list
+        let artist = match self.artists.as_ref().and_then(Artists::list) {
+            Some(_) => self.artist.unwrap_or_default(),
+            None => required("Album", "artist", self.artist)?,
+        };
+        let catalog = required("Album", "catalog", self.catalog)?;
+        let release_date = self.release_date.ok_or(Error::BuilderMissingField {
+            target: "Album",
+            field: "release_date",
+        })?;
+        if self.discs.is_empty() {
+            return Err(Error::BuilderMissingField {
+                target: "Album",
+                field: "discs",
+            });
+        }
+
+        let discs = self
+            .discs
+            .into_iter()
+            .map(|disc| {
+                if disc.catalog.is_none() {
+                    disc.catalog(catalog.as_str()).build()
+                } else {
+                    disc.build()
+                }
+            })
+            .collect::<RepoResult<Vec<_>>>()?;
+        let info = AlbumInfo {
+            album_id: self.album_id.unwrap_or_else(Uuid::new_v4),
+            title,
+            edition: self.edition.filter(|e| !e.is_empty()),
+            artist,
+            artists: self.artists,
+            release_date,
+            album_type: self.album_type.unwrap_or(TrackType::Normal),
+            catalog,
+            series: self.series.filter(|s| !s.is_empty()),
+            tags: self.tags,
+        };
+        Ok(Album::new(info, discs))
+    }
+}
+
+/// Builder of [Disc].
+///
+/// `catalog` and at least one track are required.
+#[derive(Debug, Clone, Default)]
+pub struct DiscBuilder {
+    title: Option<String>,
+    catalog: Option<String>,
+    artist: Option<String>,
+    artists: Option<Artists>,
+    disc_type: Option<TrackType>,
+    tags: Vec<TagString>,
+    tracks: Vec<TrackBuilder>,
+}
+
+impl DiscBuilder {
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn catalog<S: Into<String>>(mut self, catalog: S) -> Self {
+        self.catalog = Some(catalog.into());
+        self
+    }
+
+    pub fn artist<S: Into<String>>(mut self, artist: S) -> Self {
+        self.artist = Some(artist.into());
+        self
+    }
+
+    /// Set artists, which can be a list of names or detailed artists.
+    pub fn artists<A: Into<Artists>>(mut self, artists: A) -> Self {
+        self.artists = Some(artists.into());
+        self
+    }
+
+    pub fn disc_type(mut self, disc_type: TrackType) -> Self {
+        self.disc_type = Some(disc_type);
+        self
+    }
+
+    pub fn tag(mut self, tag: TagString) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    pub fn add_track(mut self, track: TrackBuilder) -> Self {
+        self.tracks.push(track);
+        self
+    }
+
+    pub fn build(self) -> RepoResult<Disc> {
+        let catalog = required("Disc", "catalog", self.catalog)?;
+        if self.tracks.is_empty() {
+            return Err(Error::BuilderMissingField {
+                target: "Disc",
+                field: "tracks",
+            });
+        }
+
+        let tracks = self
+            .tracks
+            .into_iter()
+            .map(TrackBuilder::build)
+            .collect::<RepoResult<Vec<_>>>()?;
+        let info = DiscInfo::new(
+            catalog,
+            self.title,
+            self.artist,
+            self.artists,
+            self.disc_type,
+            self.tags,
+        );
+        Ok(Disc::new(info, tracks))
+    }
+}
+
+/// Builder of [Track].
+///
+/// `title` is required.
+#[derive(Debug, Clone, Default)]
+pub struct TrackBuilder {
+    title: Option<String>,
+    artist: Option<String>,
+    artists: Option<Artists>,
+    track_type: Option<TrackType>,
+    duration: Option<Duration>,
+    isrc: Option<String>,
+    tags: Vec<TagString>,
+}
+
+impl TrackBuilder {
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn artist<S: Into<String>>(mut self, artist: S) -> Self {
+        self.artist = Some(artist.into());
+        self
+    }
+
+    /// Set artists, which can be a list of names or detailed artists.
+    pub fn artists<A: Into<Artists>>(mut self, artists: A) -> Self {
+        self.artists = Some(artists.into());
+        self
+    }
+
+    pub fn track_type(mut self, track_type: TrackType) -> Self {
+        self.track_type = Some(track_type);
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    pub fn isrc<S: Into<String>>(mut self, isrc: S) -> Self {
+        self.isrc = Some(isrc.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: TagString) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    pub fn build(self) -> RepoResult<Track> {
+        let title = required("Track", "title", self.title)?;
+        let mut track = Track::new(title, self.artist, self.artists, self.track_type, self.tags);
+        track.duration = self.duration;
+        track.isrc = self.isrc.filter(|isrc| !isrc.is_empty());
+        Ok(track)
+    }
+}
+
+/// Treat empty string as missing.
+fn required(
+    target: &'static str,
+    field: &'static str,
+    value: Option<String>,
+) -> RepoResult<String> {
+    value
+        .filter(|v| !v.is_empty())
+        .ok_or(Error::BuilderMissingField { target, field })
+}
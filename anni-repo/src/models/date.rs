@@ -7,7 +7,7 @@ use std::{
 };
 use toml::Value;
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
 pub struct AnniDate {
     year: u16,
     month: u8,
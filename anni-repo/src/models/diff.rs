@@ -0,0 +1,293 @@
+use crate::prelude::*;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/// Semantic change between two versions of an album, produced by [Album::diff].
+///
+/// Discs and tracks are matched by their position. Inherited values (e.g. artist of a track)
+/// are compared after resolution, so moving a value between album and disc is not a change.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlbumChange {
+    Album(FieldChange),
+    DiscAdded {
+        disc_id: usize,
+        tracks: usize,
+    },
+    DiscRemoved {
+        disc_id: usize,
+    },
+    Disc {
+        disc_id: usize,
+        #[serde(flatten)]
+        change: FieldChange,
+    },
+    TrackAdded {
+        disc_id: usize,
+        track_id: usize,
+        title: String,
+    },
+    TrackRemoved {
+        disc_id: usize,
+        track_id: usize,
+        title: String,
+    },
+    Track {
+        disc_id: usize,
+        track_id: usize,
+        #[serde(flatten)]
+        change: FieldChange,
+    },
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl Display for FieldChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value = |v: &Option<String>| match v {
+            Some(v) => format!("{v:?}"),
+            None => "(none)".to_string(),
+        };
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.field,
+            value(&self.old),
+            value(&self.new)
+        )
+    }
+}
+
+impl Display for AlbumChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlbumChange::Album(change) => write!(f, "album {change}"),
+            AlbumChange::DiscAdded { disc_id, tracks } => {
+                write!(f, "disc {disc_id} added with {tracks} tracks")
+            }
+            AlbumChange::DiscRemoved { disc_id } => write!(f, "disc {disc_id} removed"),
+            AlbumChange::Disc { disc_id, change } => write!(f, "disc {disc_id} {change}"),
+            AlbumChange::TrackAdded {
+                disc_id,
+                track_id,
+                title,
+            } => write!(f, "disc {disc_id} track {track_id} added: {title:?}"),
+            AlbumChange::TrackRemoved {
+                disc_id,
+                track_id,
+                title,
+            } => write!(f, "disc {disc_id} track {track_id} removed: {title:?}"),
+            AlbumChange::Track {
+                disc_id,
+                track_id,
+                change,
+            } => write!(f, "disc {disc_id} track {track_id} {change}"),
+        }
+    }
+}
+
+/// Push a [FieldChange] if `old` and `new` differ.
+fn compare<T: ToString + PartialEq>(
+    changes: &mut Vec<FieldChange>,
+    field: &'static str,
+    old: Option<T>,
+    new: Option<T>,
+) {
+    if old != new {
+        changes.push(FieldChange {
+            field,
+            old: old.map(|v| v.to_string()),
+            new: new.map(|v| v.to_string()),
+        });
+    }
+}
+
+fn join_tags<'a, 'tag: 'a>(tags: impl Iterator<Item = &'a TagRef<'tag>>) -> Option<String> {
+    let tags: Vec<_> = tags.map(|t| t.to_string()).collect();
+    (!tags.is_empty()).then(|| tags.join(", "))
+}
+
+fn type_str(track_type: &TrackType) -> &str {
+    track_type.as_ref()
+}
+
+impl Album {
+    /// Compare `self` as the old version with `new`.
+    pub fn diff(&self, new: &Album) -> Vec<AlbumChange> {
+        let mut album = Vec::new();
+        compare(
+            &mut album,
+            "album_id",
+            Some(self.album_id()),
+            Some(new.album_id()),
+        );
+        compare(
+            &mut album,
+            "title",
+            Some(self.title_raw()),
+            Some(new.title_raw()),
+        );
+        compare(&mut album, "edition", self.edition(), new.edition());
+        compare(
+            &mut album,
+            "artist",
+            Some(self.artist()),
+            Some(new.artist()),
+        );
+        compare(
+            &mut album,
+            "date",
+            Some(self.release_date()),
+            Some(new.release_date()),
+        );
+        compare(
+            &mut album,
+            "type",
+            Some(type_str(self.track_type())),
+            Some(type_str(new.track_type())),
+        );
+        compare(
+            &mut album,
+            "catalog",
+            Some(self.catalog()),
+            Some(new.catalog()),
+        );
+        compare(&mut album, "series", self.series(), new.series());
+        compare(
+            &mut album,
+            "tags",
+            join_tags(self.album_tags().into_iter()),
+            join_tags(new.album_tags().into_iter()),
+        );
+        let mut changes: Vec<_> = album.into_iter().map(AlbumChange::Album).collect();
+
+        let old_discs: Vec<_> = self.iter().collect();
+        let new_discs: Vec<_> = new.iter().collect();
+        for disc_index in 0..old_discs.len().max(new_discs.len()) {
+            let disc_id = disc_index + 1;
+            let (old_disc, new_disc) = match (old_discs.get(disc_index), new_discs.get(disc_index))
+            {
+                (Some(old_disc), Some(new_disc)) => (old_disc, new_disc),
+                (None, Some(new_disc)) => {
+                    changes.push(AlbumChange::DiscAdded {
+                        disc_id,
+                        tracks: new_disc.tracks_len(),
+                    });
+                    continue;
+                }
+                (Some(_), None) => {
+                    changes.push(AlbumChange::DiscRemoved { disc_id });
+                    continue;
+                }
+                (None, None) => unreachable!(),
+            };
+
+            let mut disc = Vec::new();
+            compare(
+                &mut disc,
+                "title",
+                Some(old_disc.title()),
+                Some(new_disc.title()),
+            );
+            compare(
+                &mut disc,
+                "catalog",
+                Some(old_disc.catalog()),
+                Some(new_disc.catalog()),
+            );
+            compare(
+                &mut disc,
+                "artist",
+                Some(old_disc.artist()),
+                Some(new_disc.artist()),
+            );
+            compare(
+                &mut disc,
+                "type",
+                Some(type_str(old_disc.track_type())),
+                Some(type_str(new_disc.track_type())),
+            );
+            compare(
+                &mut disc,
+                "tags",
+                join_tags(old_disc.tags_iter()),
+                join_tags(new_disc.tags_iter()),
+            );
+            changes.extend(
+                disc.into_iter()
+                    .map(|change| AlbumChange::Disc { disc_id, change }),
+            );
+
+            let old_tracks: Vec<_> = old_disc.iter().collect();
+            let new_tracks: Vec<_> = new_disc.iter().collect();
+            for track_index in 0..old_tracks.len().max(new_tracks.len()) {
+                let track_id = track_index + 1;
+                let (old_track, new_track) =
+                    match (old_tracks.get(track_index), new_tracks.get(track_index)) {
+                        (Some(old_track), Some(new_track)) => (old_track, new_track),
+                        (None, Some(new_track)) => {
+                            changes.push(AlbumChange::TrackAdded {
+                                disc_id,
+                                track_id,
+                                title: new_track.title().to_string(),
+                            });
+                            continue;
+                        }
+                        (Some(old_track), None) => {
+                            changes.push(AlbumChange::TrackRemoved {
+                                disc_id,
+                                track_id,
+                                title: old_track.title().to_string(),
+                            });
+                            continue;
+                        }
+                        (None, None) => unreachable!(),
+                    };
+
+                let mut track = Vec::new();
+                compare(
+                    &mut track,
+                    "title",
+                    Some(old_track.title()),
+                    Some(new_track.title()),
+                );
+                compare(
+                    &mut track,
+                    "artist",
+                    Some(old_track.artist()),
+                    Some(new_track.artist()),
+                );
+                compare(
+                    &mut track,
+                    "type",
+                    Some(type_str(old_track.track_type())),
+                    Some(type_str(new_track.track_type())),
+                );
+                compare(
+                    &mut track,
+                    "duration",
+                    old_track.duration().map(|d| d.as_secs()),
+                    new_track.duration().map(|d| d.as_secs()),
+                );
+                compare(&mut track, "isrc", old_track.isrc(), new_track.isrc());
+                compare(
+                    &mut track,
+                    "tags",
+                    join_tags(old_track.tags_iter()),
+                    join_tags(new_track.tags_iter()),
+                );
+                changes.extend(track.into_iter().map(|change| AlbumChange::Track {
+                    disc_id,
+                    track_id,
+                    change,
+                }));
+            }
+        }
+        changes
+    }
+}
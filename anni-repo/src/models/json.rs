@@ -1,10 +1,13 @@
-use std::{collections::HashMap, str::FromStr};
+use std::str::FromStr;
 
 use crate::error::Error;
+use crate::prelude::RepoResult;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::{is_artists_empty, Album, AlbumInfo, AnniDate, Disc, TagString, TrackType};
+use super::{
+    is_artists_empty, Album, AlbumInfo, AlbumLimits, AnniDate, Artists, Disc, TagString, TrackType,
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
@@ -29,11 +32,13 @@ pub struct JsonAlbumInfo {
     #[serde(deserialize_with = "anni_common::decode::non_empty_str")]
     pub edition: Option<String>,
     /// Album artist
+    #[serde(default)]
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub artist: String,
     /// Album artists
     #[serde(default)]
     #[serde(skip_serializing_if = "is_artists_empty")]
-    pub artists: Option<HashMap<String, String>>,
+    pub artists: Option<Artists>,
     /// Album release date
     #[serde(rename = "date")]
     pub release_date: String,
@@ -42,16 +47,32 @@ pub struct JsonAlbumInfo {
     pub album_type: TrackType,
     /// Album catalog
     pub catalog: String,
+    /// Album series, or release group
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "anni_common::decode::non_empty_str")]
+    pub series: Option<String>,
     /// Album tags
     #[serde(default)]
     pub tags: Vec<TagString>,
 }
 
 impl FromStr for JsonAlbum {
-    type Err = serde_json::Error;
+    type Err = Error;
 
+    /// Parse album json with [AlbumLimits::DEFAULT].
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        serde_json::from_str(s)
+        JsonAlbum::from_str_with_limits(s, &AlbumLimits::DEFAULT)
+    }
+}
+
+impl JsonAlbum {
+    /// Parse album json, and make sure the album does not exceed `limits`.
+    pub fn from_str_with_limits(s: &str, limits: &AlbumLimits) -> RepoResult<Self> {
+        limits.check_size(s)?;
+        let album: JsonAlbum = serde_json::from_str(s)?;
+        limits.check_discs(&album.discs)?;
+        Ok(album)
     }
 }
 
@@ -72,6 +93,7 @@ impl From<Album> for JsonAlbum {
             release_date,
             album_type,
             catalog,
+            series,
             tags,
         } = album.info;
         JsonAlbum {
@@ -84,6 +106,7 @@ impl From<Album> for JsonAlbum {
                 release_date: release_date.to_string(),
                 album_type,
                 catalog,
+                series,
                 tags,
             },
             discs: album.discs,
@@ -104,6 +127,7 @@ impl TryFrom<JsonAlbum> for Album {
             release_date,
             album_type,
             catalog,
+            series,
             tags,
         } = album.info;
         Ok(Album {
@@ -116,6 +140,7 @@ impl TryFrom<JsonAlbum> for Album {
                 release_date: AnniDate::from_str(&release_date)?,
                 album_type,
                 catalog,
+                series,
                 tags,
             },
             discs: album.discs,
@@ -138,4 +163,24 @@ mod test {
         let album_serialized_text = toml::to_string_pretty(&album).unwrap();
         assert_eq!(text, album_serialized_text);
     }
+
+    #[test]
+    fn test_json_album_edition() {
+        use super::JsonAlbum;
+        use crate::prelude::Album;
+        use std::str::FromStr;
+
+        let text = include_str!("../../tests/fixtures/test-album.toml");
+        let album = JsonAlbum::from(Album::from_str(text).unwrap()).to_string();
+        assert!(album.contains(r#""edition":"Test""#));
+        let album = Album::try_from(JsonAlbum::from_str(&album).unwrap()).unwrap();
+        assert_eq!(album.edition(), Some("Test"));
+
+        let text = text.replace("edition = \"Test\"\n", "");
+        let album = JsonAlbum::from(Album::from_str(&text).unwrap()).to_string();
+        assert!(!album.contains("edition"));
+        let album = Album::try_from(JsonAlbum::from_str(&album).unwrap()).unwrap();
+        assert!(album.edition().is_none());
+        assert_eq!(toml::to_string_pretty(&album).unwrap(), text);
+    }
 }
@@ -0,0 +1,491 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Lyric with timestamps, parsed from LRC file.
+#[derive(Debug, Clone, Default)]
+pub struct Lyric {
+    /// Offset in milliseconds from `[offset:]` tag.
+    ///
+    /// Positive offset makes lyrics appear sooner.
+    pub offset: i64,
+    /// Track length in milliseconds from `[length:]` tag.
+    pub length: Option<u64>,
+    /// Timestamped lines, in the order they appear in file.
+    ///
+    /// Lines with multiple timestamps are expanded to multiple items.
+    pub lines: Vec<LyricLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LyricLine {
+    /// Line number in file, starting from 1
+    pub line: usize,
+    /// Timestamp in milliseconds, without offset applied
+    pub time: u64,
+    pub text: String,
+}
+
+impl FromStr for Lyric {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lyric = Lyric::default();
+        for (index, line) in s.trim_start_matches('\u{feff}').lines().enumerate() {
+            let line_number = index + 1;
+            let invalid = |tag: &str| Error::InvalidLyricTag {
+                line: line_number,
+                tag: tag.to_string(),
+            };
+
+            let parsed = parse_line(line).map_err(&invalid)?;
+            for (key, value, tag) in parsed.metadata {
+                match key.to_ascii_lowercase().as_str() {
+                    "offset" => lyric.offset = parse_offset(value).ok_or_else(|| invalid(tag))?,
+                    "length" => {
+                        lyric.length = Some(parse_timestamp(value).ok_or_else(|| invalid(tag))?)
+                    }
+                    _ => {}
+                }
+            }
+
+            lyric
+                .lines
+                .extend(parsed.times.into_iter().map(|time| LyricLine {
+                    line: line_number,
+                    time,
+                    text: parsed.text.to_string(),
+                }));
+        }
+        Ok(lyric)
+    }
+}
+
+/// Tags and text of a line in LRC file.
+struct ParsedLine<'a> {
+    times: Vec<u64>,
+    /// Metadata tags before timestamps, as `(key, value, tag)`
+    metadata: Vec<(&'a str, &'a str, &'a str)>,
+    text: &'a str,
+}
+
+/// Parse leading tags of a line. Returns the malformed timestamp tag on failure.
+fn parse_line(line: &str) -> Result<ParsedLine<'_>, &str> {
+    let mut times = Vec::new();
+    let mut metadata = Vec::new();
+    let mut rest = line.trim();
+    while let Some(remaining) = rest.strip_prefix('[') {
+        let Some((tag, remaining)) = remaining.split_once(']') else {
+            break;
+        };
+
+        if tag.starts_with(|c: char| c.is_ascii_digit()) {
+            times.push(parse_timestamp(tag).ok_or(tag)?);
+        } else if let (true, Some((key, value))) = (times.is_empty(), tag.split_once(':')) {
+            // metadata tags, only valid before timestamps
+            metadata.push((key.trim(), value.trim(), tag));
+        } else {
+            // not a tag, treat as text
+            break;
+        }
+        rest = remaining.trim_start();
+    }
+
+    Ok(ParsedLine {
+        times,
+        metadata,
+        text: rest,
+    })
+}
+
+fn parse_offset(value: &str) -> Option<i64> {
+    value.trim_start_matches('+').parse().ok()
+}
+
+/// Parse `mm:ss`, `mm:ss.xx` or `mm:ss:xx` to milliseconds.
+fn parse_timestamp(input: &str) -> Option<u64> {
+    let (minutes, seconds) = input.split_once(':')?;
+    let (seconds, fraction) = match seconds.split_once(['.', ':']) {
+        Some((seconds, fraction)) => (seconds, Some(fraction)),
+        None => (seconds, None),
+    };
+
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(minutes) || !is_digits(seconds) || seconds.len() > 2 {
+        return None;
+    }
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: u64 = seconds.parse().ok()?;
+    if seconds >= 60 {
+        return None;
+    }
+
+    let millis = match fraction {
+        Some(fraction) if is_digits(fraction) && fraction.len() <= 3 => {
+            fraction.parse::<u64>().ok()? * 10u64.pow(3 - fraction.len() as u32)
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+    Some((minutes * 60 + seconds) * 1000 + millis)
+}
+
+/// Format milliseconds as `mm:ss.xx`.
+fn format_timestamp(time: u64) -> String {
+    format!(
+        "{:02}:{:02}.{:02}",
+        time / 60000,
+        time / 1000 % 60,
+        time % 1000 / 10
+    )
+}
+
+/// Format milliseconds as `mm:ss.xx`, or `mm:ss.xxx` if it can not be represented in centiseconds.
+fn format_timestamp_exact(time: u64) -> String {
+    if time % 10 == 0 {
+        format_timestamp(time)
+    } else {
+        format!(
+            "{:02}:{:02}.{:03}",
+            time / 60000,
+            time / 1000 % 60,
+            time % 1000
+        )
+    }
+}
+
+/// Result of [lyric_to_plain_text] and [shift_lyric].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertedLyric {
+    pub text: String,
+    /// Lines with malformed tags, which are kept unchanged. Line numbers start from 1.
+    pub malformed_lines: Vec<usize>,
+}
+
+/// Convert LRC lyric to plain text.
+///
+/// Timestamps are removed from each line, and lines with only metadata tags like `[ti:]` are dropped.
+pub fn lyric_to_plain_text(input: &str) -> ConvertedLyric {
+    let mut lines = Vec::new();
+    let mut malformed_lines = Vec::new();
+    for (index, line) in input.trim_start_matches('\u{feff}').lines().enumerate() {
+        match parse_line(line) {
+            Ok(parsed)
+                if parsed.times.is_empty()
+                    && !parsed.metadata.is_empty()
+                    && parsed.text.is_empty() => {}
+            Ok(parsed) => lines.push(parsed.text),
+            Err(_) => {
+                malformed_lines.push(index + 1);
+                lines.push(line);
+            }
+        }
+    }
+
+    ConvertedLyric {
+        text: join_lines(lines),
+        malformed_lines,
+    }
+}
+
+/// Apply `[offset:]` tag of LRC lyric and additional `offset` in milliseconds to all timestamps.
+///
+/// Like [Lyric::offset], positive offset makes lyrics appear sooner, and timestamps are clamped to zero.
+/// `[offset:]` tag is removed from the result, so the shifted lyric is displayed at the same time
+/// as the original one with `offset` applied at runtime.
+pub fn shift_lyric(input: &str, offset: i64) -> ConvertedLyric {
+    let input = input.trim_start_matches('\u{feff}');
+    let is_offset = |key: &str| key.eq_ignore_ascii_case("offset");
+
+    let mut malformed_lines = Vec::new();
+    let mut total_offset = offset;
+    for (index, line) in input.lines().enumerate() {
+        let Ok(parsed) = parse_line(line) else {
+            malformed_lines.push(index + 1);
+            continue;
+        };
+        for (_, value, _) in parsed.metadata.iter().filter(|(key, ..)| is_offset(key)) {
+            match parse_offset(value) {
+                Some(value) => total_offset += value,
+                None => malformed_lines.push(index + 1),
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        if malformed_lines.contains(&(index + 1)) {
+            lines.push(line.to_string());
+            continue;
+        }
+        let parsed = parse_line(line).unwrap();
+        if !parsed.metadata.iter().any(|(key, ..)| is_offset(key)) && parsed.times.is_empty() {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let mut result = String::new();
+        for (key, _, tag) in parsed.metadata {
+            if !is_offset(key) {
+                result += &format!("[{tag}]");
+            }
+        }
+        for time in parsed.times {
+            let time = (time as i64 - total_offset).max(0) as u64;
+            result += &format!("[{}]", format_timestamp_exact(time));
+        }
+        if !result.is_empty() || !parsed.text.is_empty() {
+            result += parsed.text;
+            lines.push(result);
+        }
+    }
+
+    ConvertedLyric {
+        text: join_lines(lines),
+        malformed_lines,
+    }
+}
+
+fn join_lines<S: AsRef<str>>(lines: Vec<S>) -> String {
+    let mut text = String::new();
+    for line in lines {
+        text += line.as_ref();
+        text.push('\n');
+    }
+    text
+}
+
+/// Problem of timestamps found by [Lyric::check].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LyricIssue {
+    /// Timestamp of a line is earlier than the previous line
+    OutOfOrder {
+        line: usize,
+        time: u64,
+        previous: u64,
+    },
+    /// Timestamp has been used by another line
+    Duplicated {
+        line: usize,
+        time: u64,
+        first_line: usize,
+    },
+    /// Timestamp (with offset applied) exceeds track duration
+    ExceedsDuration {
+        line: usize,
+        time: u64,
+        duration: u64,
+    },
+}
+
+impl LyricIssue {
+    pub fn line(&self) -> usize {
+        match self {
+            LyricIssue::OutOfOrder { line, .. }
+            | LyricIssue::Duplicated { line, .. }
+            | LyricIssue::ExceedsDuration { line, .. } => *line,
+        }
+    }
+}
+
+impl Display for LyricIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LyricIssue::OutOfOrder {
+                line,
+                time,
+                previous,
+            } => write!(
+                f,
+                "line {line}: timestamp {} is earlier than previous line {}",
+                format_timestamp(*time),
+                format_timestamp(*previous)
+            ),
+            LyricIssue::Duplicated {
+                line,
+                time,
+                first_line,
+            } => write!(
+                f,
+                "line {line}: timestamp {} duplicates line {first_line}",
+                format_timestamp(*time)
+            ),
+            LyricIssue::ExceedsDuration {
+                line,
+                time,
+                duration,
+            } => write!(
+                f,
+                "line {line}: timestamp {} exceeds track duration {}",
+                format_timestamp(*time),
+                format_timestamp(*duration)
+            ),
+        }
+    }
+}
+
+impl Lyric {
+    /// Check whether timestamps are monotonically non-decreasing, unique and within `duration`.
+    ///
+    /// `duration` is in milliseconds. If it's `None`, `[length:]` tag is used when present.
+    ///
+    /// For lines with multiple timestamps, only the first one is used for ordering check.
+    pub fn check(&self, duration: Option<u64>) -> Vec<LyricIssue> {
+        let mut issues = Vec::new();
+        let duration = duration.or(self.length);
+
+        let mut previous: Option<&LyricLine> = None;
+        let mut seen = HashMap::new();
+        for line in self.lines.iter() {
+            if !previous.is_some_and(|p| p.line == line.line) {
+                if let Some(previous) = previous.filter(|p| p.time > line.time) {
+                    issues.push(LyricIssue::OutOfOrder {
+                        line: line.line,
+                        time: line.time,
+                        previous: previous.time,
+                    });
+                }
+                // compare with the first timestamp of the previous line
+                previous = Some(line);
+            }
+
+            let first_line = *seen.entry(line.time).or_insert(line.line);
+            if first_line != line.line {
+                issues.push(LyricIssue::Duplicated {
+                    line: line.line,
+                    time: line.time,
+                    first_line,
+                });
+            }
+
+            if let Some(duration) = duration {
+                let time = (line.time as i64 - self.offset).max(0) as u64;
+                if time > duration {
+                    issues.push(LyricIssue::ExceedsDuration {
+                        line: line.line,
+                        time,
+                        duration,
+                    });
+                }
+            }
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lyric() {
+        let lyric = Lyric::from_str(
+            "\u{feff}[ti:Title]\n[offset:+500]\n[length: 03:20]\n\n[00:01.00]first\n[00:02.5][01:02:30] chorus\nplain text\n[00:03]",
+        )
+        .unwrap();
+        assert_eq!(lyric.offset, 500);
+        assert_eq!(lyric.length, Some(200000));
+        assert_eq!(
+            lyric.lines,
+            vec![
+                LyricLine {
+                    line: 5,
+                    time: 1000,
+                    text: "first".to_string()
+                },
+                LyricLine {
+                    line: 6,
+                    time: 2500,
+                    text: "chorus".to_string()
+                },
+                LyricLine {
+                    line: 6,
+                    time: 62300,
+                    text: "chorus".to_string()
+                },
+                LyricLine {
+                    line: 8,
+                    time: 3000,
+                    text: String::new()
+                },
+            ]
+        );
+
+        assert!(matches!(
+            Lyric::from_str("[00:01.00]ok\n[00:6x.00]bad"),
+            Err(Error::InvalidLyricTag { line: 2, .. })
+        ));
+        assert!(Lyric::from_str("[offset:abc]").is_err());
+    }
+
+    #[test]
+    fn test_check_lyric() {
+        let lyric = Lyric::from_str(
+            "[length:00:10]\n[00:01.00]a\n[00:03.00][00:08.00]b\n[00:02.00]c\n[00:03.00]d\n[00:11.00]e",
+        )
+        .unwrap();
+        assert_eq!(
+            lyric.check(None),
+            vec![
+                LyricIssue::OutOfOrder {
+                    line: 4,
+                    time: 2000,
+                    previous: 3000
+                },
+                LyricIssue::Duplicated {
+                    line: 5,
+                    time: 3000,
+                    first_line: 3
+                },
+                LyricIssue::ExceedsDuration {
+                    line: 6,
+                    time: 11000,
+                    duration: 10000
+                },
+            ]
+        );
+        assert_eq!(lyric.check(Some(20000)).len(), 2);
+        assert!(Lyric::from_str("[00:01]a\n[00:01.5]b")
+            .unwrap()
+            .check(None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_convert_lyric() {
+        let input = "\u{feff}[ti:Title]\n[offset:+500]\n\n[00:01.00]first\n[00:02.5][01:02:30] chorus\n[00:0x.00]bad\nplain text\n";
+
+        let plain = lyric_to_plain_text(input);
+        assert_eq!(plain.text, "\nfirst\nchorus\n[00:0x.00]bad\nplain text\n");
+        assert_eq!(plain.malformed_lines, vec![6]);
+
+        let shifted = shift_lyric(input, 250);
+        assert_eq!(
+            shifted.text,
+            "[ti:Title]\n\n[00:00.25]first\n[00:01.75][01:01.55]chorus\n[00:0x.00]bad\nplain text\n"
+        );
+        assert_eq!(shifted.malformed_lines, vec![6]);
+
+        // shifted lyric is displayed at the same time as the original one
+        let original = Lyric::from_str("[offset:-1000]\n[00:01.001]a\n[00:03.00]b").unwrap();
+        let shifted =
+            Lyric::from_str(&shift_lyric("[offset:-1000]\n[00:01.001]a\n[00:03.00]b", 0).text)
+                .unwrap();
+        assert_eq!(shifted.offset, 0);
+        assert_eq!(
+            original
+                .lines
+                .iter()
+                .map(|l| l.time as i64 - original.offset)
+                .collect::<Vec<_>>(),
+            shifted
+                .lines
+                .iter()
+                .map(|l| l.time as i64)
+                .collect::<Vec<_>>()
+        );
+        // timestamps are clamped to zero
+        assert_eq!(shift_lyric("[00:01.00]a", 3000).text, "[00:00.00]a\n");
+    }
+}
@@ -1,10 +1,18 @@
 mod album;
+mod artist;
+mod builder;
 mod date;
+mod diff;
+mod lyric;
 mod repo;
 mod tag;
 
 pub use album::*;
+pub use artist::*;
+pub use builder::*;
 pub use date::*;
+pub use diff::*;
+pub use lyric::*;
 pub use repo::*;
 pub use tag::*;
 
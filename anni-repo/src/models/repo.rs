@@ -5,6 +5,8 @@ use std::str::FromStr;
 #[derive(Serialize, Deserialize)]
 pub struct Repository {
     repo: RepositoryInner,
+    #[serde(default, skip_serializing_if = "AssetsSetting::is_empty")]
+    assets: AssetsSetting,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -15,6 +17,33 @@ struct RepositoryInner {
     albums: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Default)]
+struct AssetsSetting {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cover: Option<AssetSetting>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lyric: Option<AssetSetting>,
+}
+
+impl AssetsSetting {
+    fn is_empty(&self) -> bool {
+        self.cover.is_none() && self.lyric.is_none()
+    }
+}
+
+/// Setting of an asset kind (cover, lyric, etc.) stored in the repository.
+#[derive(Serialize, Deserialize)]
+pub struct AssetSetting {
+    #[serde(default = "default_true")]
+    pub enable: bool,
+    /// Root path of the asset, relative to repository root.
+    pub root: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn default_albums() -> Vec<String> {
     vec!["album".into()]
 }
@@ -50,4 +79,14 @@ impl Repository {
     pub fn albums(&self) -> &[String] {
         self.repo.albums.as_ref()
     }
+
+    /// Cover asset setting. Returns `None` if cover is not configured or disabled.
+    pub fn cover(&self) -> Option<&AssetSetting> {
+        self.assets.cover.as_ref().filter(|s| s.enable)
+    }
+
+    /// Lyric asset setting. Returns `None` if lyric is not configured or disabled.
+    pub fn lyric(&self) -> Option<&AssetSetting> {
+        self.assets.lyric.as_ref().filter(|s| s.enable)
+    }
 }
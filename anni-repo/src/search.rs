@@ -5,7 +5,7 @@ use lindera_tantivy::tokenizer::LinderaTokenizer;
 use lindera_tantivy::{DictionaryConfig, DictionaryKind, Mode};
 use tantivy::{
     doc,
-    query::QueryParser,
+    query::{Query, QueryParser, QueryParserError},
     schema::{Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, STORED},
     Document, Index, TantivyError,
 };
@@ -13,6 +13,7 @@ use tantivy::{
 pub use tantivy;
 use uuid::Uuid;
 
+use crate::normalize_title;
 use crate::prelude::TrackIdentifier;
 
 pub struct RepositorySearchManager {
@@ -65,8 +66,8 @@ impl RepositorySearchManager {
         track_id: i64,
     ) -> Document {
         doc!(
-            self.fields.title => title,
-            self.fields.artist => artist,
+            self.fields.title => normalize_title(title),
+            self.fields.artist => normalize_title(artist),
             self.fields.album_id => &album_id.as_bytes()[..],
             self.fields.disc_id => disc_id,
             self.fields.track_id => track_id,
@@ -77,6 +78,12 @@ impl RepositorySearchManager {
         QueryParser::for_index(&self.index, vec![self.fields.title, self.fields.artist])
     }
 
+    /// Parse `query` normalized the same way as indexed titles and artists.
+    pub fn parse_query(&self, query: &str) -> Result<Box<dyn Query>, QueryParserError> {
+        self.build_query_parser()
+            .parse_query(&normalize_title(query))
+    }
+
     pub fn deserialize_document(&self, doc: Document) -> TrackIdentifier {
         let album_id = doc.get_first(self.fields.album_id).unwrap();
         let disc_id = doc.get_first(self.fields.disc_id).unwrap();
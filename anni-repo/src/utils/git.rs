@@ -1,6 +1,7 @@
-use git2::Repository;
+use git2::{ErrorClass, ErrorCode, Repository};
 use std::io::{self, Write};
 use std::path::Path;
+use std::time::Duration;
 
 #[cfg(feature = "git")]
 pub fn setup_git2(proxy: Option<String>) {
@@ -16,6 +17,59 @@ pub(crate) fn setup_git2_internal() {
     }
 }
 
+/// Retry policy of network operations.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Maximum retries after the first failure.
+    pub retries: u32,
+    /// Delay before the first retry. The delay doubles after each retry.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run `f` until it succeeds, fails with a non-network error, or retries are exhausted.
+    pub(crate) fn run<T, F>(&self, mut f: F) -> Result<T, git2::Error>
+    where
+        F: FnMut() -> Result<T, git2::Error>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f() {
+                Err(e) if attempt < self.retries && is_network_error(&e) => {
+                    let delay = self.backoff.saturating_mul(2u32.saturating_pow(attempt));
+                    attempt += 1;
+                    log::warn!(
+                        "Git network operation failed: {e}, retrying in {delay:?} ({attempt}/{})",
+                        self.retries
+                    );
+                    std::thread::sleep(delay);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Whether the error is caused by network and worth retrying.
+///
+/// Authentication and certificate errors are not retried.
+fn is_network_error(e: &git2::Error) -> bool {
+    !matches!(e.code(), ErrorCode::Auth | ErrorCode::Certificate)
+        && matches!(
+            e.class(),
+            ErrorClass::Net | ErrorClass::Http | ErrorClass::Ssl | ErrorClass::Os
+        )
+}
+
 fn do_fetch<'a>(
     repo: &'a git2::Repository,
     refs: &[&str],
@@ -185,3 +239,32 @@ pub(crate) fn pull<P: AsRef<Path>>(root: P, remote_branch: &str) -> Result<(), g
     let fetch_commit = do_fetch(&repo, &[remote_branch], &mut remote)?;
     do_merge(&repo, remote_branch, fetch_commit)
 }
+
+/// Clone a repository from `url` into `root`.
+///
+/// Unlike `git2::Repository::clone`, a repository left by an interrupted clone is reused,
+/// so that objects fetched before are not downloaded again.
+pub(crate) fn clone<P: AsRef<Path>>(url: &str, root: P) -> Result<(), git2::Error> {
+    let repo = match Repository::open(root.as_ref()) {
+        Ok(repo) => repo,
+        Err(_) => {
+            let repo = Repository::init(root.as_ref())?;
+            repo.remote("origin", url)?;
+            repo
+        }
+    };
+    let mut remote = repo.find_remote("origin")?;
+
+    // find default branch of remote
+    remote.connect(git2::Direction::Fetch)?;
+    let branch = remote.default_branch()?;
+    let branch = branch
+        .as_str()
+        .and_then(|b| b.strip_prefix("refs/heads/"))
+        .ok_or_else(|| git2::Error::from_str("invalid default branch of remote"))?
+        .to_string();
+    remote.disconnect()?;
+
+    let fetch_commit = do_fetch(&repo, &[branch.as_str()], &mut remote)?;
+    do_merge(&repo, &branch, fetch_commit)
+}
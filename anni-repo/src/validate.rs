@@ -0,0 +1,164 @@
+use crate::manager::expand_catalog_range;
+use crate::prelude::*;
+use crate::RepositoryManager;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidateSeverity {
+    Error,
+    Warning,
+}
+
+/// A problem found in an album.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValidateDiagnostic {
+    pub severity: ValidateSeverity,
+    /// Disc id starting from 1, if the problem belongs to a disc or track
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disc_id: Option<usize>,
+    /// Track id starting from 1, if the problem belongs to a track
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track_id: Option<usize>,
+    pub message: String,
+}
+
+impl ValidateDiagnostic {
+    fn error(disc_id: Option<usize>, track_id: Option<usize>, message: String) -> Self {
+        Self {
+            severity: ValidateSeverity::Error,
+            disc_id,
+            track_id,
+            message,
+        }
+    }
+
+    fn warning(disc_id: Option<usize>, track_id: Option<usize>, message: String) -> Self {
+        Self {
+            severity: ValidateSeverity::Warning,
+            disc_id,
+            track_id,
+            message,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == ValidateSeverity::Error
+    }
+}
+
+/// Check an album for problems which can be found without other files:
+///
+/// - empty album or disc catalogs
+/// - albums without artist
+/// - number of discs not matching catalog range like `KSLA-0178~0180`
+/// - discs without tracks
+/// - tracks with empty titles
+/// - release dates after `today`
+pub fn validate_album(album: &Album, today: &AnniDate) -> Vec<ValidateDiagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if album.catalog().trim().is_empty() {
+        diagnostics.push(ValidateDiagnostic::error(
+            None,
+            None,
+            "Missing album catalog".to_string(),
+        ));
+    } else if let Some(catalogs) = expand_catalog_range(album.catalog(), usize::MAX) {
+        if catalogs.len() != album.discs_len() {
+            diagnostics.push(ValidateDiagnostic::error(
+                None,
+                None,
+                format!(
+                    "Catalog {} covers {} disc(s), but album has {} disc(s)",
+                    album.catalog(),
+                    catalogs.len(),
+                    album.discs_len()
+                ),
+            ));
+        }
+    }
+
+    if album.artist().trim().is_empty() {
+        diagnostics.push(ValidateDiagnostic::error(
+            None,
+            None,
+            "Missing album artist".to_string(),
+        ));
+    }
+
+    if album.release_date() > today {
+        diagnostics.push(ValidateDiagnostic::error(
+            None,
+            None,
+            format!("Release date {} is in the future", album.release_date()),
+        ));
+    }
+
+    for (disc_index, disc) in album.iter().enumerate() {
+        let disc_id = Some(disc_index + 1);
+        if disc.catalog().trim().is_empty() {
+            diagnostics.push(ValidateDiagnostic::error(
+                disc_id,
+                None,
+                "Missing disc catalog".to_string(),
+            ));
+        }
+        if disc.tracks_len() == 0 {
+            diagnostics.push(ValidateDiagnostic::error(
+                disc_id,
+                None,
+                "Disc has no tracks".to_string(),
+            ));
+        }
+
+        for (track_index, track) in disc.iter().enumerate() {
+            if track.title().trim().is_empty() {
+                diagnostics.push(ValidateDiagnostic::error(
+                    disc_id,
+                    Some(track_index + 1),
+                    "Empty track title".to_string(),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Check lyrics of an album in repository.
+///
+/// Albums with a lyric directory are expected to have lyrics of all tracks except instrumental ones,
+/// so missing lyric files are reported as warnings. Nothing is checked if lyric asset is not enabled
+/// or the album has no lyric directory.
+pub fn validate_album_lyrics(album: &Album, repo: &RepositoryManager) -> Vec<ValidateDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let Some(root) = repo.lyric_root() else {
+        return diagnostics;
+    };
+    let album_id = album.album_id();
+    if !root.join(album_id.to_string()).is_dir() {
+        return diagnostics;
+    }
+
+    for (disc_index, disc) in album.iter().enumerate() {
+        for (track_index, track) in disc.iter().enumerate() {
+            if track.track_type() == &TrackType::Instrumental {
+                continue;
+            }
+
+            let path = repo
+                .lyric_path(&album_id, disc_index + 1, track_index + 1)
+                .unwrap();
+            // plain text lyrics
+            if !path.exists() && !path.with_extension("txt").exists() {
+                diagnostics.push(ValidateDiagnostic::warning(
+                    Some(disc_index + 1),
+                    Some(track_index + 1),
+                    format!("Missing lyric file {}", path.display()),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
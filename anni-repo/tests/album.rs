@@ -1,5 +1,6 @@
 use anni_repo::prelude::*;
 use std::str::FromStr;
+use uuid::Uuid;
 
 fn album_from_str() -> Album {
     Album::from_str(include_str!("fixtures/test-album.toml")).expect("Failed to parse album toml.")
@@ -70,4 +71,325 @@ fn test_deserialize_album() {
             }
         }
     }
+
+    // artists in array form
+    let text = include_str!("fixtures/test-album.toml")
+        .replace(
+            "artist = \"やなぎなぎ\"\ndate",
+            "artists = [\n    \"やなぎなぎ\",\n    \"麻枝准\",\n]\ndate",
+        )
+        .replace(
+            "title = \"宝物になった日\"\n",
+            "title = \"宝物になった日\"\nartists = [\n    \"やなぎなぎ\",\n    \"Guest\",\n]\n",
+        );
+    let mut album = Album::from_str(&text).expect("Failed to parse album toml.");
+    assert_eq!(album.artist(), "やなぎなぎ、麻枝准");
+    assert_eq!(album.artists(), vec!["やなぎなぎ", "麻枝准"]);
+    assert!(album.detailed_artists().is_none());
+
+    let disc = album.iter().next().unwrap();
+    assert_eq!(disc.artist(), "やなぎなぎ、麻枝准");
+    let tracks: Vec<_> = disc.iter().collect();
+    // legacy single artist
+    assert_eq!(tracks[0].artist(), "やなぎなぎ");
+    assert_eq!(tracks[0].artists(), vec!["やなぎなぎ"]);
+    assert_eq!(tracks[1].artist(), "やなぎなぎ、Guest");
+    assert_eq!(tracks[1].artists(), vec!["やなぎなぎ", "Guest"]);
+    // inherited from album
+    assert_eq!(tracks[2].artist(), "やなぎなぎ、麻枝准");
+    assert_eq!(tracks[2].artists(), vec!["やなぎなぎ", "麻枝准"]);
+    assert_eq!(album.format_to_string(), text);
+}
+
+#[test]
+fn test_album_series() {
+    let text = include_str!("fixtures/test-album.toml").replace(
+        "catalog = \"KSLA-0178\"\ntags",
+        "catalog = \"KSLA-0178\"\nseries = \"Test Series\"\ntags",
+    );
+    let mut album = Album::from_str(&text).expect("Failed to parse album toml.");
+    assert_eq!(album.series(), Some("Test Series"));
+    assert_eq!(album.format_to_string(), text);
+
+    assert!(album_from_str().series().is_none());
+}
+
+#[test]
+fn test_album_edition() {
+    let mut album = album_from_str();
+    assert_eq!(album.edition(), Some("Test"));
+    assert_eq!(album.title_raw(), "夏凪ぎ／宝物になった日");
+
+    let text = include_str!("fixtures/test-album.toml").replace("edition = \"Test\"\n", "");
+    let mut album_without_edition = Album::from_str(&text).expect("Failed to parse album toml.");
+    assert!(album_without_edition.edition().is_none());
+    assert_eq!(album_without_edition.full_title(), "夏凪ぎ／宝物になった日");
+    assert_eq!(album_without_edition.format_to_string(), text);
+
+    // empty edition is treated as no edition
+    let text_empty =
+        include_str!("fixtures/test-album.toml").replace("edition = \"Test\"", "edition = \"\"");
+    let album_empty_edition = Album::from_str(&text_empty).expect("Failed to parse album toml.");
+    assert!(album_empty_edition.edition().is_none());
+
+    assert_eq!(
+        album.format_to_string(),
+        include_str!("fixtures/test-album.toml")
+    );
+}
+
+#[test]
+fn test_album_builder() {
+    let track = |title: &str| Track::builder().title(title);
+    let instrumental = |title: &str| {
+        track(title)
+            .artist("麻枝准")
+            .track_type(TrackType::Instrumental)
+    };
+    let album = Album::builder()
+        .album_id(Uuid::from_str("15006392-e2ae-4204-b7db-e59211f3cdcf").unwrap())
+        .title("夏凪ぎ／宝物になった日")
+        .edition("Test")
+        .artist("やなぎなぎ")
+        .release_date(AnniDate::new(2020, 12, 16))
+        .catalog("KSLA-0178")
+        .tag(TagString::new("tag1".to_string(), TagType::Unknown))
+        .tag(TagString::new("tag2".to_string(), TagType::Unknown))
+        .add_disc(
+            Disc::builder()
+                .add_track(track("夏凪ぎ").artist("やなぎなぎ"))
+                .add_track(track("宝物になった日"))
+                .add_track(track("夏凪ぎ(Episode 9 Ver.)"))
+                .add_track(track("宝物になった日(Episode 5 Ver.)"))
+                .add_track(instrumental("夏凪ぎ(Instrumental)"))
+                .add_track(instrumental("宝物になった日(Instrumental)")),
+        )
+        .build()
+        .expect("Failed to build album.");
+    assert_eq!(album.to_string(), include_str!("fixtures/test-album.toml"));
+}
+
+#[test]
+fn test_album_builder_validation() {
+    let disc = || Disc::builder().add_track(Track::builder().title("Track 1"));
+    let album = || {
+        Album::builder()
+            .title("Title")
+            .artist("Artist")
+            .release_date(AnniDate::new(2023, 1, 1))
+            .catalog("CATALOG-0001")
+    };
+
+    assert!(album().add_disc(disc()).build().is_ok());
+    assert!(matches!(
+        album().build(),
+        Err(Error::BuilderMissingField { field: "discs", .. })
+    ));
+    assert!(matches!(
+        album().title("").add_disc(disc()).build(),
+        Err(Error::BuilderMissingField { field: "title", .. })
+    ));
+    assert!(matches!(
+        album().add_disc(Disc::builder()).build(),
+        Err(Error::BuilderMissingField {
+            field: "tracks",
+            ..
+        })
+    ));
+    assert!(matches!(
+        album().add_disc(disc().add_track(Track::builder())).build(),
+        Err(Error::BuilderMissingField {
+            target: "Track",
+            field: "title"
+        })
+    ));
+    // disc catalog is only inherited when built with album
+    assert!(matches!(
+        disc().build(),
+        Err(Error::BuilderMissingField {
+            target: "Disc",
+            field: "catalog"
+        })
+    ));
+}
+
+#[test]
+fn test_album_diff() {
+    let old = album_from_str();
+    assert!(old.diff(&old).is_empty());
+
+    let text = include_str!("fixtures/test-album.toml")
+        .replace("edition = \"Test\"\n", "")
+        .replace("title = \"宝物になった日\"", "title = \"宝物になった日\"\ntype = \"vocal\"")
+        .replace(
+            "\n[[discs.tracks]]\ntitle = \"宝物になった日(Instrumental)\"\nartist = \"麻枝准\"\ntype = \"instrumental\"\n",
+            "\n",
+        );
+    let new = Album::from_str(&text).expect("Failed to parse album toml.");
+    let changes = old.diff(&new);
+    assert_eq!(
+        changes,
+        vec![
+            AlbumChange::Album(FieldChange {
+                field: "edition",
+                old: Some("Test".to_string()),
+                new: None,
+            }),
+            AlbumChange::Track {
+                disc_id: 1,
+                track_id: 2,
+                change: FieldChange {
+                    field: "type",
+                    old: Some("normal".to_string()),
+                    new: Some("vocal".to_string()),
+                },
+            },
+            AlbumChange::TrackRemoved {
+                disc_id: 1,
+                track_id: 6,
+                title: "宝物になった日(Instrumental)".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        changes[1].to_string(),
+        r#"disc 1 track 2 type: "normal" -> "vocal""#
+    );
+
+    // moving artist from tracks to disc is not a change
+    let text = include_str!("fixtures/test-album.toml").replace(
+        "[[discs]]\ncatalog = \"KSLA-0178\"\n",
+        "[[discs]]\ncatalog = \"KSLA-0178\"\nartist = \"やなぎなぎ\"\n",
+    );
+    let new = Album::from_str(&text).expect("Failed to parse album toml.");
+    assert!(old.diff(&new).is_empty());
+}
+
+#[test]
+fn test_album_limits() {
+    let input = include_str!("fixtures/test-album.toml");
+    assert!(Album::from_str_with_limits(input, &AlbumLimits::DEFAULT).is_ok());
+    assert!(matches!(
+        Album::from_str_with_limits(
+            input,
+            &AlbumLimits {
+                max_discs: 0,
+                ..AlbumLimits::DEFAULT
+            }
+        ),
+        Err(Error::AlbumLimitExceeded {
+            target: "discs",
+            limit: 0,
+            actual: 1
+        })
+    ));
+    assert!(matches!(
+        Album::from_str_with_limits(
+            input,
+            &AlbumLimits {
+                max_tracks_per_disc: 2,
+                ..AlbumLimits::DEFAULT
+            }
+        ),
+        Err(Error::AlbumLimitExceeded { limit: 2, .. })
+    ));
+    // length is checked before parsing, so it applies to invalid documents too
+    assert!(matches!(
+        Album::from_str_with_limits(
+            &"x".repeat(11),
+            &AlbumLimits {
+                max_size: 10,
+                ..AlbumLimits::DEFAULT
+            }
+        ),
+        Err(Error::AlbumLimitExceeded {
+            target: "bytes",
+            limit: 10,
+            actual: 11
+        })
+    ));
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn test_json_album_limits() {
+    use anni_repo::models::JsonAlbum;
+
+    let album = Album::from_str(include_str!("fixtures/test-album.toml")).unwrap();
+    let input = JsonAlbum::from(album).to_string();
+    assert!(JsonAlbum::from_str_with_limits(&input, &AlbumLimits::DEFAULT).is_ok());
+    assert!(matches!(
+        JsonAlbum::from_str_with_limits(
+            &input,
+            &AlbumLimits {
+                max_discs: 0,
+                ..AlbumLimits::DEFAULT
+            }
+        ),
+        Err(Error::AlbumLimitExceeded {
+            target: "discs",
+            ..
+        })
+    ));
+    assert!(matches!(
+        JsonAlbum::from_str_with_limits(
+            &input,
+            &AlbumLimits {
+                max_size: input.len() - 1,
+                ..AlbumLimits::DEFAULT
+            }
+        ),
+        Err(Error::AlbumLimitExceeded {
+            target: "bytes",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_track_normalize_title() {
+    let mut track = Track::empty();
+    track.title = "ＡＢＣ／Pok\u{65}\u{301}mon　".to_string();
+    assert_eq!(track.normalize_title(), "ABC/Pok\u{e9}mon");
+}
+
+#[test]
+fn test_track_duration_and_isrc() {
+    use std::time::Duration;
+
+    let text = include_str!("fixtures/test-album.toml")
+        .replace(
+            "artist = \"やなぎなぎ\"\n\n",
+            "artist = \"やなぎなぎ\"\nduration = 275\nisrc = \"JPK651401201\"\n\n",
+        )
+        .replace(
+            "title = \"宝物になった日\"\n",
+            "title = \"宝物になった日\"\nduration = \"4:05\"\n",
+        );
+    let mut album = Album::from_str(&text).expect("Failed to parse album toml.");
+    let disc = album.iter().next().unwrap();
+    let tracks: Vec<_> = disc.iter().collect();
+    assert_eq!(tracks[0].duration(), Some(Duration::from_secs(275)));
+    assert_eq!(tracks[0].isrc(), Some("JPK651401201"));
+    assert_eq!(tracks[1].duration(), Some(Duration::from_secs(245)));
+    assert_eq!(tracks[1].isrc(), None);
+    assert_eq!(tracks[2].duration(), None);
+
+    // durations are written as mm:ss
+    let expected = include_str!("fixtures/test-album.toml")
+        .replace(
+            "artist = \"やなぎなぎ\"\n\n",
+            "artist = \"やなぎなぎ\"\nduration = \"04:35\"\nisrc = \"JPK651401201\"\n\n",
+        )
+        .replace(
+            "title = \"宝物になった日\"\n",
+            "title = \"宝物になった日\"\nduration = \"04:05\"\n",
+        );
+    assert_eq!(album.format_to_string(), expected);
+
+    let invalid = include_str!("fixtures/test-album.toml").replace(
+        "title = \"夏凪ぎ\"\n",
+        "title = \"夏凪ぎ\"\nduration = \"4:60\"\n",
+    );
+    assert!(Album::from_str(&invalid).is_err());
 }
@@ -1,4 +1,4 @@
-use anni_repo::{error::Error, prelude::*, RepositoryManager};
+use anni_repo::{error::Error, prelude::*, OwnedRepositoryManager, RepositoryManager};
 use std::{path::PathBuf, str::FromStr};
 
 fn repo_from_str() -> Repository {
@@ -23,6 +23,27 @@ albums = ["album"]
     );
 }
 
+#[test]
+fn repo_assets() {
+    let repo = Repository::from_str(
+        r#"[repo]
+name = "Yesterday17's Metadata Repo"
+edition = "1.3"
+
+[assets.cover]
+root = "cover"
+
+[assets.lyric]
+enable = false
+root = "lyric"
+"#,
+    )
+    .expect("Failed to parse toml");
+    assert_eq!(repo.cover().map(|c| c.root.as_str()), Some("cover"));
+    assert!(repo.lyric().is_none());
+    assert!(repo_from_str().cover().is_none());
+}
+
 #[test]
 fn test_empty_repository() {
     let manager =
@@ -140,3 +161,228 @@ fn test_repo_album_tags() {
     assert_eq!(tracks[0].artist(), "Artist1");
     assert_eq!(tracks[0].track_type(), &TrackType::Absolute);
 }
+
+#[test]
+fn test_resolve_catalog_range() {
+    let manager = RepositoryManager::new("tests/repos/catalog-range")
+        .expect("Failed to load metadata repository");
+    // album is stored with catalog of its first disc
+    assert!(manager.load_albums("KSLA-0178~0180").unwrap().is_empty());
+
+    for catalog in ["KSLA-0178~0180", "KSLA-0178~80"] {
+        let albums = manager.resolve_albums(catalog).unwrap();
+        assert_eq!(albums.len(), 1, "{catalog}");
+        assert_eq!(albums[0].catalog(), "KSLA-0178~0180");
+        assert_eq!(albums[0].iter().count(), 3);
+        assert!(albums[0]
+            .to_string()
+            .contains(r#"catalog = "KSLA-0178~0180""#));
+    }
+    assert!(manager.resolve_albums("KSLA-0179~0181").unwrap().is_empty());
+    assert!(manager.resolve_albums("KSLA-0181").unwrap().is_empty());
+}
+
+#[test]
+fn test_orphan_lyrics() {
+    let manager = RepositoryManager::new("tests/repos/lyrics")
+        .expect("Failed to load metadata repository")
+        .into_owned_manager()
+        .unwrap();
+    let root = manager.repo.lyric_root().unwrap();
+    let album = "5c8f0e1a-9d3b-4f6e-8a2c-7b4d1e9f3a60";
+    let mut expected = vec![
+        root.join(album).join("1").join("3.lrc"),
+        root.join(album).join("2").join("1.lrc"),
+        root.join("2f6b9c1d-3e4a-4b8f-9c7d-1a2b3c4d5e6f")
+            .join("1")
+            .join("1.lrc"),
+    ];
+    expected.sort();
+    assert_eq!(manager.orphan_lyrics().unwrap(), expected);
+}
+
+#[test]
+fn test_tag_graph_cycles() {
+    let manager = RepositoryManager::new("tests/repos/tag-cycle")
+        .expect("Failed to load metadata repository")
+        .into_owned_manager()
+        .unwrap();
+    let a = TagRef::new("A", TagType::Group);
+    let b = TagRef::new("B", TagType::Group);
+    // C is included by A but not in a cycle
+    assert_eq!(
+        manager.validate_tag_graph(),
+        Err(vec![vec![a.clone(), b.clone(), a.clone()]])
+    );
+    assert_eq!(manager.check_tags_loop(), Some(vec![&a, &b, &a]));
+}
+
+#[test]
+fn test_duplicate_catalogs() {
+    let manager = RepositoryManager::new("tests/repos/duplicate-catalogs")
+        .expect("Failed to load metadata repository")
+        .into_owned_manager()
+        .unwrap();
+    // editions in album/TEST-0001/ share their catalog on purpose
+    assert_eq!(
+        manager.duplicate_catalogs(),
+        [(
+            "TEST-0002".to_string(),
+            vec![
+                uuid::Uuid::parse_str("6e8a0c2e-4f6b-48d0-b2c4-e6f8a0c2e4f6").unwrap(),
+                uuid::Uuid::parse_str("1c3e5a7b-9d2f-4b6d-8f0a-2c4e6a8b0d3f").unwrap(),
+            ]
+        )]
+    );
+}
+
+#[test]
+fn test_search_albums() {
+    let manager = RepositoryManager::new("tests/repos/search")
+        .expect("Failed to load metadata repository")
+        .into_owned_manager()
+        .unwrap();
+    let catalogs = |albums: Vec<&Album>| -> Vec<String> {
+        albums
+            .iter()
+            .map(|album| album.catalog().to_string())
+            .collect()
+    };
+
+    assert_eq!(
+        catalogs(manager.search_albums(None, None, None)),
+        ["TEST-0001", "TEST-0002", "TEST-0003"]
+    );
+    // artist in artist list
+    assert_eq!(
+        catalogs(manager.search_albums(Some("nagi"), None, None)),
+        ["TEST-0001", "TEST-0002"]
+    );
+    assert_eq!(
+        catalogs(manager.search_albums(None, Some("SUMMER"), None)),
+        ["TEST-0001", "TEST-0003"]
+    );
+    assert_eq!(
+        catalogs(manager.search_albums(Some("maeda"), Some("summer"), None)),
+        Vec::<String>::new()
+    );
+
+    let tag = TagRef::new("Nagi", TagType::Artist);
+    assert_eq!(
+        catalogs(manager.search_albums(None, None, Some(&tag))),
+        ["TEST-0001"]
+    );
+    let tag = TagRef::new("Nagi", TagType::Group);
+    assert!(manager.search_albums(None, None, Some(&tag)).is_empty());
+}
+
+#[test]
+fn test_iter_albums_with_tags_only() {
+    let manager =
+        RepositoryManager::new("tests/repos/export").expect("Failed to load metadata repository");
+    let albums: Vec<_> = manager.iter_albums().unwrap().collect();
+    assert_eq!(albums.len(), 1);
+    assert_eq!(
+        albums[0].0,
+        PathBuf::from("tests/repos/export/album/album.toml")
+    );
+
+    let manager = OwnedRepositoryManager::new_tags_only(manager).unwrap();
+    assert!(manager.albums().is_empty());
+    let (path, album) = manager.repo.iter_albums().unwrap().next().unwrap();
+    let mut album = album.unwrap();
+    manager.resolve_album_tags(&mut album).unwrap();
+    assert_eq!(album.tags()[0], &TagRef::new("Test", TagType::Artist));
+    assert!(path.ends_with("album.toml"));
+}
+
+#[test]
+fn test_validate_albums() {
+    use anni_repo::validate::ValidateSeverity;
+
+    let manager = RepositoryManager::new("tests/repos/validate")
+        .expect("Failed to load metadata repository")
+        .into_owned_manager()
+        .unwrap();
+    let result = manager.validate(&AnniDate::new(2023, 4, 1));
+    assert_eq!(result.len(), 1);
+
+    let (path, album, diagnostics) = &result[0];
+    assert_eq!(*path, PathBuf::from("album").join("TEST-0002.toml"));
+    assert_eq!(album.catalog(), "TEST-0002~0004");
+    let diagnostics: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.severity, d.disc_id, d.track_id, d.is_error()))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            // disc count mismatch
+            (ValidateSeverity::Error, None, None, true),
+            // date in the future
+            (ValidateSeverity::Error, None, None, true),
+            // empty track title
+            (ValidateSeverity::Error, Some(1), Some(2), true),
+            // missing disc catalog
+            (ValidateSeverity::Error, Some(2), None, true),
+            // missing lyric, while instrumental track on disc 2 is skipped
+            (ValidateSeverity::Warning, Some(1), Some(2), false),
+        ]
+    );
+
+    // nothing is in the future after release date
+    let result = manager.validate(&AnniDate::new(2999, 12, 31));
+    assert_eq!(result[0].2.len(), 4);
+}
+
+#[cfg(feature = "db")]
+#[test]
+fn test_database_in_memory() {
+    use anni_repo::db::RepoDatabaseRead;
+    use uuid::Uuid;
+
+    let manager = RepositoryManager::new("tests/repos/catalog-range")
+        .expect("Failed to load metadata repository")
+        .into_owned_manager()
+        .unwrap();
+    let path = std::env::temp_dir().join(format!("anni-repo-{}.db", Uuid::new_v4()));
+    manager.to_database(&path).unwrap();
+
+    let album_id = Uuid::parse_str("0d8e6f2a-5b8d-4d4c-9f5e-6d1d0a3c2b71").unwrap();
+    let mut db = RepoDatabaseRead::new_in_memory(&path).unwrap();
+    assert!(db.is_in_memory());
+    std::fs::remove_file(&path).unwrap();
+    // database file is no longer needed after loading
+    assert!(db.get_album(album_id).unwrap().is_some());
+
+    // reload rebuilds the copy from file
+    manager.to_database(&path).unwrap();
+    db.reload().unwrap();
+    assert!(db.get_album(album_id).unwrap().is_some());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "db")]
+#[test]
+fn test_database_album_artists() {
+    use anni_repo::db::{ArtistEntry, RepoDatabaseRead};
+    use uuid::Uuid;
+
+    let manager = RepositoryManager::new("tests/repos/catalog-range")
+        .expect("Failed to load metadata repository")
+        .into_owned_manager()
+        .unwrap();
+    let path = std::env::temp_dir().join(format!("anni-repo-{}.db", Uuid::new_v4()));
+    manager.to_database(&path).unwrap();
+
+    let db = RepoDatabaseRead::new(&path).unwrap();
+    assert_eq!(
+        db.album_artists().unwrap(),
+        [ArtistEntry {
+            artist: "Artist".to_string(),
+            albums: 1,
+        }]
+    );
+    drop(db);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -21,7 +21,14 @@ command_decoder!(FlacCommandDecoder, "flac", ["-c", "-d", FILE_PLACEHOLDER]);
 command_encoder!(
     FlacCommandEncoder,
     "flac",
-    ["--totally-silent", "-", "-o", FILE_PLACEHOLDER]
+    [
+        "--totally-silent",
+        // the last track of streamed wave does not know its data size
+        "--ignore-chunk-sizes",
+        "-",
+        "-o",
+        FILE_PLACEHOLDER
+    ]
 );
 command_decoder!(ApeCommandDecoder, "mac", [FILE_PLACEHOLDER, "-", "-d"]);
 command_decoder!(TakCommandDecoder, "takc", ["-d", FILE_PLACEHOLDER, "-"]);
@@ -30,6 +37,24 @@ command_decoder!(
     "ttaenc",
     ["-d", "-o", "-", FILE_PLACEHOLDER]
 );
+command_decoder!(
+    M4aCommandDecoder,
+    "ffmpeg",
+    [
+        "-v",
+        "error",
+        "-i",
+        FILE_PLACEHOLDER,
+        // keep LIST chunk out of the header
+        "-map_metadata",
+        "-1",
+        "-fflags",
+        "+bitexact",
+        "-f",
+        "wav",
+        "-"
+    ]
+);
 
 #[cfg(test)]
 mod tests {
@@ -3,7 +3,7 @@ use anni_common::encode::{btoken_w, u16_le_w, u32_le_w};
 use anni_common::traits::{Decode, Encode};
 use log::{debug, error};
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
 use super::{Decoder, Encoder};
@@ -79,7 +79,7 @@ impl Encode for WaveHeader {
 
     fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Self::Err> {
         btoken_w(writer, b"RIFF")?;
-        u32_le_w(writer, self.data_size + 16)?; // chunk size
+        u32_le_w(writer, self.data_size.saturating_add(16))?; // chunk size
         btoken_w(writer, b"WAVE")?;
         btoken_w(writer, b"fmt ")?;
         u32_le_w(writer, 16)?; // PCM chunk size
@@ -96,6 +96,9 @@ impl Encode for WaveHeader {
 }
 
 impl WaveHeader {
+    /// Data size of wave whose length is unknown while streaming, like the output of ffmpeg to pipe.
+    pub const UNKNOWN_DATA_SIZE: u32 = u32::MAX;
+
     pub fn offset_from_second_frames(&self, s: u32, f: u32) -> u32 {
         let br = self.byte_rate;
         br * s + br * f / 75
@@ -116,8 +119,18 @@ pub struct WavEncoder<P: AsRef<Path>>(pub P);
 
 impl<P: AsRef<Path>> Encoder for WavEncoder<P> {
     fn encode(self, mut input: impl Read) -> Result<(), crate::error::SplitError> {
+        let mut header = WaveHeader::from_reader(&mut input)?;
         let mut output = File::create(self.0)?;
-        std::io::copy(&mut input, &mut output)?;
+        header.write_to(&mut output)?;
+        let size = std::io::copy(&mut input, &mut output)?;
+
+        // data size is only known after data is written
+        if header.data_size == WaveHeader::UNKNOWN_DATA_SIZE {
+            header.data_size =
+                u32::try_from(size).map_err(|_| crate::error::SplitError::DataTooLarge(size))?;
+            output.seek(SeekFrom::Start(0))?;
+            header.write_to(&mut output)?;
+        }
         Ok(())
     }
 }
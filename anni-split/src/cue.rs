@@ -10,8 +10,8 @@ pub struct CueBreakpoint {
 }
 
 impl Breakpoint for CueBreakpoint {
-    fn position(&self, header: &WaveHeader) -> u32 {
-        header.offset_from_second_frames(self.seconds, self.frames)
+    fn position(&self, header: &WaveHeader) -> Result<u32, SplitError> {
+        Ok(header.offset_from_second_frames(self.seconds, self.frames))
     }
 }
 
@@ -14,4 +14,10 @@ pub enum SplitError {
 
     #[error(transparent)]
     IOError(#[from] io::Error),
+
+    #[error("breakpoint at sample {0} is beyond the 4 GiB limit of WAVE")]
+    BreakpointOutOfRange(u64),
+
+    #[error("wave data of {0} bytes does not fit in the 4 GiB limit of WAVE")]
+    DataTooLarge(u64),
 }
@@ -49,11 +49,18 @@ where
     for (index, end) in breakpoints
         .into_iter()
         .map(|b| b.position(&header))
-        .chain([header.data_size])
+        .chain([Ok(header.data_size)])
         .enumerate()
     {
+        let end = end?;
         let encoder = output(index)?;
-        let size = end - start;
+        // wave streamed by some decoders, e.g. ffmpeg, does not know its data size,
+        // so the last track is streamed to the end, and its size is left for encoder to find out
+        let (size, limit) = if end == WaveHeader::UNKNOWN_DATA_SIZE {
+            (WaveHeader::UNKNOWN_DATA_SIZE, u64::MAX)
+        } else {
+            (end - start, (end - start) as u64)
+        };
 
         let mut header_buf = Cursor::new([0; 44]);
         let mut header = header.clone();
@@ -61,7 +68,7 @@ where
         header.write_to(&mut header_buf)?;
         header_buf.set_position(0);
 
-        let body = &mut reader.take(size as u64);
+        let body = &mut reader.take(limit);
         encoder.encode(header_buf.chain(body))?;
 
         start = end;
@@ -71,23 +78,107 @@ where
 }
 
 pub trait Breakpoint {
-    fn position(&self, header: &WaveHeader) -> u32;
+    /// Byte offset of the breakpoint in wave data.
+    fn position(&self, header: &WaveHeader) -> Result<u32, SplitError>;
 }
 
 pub struct RawBreakpoint(pub u32);
 
 impl Breakpoint for RawBreakpoint {
-    fn position(&self, _: &WaveHeader) -> u32 {
+    fn position(&self, _: &WaveHeader) -> Result<u32, SplitError> {
+        Ok(self.0)
+    }
+}
+
+/// Breakpoint at a sample offset, which keeps sample accuracy.
+pub struct SampleBreakpoint(pub u64);
+
+impl Breakpoint for SampleBreakpoint {
+    fn position(&self, header: &WaveHeader) -> Result<u32, SplitError> {
         self.0
+            .checked_mul(header.block_align as u64)
+            .and_then(|position| u32::try_from(position).ok())
+            .ok_or(SplitError::BreakpointOutOfRange(self.0))
+    }
+}
+
+impl<B: Breakpoint + ?Sized> Breakpoint for Box<B> {
+    fn position(&self, header: &WaveHeader) -> Result<u32, SplitError> {
+        (**self).position(header)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::codec::wav::WavDecoder;
-    use crate::codec::FlacCommandEncoder;
+    use crate::codec::wav::{WavDecoder, WavEncoder, WaveHeader};
+    use crate::codec::{Decoder, FlacCommandEncoder};
     use crate::cue::cue_breakpoints;
+    use crate::error::SplitError;
     use crate::split;
+    use crate::split::{Breakpoint, RawBreakpoint, SampleBreakpoint};
+    use anni_common::traits::{Decode, Encode};
+    use std::io::Cursor;
+
+    struct MemoryDecoder(Vec<u8>);
+
+    impl Decoder for MemoryDecoder {
+        type Output = Cursor<Vec<u8>>;
+
+        fn decode(self) -> Result<Self::Output, SplitError> {
+            Ok(Cursor::new(self.0))
+        }
+    }
+
+    fn header(data_size: u32) -> WaveHeader {
+        WaveHeader {
+            channels: 2,
+            sample_rate: 44100,
+            byte_rate: 44100 * 4,
+            block_align: 4,
+            bit_per_sample: 16,
+            data_size,
+        }
+    }
+
+    #[test]
+    fn test_sample_breakpoint() {
+        let header = header(0);
+        assert_eq!(SampleBreakpoint(441).position(&header).unwrap(), 1764);
+        assert!(matches!(
+            SampleBreakpoint(1 << 31).position(&header),
+            Err(SplitError::BreakpointOutOfRange(_))
+        ));
+        assert!(matches!(
+            SampleBreakpoint(u64::MAX).position(&header),
+            Err(SplitError::BreakpointOutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_split_unknown_size() {
+        let mut input = Vec::new();
+        header(WaveHeader::UNKNOWN_DATA_SIZE)
+            .write_to(&mut input)
+            .unwrap();
+        input.extend((0..100).map(|i| i as u8));
+
+        let root = tempfile::tempdir().unwrap();
+        let path = |index: usize| root.path().join(format!("{index}.wav"));
+        split(
+            MemoryDecoder(input),
+            |index| Ok(WavEncoder(path(index))),
+            [RawBreakpoint(40)],
+        )
+        .unwrap();
+
+        for (index, data) in [(0, 0..40), (1, 40..100)] {
+            let output = std::fs::read(path(index)).unwrap();
+            let mut reader = output.as_slice();
+            let header = WaveHeader::from_reader(&mut reader).unwrap();
+            assert_eq!(header.data_size as usize, data.len());
+            assert_eq!(reader, data.map(|i| i as u8).collect::<Vec<_>>());
+        }
+    }
 
     #[test]
     fn test_split_a_cd() {
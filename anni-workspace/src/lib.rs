@@ -7,7 +7,7 @@ use crate::config::WorkspaceConfig;
 use anni_common::fs;
 use anni_repo::library::file_name;
 use anni_repo::prelude::{AnniDate, UNKNOWN_ARTIST};
-use anni_repo::RepositoryManager;
+use anni_repo::{OwnedRepositoryManager, RepositoryManager};
 use config::LibraryConfig;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
@@ -220,6 +220,46 @@ impl AnniWorkspace {
         Ok(())
     }
 
+    /// Scan userland of the workspace for directories with flac files but without `.album` symlink.
+    ///
+    /// Such directories are usually albums which have not been created with `anni workspace create`.
+    /// Directories inside albums are not scanned.
+    pub fn scan_unlinked(&self) -> Result<Vec<PathBuf>, WorkspaceError> {
+        let mut result = Vec::new();
+        self.scan_unlinked_directory(&mut result, self.workspace_root())?;
+        result.sort();
+        Ok(result)
+    }
+
+    fn scan_unlinked_directory(
+        &self,
+        result: &mut Vec<PathBuf>,
+        path: &Path,
+    ) -> Result<(), WorkspaceError> {
+        let mut has_flac = false;
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            if entry.file_name() == ".anni" {
+                continue;
+            }
+
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                if !path.join(".album").is_symlink() {
+                    self.scan_unlinked_directory(result, &path)?;
+                }
+            } else if path.extension().map_or(false, |ext| ext == "flac") {
+                has_flac = true;
+            }
+        }
+
+        if has_flac && path != self.workspace_root() {
+            result.push(path.to_path_buf());
+        }
+        Ok(())
+    }
+
     /// Internal: scan controlled part
     fn scan_controlled_directory<P>(
         &self,
@@ -614,11 +654,24 @@ impl AnniWorkspace {
     where
         P: AsRef<Path>,
     {
-        let album_id = self.get_album_id(album_path)?;
-        let controlled_album_path = self.get_album_controlled_path(&album_id)?;
-
         let repo = self.to_repository_manager()?;
         let repo = repo.into_owned_manager()?;
+        self.apply_tags_with(album_path, &repo)
+    }
+
+    /// Apply tags with metadata from a loaded repository `repo`.
+    ///
+    /// Repository can only be loaded once at a time, so use this to apply tags of multiple albums.
+    pub fn apply_tags_with<P>(
+        &self,
+        album_path: P,
+        repo: &OwnedRepositoryManager,
+    ) -> Result<(), WorkspaceError>
+    where
+        P: AsRef<Path>,
+    {
+        let album_id = self.get_album_id(album_path)?;
+        let controlled_album_path = self.get_album_controlled_path(&album_id)?;
 
         // TODO: do not panic here
         let album = repo
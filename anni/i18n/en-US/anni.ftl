@@ -7,6 +7,17 @@ export-to = Path for exported data.
 flac = Provide FLAC-related utilities.
 flac-export = Export data.
 flac-export-type = Type of data to export.
+flac-analyze = Analyze sample peak, true peak and clipping.
+flac-analyze-fail-on-clip = Exit with error if any track clips.
+flac-analyze-jobs = Number of files to analyze in parallel. Defaults to the number of CPUs.
+flac-gapless-check = Check whether track boundaries of an album are gapless.
+flac-gapless-check-min-silence-ms = Minimum length of digital silence around a boundary to be reported, in milliseconds.
+flac-gapless-check-directories = Album (or disc) directories containing FLAC tracks.
+flac-tags = Manage vorbis comments.
+flac-tags-lint = Detect vorbis comments outside of allowed tags.
+flac-tags-lint-fix = Normalize casing of allowed tags and remove disallowed tags.
+flac-verify = Verify integrity of FLAC files by decoding them.
+flac-verify-jobs = Number of files to verify in parallel. Defaults to the number of CPUs.
 
 
 ## split
@@ -15,7 +26,18 @@ split-format-input = Format of input audio file.
 split-format-output = Format of output audio file.
 split-clean = Keep split ao files clean with no metadata or cover written into.
 split-no-import-cover = Do not import cover to audio file.
+split-cover = Cover to embed in split tracks. Defaults to cover.jpg in the input directory.
 split-output-file-exist = Output file {$filename} exists. Please remove the file and try again.
+split-output-dir = Directory to write split tracks to. Tracks are written next to the cue file if not provided.
+split-layout = Layout of tracks in output directory. `flat` puts tracks directly in it, `strict` uses the same layout as objects store.
+split-album-id = Album id used in strict layout. A new id would be generated if not provided.
+split-disc-id = Disc id used in strict layout.
+split-force = Write to non-empty output directory, and overwrite existing files.
+split-output-dir-not-dir = Output path {$path} is not a directory.
+split-output-dir-not-empty = Output directory {$path} is not empty. Use --force to write to it anyway.
+split-output-dir-not-writable = Output directory {$path} is not writable: {$error}
+split-album-id-multiple-directories = --album-id can not be used when splitting multiple directories.
+split-from-chapters = Read track boundaries from chapters embedded in audio file (CUESHEET of FLAC, or chapters of M4A read by ffprobe), falling back to cue file if not found.
 
 
 ## convention
@@ -57,27 +79,133 @@ repo-cue-insufficient-information = Insufficient information from CUE file.
 
 repo-edit = Open text editor for an album if metadata exists.
 repo-lint = Check whether data in repository is valid.
+repo-validate = Check albums in repository for missing catalogs, disc count mismatches, empty track titles, missing lyrics, future release dates and tag cycles.
+repo-validate-format = Output format of problems found.
+repo-validate-failed = {$count} error(s) found in repository.
+repo-validate-passed = {$count} album(s) validated.
+
+repo-check-lyrics = Check timestamps of LRC lyrics of albums.
+repo-check-lyrics-albums = Catalogs or album ids of albums to check.
+repo-check-lyrics-failed = {$count} problem(s) found in lyrics.
+repo-check-lyrics-passed = Lyrics of {$count} album(s) checked.
+repo-lyric-not-enabled = Lyric asset is not enabled in repo.toml.
+repo-lyric = Convert LRC lyric to plain text, or apply offset to its timestamps.
+repo-lyric-to = Format to convert the lyric to.
+repo-lyric-offset = Additional offset in milliseconds applied with `--to lrc`. Positive offset makes lyrics appear sooner.
+repo-lyric-input = Path of the lyric file, or `-` for stdin.
+repo-lyric-malformed = Line {$line} has malformed timestamp and is kept unchanged.
+repo-orphan-lyrics = List lyric files not referenced by any track.
+repo-orphan-lyrics-delete = Delete orphan lyric files.
+repo-orphan-lyrics-found = {$count} orphan lyric file(s) found.
+repo-orphan-lyrics-deleted = {$count} orphan lyric file(s) deleted.
 
 repo-print = Print metadata information of given catalog.
 repo-print-type = Print type.
 repo-print-clean = Do not print REM COMMENT "Generated by Anni" in cue mode.
 repo-print-input = Target to print. For example, tag name or album catalog. '/{"{disc_id}"}' can be appended to indicate the disc id of an album. Disc id equals to 0 or 1 both indicates the first disc.
+repo-print-resolve = Resolve catalog ranges like KSLA-0178~0180 to the album covering them.
+
+repo-export = Export all albums in repository, loading one album at a time.
+repo-export-format = Format of exported albums.
+repo-export-include-tags = Embed resolved tags of each album, including tags of discs and tracks.
+repo-where = Print path of album metadata file.
+repo-where-input = Catalog or album id of the album. All paths are printed if the catalog matches multiple albums.
+repo-duplicates = List catalogs shared by more than one album.
+repo-duplicates-found = {$count} catalog(s) shared by more than one album.
+repo-duplicates-not-found = No duplicate catalog found.
+repo-search = Search albums by artist, title and tag.
+repo-search-artist = Match albums whose artist contains the given text, case-insensitive.
+repo-search-title = Match albums whose title contains the given text, case-insensitive.
+repo-search-tag = Match albums with the given tag, in `type:name` format.
+repo-search-format = Output format of matched albums.
+repo-diff = Print semantic changes between two versions of an album file.
+repo-diff-json = Print changes as JSON.
+repo-diff-rev = Compare album file in working tree with the version at given git revision.
+repo-diff-old = Album file of the old version, or the album file to compare with --rev.
+repo-diff-new = Album file of the new version.
+repo-diff-no-change = No changes found.
+repo-diff-invalid-path = Invalid album file path: {$path}
+repo-diff-git-failed = Failed to read album file at revision {$rev}: {$error}
 
 repo-db = Generate metadata database from repository.
 
+repo-cover = Manage album covers in repository.
+repo-cover-download = Download missing album covers from Cover Art Archive.
+repo-cover-download-force = Overwrite existing covers.
+repo-cover-download-min-resolution = Minimal width and height of downloaded covers.
+repo-cover-download-mbid = MusicBrainz release id to fetch cover from. Only valid when a single catalog is given.
+repo-cover-not-enabled = Cover asset is not enabled in repo.toml.
+repo-cover-exists = Cover of {$catalog} already exists, skipping.
+repo-cover-downloaded = Cover of {$catalog} saved to {$path}.
+repo-cover-not-found = No suitable cover found for {$catalog}.
+repo-check-covers = Check presence, format and resolution of album covers.
+repo-check-covers-min-resolution = Minimal width and height of covers.
+repo-check-covers-formats = Allowed image formats of covers.
+repo-check-covers-failed = {$count} problem(s) found in covers.
+repo-check-covers-passed = Covers of {$count} album(s) checked.
+
+repo-series = Manage album series in repository.
+repo-series-list = List series, or albums in a series.
+repo-series-list-series = List albums in the given series instead of all series.
+repo-artist = Manage album artists in repository.
+repo-artist-list = List album artists with the number of their albums.
+repo-artist-list-min = Only list artists with at least N albums.
+repo-artist-list-json = Print artists in JSON format.
+repo-artist-rename = Rename an artist in albums, discs and tracks.
+repo-artist-rename-old = Artist to rename.
+repo-artist-rename-new = New name of the artist.
+repo-artist-rename-dry-run = Only report entries to rename without modifying files.
+repo-artist-renamed = Renamed {$count} artist(s) in {$path}.
+repo-artist-rename-finished = {$count} artist(s) renamed.
+repo-artist-to-rename = {$count} artist(s) to rename in {$path}.
+repo-artist-rename-dry-run-finished = {$count} artist(s) to rename.
+repo-artist-not-found = Artist {$artist} was not found in repo, nothing to do.
+
+repo-tag = Manage tags of albums.
+repo-tag-add = Add a tag to an album.
+repo-tag-remove = Remove a tag from an album.
+repo-tag-album = Album ID or catalog of the album.
+repo-tag-add-tag = Tag to add, which must exist in repo.
+repo-tag-remove-tag = Tag to remove. Tags not defined in repo can also be removed.
+repo-tag-not-found = Tag {$tag} was not found in repo.
+repo-tag-ambiguous = Multiple tags named {$tag} exist in repo, please specify tag type like `type:name`.
+repo-tag-album-ambiguous = Multiple albums match {$album}, please use album ID instead.
+repo-tag-already-added = Album already has tag {$tag}, nothing to do.
+repo-tag-not-in-album = Album does not have tag {$tag}, nothing to do.
+repo-tag-modified = Modified {$path}.
+
 repo-migrate = Migrate metadata repository to new version.
 repo-migrate-album-id = Add album_id field to album metadata.
+repo-migrate-edition = Migrate album files to a newer repository edition.
+repo-migrate-edition-to = Target edition of repository.
+repo-migrate-edition-dry-run = Print changes instead of writing them to files.
+repo-migrate-edition-up-to-date = Repository is already at edition {$edition}.
+repo-migrate-edition-downgrade = Can not migrate repository from edition {$from} to older edition {$to}.
+repo-migrate-edition-migrated = Migrated {$path}.
+repo-migrate-edition-unknown = Unknown edition {$edition}, latest edition is {$latest}.
 
 
 ## Library
 library = Anni Audio library manager.
 library-tag = Apply metadata from repository to album.
 library-link = Link library to strict format.
+library-stats = Summarize audio files in a library directory.
+library-stats-jobs = Number of files to read in parallel. Defaults to the number of CPUs.
+library-verify = Verify that tracks of albums in repository exist in library.
+library-verify-library = Root of audio library in convention layout.
+library-fingerprint = Find duplicate tracks across albums by acoustic fingerprints.
+library-fingerprint-layer = Number of directory layers of albums in the strict library.
+library-fingerprint-store = File to store fingerprints in. Defaults to `fingerprints.json` in library root.
+library-fingerprint-threshold = Minimum similarity of two tracks to be reported as duplicates, between 0 and 1.
+library-fingerprint-refresh = Compute fingerprints of all tracks again instead of reusing stored ones.
+library-fingerprint-path = Root of audio library in strict layout.
 
 ## Workspace
 workspace = Manage audio and metadata workspace.
 workspace-init = Initialize workspace.
 workspace-create = Create new albums in workspace.
+workspace-create-template = Scaffold album metadata with template. Builtin templates are single, ep and album, and can be overridden by files in .anni/templates/.
+workspace-create-tracks = Number of tracks in each disc, overrides the template.
 
 workspace-add = Make an album stashed by workspace.
 workspace-add-import-tags=Import metadata from audio files while adding.
@@ -93,6 +221,14 @@ workspace-serve = Serve workspace as a remote service.
 workspace-fsck = Check and fix workspace.
 
 
+## Annil
+annil = Tools for annil servers.
+annil-test-token = Decode a token, and test it against albums, audio and cover of an annil server.
+annil-test-token-url = Base url of annil server.
+annil-test-token-token = User token or share token to test.
+annil-test-token-album = Album to request with the token.
+
+
 ## Completions
 completions = Generate shell completion.
 completions-shell = Shell to generate completion.
@@ -7,6 +7,17 @@ export-to = 导出内容存放的路径
 flac = 提供 FLAC 处理相关的功能
 flac-export = 导出内容
 flac-export-type = 导出内容类型
+flac-analyze = 分析采样峰值、真峰值与削波
+flac-analyze-fail-on-clip = 存在削波的曲目时以错误退出
+flac-analyze-jobs = 并行分析的文件数，默认为 CPU 数量
+flac-gapless-check = 检查专辑音轨间是否无缝衔接
+flac-gapless-check-min-silence-ms = 边界附近被报告的数字静音的最短长度，单位为毫秒
+flac-gapless-check-directories = 包含 FLAC 音轨的专辑（或碟片）目录
+flac-tags = 管理 Vorbis 注释
+flac-tags-lint = 检查不在允许列表中的 Vorbis 注释
+flac-tags-lint-fix = 规范允许标签的大小写，并移除不允许的标签
+flac-verify = 通过解码校验 FLAC 文件的完整性
+flac-verify-jobs = 并行校验的文件数，默认为 CPU 数量
 
 
 ## split
@@ -15,7 +26,18 @@ split-format-input = 待切分音频的文件类型
 split-format-output = 切分后输出音频的文件类型
 split-clean = 不向切分后的音频文件中写入元数据和封面等信息
 split-no-import-cover = 不从切分目录寻找封面写入音频文件
+split-cover = 写入切分后音频文件的封面，默认使用输入目录中的 cover.jpg
 split-output-file-exist = 输出路径下已存在文件 {$filename}，请删除文件后重试
+split-output-dir = 切分后音轨的输出目录，未指定时输出到 cue 文件所在目录
+split-layout = 输出目录中的音轨布局，flat 为直接输出到目录下，strict 为与 objects 存储相同的布局
+split-album-id = strict 布局下使用的专辑 ID，未指定时生成新的 ID
+split-disc-id = strict 布局下使用的碟片 ID
+split-force = 允许输出到非空目录，并覆盖已存在的文件
+split-output-dir-not-dir = 输出路径 {$path} 不是目录
+split-output-dir-not-empty = 输出目录 {$path} 非空，可使用 --force 强制输出
+split-output-dir-not-writable = 输出目录 {$path} 不可写：{$error}
+split-album-id-multiple-directories = 切分多个目录时不能使用 --album-id
+split-from-chapters = 从音频文件内嵌的章节信息（FLAC 的 CUESHEET，或通过 ffprobe 读取的 M4A 章节）读取音轨分界，未找到时使用 cue 文件
 
 
 ## convention
@@ -56,28 +78,134 @@ repo-cue-insufficient-information = CUE 文件未能提供足够的信息
 
 repo-edit = 当元数据仓库中存在该专辑时，打开仓库中对应的文件
 repo-lint = 检查仓库数据的合法性
+repo-validate = 检查仓库中专辑的品番缺失、碟片数量不符、空曲目标题、歌词缺失、未来的发售日期与标签循环
+repo-validate-format = 问题的输出格式
+repo-validate-failed = 仓库中发现 {$count} 个错误
+repo-validate-passed = 已校验 {$count} 张专辑
+
+repo-check-lyrics = 检查专辑 LRC 歌词的时间戳
+repo-check-lyrics-albums = 要检查的专辑的品番或专辑 ID
+repo-check-lyrics-failed = 歌词中发现 {$count} 个问题
+repo-check-lyrics-passed = 已检查 {$count} 张专辑的歌词
+repo-lyric-not-enabled = repo.toml 中未启用歌词资源
+repo-lyric = 将 LRC 歌词转换为纯文本，或将偏移应用到时间戳上
+repo-lyric-to = 歌词转换的目标格式
+repo-lyric-offset = 使用 `--to lrc` 时额外应用的偏移毫秒数，正数使歌词提前显示
+repo-lyric-input = 歌词文件路径，`-` 表示标准输入
+repo-lyric-malformed = 第 {$line} 行的时间戳格式错误，已保持原样
+repo-orphan-lyrics = 列出未被任何曲目引用的歌词文件
+repo-orphan-lyrics-delete = 删除未被引用的歌词文件
+repo-orphan-lyrics-found = 发现 {$count} 个未被引用的歌词文件
+repo-orphan-lyrics-deleted = 已删除 {$count} 个未被引用的歌词文件
 
 repo-print = 根据品番输出元数据仓库中的数据
 repo-print-type = 输出数据的类型
 repo-print-clean = 省略 cue 输出中的 REM COMMENT "Generated by Anni"
 repo-print-input = 需要输出的对象。可以是标签名称或专辑品番。当表示专辑品番时，可以通过get_albums_by_tag后缀 '/{"{disc_id}"}' 指定需要输出信息的碟片编号，0 和 1 均代表第一张碟片
+repo-print-resolve = 将 KSLA-0178~0180 形式的品番范围解析为对应的专辑
+
+repo-export = 导出仓库中的所有专辑，每次仅加载一张专辑
+repo-export-format = 导出专辑的格式
+repo-export-include-tags = 附带每张专辑解析后的标签，包括碟片与曲目的标签
+repo-where = 输出专辑元数据文件的路径
+repo-where-input = 专辑的品番或 ID。当品番对应多张专辑时输出所有路径
+repo-duplicates = 列出被多张专辑共用的品番
+repo-duplicates-found = 发现 {$count} 个被多张专辑共用的品番
+repo-duplicates-not-found = 未发现重复的品番
+repo-search = 按艺术家、标题与标签搜索专辑
+repo-search-artist = 匹配艺术家包含给定文本的专辑，不区分大小写
+repo-search-title = 匹配标题包含给定文本的专辑，不区分大小写
+repo-search-tag = 匹配带有给定标签的专辑，格式为 `类型:名称`
+repo-search-format = 匹配专辑的输出格式
+repo-diff = 输出专辑文件两个版本间的元数据变更
+repo-diff-json = 以 JSON 格式输出变更
+repo-diff-rev = 比较工作区中的专辑文件与指定 git 版本中的文件
+repo-diff-old = 旧版本的专辑文件，使用 --rev 时为待比较的专辑文件
+repo-diff-new = 新版本的专辑文件
+repo-diff-no-change = 未发现变更
+repo-diff-invalid-path = 无效的专辑文件路径：{$path}
+repo-diff-git-failed = 读取版本 {$rev} 中的专辑文件失败：{$error}
 
 repo-db = 生成元数据仓库对应的数据库文件
 
+repo-cover = 管理元数据仓库中的专辑封面
+repo-cover-download = 从 Cover Art Archive 下载缺失的专辑封面
+repo-cover-download-force = 覆盖已存在的封面
+repo-cover-download-min-resolution = 下载封面的最小宽度与高度
+repo-cover-download-mbid = 获取封面的 MusicBrainz 发行 ID，仅在指定单个品番时有效
+repo-cover-not-enabled = repo.toml 中未启用封面资源
+repo-cover-exists = {$catalog} 的封面已存在，跳过
+repo-cover-downloaded = {$catalog} 的封面已保存至 {$path}
+repo-cover-not-found = 未找到 {$catalog} 的合适封面
+repo-check-covers = 检查专辑封面是否存在及其格式与分辨率
+repo-check-covers-min-resolution = 封面的最小宽度与高度
+repo-check-covers-formats = 允许的封面图片格式
+repo-check-covers-failed = 封面中发现 {$count} 个问题
+repo-check-covers-passed = 已检查 {$count} 张专辑的封面
+
+repo-series = 管理元数据仓库中的专辑系列
+repo-series-list = 列出所有系列，或某一系列中的专辑
+repo-series-list-series = 列出指定系列中的专辑，而不是所有系列
+repo-artist = 管理元数据仓库中的专辑艺术家
+repo-artist-list = 列出专辑艺术家及其专辑数量
+repo-artist-list-min = 仅列出专辑数量不少于 N 的艺术家
+repo-artist-list-json = 以 JSON 格式输出艺术家
+repo-artist-rename = 重命名专辑、碟片与曲目中的艺术家
+repo-artist-rename-old = 要重命名的艺术家
+repo-artist-rename-new = 艺术家的新名称
+repo-artist-rename-dry-run = 仅报告需要重命名的条目，不修改文件
+repo-artist-renamed = 已重命名 {$path} 中的 {$count} 处艺术家
+repo-artist-rename-finished = 共重命名 {$count} 处艺术家
+repo-artist-to-rename = {$path} 中有 {$count} 处艺术家待重命名
+repo-artist-rename-dry-run-finished = 共 {$count} 处艺术家待重命名
+repo-artist-not-found = 元数据仓库中不存在艺术家 {$artist}，无需操作
+
+repo-tag = 管理专辑标签
+repo-tag-add = 为专辑添加标签
+repo-tag-remove = 移除专辑的标签
+repo-tag-album = 专辑的 ID 或品番
+repo-tag-add-tag = 要添加的标签，必须已在仓库中定义
+repo-tag-remove-tag = 要移除的标签，未在仓库中定义的标签也可移除
+repo-tag-not-found = 仓库中不存在标签 {$tag}
+repo-tag-ambiguous = 仓库中存在多个名为 {$tag} 的标签，请以 `类型:名称` 的形式指定标签类型
+repo-tag-album-ambiguous = 存在多张匹配 {$album} 的专辑，请使用专辑 ID
+repo-tag-already-added = 专辑已有标签 {$tag}，无需修改
+repo-tag-not-in-album = 专辑没有标签 {$tag}，无需修改
+repo-tag-modified = 已修改 {$path}
+
 repo-migrate = 迁移旧版本元数据仓库到新版本
 repo-migrate-album-id = 为缺少 album_id 字段的专辑添加这一字段
+repo-migrate-edition = 将专辑文件迁移到更新的仓库版本
+repo-migrate-edition-to = 仓库的目标版本
+repo-migrate-edition-dry-run = 输出变更而不写入文件
+repo-migrate-edition-up-to-date = 仓库已处于版本 {$edition}
+repo-migrate-edition-downgrade = 无法将仓库从版本 {$from} 迁移到更旧的版本 {$to}
+repo-migrate-edition-migrated = 已迁移 {$path}
+repo-migrate-edition-unknown = 未知版本 {$edition}，最新版本为 {$latest}
 
 
 ## Library
 library = 提供音频仓库的管理功能
 library-tag = 将元数据仓库中的数据应用到专辑
 library-link = 以符号链接将约定目录格式转换为严格目录格式
+library-stats = 统计音频库目录中的音频文件
+library-stats-jobs = 并行读取的文件数，默认为 CPU 数量
+library-verify = 检查元数据仓库中专辑的曲目是否存在于音频库中
+library-verify-library = 约定目录格式音频库的根目录
+library-fingerprint = 通过声学指纹查找不同专辑间的重复曲目
+library-fingerprint-layer = 严格目录格式音频库中专辑所在的目录层数
+library-fingerprint-store = 保存指纹的文件，默认为音频库根目录下的 `fingerprints.json`
+library-fingerprint-threshold = 两首曲目被报告为重复的最低相似度，介于 0 与 1 之间
+library-fingerprint-refresh = 重新计算所有曲目的指纹，而不复用已保存的指纹
+library-fingerprint-path = 严格目录格式音频库的根目录
 
 
 ## Workspace
 workspace = 管理音频整理工作空间
 workspace-init = 初始化工作空间
 workspace-create = 在工作空间中创建新专辑
+workspace-create-template = 使用模板生成专辑元数据。内置模板为 single、ep 和 album，可以通过 .anni/templates/ 中的文件覆盖
+workspace-create-tracks = 每张碟片中的音轨数量，会覆盖模板中的设置
 
 workspace-add = 将工作空间中专辑对状态从未跟踪转换为已跟踪
 workspace-add-import-tags=从音频文件中导入元数据
@@ -93,6 +221,14 @@ workspace-serve = 将工作空间作为 http 服务启动
 workspace-fsck = 检查并修复工作空间
 
 
+## Annil
+annil = 提供与 annil 服务相关的工具
+annil-test-token = 解析令牌，并在 annil 服务上测试该令牌对专辑列表、音频与封面的访问
+annil-test-token-url = annil 服务的地址
+annil-test-token-token = 需要测试的用户令牌或分享令牌
+annil-test-token-album = 使用令牌请求的专辑
+
+
 ## Completions
 completions = 生成 Shell 的补全脚本
 completions-shell = 生成补全脚本的 Shell
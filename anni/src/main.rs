@@ -38,6 +38,7 @@ pub enum AnniSubcommand {
     Library(LibrarySubcommand),
     Completions(CompletionsSubcommand),
     Workspace(WorkspaceSubcommand),
+    Annil(AnnilSubcommand),
 }
 
 #[tokio::main]
@@ -0,0 +1,164 @@
+use crate::ll;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Local, TimeZone};
+use clap::{Args, Subcommand};
+use clap_handler::{handler, Handler};
+use reqwest::{Method, StatusCode};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Args, Debug, Clone, Handler)]
+#[clap(about = ll!("annil"))]
+pub struct AnnilSubcommand {
+    #[clap(subcommand)]
+    action: AnnilAction,
+}
+
+#[derive(Subcommand, Debug, Clone, Handler)]
+pub enum AnnilAction {
+    #[clap(name = "test-token")]
+    #[clap(about = ll!("annil-test-token"))]
+    TestToken(AnnilTestTokenAction),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct AnnilTestTokenAction {
+    #[clap(long)]
+    #[clap(help = ll!("annil-test-token-url"))]
+    url: String,
+    #[clap(long)]
+    #[clap(help = ll!("annil-test-token-token"))]
+    token: String,
+    #[clap(long)]
+    #[clap(help = ll!("annil-test-token-album"))]
+    album: Uuid,
+}
+
+#[handler(AnnilTestTokenAction)]
+async fn annil_test_token(me: AnnilTestTokenAction) -> anyhow::Result<()> {
+    let claims = decode_claims(&me.token)?;
+    print_claims(&claims, &me.album);
+
+    let url = me.url.trim_end_matches('/');
+    let album_id = me.album;
+    let client = reqwest::Client::new();
+    let checks = [
+        ("info", Method::GET, format!("{url}/info")),
+        ("albums", Method::GET, format!("{url}/albums")),
+        (
+            "audio",
+            Method::HEAD,
+            format!("{url}/{album_id}/1/1?quality=lossless"),
+        ),
+        ("cover", Method::GET, format!("{url}/{album_id}/cover")),
+    ];
+
+    let mut failed = 0;
+    for (name, method, url) in checks {
+        let response = match client
+            .request(method, &url)
+            .header("Authorization", &me.token)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                error!("[{name}] request to {url} failed: {e}");
+                failed += 1;
+                continue;
+            }
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            error!("[{name}] {status} {}", body.trim());
+            failed += 1;
+            continue;
+        }
+
+        // album list tells whether the album is hosted by annil
+        if name == "albums" {
+            let albums: Vec<String> = response.json().await.unwrap_or_default();
+            if !albums.contains(&album_id.to_string()) {
+                error!("[{name}] {status}, but album {album_id} is not in the album list");
+                failed += 1;
+                continue;
+            }
+        }
+        info!("[{name}] {status}");
+    }
+
+    if failed > 0 {
+        bail!("{failed} request(s) failed");
+    }
+    Ok(())
+}
+
+/// Decode claims of a JWT without verifying its signature.
+fn decode_claims(token: &str) -> anyhow::Result<Value> {
+    let payload = token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| anyhow!("Invalid token: missing payload"))?;
+    let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('='))?;
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+fn print_claims(claims: &Value, album_id: &Uuid) {
+    match claims["type"].as_str() {
+        Some("user") => info!(
+            "User token of {}",
+            claims["user_id"].as_str().unwrap_or("<unknown>")
+        ),
+        Some("share") => {
+            let audios = claims["audios"].as_object();
+            info!(
+                "Share token of {} album(s)",
+                audios.map_or(0, |audios| audios.len())
+            );
+            if !audios.is_some_and(|audios| audios.contains_key(&album_id.to_string())) {
+                warn!("Album {album_id} is not shared by the token");
+            }
+        }
+        _ => warn!("Unknown token type: {}", claims["type"]),
+    }
+    info!("Scope: {}", claims["scope"].as_str().unwrap_or("both"));
+
+    if let Some(issued_at) = claims["iat"].as_i64() {
+        info!("Issued at: {}", format_time(issued_at));
+    }
+    match claims["exp"].as_i64() {
+        Some(expires_at) if expires_at < Local::now().timestamp() => {
+            warn!("Expired at: {}", format_time(expires_at))
+        }
+        Some(expires_at) => info!("Expires at: {}", format_time(expires_at)),
+        None => info!("Expires at: never"),
+    }
+}
+
+fn format_time(timestamp: i64) -> String {
+    match Local.timestamp_opt(timestamp, 0) {
+        chrono::LocalResult::Single(time) => time.to_rfc3339(),
+        _ => timestamp.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_claims;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    #[test]
+    fn test_decode_claims() {
+        let payload = URL_SAFE_NO_PAD.encode(r#"{"type":"user","user_id":"test","exp":1}"#);
+        let claims = decode_claims(&format!("header.{payload}.signature")).unwrap();
+        assert_eq!(claims["type"], "user");
+        assert_eq!(claims["user_id"], "test");
+        assert_eq!(claims["exp"], 1);
+
+        assert!(decode_claims("not a token").is_err());
+    }
+}
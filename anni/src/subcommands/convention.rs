@@ -1,6 +1,8 @@
 use crate::args::{FlacInputPath, InputPath};
 use crate::config::read_config;
 use crate::ll;
+use anni_common::fs::sanitize_file_name;
+use anni_common::text::normalize_title;
 use anni_common::validator::*;
 use anni_flac::blocks::{BlockStreamInfo, BlockVorbisComment, PictureType};
 use anni_flac::{FlacHeader, MetadataBlockData};
@@ -263,15 +265,18 @@ impl ConventionRules {
 
         // Filename check
         if let (Some(title), Some(track_number)) = (title, track_number) {
-            let filename_expected: &str =
-                &format!("{:0>2}. {}.flac", track_number, title).replace("/", "／");
+            let filename_expected = format!("{:0>2}. {}.flac", track_number, title);
+            let filename_expected: &str = &sanitize_file_name(&filename_expected);
             let filename_raw = filename
                 .as_ref()
                 .file_name()
                 .unwrap()
                 .to_str()
                 .expect("Non-UTF8 filenames are currently not supported!");
-            if filename_raw != filename_expected {
+            // file names differing only in character width are accepted
+            if filename_raw != filename_expected
+                && normalize_title(filename_raw) != normalize_title(filename_expected)
+            {
                 error!(target: "convention/filename", "Filename of file: {filename_str} mismatch. Expected {filename_expected}");
                 if fix {
                     // use correct filename
@@ -0,0 +1,223 @@
+use crate::args::{FlacInputPath, InputPath};
+use crate::ll;
+use anni_common::parallel;
+use anni_flac::FlacHeader;
+use clap::Args;
+use clap_handler::handler;
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug, Clone)]
+pub struct FlacAnalyzeAction {
+    #[clap(long)]
+    #[clap(help = ll!("flac-analyze-fail-on-clip"))]
+    fail_on_clip: bool,
+
+    #[clap(short, long)]
+    #[clap(help = ll!("flac-analyze-jobs"))]
+    jobs: Option<usize>,
+
+    #[clap(required = true)]
+    filename: Vec<InputPath<FlacInputPath>>,
+}
+
+#[handler(FlacAnalyzeAction)]
+fn flac_analyze(me: &FlacAnalyzeAction) -> anyhow::Result<()> {
+    let files: Vec<PathBuf> = me.filename.iter().flat_map(|path| path.iter()).collect();
+    // analyze files in parallel, keeping the original order in output
+    let results = parallel::map(files.iter(), parallel::jobs(me.jobs), |path| {
+        analyze_file(path)
+    });
+
+    let mut clipped = 0;
+    let mut over = 0;
+    let mut failed = 0;
+    println!(
+        "{:>10}  {:>10}  {:>8}  File",
+        "Peak(dB)", "TP(dBTP)", "Clipped"
+    );
+    for (path, result) in files.iter().zip(results) {
+        match result {
+            Ok(analysis) => {
+                let flag = if analysis.clipped > 0 {
+                    clipped += 1;
+                    " [CLIP]"
+                } else if analysis.true_peak > 1.0 {
+                    over += 1;
+                    " [OVER]"
+                } else {
+                    ""
+                };
+                println!(
+                    "{:>10}  {:>10}  {:>8}  {}{flag}",
+                    to_db(analysis.peak),
+                    to_db(analysis.true_peak),
+                    analysis.clipped,
+                    path.display(),
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                error!("Failed to analyze {}: {e}", path.display());
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "{} tracks analyzed, {clipped} clipped, {over} with true peak above 0 dBTP, {failed} failed.",
+        files.len()
+    );
+
+    if me.fail_on_clip && clipped > 0 {
+        bail!("{clipped} tracks clipped");
+    }
+    Ok(())
+}
+
+fn to_db(value: f64) -> String {
+    if value <= 0.0 {
+        "-inf".to_string()
+    } else {
+        format!("{:.2}", 20.0 * value.log10())
+    }
+}
+
+struct TrackAnalysis {
+    /// Sample peak, relative to full scale
+    peak: f64,
+    /// Inter-sample peak estimated by oversampling, relative to full scale
+    true_peak: f64,
+    /// Number of samples in clipping runs
+    clipped: u64,
+}
+
+/// Decode `path` and measure sample peak, true peak and clipping.
+///
+/// Audio is decoded frame by frame, so memory usage does not depend on track length.
+fn analyze_file(path: &Path) -> anyhow::Result<TrackAnalysis> {
+    let header = FlacHeader::from_file(path)?;
+    let info = header.stream_info();
+    let channels = info.channels as usize;
+    let bits = info.bits_per_sample as u32;
+
+    let full_scale = (1i64 << (bits - 1)) as f64;
+    let max = (1i64 << (bits - 1)) - 1;
+    let min = -(1i64 << (bits - 1));
+    let mut meters: Vec<_> = (0..channels).map(|_| PeakMeter::new()).collect();
+
+    let mut frames = header.frames()?;
+    while let Some(block) = frames.next_block()? {
+        for (meter, samples) in meters.iter_mut().zip(block.iter()) {
+            for &value in samples {
+                let value = value as i64;
+                meter.push(value as f64 / full_scale, value >= max || value <= min);
+            }
+        }
+    }
+
+    Ok(meters.into_iter().fold(
+        TrackAnalysis {
+            peak: 0.0,
+            true_peak: 0.0,
+            clipped: 0,
+        },
+        |result, meter| TrackAnalysis {
+            peak: result.peak.max(meter.peak),
+            true_peak: result.true_peak.max(meter.true_peak),
+            clipped: result.clipped + meter.finish(),
+        },
+    ))
+}
+
+/// Taps of interpolation filter used for true peak estimation.
+const TAPS: usize = 16;
+/// Oversampling factor used for true peak estimation.
+const OVERSAMPLE: usize = 4;
+/// Consecutive full scale samples to be considered as clipping.
+const CLIP_RUN: u64 = 3;
+
+struct PeakMeter {
+    coefficients: [[f64; TAPS]; OVERSAMPLE - 1],
+    history: [f64; TAPS],
+
+    peak: f64,
+    true_peak: f64,
+
+    run: u64,
+    clipped: u64,
+}
+
+impl PeakMeter {
+    fn new() -> Self {
+        // windowed sinc interpolation between the two samples in the middle of history
+        let mut coefficients = [[0.0; TAPS]; OVERSAMPLE - 1];
+        for (phase, coefficients) in coefficients.iter_mut().enumerate() {
+            let offset = (phase + 1) as f64 / OVERSAMPLE as f64;
+            for (i, coefficient) in coefficients.iter_mut().enumerate() {
+                let t = (TAPS / 2 - 1) as f64 + offset - i as f64;
+                let sinc = (std::f64::consts::PI * t).sin() / (std::f64::consts::PI * t);
+                let window = 0.5 * (1.0 + (std::f64::consts::PI * t / (TAPS / 2) as f64).cos());
+                *coefficient = sinc * window;
+            }
+        }
+
+        Self {
+            coefficients,
+            history: [0.0; TAPS],
+            peak: 0.0,
+            true_peak: 0.0,
+            run: 0,
+            clipped: 0,
+        }
+    }
+
+    fn push(&mut self, sample: f64, full_scale: bool) {
+        let abs = sample.abs();
+        self.peak = self.peak.max(abs);
+        self.true_peak = self.true_peak.max(abs);
+
+        if full_scale {
+            self.run += 1;
+        } else {
+            self.end_run();
+        }
+
+        self.history.copy_within(1.., 0);
+        self.history[TAPS - 1] = sample;
+        for coefficients in self.coefficients.iter() {
+            let interpolated: f64 = coefficients
+                .iter()
+                .zip(self.history.iter())
+                .map(|(c, x)| c * x)
+                .sum();
+            self.true_peak = self.true_peak.max(interpolated.abs());
+        }
+    }
+
+    fn end_run(&mut self) {
+        if self.run >= CLIP_RUN {
+            self.clipped += self.run;
+        }
+        self.run = 0;
+    }
+
+    /// Finish metering and return clipped samples.
+    fn finish(mut self) -> u64 {
+        self.end_run();
+        self.clipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::analyze_file;
+
+    #[test]
+    fn test_analyze_file() {
+        // 1kHz sine without clipping
+        let analysis = analyze_file("../assets/1s.flac".as_ref()).unwrap();
+        assert_eq!(analysis.clipped, 0);
+        assert!(analysis.peak > 0.0 && analysis.peak < 1.0);
+        assert!(analysis.true_peak >= analysis.peak);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::ll;
+use anni_common::fs;
+use anni_flac::FlacHeader;
+use clap::Args;
+use clap_handler::handler;
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug, Clone)]
+pub struct FlacGaplessCheckAction {
+    #[clap(long, default_value = "10")]
+    #[clap(help = ll!("flac-gapless-check-min-silence-ms"))]
+    min_silence_ms: u32,
+
+    #[clap(required = true)]
+    #[clap(help = ll!("flac-gapless-check-directories"))]
+    directories: Vec<PathBuf>,
+}
+
+#[handler(FlacGaplessCheckAction)]
+fn flac_gapless_check(me: &FlacGaplessCheckAction) -> anyhow::Result<()> {
+    let mut boundaries = 0;
+    let mut broken = 0;
+    for directory in me.directories.iter() {
+        let mut files = fs::get_ext_files(directory, "flac", false)?;
+        if files.len() < 2 {
+            warn!(
+                "Less than 2 FLAC files found in {}, skipped",
+                directory.display()
+            );
+            continue;
+        }
+        alphanumeric_sort::sort_path_slice(&mut files);
+
+        let tracks = files
+            .iter()
+            .map(|path| TrackEdge::from_file(path))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for pair in tracks.windows(2) {
+            let (prev, next) = (&pair[0], &pair[1]);
+            boundaries += 1;
+
+            let problems = check_boundary(prev, next, me.min_silence_ms)?;
+            println!("{} -> {}", prev.path.display(), next.path.display());
+            if problems.is_empty() {
+                println!("  OK");
+            } else {
+                broken += 1;
+                for problem in problems {
+                    println!("  [GAP] {problem}");
+                }
+            }
+        }
+    }
+
+    println!();
+    println!("{boundaries} boundaries checked, {broken} with problems.");
+    if broken > 0 {
+        bail!("{broken} track boundaries are not gapless");
+    }
+    Ok(())
+}
+
+/// Audio parameters of a track read from STREAMINFO.
+struct TrackEdge {
+    path: PathBuf,
+    sample_rate: u32,
+    channels: u8,
+    bits_per_sample: u8,
+    total_samples: u64,
+}
+
+impl TrackEdge {
+    fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let header = FlacHeader::from_file(path)?;
+        let info = header.stream_info();
+        Ok(Self {
+            path: path.to_path_buf(),
+            sample_rate: info.sample_rate,
+            channels: info.channels,
+            bits_per_sample: info.bits_per_sample,
+            total_samples: info.total_samples,
+        })
+    }
+
+    /// Number of samples in a CD frame (1/75 second), if the sample rate allows.
+    fn cd_frame_samples(&self) -> Option<u64> {
+        (self.sample_rate % 75 == 0).then_some(self.sample_rate as u64 / 75)
+    }
+}
+
+/// Check boundary between `prev` and `next`, returning found problems.
+fn check_boundary(
+    prev: &TrackEdge,
+    next: &TrackEdge,
+    min_silence_ms: u32,
+) -> anyhow::Result<Vec<String>> {
+    let mut problems = Vec::new();
+    if prev.sample_rate != next.sample_rate {
+        problems.push(format!(
+            "sample rate mismatch: {} Hz -> {} Hz",
+            prev.sample_rate, next.sample_rate
+        ));
+    }
+    if prev.channels != next.channels {
+        problems.push(format!(
+            "channel count mismatch: {} -> {}",
+            prev.channels, next.channels
+        ));
+    }
+    if prev.bits_per_sample != next.bits_per_sample {
+        problems.push(format!(
+            "bit depth mismatch: {} -> {}",
+            prev.bits_per_sample, next.bits_per_sample
+        ));
+    }
+    if prev.total_samples == 0 || next.total_samples == 0 {
+        problems.push("total samples unknown".to_string());
+    }
+    if !problems.is_empty() {
+        // decoded samples are not comparable
+        return Ok(problems);
+    }
+
+    if let Some(frame) = prev.cd_frame_samples() {
+        let remainder = prev.total_samples % frame;
+        if remainder != 0 {
+            problems.push(format!(
+                "track ends {remainder} samples after a CD frame boundary ({} samples in total)",
+                prev.total_samples
+            ));
+        }
+    }
+
+    // silence inserted by a broken rip is usually much shorter than a second
+    let window = prev.sample_rate as u64;
+    let tail = decode(
+        prev,
+        prev.total_samples.saturating_sub(window),
+        prev.total_samples,
+    )?;
+    let head = decode(next, 0, next.total_samples.min(window))?;
+    let channels = prev.channels as usize;
+    let (trailing, leading) = (
+        trailing_silence(&tail, channels),
+        leading_silence(&head, channels),
+    );
+    // silence on both sides is an ordinary pause between tracks,
+    // while silence on one side breaks audio continuing across the boundary
+    let silence = if trailing == 0 || leading == 0 {
+        trailing + leading
+    } else {
+        0
+    };
+    let min_silence = prev.sample_rate as u64 * min_silence_ms as u64 / 1000;
+    if silence > 0 && silence >= min_silence {
+        problems.push(format!(
+            "{silence} samples ({:.2} ms) of digital silence around boundary",
+            silence as f64 * 1000.0 / prev.sample_rate as f64
+        ));
+    }
+    Ok(problems)
+}
+
+/// Decode samples in `[skip, until)` of `track` as interleaved values.
+fn decode(track: &TrackEdge, skip: u64, until: u64) -> anyhow::Result<Vec<i32>> {
+    let header = FlacHeader::from_file(&track.path)?;
+    let mut frames = header.frames()?;
+    let mut samples = Vec::new();
+    let mut position = 0;
+    while position < until {
+        let Some(block) = frames.next_block()? else {
+            break;
+        };
+        let length = block.first().map_or(0, Vec::len) as u64;
+        let start = skip.clamp(position, position + length) - position;
+        let end = until.clamp(position, position + length) - position;
+        for i in start..end {
+            samples.extend(block.iter().map(|channel| channel[i as usize]));
+        }
+        position += length;
+    }
+    Ok(samples)
+}
+
+fn is_silent(frame: &[i32]) -> bool {
+    frame.iter().all(|s| *s == 0)
+}
+
+/// Number of silent inter-channel samples at the start of `samples`.
+fn leading_silence(samples: &[i32], channels: usize) -> u64 {
+    samples
+        .chunks_exact(channels)
+        .take_while(|frame| is_silent(frame))
+        .count() as u64
+}
+
+/// Number of silent inter-channel samples at the end of `samples`.
+fn trailing_silence(samples: &[i32], channels: usize) -> u64 {
+    samples
+        .chunks_exact(channels)
+        .rev()
+        .take_while(|frame| is_silent(frame))
+        .count() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_boundary, TrackEdge};
+    use std::path::Path;
+
+    const SAMPLE_RATE: u32 = 44100;
+    const BLOCK_SIZE: usize = 4096;
+
+    fn crc8(data: &[u8]) -> u8 {
+        data.iter().fold(0, |crc, byte| {
+            (0..8).fold(crc ^ byte, |crc, _| {
+                if crc & 0x80 != 0 {
+                    (crc << 1) ^ 0x07
+                } else {
+                    crc << 1
+                }
+            })
+        })
+    }
+
+    fn crc16(data: &[u8]) -> u16 {
+        data.iter().fold(0, |crc, byte| {
+            (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+                if crc & 0x8000 != 0 {
+                    (crc << 1) ^ 0x8005
+                } else {
+                    crc << 1
+                }
+            })
+        })
+    }
+
+    /// Write mono 16-bit `samples` as a FLAC file with verbatim subframes.
+    fn write_flac(path: &Path, samples: &[i16]) {
+        let mut data = b"fLaC".to_vec();
+        // last metadata block, STREAMINFO with 34 bytes
+        data.extend_from_slice(&[0x80, 0, 0, 34]);
+        data.extend_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+        data.extend_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+        data.extend_from_slice(&[0; 6]);
+        let info = (SAMPLE_RATE as u64) << 44 | 15 << 36 | samples.len() as u64;
+        data.extend_from_slice(&info.to_be_bytes());
+        data.extend_from_slice(&[0; 16]);
+
+        for (index, block) in samples.chunks(BLOCK_SIZE).enumerate() {
+            // 16-bit block size, 44.1kHz, mono, 16 bits per sample, frame number
+            let mut frame = vec![0xff, 0xf8, 0x79, 0x08, index as u8];
+            frame.extend_from_slice(&(block.len() as u16 - 1).to_be_bytes());
+            frame.push(crc8(&frame));
+            // verbatim subframe
+            frame.push(0x02);
+            for sample in block {
+                frame.extend_from_slice(&sample.to_be_bytes());
+            }
+            frame.extend_from_slice(&crc16(&frame).to_be_bytes());
+            data.extend_from_slice(&frame);
+        }
+        std::fs::write(path, data).unwrap();
+    }
+
+    /// One second of audio, with `silence` samples of silence at the start and the end.
+    fn track(dir: &Path, name: &str, silence: (usize, usize)) -> TrackEdge {
+        let length = SAMPLE_RATE as usize;
+        let samples: Vec<i16> = (0..length)
+            .map(|i| {
+                if i < silence.0 || i >= length - silence.1 {
+                    0
+                } else {
+                    1000 + (i % 100) as i16
+                }
+            })
+            .collect();
+        let path = dir.join(name);
+        write_flac(&path, &samples);
+        TrackEdge::from_file(&path).unwrap()
+    }
+
+    #[test]
+    fn test_check_boundary() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dir = dir.path();
+        let audio = track(dir, "audio.flac", (0, 0));
+        let ending = track(dir, "ending.flac", (0, 4410));
+        let starting = track(dir, "starting.flac", (4410, 0));
+
+        assert!(check_boundary(&audio, &audio, 10)?.is_empty());
+        // pause between tracks
+        assert!(check_boundary(&ending, &starting, 10)?.is_empty());
+        // silence inserted before audio continues
+        assert_eq!(
+            check_boundary(&ending, &audio, 10)?,
+            vec!["4410 samples (100.00 ms) of digital silence around boundary"]
+        );
+        assert_eq!(check_boundary(&audio, &starting, 10)?.len(), 1);
+        // shorter than the minimum
+        assert!(check_boundary(&ending, &audio, 200)?.is_empty());
+        Ok(())
+    }
+}
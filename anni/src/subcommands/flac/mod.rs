@@ -6,6 +6,15 @@ use clap::{Args, Subcommand, ValueEnum};
 use clap_handler::{handler, Handler};
 use std::io::Write;
 
+mod analyze;
+mod gapless;
+mod tags;
+mod verify;
+use analyze::FlacAnalyzeAction;
+use gapless::FlacGaplessCheckAction;
+use tags::FlacTagsAction;
+use verify::FlacVerifyAction;
+
 #[derive(Args, Handler, Debug, Clone)]
 #[clap(about = ll!("flac"))]
 pub struct FlacSubcommand {
@@ -17,6 +26,14 @@ pub struct FlacSubcommand {
 pub enum FlacAction {
     #[clap(about = ll!("flac-export"))]
     Export(FlacExportAction),
+    #[clap(about = ll!("flac-analyze"))]
+    Analyze(FlacAnalyzeAction),
+    #[clap(about = ll!("flac-gapless-check"))]
+    GaplessCheck(FlacGaplessCheckAction),
+    #[clap(about = ll!("flac-tags"))]
+    Tags(FlacTagsAction),
+    #[clap(about = ll!("flac-verify"))]
+    Verify(FlacVerifyAction),
     RemoveID3(FlacRemoveID3Action),
     RemoveUUID(FlacRemoveUUIDAction),
 }
@@ -0,0 +1,154 @@
+use crate::args::{FlacInputPath, InputPath};
+use crate::config::read_config;
+use crate::ll;
+use anni_flac::blocks::UserComment;
+use anni_flac::FlacHeader;
+use clap::{Args, Subcommand};
+use clap_handler::{handler, Handler};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Args, Handler, Debug, Clone)]
+pub struct FlacTagsAction {
+    #[clap(subcommand)]
+    subcommand: FlacTagsSubcommand,
+}
+
+#[derive(Subcommand, Handler, Debug, Clone)]
+pub enum FlacTagsSubcommand {
+    #[clap(about = ll!("flac-tags-lint"))]
+    Lint(FlacTagsLintAction),
+}
+
+/// Allowed vorbis comment fields, read from `tags.toml` in config directory.
+#[derive(Deserialize)]
+struct TagsConfig {
+    /// Allowed tags in canonical casing.
+    allowed: Vec<String>,
+}
+
+impl Default for TagsConfig {
+    fn default() -> Self {
+        Self {
+            allowed: [
+                "TITLE",
+                "ARTIST",
+                "ALBUM",
+                "DATE",
+                "TRACKNUMBER",
+                "TRACKTOTAL",
+                "DISCNUMBER",
+                "DISCTOTAL",
+                "ALBUMARTIST",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        }
+    }
+}
+
+impl TagsConfig {
+    /// Map from uppercase key to canonical key.
+    fn canonical_keys(&self) -> HashMap<String, &str> {
+        self.allowed
+            .iter()
+            .map(|key| (key.to_ascii_uppercase(), key.as_str()))
+            .collect()
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FlacTagsLintAction {
+    #[clap(long)]
+    #[clap(help = ll!("flac-tags-lint-fix"))]
+    fix: bool,
+
+    #[clap(required = true)]
+    filename: Vec<InputPath<FlacInputPath>>,
+}
+
+#[handler(FlacTagsLintAction)]
+fn flac_tags_lint(me: &FlacTagsLintAction) -> anyhow::Result<()> {
+    let config: TagsConfig = match read_config("tags") {
+        Ok(config) => config,
+        // a missing tags.toml is not an error, but a broken one is
+        Err(e)
+            if e.downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound) =>
+        {
+            debug!("tags.toml not found, using default allowed tags");
+            TagsConfig::default()
+        }
+        Err(e) => return Err(e.context("failed to read tags.toml")),
+    };
+    let canonical = config.canonical_keys();
+
+    let mut problems = 0;
+    for path in me.filename.iter() {
+        for file in path.iter() {
+            match lint_file(&file, &canonical, me.fix) {
+                Ok(true) if !me.fix => problems += 1,
+                Ok(_) => {}
+                Err(e) => error!("Failed to lint {}: {e}", file.display()),
+            }
+        }
+    }
+
+    if problems > 0 {
+        bail!("{problems} files have non-standard tags");
+    }
+    Ok(())
+}
+
+/// Lint tags of a single file and returns whether any problem was found.
+fn lint_file(path: &Path, canonical: &HashMap<String, &str>, fix: bool) -> anyhow::Result<bool> {
+    let mut header = FlacHeader::from_file(path)?;
+    let Some(comments) = header.comments() else {
+        return Ok(false);
+    };
+
+    let mut disallowed = Vec::new();
+    let mut miscased = Vec::new();
+    for comment in comments.comments.iter() {
+        let key = comment.key_raw();
+        match canonical.get(&comment.key()) {
+            None => disallowed.push(key.to_string()),
+            Some(expected) if key != *expected => miscased.push(format!("{key} -> {expected}")),
+            Some(_) => {}
+        }
+    }
+    if disallowed.is_empty() && miscased.is_empty() {
+        return Ok(false);
+    }
+
+    if !disallowed.is_empty() {
+        warn!(
+            "Disallowed tags in {}: {}",
+            path.display(),
+            disallowed.join(", ")
+        );
+    }
+    if !miscased.is_empty() {
+        warn!(
+            "Non-canonical tags in {}: {}",
+            path.display(),
+            miscased.join(", ")
+        );
+    }
+
+    if fix {
+        let comments = &mut header.comments_mut().comments;
+        *comments = std::mem::take(comments)
+            .into_iter()
+            .filter_map(|comment| {
+                let key = *canonical.get(&comment.key())?;
+                Some(UserComment::new(format!("{key}={}", comment.value())))
+            })
+            .collect();
+        header.save::<String>(None)?;
+        info!("Fixed tags of {}", path.display());
+    }
+    Ok(true)
+}
@@ -0,0 +1,116 @@
+use crate::args::{FlacInputPath, InputPath};
+use crate::ll;
+use anni_common::parallel;
+use anni_flac::FlacHeader;
+use clap::Args;
+use clap_handler::handler;
+use indicatif::{ProgressBar, ProgressStyle};
+use md5::{Digest, Md5};
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug, Clone)]
+pub struct FlacVerifyAction {
+    #[clap(short, long)]
+    #[clap(help = ll!("flac-verify-jobs"))]
+    jobs: Option<usize>,
+
+    #[clap(required = true)]
+    filename: Vec<InputPath<FlacInputPath>>,
+}
+
+#[handler(FlacVerifyAction)]
+fn flac_verify(me: &FlacVerifyAction) -> anyhow::Result<()> {
+    let files: Vec<PathBuf> = me.filename.iter().flat_map(|path| path.iter()).collect();
+    verify(&files, parallel::jobs(me.jobs))
+}
+
+/// Verify `files` on `jobs` threads, and fail if any of them is broken.
+///
+/// A bad file does not stop verification of other files.
+fn verify(files: &[PathBuf], jobs: usize) -> anyhow::Result<()> {
+    let progress = ProgressBar::new(files.len() as u64);
+    progress.set_style(
+        ProgressStyle::with_template("[{bar:40}] {pos}/{len} files ({eta})")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    let results = parallel::map(files.iter(), jobs, |path| {
+        let result = verify_file(path);
+        progress.inc(1);
+        result
+    });
+    progress.finish_and_clear();
+
+    let mut failed = 0;
+    for (path, result) in files.iter().zip(results) {
+        if let Err(e) = result {
+            error!("{}: {e}", path.display());
+            failed += 1;
+        }
+    }
+
+    info!("{} files verified, {failed} failed", files.len());
+    if failed > 0 {
+        bail!("{failed} files failed verification");
+    }
+    Ok(())
+}
+
+/// Decode `path` and check MD5 signature of decoded audio.
+fn verify_file(path: &Path) -> anyhow::Result<()> {
+    let header = FlacHeader::from_file(path)?;
+    let info = header.stream_info();
+    let bytes = (info.bits_per_sample as usize).div_ceil(8);
+
+    let mut frames = header.frames()?;
+    let mut md5 = Md5::new();
+    let mut samples = 0;
+    while let Some(block) = frames.next_block()? {
+        let length = block.first().map_or(0, Vec::len);
+        for i in 0..length {
+            for channel in block.iter() {
+                md5.update(&channel[i].to_le_bytes()[..bytes]);
+            }
+        }
+        samples += length as u64;
+    }
+
+    // zero means unknown for both of them
+    if info.total_samples != 0 && samples != info.total_samples {
+        bail!("expected {} samples, decoded {samples}", info.total_samples);
+    }
+    if info.md5_signature != [0; 16] && md5.finalize()[..] != info.md5_signature {
+        bail!("MD5 signature mismatch");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_verify() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let data = std::fs::read("../assets/1s.flac")?;
+
+        let valid = PathBuf::from("../assets/1s.flac");
+        // broken frame
+        let corrupted = dir.path().join("corrupted.flac");
+        let mut corrupted_data = data.clone();
+        let len = corrupted_data.len();
+        corrupted_data[len - 100] ^= 0xff;
+        std::fs::write(&corrupted, corrupted_data)?;
+        // MD5 signature at the end of STREAMINFO
+        let mismatch = dir.path().join("mismatch.flac");
+        let mut mismatch_data = data;
+        mismatch_data[41] ^= 0xff;
+        std::fs::write(&mismatch, mismatch_data)?;
+
+        verify(std::slice::from_ref(&valid), 2)?;
+        let error = verify(&[corrupted, valid, mismatch], 2).unwrap_err();
+        assert_eq!(error.to_string(), "2 files failed verification");
+        Ok(())
+    }
+}
@@ -1,14 +1,22 @@
 use crate::{ball, ll};
-use anni_common::fs;
+use anni_common::{fs, parallel};
+use anni_flac::FlacHeader;
+use anni_provider::format::{wav_format, AUDIO_HEADER_SIZE};
 use anni_provider::fs::LocalFileSystemProvider;
-use anni_provider::providers::CommonConventionProvider;
-use anni_provider::strict_album_path;
+use anni_provider::providers::{CommonConventionProvider, CommonStrictProvider};
+use anni_provider::{strict_album_path, AnniProvider, Range};
 use anni_repo::db::RepoDatabaseRead;
-use anni_repo::library::{file_name, AlbumFolderInfo};
-use anni_repo::RepositoryManager;
+use anni_repo::library::{file_name, AlbumFolderInfo, DiscFolderInfo};
+use anni_repo::{OwnedRepositoryManager, RepositoryManager};
 use clap::{Args, Subcommand};
 use clap_handler::{handler, Context, Handler};
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::num::NonZeroU8;
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use std::str::FromStr;
 use uuid::Uuid;
 
@@ -38,6 +46,13 @@ pub enum LibraryAction {
     ApplyTag(LibraryApplyTagAction),
     Link(LibraryLinkAction),
     Check(LibraryCheckAction),
+    #[clap(about = ll!("library-stats"))]
+    Stats(LibraryStatsAction),
+    #[clap(name = "verify-against-repo")]
+    #[clap(about = ll!("library-verify"))]
+    Verify(LibraryVerifyAction),
+    #[clap(about = ll!("library-fingerprint"))]
+    Fingerprint(LibraryFingerprintAction),
 }
 
 #[derive(Args, Debug, Clone)]
@@ -282,3 +297,740 @@ pub async fn library_check(
 
     Ok(())
 }
+
+#[derive(Args, Debug, Clone)]
+pub struct LibraryVerifyAction {
+    #[clap(long)]
+    #[clap(help = ll!("library-verify-library"))]
+    library: PathBuf,
+}
+
+#[handler(LibraryVerifyAction)]
+pub async fn library_verify(
+    me: LibraryVerifyAction,
+    manager: RepositoryManager,
+) -> anyhow::Result<()> {
+    let manager = manager.into_owned_manager()?;
+    let library = me.library.canonicalize()?;
+
+    // albums in library are matched by the generated database, which is kept in memory
+    let db = {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("repo.db");
+        manager.to_database(&path)?;
+        RepoDatabaseRead::new_in_memory(&path)?
+    };
+    let provider =
+        CommonConventionProvider::new(library, db, Box::new(LocalFileSystemProvider)).await?;
+    let (albums, missing, extra) = verify_library(&manager, &provider)?;
+
+    info!("{albums} album(s) checked, {missing} missing track(s), {extra} extra file(s) found");
+    if missing + extra > 0 {
+        bail!(
+            "{} discrepancies found between library and repo",
+            missing + extra
+        );
+    }
+    Ok(())
+}
+
+/// Check tracks of all albums in repo, returns number of albums, missing tracks and extra files.
+fn verify_library(
+    manager: &OwnedRepositoryManager,
+    provider: &CommonConventionProvider,
+) -> anyhow::Result<(usize, usize, usize)> {
+    let mut albums: Vec<_> = manager.albums_iter().collect();
+    albums.sort_by(|a, b| a.catalog().cmp(b.catalog()));
+
+    let (mut missing, mut extra) = (0, 0);
+    for album in albums.iter() {
+        let album_id = album.album_id().to_string();
+        let catalog = album.catalog();
+        let Some(album_entry) = provider.albums.get(&album_id) else {
+            error!(
+                "[MISSING] Album not found in library: [{catalog}] {}",
+                album.full_title()
+            );
+            missing += album.iter().map(|disc| disc.tracks_len()).sum::<usize>();
+            continue;
+        };
+
+        let discs = provider.discs.get(&album_id);
+        for (disc_index, disc) in album.iter().enumerate() {
+            let disc_id = disc_index + 1;
+            let disc_path = match discs {
+                Some(discs) => discs.get(disc_index).map(|disc| &disc.path),
+                None => Some(&album_entry.path),
+            };
+            let Some(disc_path) = disc_path else {
+                error!("[MISSING] Disc not found: [{catalog}] disc = {disc_id}");
+                missing += disc.tracks_len();
+                continue;
+            };
+
+            let (missing_tracks, extra_files) = verify_disc(disc_path, disc.tracks_len())?;
+            for track_id in missing_tracks.iter() {
+                error!(
+                    "[MISSING] Track not found: [{catalog}] disc = {disc_id}, track = {track_id}"
+                );
+            }
+            for file in extra_files.iter() {
+                warn!(
+                    "[EXTRA] Unknown audio file in [{catalog}]: {}",
+                    file.display()
+                );
+            }
+            missing += missing_tracks.len();
+            extra += extra_files.len();
+        }
+    }
+    Ok((albums.len(), missing, extra))
+}
+
+/// Find missing tracks and extra audio files in a disc folder.
+///
+/// Tracks are expected to be named as `{track_id:02}. {title}.{ext}`.
+fn verify_disc(path: &Path, tracks: usize) -> anyhow::Result<(Vec<usize>, Vec<PathBuf>)> {
+    let mut found = vec![false; tracks];
+    let mut extra = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if !path.is_file() || audio_extension(&path).is_none() {
+            continue;
+        }
+
+        let track_id = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split_once('.'))
+            .and_then(|(track_id, _)| track_id.parse::<usize>().ok());
+        match track_id {
+            Some(track_id) if (1..=tracks).contains(&track_id) && !found[track_id - 1] => {
+                found[track_id - 1] = true
+            }
+            _ => extra.push(path),
+        }
+    }
+    extra.sort();
+
+    let missing = found
+        .iter()
+        .enumerate()
+        .filter(|(_, found)| !**found)
+        .map(|(index, _)| index + 1)
+        .collect();
+    Ok((missing, extra))
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct LibraryStatsAction {
+    #[clap(short, long)]
+    #[clap(help = ll!("library-stats-jobs"))]
+    jobs: Option<usize>,
+
+    path: PathBuf,
+}
+
+const AUDIO_EXTENSIONS: &[&str] = &["flac", "wav", "m4a", "mp3", "ogg", "opus", "aac"];
+
+/// Basic information read from the header of an audio file.
+#[derive(Default)]
+struct AudioFileInfo {
+    sample_rate: Option<u32>,
+    bit_depth: Option<u8>,
+}
+
+#[handler(LibraryStatsAction)]
+pub fn library_stats(me: LibraryStatsAction) -> anyhow::Result<()> {
+    if !me.path.is_dir() {
+        bail!("{} is not a directory", me.path.display());
+    }
+
+    let files: Vec<PathBuf> =
+        fs::PathWalker::new(&me.path, true, true, vec![".anni".to_string()]).collect();
+    let audios: Vec<&PathBuf> = files
+        .iter()
+        .filter(|file| audio_extension(file).is_some())
+        .collect();
+
+    // read audio headers in parallel
+    let infos = parallel::map(audios.iter(), parallel::jobs(me.jobs), |path| {
+        read_audio_info(path).unwrap_or_else(|e| {
+            warn!("Failed to read header of {}: {e}", path.display());
+            Default::default()
+        })
+    });
+
+    let mut total_size = 0;
+    let mut formats: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+    let mut albums: HashMap<PathBuf, u64> = HashMap::new();
+    for file in files.iter() {
+        let size = std::fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+        total_size += size;
+
+        if let Some(extension) = audio_extension(file) {
+            let format = formats.entry(extension).or_default();
+            format.0 += 1;
+            format.1 += size;
+            if let Some(album) = album_path(file) {
+                *albums.entry(album).or_default() += size;
+            }
+        }
+    }
+
+    let mut sample_rates: BTreeMap<Option<u32>, usize> = BTreeMap::new();
+    let mut bit_depths: BTreeMap<Option<u8>, usize> = BTreeMap::new();
+    for info in infos {
+        *sample_rates.entry(info.sample_rate).or_default() += 1;
+        *bit_depths.entry(info.bit_depth).or_default() += 1;
+    }
+
+    println!("Files: {} ({} audio)", files.len(), audios.len());
+    println!("Total size: {}", HumanBytes(total_size));
+    let album_size: u64 = albums.values().sum();
+    println!(
+        "Albums: {}, average size: {}",
+        albums.len(),
+        HumanBytes(album_size.checked_div(albums.len() as u64).unwrap_or(0))
+    );
+
+    println!();
+    println!("By format:");
+    for (format, (count, size)) in formats {
+        println!("  {format:<8}{count:>8}  {}", HumanBytes(size));
+    }
+
+    println!();
+    println!("By sample rate:");
+    for (sample_rate, count) in sample_rates {
+        let sample_rate = match sample_rate {
+            Some(rate) => format!("{rate} Hz"),
+            None => "unknown".to_string(),
+        };
+        println!("  {sample_rate:<10}{count:>8}");
+    }
+
+    println!();
+    println!("By bit depth:");
+    for (bit_depth, count) in bit_depths {
+        let bit_depth = match bit_depth {
+            Some(depth) => format!("{depth} bit"),
+            None => "unknown".to_string(),
+        };
+        println!("  {bit_depth:<10}{count:>8}");
+    }
+
+    Ok(())
+}
+
+/// Get lowercase extension of `path` if it's an audio file.
+fn audio_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+    AUDIO_EXTENSIONS
+        .contains(&extension.as_str())
+        .then_some(extension)
+}
+
+/// Get album directory of an audio file.
+///
+/// Disc directories in strict (`1`, `2`, ...) and convention (`[catalog] title [disc]`) layouts
+/// are considered as part of their parent album.
+fn album_path(file: &Path) -> Option<PathBuf> {
+    let parent = file.parent()?;
+    let name = parent.file_name()?.to_string_lossy();
+    if name.parse::<u8>().is_ok() || DiscFolderInfo::from_str(&name).is_ok() {
+        parent.parent().map(Path::to_path_buf)
+    } else {
+        Some(parent.to_path_buf())
+    }
+}
+
+fn read_audio_info(path: &Path) -> anyhow::Result<AudioFileInfo> {
+    match audio_extension(path).as_deref() {
+        Some("flac") => {
+            let header = FlacHeader::from_file(path)?;
+            let info = header.stream_info();
+            Ok(AudioFileInfo {
+                sample_rate: Some(info.sample_rate),
+                bit_depth: Some(info.bits_per_sample),
+            })
+        }
+        Some("wav") => read_wav_info(path),
+        _ => Ok(Default::default()),
+    }
+}
+
+/// Read sample rate and bit depth from `fmt ` chunk of a WAVE file.
+fn read_wav_info(path: &Path) -> anyhow::Result<AudioFileInfo> {
+    let mut header = Vec::new();
+    std::fs::File::open(path)?
+        .take(AUDIO_HEADER_SIZE)
+        .read_to_end(&mut header)?;
+    let Some(format) = wav_format(&header) else {
+        bail!("invalid WAVE header");
+    };
+    Ok(AudioFileInfo {
+        sample_rate: Some(format.sample_rate),
+        bit_depth: Some(format.bits_per_sample as u8),
+    })
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct LibraryFingerprintAction {
+    #[clap(short, long, default_value = "2")]
+    #[clap(help = ll!("library-fingerprint-layer"))]
+    layer: usize,
+
+    #[clap(long)]
+    #[clap(help = ll!("library-fingerprint-store"))]
+    store: Option<PathBuf>,
+
+    #[clap(long, default_value = "0.85")]
+    #[clap(help = ll!("library-fingerprint-threshold"))]
+    threshold: f64,
+
+    #[clap(long)]
+    #[clap(help = ll!("library-fingerprint-refresh"))]
+    refresh: bool,
+
+    #[clap(help = ll!("library-fingerprint-path"))]
+    path: PathBuf,
+}
+
+/// Raw Chromaprint fingerprint of a track.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TrackFingerprint {
+    album_id: String,
+    disc_id: u8,
+    track_id: u8,
+    /// Size of the audio file when fingerprint was computed
+    #[serde(default)]
+    size: usize,
+    fingerprint: Vec<u32>,
+}
+
+/// Tracks whose fingerprints match tracks of other albums.
+#[derive(Default)]
+struct DuplicateCluster {
+    /// Indexes of tracks in the cluster, sorted
+    tracks: Vec<usize>,
+    /// Matched pairs of tracks and their similarity
+    pairs: Vec<(usize, usize, f64)>,
+}
+
+/// Maximum offset in fingerprint items when aligning two tracks, about 10 seconds.
+const MAX_ALIGN_OFFSET: usize = 80;
+/// Minimum number of identical fingerprint items for two tracks to be compared.
+const MIN_SHARED_ITEMS: usize = 10;
+/// Fingerprint items shared by more tracks are usually silence and are not indexed.
+const MAX_ITEM_TRACKS: usize = 64;
+
+#[handler(LibraryFingerprintAction)]
+pub async fn library_fingerprint(
+    me: LibraryFingerprintAction,
+    manager: RepositoryManager,
+) -> anyhow::Result<()> {
+    let manager = manager.into_owned_manager()?;
+    let root = me.path.canonicalize()?;
+    let store = me.store.unwrap_or_else(|| root.join("fingerprints.json"));
+
+    let mut stored: HashMap<(String, u8, u8), (usize, Vec<u32>)> = HashMap::new();
+    if !me.refresh && store.exists() {
+        let fingerprints: Vec<TrackFingerprint> =
+            serde_json::from_str(&std::fs::read_to_string(&store)?)?;
+        for track in fingerprints {
+            stored.insert(
+                (track.album_id, track.disc_id, track.track_id),
+                (track.size, track.fingerprint),
+            );
+        }
+    }
+
+    let provider =
+        CommonStrictProvider::new(root, me.layer, Box::new(LocalFileSystemProvider)).await?;
+    let mut album_ids: Vec<String> = provider
+        .albums()
+        .await?
+        .into_iter()
+        .map(|album_id| album_id.into_owned())
+        .collect();
+    album_ids.sort();
+
+    // tracks to fingerprint, in (album_id, disc_id, track_id) order
+    let mut keys = Vec::new();
+    for album_id in album_ids {
+        let Some(album) = Uuid::parse_str(&album_id)
+            .ok()
+            .and_then(|id| manager.album(&id))
+        else {
+            warn!("Album {album_id} in library is not found in metadata repository");
+            continue;
+        };
+        for (disc_index, disc) in album.iter().enumerate() {
+            for track_index in 0..disc.tracks_len() {
+                keys.push((
+                    album_id.clone(),
+                    disc_index as u8 + 1,
+                    track_index as u8 + 1,
+                ));
+            }
+        }
+    }
+
+    let progress = ProgressBar::new(keys.len() as u64);
+    progress.set_style(
+        ProgressStyle::with_template("[{bar:40}] {pos}/{len} tracks ({eta})")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    let mut tracks = Vec::with_capacity(keys.len());
+    for (album_id, disc_id, track_id) in keys {
+        // stored fingerprint is outdated if the audio file has been replaced since
+        let stored = match stored.remove(&(album_id.clone(), disc_id, track_id)) {
+            Some((size, fingerprint)) => {
+                let current = audio_size(&provider, &album_id, disc_id, track_id).await;
+                (current.ok() == Some(size)).then_some((size, fingerprint))
+            }
+            None => None,
+        };
+        let fingerprint = match stored {
+            Some(stored) => Ok(stored),
+            None => compute_fingerprint(&provider, &album_id, disc_id, track_id).await,
+        };
+        match fingerprint {
+            Ok((size, fingerprint)) => tracks.push(TrackFingerprint {
+                album_id,
+                disc_id,
+                track_id,
+                size,
+                fingerprint,
+            }),
+            Err(e) => progress.suspend(|| {
+                warn!("Failed to fingerprint {album_id} disc {disc_id} track {track_id}: {e}")
+            }),
+        }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+    std::fs::write(&store, serde_json::to_string(&tracks)?)?;
+
+    let clusters = find_duplicates(&tracks, me.threshold);
+    if clusters.is_empty() {
+        info!("No duplicate tracks found in {} track(s)", tracks.len());
+        return Ok(());
+    }
+
+    let describe = |index: usize| {
+        let track = &tracks[index];
+        let album = Uuid::parse_str(&track.album_id)
+            .ok()
+            .and_then(|id| manager.album(&id));
+        match album {
+            Some(album) => {
+                let title = album
+                    .iter()
+                    .nth(track.disc_id as usize - 1)
+                    .and_then(|disc| {
+                        disc.iter()
+                            .nth(track.track_id as usize - 1)
+                            .map(|track| track.title().to_string())
+                    })
+                    .unwrap_or_default();
+                format!(
+                    "{} {}/{} [{}] {title}",
+                    track.album_id,
+                    track.disc_id,
+                    track.track_id,
+                    album.catalog()
+                )
+            }
+            None => format!("{} {}/{}", track.album_id, track.disc_id, track.track_id),
+        }
+    };
+
+    let mut albums: BTreeMap<(&str, &str), Vec<f64>> = BTreeMap::new();
+    for (index, cluster) in clusters.iter().enumerate() {
+        let (min, max) = cluster
+            .pairs
+            .iter()
+            .fold((1f64, 0f64), |(min, max), (_, _, s)| {
+                (min.min(*s), max.max(*s))
+            });
+        println!("Cluster {}: similarity {min:.2} - {max:.2}", index + 1);
+        for track in cluster.tracks.iter() {
+            println!("  {}", describe(*track));
+        }
+        println!();
+
+        for (a, b, similarity) in cluster.pairs.iter() {
+            let (a, b) = (tracks[*a].album_id.as_str(), tracks[*b].album_id.as_str());
+            albums
+                .entry((a.min(b), a.max(b)))
+                .or_default()
+                .push(*similarity);
+        }
+    }
+
+    println!("Albums sharing duplicate tracks:");
+    for ((a, b), similarities) in albums {
+        println!(
+            "  {a} <-> {b}: {} track(s), average similarity {:.2}",
+            similarities.len(),
+            similarities.iter().sum::<f64>() / similarities.len() as f64
+        );
+    }
+    Ok(())
+}
+
+/// Size of audio file of a track.
+async fn audio_size(
+    provider: &CommonStrictProvider,
+    album_id: &str,
+    disc_id: u8,
+    track_id: u8,
+) -> anyhow::Result<usize> {
+    let info = provider
+        .get_audio_info(
+            album_id,
+            NonZeroU8::new(disc_id).unwrap(),
+            NonZeroU8::new(track_id).unwrap(),
+        )
+        .await?;
+    Ok(info.size)
+}
+
+/// Compute raw Chromaprint fingerprint of a track by piping its audio stream into ffmpeg,
+/// along with size of its audio file.
+///
+/// ffmpeg must be built with `--enable-chromaprint`.
+async fn compute_fingerprint(
+    provider: &CommonStrictProvider,
+    album_id: &str,
+    disc_id: u8,
+    track_id: u8,
+) -> anyhow::Result<(usize, Vec<u32>)> {
+    let audio = provider
+        .get_audio(
+            album_id,
+            NonZeroU8::new(disc_id).unwrap(),
+            NonZeroU8::new(track_id).unwrap(),
+            Range::FULL,
+        )
+        .await?;
+    let size = audio.info.size;
+
+    let mut child = tokio::process::Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-i", "pipe:0"])
+        .args(["-f", "chromaprint", "-fp_format", "raw", "pipe:1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut reader = audio.reader;
+    let feed = tokio::spawn(async move { tokio::io::copy(&mut reader, &mut stdin).await });
+    let output = child.wait_with_output().await?;
+    // ffmpeg may exit before reading the whole stream, so failures of feeding are ignored
+    let _ = feed.await;
+
+    if !output.status.success() {
+        bail!(
+            "ffmpeg exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let fingerprint = output
+        .stdout
+        .chunks_exact(4)
+        .map(|item| u32::from_ne_bytes(item.try_into().unwrap()))
+        .collect();
+    Ok((size, fingerprint))
+}
+
+/// Similarity of two raw fingerprints between 0 and 1.
+///
+/// It's the ratio of equal bits at the best alignment, scaled by how much of the longer
+/// fingerprint is overlapped. Unrelated tracks are about 0.5.
+fn fingerprint_similarity(a: &[u32], b: &[u32]) -> f64 {
+    let longest = a.len().max(b.len());
+    let mut best = 0f64;
+    for offset in 0..=MAX_ALIGN_OFFSET {
+        for (a, b) in [(a, b), (b, a)] {
+            let Some(a) = a.get(offset..) else {
+                continue;
+            };
+            let length = a.len().min(b.len());
+            if length == 0 {
+                continue;
+            }
+            let errors: u32 = a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum();
+            let similarity =
+                (1.0 - errors as f64 / (length * 32) as f64) * length as f64 / longest as f64;
+            best = best.max(similarity);
+        }
+    }
+    best
+}
+
+/// Find clusters of tracks from different albums with similarity of at least `threshold`.
+///
+/// Only tracks sharing enough identical fingerprint items are compared, so it does not
+/// compare every pair of tracks in a large library.
+fn find_duplicates(tracks: &[TrackFingerprint], threshold: f64) -> Vec<DuplicateCluster> {
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, track) in tracks.iter().enumerate() {
+        let mut items = track.fingerprint.clone();
+        items.sort_unstable();
+        items.dedup();
+        for item in items {
+            index.entry(item).or_default().push(i);
+        }
+    }
+
+    let mut shared: HashMap<(usize, usize), usize> = HashMap::new();
+    for indexes in index.values() {
+        if indexes.len() > MAX_ITEM_TRACKS {
+            continue;
+        }
+        for (n, &a) in indexes.iter().enumerate() {
+            for &b in &indexes[n + 1..] {
+                if tracks[a].album_id != tracks[b].album_id {
+                    *shared.entry((a, b)).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let mut pairs: Vec<_> = shared
+        .into_iter()
+        .filter(|(_, count)| *count >= MIN_SHARED_ITEMS)
+        .filter_map(|((a, b), _)| {
+            let similarity = fingerprint_similarity(&tracks[a].fingerprint, &tracks[b].fingerprint);
+            (similarity >= threshold).then_some((a, b, similarity))
+        })
+        .collect();
+    pairs.sort_by_key(|(a, b, _)| (*a, *b));
+
+    // union matched pairs into clusters
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut parent: Vec<usize> = (0..tracks.len()).collect();
+    for (a, b, _) in pairs.iter() {
+        let (a, b) = (root(&mut parent, *a), root(&mut parent, *b));
+        parent[a.max(b)] = a.min(b);
+    }
+
+    let mut clusters: BTreeMap<usize, DuplicateCluster> = BTreeMap::new();
+    for pair in pairs {
+        let cluster = clusters.entry(root(&mut parent, pair.0)).or_default();
+        cluster.tracks.extend([pair.0, pair.1]);
+        cluster.pairs.push(pair);
+    }
+    clusters
+        .into_values()
+        .map(|mut cluster| {
+            cluster.tracks.sort_unstable();
+            cluster.tracks.dedup();
+            cluster
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_duplicates, fingerprint_similarity, verify_disc, TrackFingerprint};
+
+    /// Pseudo random fingerprint generated by xorshift.
+    fn fingerprint(seed: u32, length: usize) -> Vec<u32> {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state
+            })
+            .collect()
+    }
+
+    fn track(album_id: &str, track_id: u8, fingerprint: Vec<u32>) -> TrackFingerprint {
+        TrackFingerprint {
+            album_id: album_id.to_string(),
+            disc_id: 1,
+            track_id,
+            size: 0,
+            fingerprint,
+        }
+    }
+
+    #[test]
+    fn test_verify_disc() -> anyhow::Result<()> {
+        let disc = tempfile::tempdir()?;
+        for name in [
+            "01. Track 1.flac",
+            "03. Track 3.flac",
+            "04. Bonus.flac",
+            "03. Track 3 (copy).flac",
+            "cover.jpg",
+        ] {
+            std::fs::write(disc.path().join(name), b"")?;
+        }
+
+        let (missing, mut extra) = verify_disc(disc.path(), 3)?;
+        assert_eq!(missing, [2]);
+        extra
+            .iter_mut()
+            .for_each(|path| *path = path.strip_prefix(disc.path()).unwrap().into());
+        assert_eq!(extra.len(), 2);
+        assert!(extra
+            .iter()
+            .any(|path| path.as_os_str() == "04. Bonus.flac"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_similarity() {
+        let original = fingerprint(1, 400);
+        assert_eq!(fingerprint_similarity(&original, &original), 1.0);
+
+        // shifted by 2 seconds with a few flipped bits
+        let mut copy = original[16..].to_vec();
+        copy.iter_mut().step_by(7).for_each(|item| *item ^= 0b101);
+        let similarity = fingerprint_similarity(&original, &copy);
+        assert!(similarity > 0.9, "{similarity}");
+
+        let similarity = fingerprint_similarity(&original, &fingerprint(2, 400));
+        assert!(similarity < 0.6, "{similarity}");
+        assert_eq!(fingerprint_similarity(&original, &[]), 0.0);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let tracks = vec![
+            track("a", 1, fingerprint(1, 300)),
+            track("a", 2, fingerprint(1, 300)),
+            track("a", 3, fingerprint(2, 300)),
+            track("b", 1, fingerprint(1, 300)),
+            track("b", 2, fingerprint(3, 300)),
+            track("c", 1, fingerprint(2, 300)[8..].to_vec()),
+        ];
+
+        let clusters = find_duplicates(&tracks, 0.85);
+        assert_eq!(clusters.len(), 2);
+        // tracks of the same album are clustered only by matching another album
+        assert_eq!(clusters[0].tracks, [0, 1, 3]);
+        assert!(clusters[0]
+            .pairs
+            .iter()
+            .all(|(a, b, _)| tracks[*a].album_id != tracks[*b].album_id));
+        assert_eq!(clusters[1].tracks, [2, 5]);
+        assert!(clusters[1].pairs[0].2 > 0.9);
+    }
+}
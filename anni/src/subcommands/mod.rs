@@ -1,3 +1,4 @@
+pub mod annil;
 pub mod completions;
 pub mod convention;
 pub mod flac;
@@ -6,6 +7,7 @@ pub mod repo;
 pub mod split;
 pub mod workspace;
 
+pub use annil::AnnilSubcommand;
 pub use completions::CompletionsSubcommand;
 pub use convention::ConventionSubcommand;
 pub use flac::FlacSubcommand;
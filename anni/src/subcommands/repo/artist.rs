@@ -0,0 +1,108 @@
+use crate::{fl, ll};
+use anni_common::fs;
+use anni_repo::db::RepoDatabaseRead;
+use anni_repo::edit::rename_artist;
+use anni_repo::RepositoryManager;
+use clap::{Args, Subcommand};
+use clap_handler::{handler, Handler};
+
+#[derive(Args, Handler, Debug, Clone)]
+pub struct RepoArtistAction {
+    #[clap(subcommand)]
+    subcommand: RepoArtistSubcommand,
+}
+
+#[derive(Subcommand, Handler, Debug, Clone)]
+pub enum RepoArtistSubcommand {
+    #[clap(about = ll!("repo-artist-list"))]
+    List(RepoArtistListAction),
+    #[clap(about = ll!("repo-artist-rename"))]
+    Rename(RepoArtistRenameAction),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoArtistListAction {
+    #[clap(long, default_value = "1")]
+    #[clap(help = ll!("repo-artist-list-min"))]
+    min: usize,
+
+    #[clap(long)]
+    #[clap(help = ll!("repo-artist-list-json"))]
+    json: bool,
+}
+
+#[handler(RepoArtistListAction)]
+fn repo_artist_list(me: RepoArtistListAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    let manager = manager.into_owned_manager()?;
+
+    // artists are counted by the generated database, which is kept in memory
+    let db = {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("repo.db");
+        manager.to_database(&path)?;
+        RepoDatabaseRead::new_in_memory(&path)?
+    };
+
+    let artists: Vec<_> = db
+        .album_artists()?
+        .into_iter()
+        .filter(|artist| artist.albums >= me.min)
+        .collect();
+    if me.json {
+        println!("{}", serde_json::to_string_pretty(&artists)?);
+    } else {
+        for artist in artists {
+            println!("{} ({})", artist.artist, artist.albums);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoArtistRenameAction {
+    #[clap(help = ll!("repo-artist-rename-old"))]
+    old: String,
+
+    #[clap(help = ll!("repo-artist-rename-new"))]
+    new: String,
+
+    #[clap(long)]
+    #[clap(help = ll!("repo-artist-rename-dry-run"))]
+    dry_run: bool,
+}
+
+#[handler(RepoArtistRenameAction)]
+fn repo_artist_rename(
+    me: RepoArtistRenameAction,
+    manager: RepositoryManager,
+) -> anyhow::Result<()> {
+    let mut total = 0;
+    for path in manager.all_album_paths()? {
+        let input = fs::read_to_string(&path)?;
+        if let Some((output, count)) = rename_artist(&input, &me.old, &me.new)? {
+            let path = path.display().to_string();
+            if me.dry_run {
+                info!(
+                    "{}",
+                    fl!("repo-artist-to-rename", path = path, count = count)
+                );
+            } else {
+                fs::write(&path, output)?;
+                info!("{}", fl!("repo-artist-renamed", path = path, count = count));
+            }
+            total += count;
+        }
+    }
+
+    if total == 0 {
+        warn!("{}", fl!("repo-artist-not-found", artist = me.old.as_str()));
+    } else if me.dry_run {
+        info!(
+            "{}",
+            fl!("repo-artist-rename-dry-run-finished", count = total)
+        );
+    } else {
+        info!("{}", fl!("repo-artist-rename-finished", count = total));
+    }
+    Ok(())
+}
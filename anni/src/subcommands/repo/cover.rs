@@ -0,0 +1,381 @@
+use crate::{ball, fl, ll};
+use anni_common::fs;
+use anni_repo::prelude::*;
+use anni_repo::RepositoryManager;
+use clap::{Args, Subcommand, ValueEnum};
+use clap_handler::{handler, Handler};
+use image::{GenericImageView, ImageFormat};
+use serde::Deserialize;
+use std::io::Cursor;
+
+#[derive(Args, Handler, Debug, Clone)]
+pub struct RepoCoverAction {
+    #[clap(subcommand)]
+    subcommand: RepoCoverSubcommand,
+}
+
+#[derive(Subcommand, Handler, Debug, Clone)]
+pub enum RepoCoverSubcommand {
+    #[clap(about = ll!("repo-cover-download"))]
+    Download(RepoCoverDownloadAction),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoCoverDownloadAction {
+    #[clap(short, long)]
+    #[clap(help = ll!("repo-cover-download-force"))]
+    force: bool,
+
+    #[clap(long, default_value = "500")]
+    #[clap(help = ll!("repo-cover-download-min-resolution"))]
+    min_resolution: u32,
+
+    #[clap(long)]
+    #[clap(help = ll!("repo-cover-download-mbid"))]
+    mbid: Option<String>,
+
+    #[clap(required = true)]
+    catalogs: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseSearchResult {
+    releases: Vec<ReleaseSearchItem>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseSearchItem {
+    id: String,
+}
+
+/// MusicBrainz and Cover Art Archive, where covers are downloaded from.
+struct CoverSource {
+    client: reqwest::Client,
+    musicbrainz: String,
+    cover_art_archive: String,
+}
+
+impl CoverSource {
+    const USER_AGENT: &'static str = concat!("anni/", env!("CARGO_PKG_VERSION"));
+
+    fn new() -> anyhow::Result<Self> {
+        Self::with_endpoints("https://musicbrainz.org", "https://coverartarchive.org")
+    }
+
+    fn with_endpoints(musicbrainz: &str, cover_art_archive: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .user_agent(Self::USER_AGENT)
+                .build()?,
+            musicbrainz: musicbrainz.to_string(),
+            cover_art_archive: cover_art_archive.to_string(),
+        })
+    }
+
+    /// Search MusicBrainz for releases with the given catalog number.
+    async fn search_releases(&self, catalog: &str) -> anyhow::Result<Vec<String>> {
+        let result: ReleaseSearchResult = self
+            .client
+            .get(format!("{}/ws/2/release/", self.musicbrainz))
+            .query(&[
+                ("query", format!("catno:\"{catalog}\"")),
+                ("fmt", "json".to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(result.releases.into_iter().map(|r| r.id).collect())
+    }
+
+    /// Fetch front cover of a release from Cover Art Archive.
+    ///
+    /// Returns `None` if the release does not have a front cover.
+    async fn fetch_front_cover(&self, mbid: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let response = self
+            .client
+            .get(format!("{}/release/{mbid}/front", self.cover_art_archive))
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+    }
+}
+
+#[handler(RepoCoverDownloadAction)]
+async fn repo_cover_download(
+    me: RepoCoverDownloadAction,
+    manager: &RepositoryManager,
+) -> anyhow::Result<()> {
+    if me.mbid.is_some() && me.catalogs.len() > 1 {
+        bail!("--mbid can only be used with a single catalog");
+    }
+
+    let root = manager
+        .cover_root()
+        .ok_or_else(|| anyhow!(fl!("repo-cover-not-enabled")))?;
+    fs::create_dir_all(&root)?;
+
+    let source = CoverSource::new()?;
+    for catalog in me.catalogs.iter() {
+        let albums = manager.load_albums(catalog)?;
+        if albums.is_empty() {
+            error!(
+                "{}",
+                fl!("repo-album-not-found", catalog = catalog.as_str())
+            );
+            continue;
+        }
+
+        for album in albums {
+            if let Err(e) = me.download(&source, manager, &album).await {
+                error!("{}: {e}", album.catalog());
+            }
+        }
+    }
+    Ok(())
+}
+
+impl RepoCoverDownloadAction {
+    async fn download(
+        &self,
+        source: &CoverSource,
+        manager: &RepositoryManager,
+        album: &Album,
+    ) -> anyhow::Result<()> {
+        let catalog = album.catalog();
+        // cover_root has been checked before, so it's safe to unwrap here
+        let path = manager.cover_path(&album.album_id()).unwrap();
+        if path.exists() && !self.force {
+            info!("{}", fl!("repo-cover-exists", catalog = catalog));
+            return Ok(());
+        }
+
+        let Some(cover) = self.find_cover(source, catalog).await? else {
+            bail!(fl!("repo-cover-not-found", catalog = catalog))
+        };
+        fs::write(&path, cover)?;
+        info!(
+            "{}",
+            fl!(
+                "repo-cover-downloaded",
+                catalog = catalog,
+                path = path.display().to_string()
+            )
+        );
+        Ok(())
+    }
+
+    /// Find the first front cover of releases of `catalog` which is large enough, encoded in JPEG.
+    async fn find_cover(
+        &self,
+        source: &CoverSource,
+        catalog: &str,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let releases = match &self.mbid {
+            Some(mbid) => vec![mbid.to_string()],
+            None => source.search_releases(catalog).await?,
+        };
+
+        for release in releases {
+            debug!("Trying release {release} for {catalog}");
+            let Some(data) = source.fetch_front_cover(&release).await? else {
+                continue;
+            };
+
+            let image = match image::load_from_memory(&data) {
+                Ok(image) => image,
+                Err(e) => {
+                    warn!("Failed to decode cover of release {release}: {e}");
+                    continue;
+                }
+            };
+            let (width, height) = image.dimensions();
+            if width < self.min_resolution || height < self.min_resolution {
+                warn!(
+                    "Cover of release {release} is too small: {width}x{height}, expected at least {0}x{0}",
+                    self.min_resolution
+                );
+                continue;
+            }
+
+            if let Ok(ImageFormat::Jpeg) = image::guess_format(&data) {
+                return Ok(Some(data));
+            }
+            let mut jpeg = Cursor::new(Vec::new());
+            image.write_to(&mut jpeg, ImageFormat::Jpeg)?;
+            return Ok(Some(jpeg.into_inner()));
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoCheckCoversAction {
+    #[clap(long, default_value = "500")]
+    #[clap(help = ll!("repo-check-covers-min-resolution"))]
+    min_resolution: u32,
+
+    #[clap(value_enum)]
+    #[clap(long = "format", default_value = "jpeg")]
+    #[clap(help = ll!("repo-check-covers-formats"))]
+    formats: Vec<CoverImageFormat>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverImageFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl From<CoverImageFormat> for ImageFormat {
+    fn from(format: CoverImageFormat) -> Self {
+        match format {
+            CoverImageFormat::Jpeg => ImageFormat::Jpeg,
+            CoverImageFormat::Png => ImageFormat::Png,
+            CoverImageFormat::Webp => ImageFormat::WebP,
+        }
+    }
+}
+
+#[handler(RepoCheckCoversAction)]
+fn repo_check_covers(me: RepoCheckCoversAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    if manager.cover_root().is_none() {
+        ball!("repo-cover-not-enabled");
+    }
+
+    let manager = manager.into_owned_manager()?;
+    let mut albums: Vec<_> = manager.albums_iter().collect();
+    albums.sort_by(|a, b| a.catalog().cmp(b.catalog()));
+
+    let mut errors = 0;
+    for album in albums.iter() {
+        // cover_root has been checked before, so it's safe to unwrap here
+        let path = manager.repo.cover_path(&album.album_id()).unwrap();
+        if let Err(e) = me.check(&path) {
+            error!("[{}] {}: {e}", album.catalog(), path.display());
+            errors += 1;
+        }
+    }
+
+    if errors > 0 {
+        ball!("repo-check-covers-failed", count = errors);
+    }
+    info!("{}", fl!("repo-check-covers-passed", count = albums.len()));
+    Ok(())
+}
+
+impl RepoCheckCoversAction {
+    fn check(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        if !path.exists() {
+            bail!("cover is missing");
+        }
+
+        let data = fs::read(path)?;
+        let format = image::guess_format(&data).map_err(|_| anyhow!("unknown image format"))?;
+        if !self.formats.iter().any(|f| ImageFormat::from(*f) == format) {
+            bail!("format {format:?} is not allowed");
+        }
+
+        let image = image::load_from_memory_with_format(&data, format)
+            .map_err(|e| anyhow!("failed to decode cover: {e}"))?;
+        let (width, height) = image.dimensions();
+        if width < self.min_resolution || height < self.min_resolution {
+            bail!(
+                "cover is too small: {width}x{height}, expected at least {0}x{0}",
+                self.min_resolution
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path, Query};
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use image::{DynamicImage, RgbImage};
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+
+    fn png(size: u32) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(RgbImage::new(size, size))
+            .write_to(&mut data, ImageFormat::Png)
+            .unwrap();
+        data.into_inner()
+    }
+
+    /// Serve MusicBrainz and Cover Art Archive on a local port.
+    fn serve() -> String {
+        let app = Router::new()
+            .route(
+                "/ws/2/release/",
+                get(|Query(query): Query<HashMap<String, String>>| async move {
+                    assert_eq!(query["query"], r#"catno:"TEST-0001""#);
+                    assert_eq!(query["fmt"], "json");
+                    Json(serde_json::json!({
+                        "releases": [{ "id": "small" }, { "id": "missing" }, { "id": "large" }]
+                    }))
+                }),
+            )
+            .route(
+                "/release/:mbid/front",
+                get(|Path(mbid): Path<String>| async move {
+                    match mbid.as_str() {
+                        "small" => Ok(png(100)),
+                        "large" => Ok(png(600)),
+                        _ => Err(StatusCode::NOT_FOUND),
+                    }
+                }),
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+        endpoint
+    }
+
+    fn action(mbid: Option<&str>) -> RepoCoverDownloadAction {
+        RepoCoverDownloadAction {
+            force: false,
+            min_resolution: 500,
+            mbid: mbid.map(str::to_string),
+            catalogs: vec!["TEST-0001".to_string()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_cover() {
+        let endpoint = serve();
+        let source = CoverSource::with_endpoints(&endpoint, &endpoint).unwrap();
+
+        // small cover and release without cover are skipped
+        let cover = action(None)
+            .find_cover(&source, "TEST-0001")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(image::guess_format(&cover).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(
+            image::load_from_memory(&cover).unwrap().dimensions(),
+            (600, 600)
+        );
+
+        let cover = action(Some("small"))
+            .find_cover(&source, "TEST-0001")
+            .await
+            .unwrap();
+        assert!(cover.is_none());
+    }
+}
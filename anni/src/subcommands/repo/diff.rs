@@ -0,0 +1,79 @@
+use crate::{ball, fl, ll};
+use anni_common::fs;
+use anni_repo::prelude::*;
+use clap::Args;
+use clap_handler::handler;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoDiffAction {
+    #[clap(long)]
+    #[clap(help = ll!("repo-diff-json"))]
+    json: bool,
+
+    #[clap(long, conflicts_with = "new")]
+    #[clap(help = ll!("repo-diff-rev"))]
+    rev: Option<String>,
+
+    #[clap(help = ll!("repo-diff-old"))]
+    old: PathBuf,
+
+    #[clap(required_unless_present = "rev")]
+    #[clap(help = ll!("repo-diff-new"))]
+    new: Option<PathBuf>,
+}
+
+#[handler(RepoDiffAction)]
+fn repo_diff(me: RepoDiffAction) -> anyhow::Result<()> {
+    let (old, new) = match (&me.rev, &me.new) {
+        (Some(rev), _) => (read_at_rev(&me.old, rev)?, fs::read_to_string(&me.old)?),
+        (None, Some(new)) => (fs::read_to_string(&me.old)?, fs::read_to_string(new)?),
+        (None, None) => unreachable!(),
+    };
+    let old = Album::from_str(&old)?;
+    let new = Album::from_str(&new)?;
+
+    let changes = old.diff(&new);
+    if me.json {
+        println!("{}", serde_json::to_string_pretty(&changes)?);
+    } else if changes.is_empty() {
+        info!("{}", fl!("repo-diff-no-change"));
+    } else {
+        for change in changes.iter() {
+            let line = change.to_string();
+            let line = match change {
+                AlbumChange::DiscAdded { .. } | AlbumChange::TrackAdded { .. } => line.green(),
+                AlbumChange::DiscRemoved { .. } | AlbumChange::TrackRemoved { .. } => line.red(),
+                _ => line.yellow(),
+            };
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+/// Read content of `path` at git revision `rev`.
+fn read_at_rev(path: &Path, rev: &str) -> anyhow::Result<String> {
+    let path = path.canonicalize()?;
+    let (Some(directory), Some(file_name)) = (path.parent(), path.file_name()) else {
+        ball!("repo-diff-invalid-path", path = path.display().to_string());
+    };
+
+    // `./` makes path relative to current directory instead of repository root
+    let output = Command::new("git")
+        .current_dir(directory)
+        .arg("show")
+        .arg(format!("{rev}:./{}", file_name.to_string_lossy()))
+        .output()?;
+    if !output.status.success() {
+        ball!(
+            "repo-diff-git-failed",
+            rev = rev,
+            error = String::from_utf8_lossy(&output.stderr).trim().to_string()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
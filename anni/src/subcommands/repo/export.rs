@@ -0,0 +1,77 @@
+use crate::args::ActionFile;
+use crate::ll;
+use anni_repo::prelude::*;
+use anni_repo::{OwnedRepositoryManager, RepositoryManager};
+use clap::{Args, ValueEnum};
+use clap_handler::handler;
+use serde::Serialize;
+use std::io::{BufWriter, Write};
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoExportAction {
+    #[clap(short, long)]
+    #[clap(value_enum, default_value = "jsonl")]
+    #[clap(help = ll!("repo-export-format"))]
+    format: RepoExportFormat,
+
+    #[clap(long)]
+    #[clap(help = ll!("repo-export-include-tags"))]
+    include_tags: bool,
+
+    #[clap(short, long, default_value = "-")]
+    #[clap(help = ll!("export-to"))]
+    output: ActionFile,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum RepoExportFormat {
+    /// One album json object per line
+    Jsonl,
+}
+
+/// Album with its resolved tags, exported when `--include-tags` is set.
+#[derive(Serialize)]
+struct AlbumWithTags {
+    #[serde(flatten)]
+    album: JsonAlbum,
+    resolved_tags: Vec<String>,
+}
+
+#[handler(RepoExportAction)]
+fn repo_export(me: RepoExportAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    // albums are loaded and written one by one, so only tags are kept in memory
+    let owned;
+    let (manager, tags) = if me.include_tags {
+        owned = OwnedRepositoryManager::new_tags_only(manager)?;
+        (&owned.repo, Some(&owned))
+    } else {
+        (&manager, None)
+    };
+
+    let mut dst = BufWriter::new(me.output.to_writer()?);
+    for (path, album) in manager.iter_albums()? {
+        let mut album = match album {
+            Ok(album) => album,
+            Err(e) => bail!("Failed to load album in {}: {e}", path.display()),
+        };
+
+        match me.format {
+            RepoExportFormat::Jsonl => {
+                if let Some(tags) = tags {
+                    tags.resolve_album_tags(&mut album)?;
+                    let resolved_tags = album.tags().iter().map(|t| t.to_string()).collect();
+                    let album = AlbumWithTags {
+                        album: JsonAlbum::from(album),
+                        resolved_tags,
+                    };
+                    serde_json::to_writer(&mut dst, &album)?;
+                } else {
+                    serde_json::to_writer(&mut dst, &JsonAlbum::from(album))?;
+                }
+                writeln!(dst)?;
+            }
+        }
+    }
+    dst.flush()?;
+    Ok(())
+}
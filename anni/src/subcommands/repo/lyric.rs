@@ -0,0 +1,165 @@
+use crate::args::ActionFile;
+use crate::{ball, fl, ll};
+use anni_repo::prelude::*;
+use anni_repo::RepositoryManager;
+use clap::{Args, ValueEnum};
+use clap_handler::handler;
+use std::io::Read;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoCheckLyricsAction {
+    #[clap(required = true)]
+    #[clap(help = ll!("repo-check-lyrics-albums"))]
+    albums: Vec<String>,
+}
+
+#[handler(RepoCheckLyricsAction)]
+fn repo_check_lyrics(me: RepoCheckLyricsAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    if manager.lyric_root().is_none() {
+        ball!("repo-lyric-not-enabled");
+    }
+
+    let mut albums = Vec::new();
+    // only loaded if album id is given
+    let mut owned_manager = None;
+    for input in me.albums.iter() {
+        let found = match Uuid::from_str(input) {
+            Ok(album_id) => {
+                if owned_manager.is_none() {
+                    owned_manager =
+                        Some(RepositoryManager::new(manager.root())?.into_owned_manager()?);
+                }
+                let owned_manager = owned_manager.as_ref().unwrap();
+                owned_manager
+                    .album(&album_id)
+                    .cloned()
+                    .into_iter()
+                    .collect()
+            }
+            Err(_) => manager.load_albums(input)?,
+        };
+        if found.is_empty() {
+            ball!("repo-album-not-found", catalog = input.as_str());
+        }
+        albums.extend(found);
+    }
+
+    let mut errors = 0;
+    for album in albums.iter() {
+        for (disc_index, disc) in album.iter().enumerate() {
+            for (track_index, _) in disc.iter().enumerate() {
+                let path = manager
+                    .lyric_path(&album.album_id(), disc_index + 1, track_index + 1)
+                    .unwrap();
+                // plain text lyrics do not have timestamps
+                if !path.exists() {
+                    continue;
+                }
+
+                let text = std::fs::read_to_string(&path)?;
+                let issues = match Lyric::from_str(&text) {
+                    Ok(lyric) => lyric.check(None),
+                    Err(e) => {
+                        error!("{}: {e}", path.display());
+                        errors += 1;
+                        continue;
+                    }
+                };
+                let lines: Vec<_> = text.lines().collect();
+                for issue in issues.iter() {
+                    let content = lines.get(issue.line() - 1).copied().unwrap_or_default();
+                    error!("{}: {issue}\n    {content}", path.display());
+                }
+                errors += issues.len();
+            }
+        }
+    }
+
+    if errors > 0 {
+        ball!("repo-check-lyrics-failed", count = errors);
+    }
+    info!("{}", fl!("repo-check-lyrics-passed", count = albums.len()));
+    Ok(())
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoOrphanLyricsAction {
+    #[clap(long)]
+    #[clap(help = ll!("repo-orphan-lyrics-delete"))]
+    delete: bool,
+}
+
+#[handler(RepoOrphanLyricsAction)]
+fn repo_orphan_lyrics(
+    me: RepoOrphanLyricsAction,
+    manager: RepositoryManager,
+) -> anyhow::Result<()> {
+    if manager.lyric_root().is_none() {
+        ball!("repo-lyric-not-enabled");
+    }
+
+    let manager = manager.into_owned_manager()?;
+    let orphans = manager.orphan_lyrics()?;
+    for path in orphans.iter() {
+        println!("{}", path.display());
+        if me.delete {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    if me.delete {
+        info!(
+            "{}",
+            fl!("repo-orphan-lyrics-deleted", count = orphans.len())
+        );
+    } else {
+        info!("{}", fl!("repo-orphan-lyrics-found", count = orphans.len()));
+    }
+    Ok(())
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoLyricAction {
+    #[clap(value_enum)]
+    #[clap(long, default_value = "text")]
+    #[clap(help = ll!("repo-lyric-to"))]
+    to: RepoLyricFormat,
+
+    #[clap(long, default_value_t = 0, allow_hyphen_values = true)]
+    #[clap(help = ll!("repo-lyric-offset"))]
+    offset: i64,
+
+    #[clap(help = ll!("repo-lyric-input"))]
+    input: ActionFile,
+
+    #[clap(short, long, default_value = "-")]
+    #[clap(help = ll!("export-to"))]
+    output: ActionFile,
+}
+
+#[derive(ValueEnum, Debug, Clone)]
+pub enum RepoLyricFormat {
+    /// Plain text without timestamps
+    Text,
+    /// LRC with offset applied to timestamps
+    Lrc,
+}
+
+#[handler(RepoLyricAction)]
+fn repo_lyric(me: RepoLyricAction) -> anyhow::Result<()> {
+    let mut input = String::new();
+    me.input.to_reader()?.read_to_string(&mut input)?;
+
+    let converted = match me.to {
+        RepoLyricFormat::Text => lyric_to_plain_text(&input),
+        RepoLyricFormat::Lrc => shift_lyric(&input, me.offset),
+    };
+    for &line in converted.malformed_lines.iter() {
+        warn!("{}", fl!("repo-lyric-malformed", line = line));
+    }
+
+    write!(me.output.to_writer()?, "{}", converted.text)?;
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use crate::ll;
+use crate::{ball, fl};
+use anni_common::fs;
+use anni_repo::migrate::{
+    compare_editions, migrate_album, migrations_between, set_repo_edition, LATEST_EDITION,
+};
+use anni_repo::RepositoryManager;
+use clap::Args;
+use clap_handler::handler;
+use colored::Colorize;
+use similar::{ChangeTag, TextDiff};
+use std::cmp::Ordering;
+use std::path::Path;
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoMigrateEditionAction {
+    #[clap(long)]
+    #[clap(help = ll!("repo-migrate-edition-to"))]
+    to: String,
+
+    #[clap(long)]
+    #[clap(help = ll!("repo-migrate-edition-dry-run"))]
+    dry_run: bool,
+}
+
+#[handler(RepoMigrateEditionAction)]
+fn repo_migrate_edition(
+    me: RepoMigrateEditionAction,
+    manager: &RepositoryManager,
+) -> anyhow::Result<()> {
+    if compare_editions(&me.to, LATEST_EDITION) == Ordering::Greater {
+        ball!(
+            "repo-migrate-edition-unknown",
+            edition = me.to.as_str(),
+            latest = LATEST_EDITION
+        );
+    }
+
+    match compare_editions(manager.edition(), &me.to) {
+        Ordering::Less => {}
+        Ordering::Equal => {
+            info!(
+                "{}",
+                fl!("repo-migrate-edition-up-to-date", edition = me.to.as_str())
+            );
+            return Ok(());
+        }
+        Ordering::Greater => ball!(
+            "repo-migrate-edition-downgrade",
+            from = manager.edition(),
+            to = me.to.as_str()
+        ),
+    }
+
+    let steps = migrations_between(manager.edition(), &me.to);
+    for step in steps.iter() {
+        debug!("Migration step: {} ({})", step.name, step.edition);
+    }
+
+    for path in manager.all_album_paths()? {
+        let input = fs::read_to_string(&path)?;
+        if let Some(output) = migrate_album(&input, &steps)? {
+            me.write(&path, &input, &output)?;
+        }
+    }
+
+    let repo_path = manager.root().join("repo.toml");
+    let input = fs::read_to_string(&repo_path)?;
+    let output = set_repo_edition(&input, &me.to)?;
+    if input != output {
+        me.write(&repo_path, &input, &output)?;
+    }
+    Ok(())
+}
+
+impl RepoMigrateEditionAction {
+    fn write(&self, path: &Path, input: &str, output: &str) -> anyhow::Result<()> {
+        if self.dry_run {
+            println!("{}", format!("--- {}", path.display()).bold());
+            print_diff(input, output);
+        } else {
+            fs::write(path, output)?;
+            info!(
+                "{}",
+                fl!(
+                    "repo-migrate-edition-migrated",
+                    path = path.display().to_string()
+                )
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Print line based diff of `before` and `after`.
+fn print_diff(before: &str, after: &str) {
+    for change in TextDiff::from_lines(before, after).iter_all_changes() {
+        let line = change.to_string_lossy();
+        let line = line.trim_end_matches('\n');
+        match change.tag() {
+            ChangeTag::Equal => {}
+            ChangeTag::Delete => println!("{}", format!("-{line}").red()),
+            ChangeTag::Insert => println!("{}", format!("+{line}").green()),
+        }
+    }
+}
@@ -1,15 +1,37 @@
 mod add;
+mod artist;
+#[cfg(feature = "cover-download")]
+mod cover;
+mod diff;
+mod export;
 mod get;
 mod lint;
+mod lyric;
+mod migrate;
 mod print;
+mod search;
+mod series;
+mod tag;
+mod validate;
 mod watch;
 
 use crate::args::ActionFile;
 use crate::{ball, fl, ll};
 use add::*;
 use anni_workspace::AnniWorkspace;
+use artist::*;
+#[cfg(feature = "cover-download")]
+use cover::*;
+use diff::*;
+use export::*;
 use lint::*;
+use lyric::*;
+use migrate::*;
 use print::*;
+use search::*;
+use series::*;
+use tag::*;
+use validate::*;
 use watch::*;
 
 use anni_repo::library::{file_name, AlbumFolderInfo};
@@ -21,6 +43,7 @@ use get::RepoGetAction;
 use std::io::Read;
 use std::path::PathBuf;
 use std::str::FromStr;
+use uuid::Uuid;
 
 #[derive(Args, Debug, Clone, Handler)]
 #[clap(about = ll!("repo"))]
@@ -66,8 +89,40 @@ pub enum RepoAction {
     Edit(RepoEditAction),
     #[clap(about = ll!("repo-lint"))]
     Lint(RepoLintAction),
+    #[clap(about = ll!("repo-validate"))]
+    Validate(RepoValidateAction),
+    #[clap(about = ll!("repo-check-lyrics"))]
+    CheckLyrics(RepoCheckLyricsAction),
+    #[clap(about = ll!("repo-lyric"))]
+    Lyric(RepoLyricAction),
+    #[clap(about = ll!("repo-orphan-lyrics"))]
+    OrphanLyrics(RepoOrphanLyricsAction),
     #[clap(about = ll!("repo-print"))]
     Print(RepoPrintAction),
+    #[clap(about = ll!("repo-export"))]
+    Export(RepoExportAction),
+    #[clap(about = ll!("repo-where"))]
+    Where(RepoWhereAction),
+    #[clap(about = ll!("repo-search"))]
+    Search(RepoSearchAction),
+    #[clap(about = ll!("repo-duplicates"))]
+    Duplicates(RepoDuplicatesAction),
+    #[clap(about = ll!("repo-diff"))]
+    Diff(RepoDiffAction),
+    #[cfg(feature = "cover-download")]
+    #[clap(about = ll!("repo-cover"))]
+    Cover(RepoCoverAction),
+    #[cfg(feature = "cover-download")]
+    #[clap(about = ll!("repo-check-covers"))]
+    CheckCovers(RepoCheckCoversAction),
+    #[clap(about = ll!("repo-series"))]
+    Series(RepoSeriesAction),
+    #[clap(about = ll!("repo-artist"))]
+    Artist(RepoArtistAction),
+    #[clap(about = ll!("repo-tag"))]
+    Tag(RepoTagAction),
+    #[clap(about = ll!("repo-migrate-edition"))]
+    MigrateEdition(RepoMigrateEditionAction),
     #[clap(name = "db")]
     #[clap(about = ll!("repo-db"))]
     Database(RepoDatabaseAction),
@@ -157,6 +212,58 @@ fn repo_edit(me: &RepoEditAction, manager: &RepositoryManager) -> anyhow::Result
     Ok(())
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct RepoWhereAction {
+    #[clap(help = ll!("repo-where-input"))]
+    input: String,
+}
+
+#[handler(RepoWhereAction)]
+fn repo_where(me: RepoWhereAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    let paths = if let Ok(album_id) = Uuid::parse_str(&me.input) {
+        let manager = manager.into_owned_manager()?;
+        let root = manager.repo.root();
+        manager
+            .album_path(&album_id)
+            .map(|path| root.join(path))
+            .into_iter()
+            .collect()
+    } else {
+        manager.album_paths(&me.input)?
+    };
+
+    if paths.is_empty() {
+        ball!("repo-album-not-found", catalog = me.input);
+    }
+    // multiple paths are printed if catalog is ambiguous
+    for path in paths {
+        println!("{}", path.canonicalize().unwrap_or(path).display());
+    }
+    Ok(())
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoDuplicatesAction;
+
+#[handler(RepoDuplicatesAction)]
+fn repo_duplicates(_: RepoDuplicatesAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    let manager = manager.into_owned_manager()?;
+    let duplicates = manager.duplicate_catalogs();
+    for (catalog, album_ids) in duplicates.iter() {
+        println!("{catalog}");
+        for album_id in album_ids {
+            let path = manager.album_path(album_id).unwrap();
+            println!("  {album_id} {}", path.display());
+        }
+    }
+
+    if !duplicates.is_empty() {
+        ball!("repo-duplicates-found", count = duplicates.len());
+    }
+    info!("{}", fl!("repo-duplicates-not-found"));
+    Ok(())
+}
+
 fn is_album_folder(input: &str) -> bool {
     let bytes = input.as_bytes();
     let second_last_byte = bytes[bytes.len() - 2];
@@ -167,6 +274,10 @@ fn is_album_folder(input: &str) -> bool {
 // Repo database
 #[derive(Args, Debug, Clone)]
 pub struct RepoDatabaseAction {
+    /// Analyze and vacuum the database after creation, which is suitable for distribution.
+    #[clap(long)]
+    optimized: bool,
+
     #[clap(help = ll!("export-to"))]
     output: PathBuf,
 }
@@ -178,7 +289,12 @@ fn repo_database_action(me: RepoDatabaseAction, manager: RepositoryManager) -> a
     }
 
     let manager = manager.into_owned_manager()?;
-    manager.to_database(&me.output.join("repo.db"))?;
+    let path = me.output.join("repo.db");
+    if me.optimized {
+        manager.to_optimized_database(&path)?;
+    } else {
+        manager.to_database(&path)?;
+    }
 
     Ok(())
 }
@@ -1,6 +1,7 @@
 use crate::{args::ActionFile, ll};
+use anni_common::fs::sanitize_file_name;
 use anni_repo::{
-    prelude::{JsonAlbum, TagRef},
+    prelude::{Album, JsonAlbum, TagRef},
     OwnedRepositoryManager, RepositoryManager,
 };
 use clap::{crate_version, ArgAction, Args, ValueEnum};
@@ -19,6 +20,10 @@ pub struct RepoPrintAction {
     #[clap(help = ll!("repo-print-clean"))]
     add_generated_by: bool,
 
+    #[clap(long)]
+    #[clap(help = ll!("repo-print-resolve"))]
+    resolve: bool,
+
     #[clap(help = ll!("repo-print-input"))]
     input: String,
 
@@ -41,8 +46,15 @@ fn repo_print(me: RepoPrintAction, manager: RepositoryManager) -> anyhow::Result
                 .map_or(1, |x| x.parse::<u32>().expect("Invalid disc id"));
             let disc_id = if disc_id > 0 { disc_id - 1 } else { disc_id };
 
-            // FIXME: pick the correct album
-            let mut album = manager.load_albums(catalog)?;
+            let mut album = me.load_albums(&manager, catalog)?;
+            if album.len() > 1 {
+                // use `repo where` or album id to pick another one
+                log::warn!(
+                    "{} albums share catalog {catalog}, printing {}",
+                    album.len(),
+                    album.last().unwrap().album_id()
+                );
+            }
             let album = album.pop().unwrap();
             match me.print_type {
                 RepoPrintType::Title => writeln!(dst, "{}", album.full_title())?,
@@ -78,11 +90,11 @@ FILE "{filename}" WAVE
     TITLE "{title}"
     PERFORMER "{artist}"
     INDEX 01 00:00:00"#,
-                                filename = format!(
+                                filename = sanitize_file_name(&format!(
                                     "{:02}. {}.flac",
                                     track_id,
-                                    track.title().replace("/", "／")
-                                ),
+                                    track.title()
+                                )),
                                 title = track.title(),
                                 artist = track.artist(),
                             )?;
@@ -116,7 +128,7 @@ FILE "{filename}" WAVE
                 }
             } else {
                 // me.input -> catalog
-                let album = manager.load_albums(&me.input)?;
+                let album = me.load_albums(&manager, &me.input)?;
                 match me.print_type {
                     RepoPrintType::Toml => toml::to_string_pretty(&album[0])?,
                     RepoPrintType::Json => {
@@ -139,7 +151,7 @@ FILE "{filename}" WAVE
             }
 
             let mut tree = TreeBuilder::new(tag_to_string(&tag, &manager));
-            build_tree(&manager, &tag, &mut tree);
+            build_tree(&manager, &tag, &mut tree, &mut vec![tag.to_string()]);
             ptree::print_tree(&tree.build())?;
 
             fn tag_to_string(tag: &TagRef, manager: &OwnedRepositoryManager) -> String {
@@ -150,11 +162,26 @@ FILE "{filename}" WAVE
                 format!("{tag_type} {}", tag_full.name())
             }
 
-            fn build_tree(manager: &OwnedRepositoryManager, tag: &TagRef, tree: &mut TreeBuilder) {
+            /// `ancestors` are tags from the root to `tag`, which are not expanded again in case of tag cycles.
+            fn build_tree(
+                manager: &OwnedRepositoryManager,
+                tag: &TagRef,
+                tree: &mut TreeBuilder,
+                ancestors: &mut Vec<String>,
+            ) {
                 let child_tags = manager.child_tags(&tag);
                 for tag in child_tags {
+                    let name = tag.to_string();
+                    if ancestors.contains(&name) {
+                        log::warn!("Tag cycle detected: {} -> {name}", ancestors.join(" -> "));
+                        tree.add_empty_child(format!("{} (cycle)", tag_to_string(tag, manager)));
+                        continue;
+                    }
+
                     tree.begin_child(tag_to_string(tag, manager));
-                    build_tree(manager, tag, tree);
+                    ancestors.push(name);
+                    build_tree(manager, tag, tree, ancestors);
+                    ancestors.pop();
                     tree.end_child();
                 }
 
@@ -171,6 +198,21 @@ FILE "{filename}" WAVE
     Ok(())
 }
 
+impl RepoPrintAction {
+    fn load_albums(
+        &self,
+        manager: &RepositoryManager,
+        catalog: &str,
+    ) -> anyhow::Result<Vec<Album>> {
+        let albums = if self.resolve {
+            manager.resolve_albums(catalog)?
+        } else {
+            manager.load_albums(catalog)?
+        };
+        Ok(albums)
+    }
+}
+
 #[derive(ValueEnum, Debug, PartialEq, Clone)]
 pub enum RepoPrintType {
     Title,
@@ -0,0 +1,78 @@
+use crate::{ball, ll};
+use anni_repo::prelude::*;
+use anni_repo::RepositoryManager;
+use clap::{Args, ValueEnum};
+use clap_handler::handler;
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoSearchAction {
+    #[clap(long)]
+    #[clap(help = ll!("repo-search-artist"))]
+    artist: Option<String>,
+
+    #[clap(long)]
+    #[clap(help = ll!("repo-search-title"))]
+    title: Option<String>,
+
+    #[clap(long)]
+    #[clap(help = ll!("repo-search-tag"))]
+    tag: Option<String>,
+
+    #[clap(short, long)]
+    #[clap(value_enum, default_value = "text")]
+    #[clap(help = ll!("repo-search-format"))]
+    format: RepoSearchFormat,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum RepoSearchFormat {
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+struct SearchResult<'a> {
+    album_id: Uuid,
+    title: String,
+    catalog: &'a str,
+}
+
+#[handler(RepoSearchAction)]
+fn repo_search(me: RepoSearchAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    let manager = manager.into_owned_manager()?;
+
+    let tag = me.tag.as_deref().map(TagRef::from_cow_str);
+    if let Some(tag) = &tag {
+        if manager.tag(tag).is_none() {
+            ball!("repo-tag-not-found", tag = tag.to_string());
+        }
+    }
+
+    let albums = manager.search_albums(me.artist.as_deref(), me.title.as_deref(), tag.as_ref());
+    match me.format {
+        RepoSearchFormat::Text => {
+            for album in albums {
+                println!(
+                    "{} {} {}",
+                    album.album_id(),
+                    album.catalog(),
+                    album.full_title()
+                );
+            }
+        }
+        RepoSearchFormat::Json => {
+            let result: Vec<_> = albums
+                .into_iter()
+                .map(|album| SearchResult {
+                    album_id: album.album_id(),
+                    title: album.full_title().to_string(),
+                    catalog: album.catalog(),
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use crate::ll;
+use anni_repo::RepositoryManager;
+use clap::{Args, Subcommand};
+use clap_handler::{handler, Handler};
+
+#[derive(Args, Handler, Debug, Clone)]
+pub struct RepoSeriesAction {
+    #[clap(subcommand)]
+    subcommand: RepoSeriesSubcommand,
+}
+
+#[derive(Subcommand, Handler, Debug, Clone)]
+pub enum RepoSeriesSubcommand {
+    #[clap(about = ll!("repo-series-list"))]
+    List(RepoSeriesListAction),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoSeriesListAction {
+    #[clap(help = ll!("repo-series-list-series"))]
+    series: Option<String>,
+}
+
+#[handler(RepoSeriesListAction)]
+fn repo_series_list(me: RepoSeriesListAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    let manager = manager.into_owned_manager()?;
+    match me.series {
+        Some(series) => {
+            for album in manager.albums_in_series(&series) {
+                println!(
+                    "[{}] {} ({})",
+                    album.catalog(),
+                    album.full_title(),
+                    album.album_id()
+                );
+            }
+        }
+        None => {
+            let mut series: Vec<_> = manager.series().into_iter().collect();
+            series.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (name, count) in series {
+                println!("{name} ({count})");
+            }
+        }
+    }
+    Ok(())
+}
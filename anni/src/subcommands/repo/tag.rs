@@ -0,0 +1,112 @@
+use crate::{ball, fl, ll};
+use anni_common::fs;
+use anni_repo::edit::{add_album_tag, remove_album_tag};
+use anni_repo::prelude::*;
+use anni_repo::{OwnedRepositoryManager, RepositoryManager};
+use clap::{Args, Subcommand};
+use clap_handler::{handler, Handler};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+#[derive(Args, Handler, Debug, Clone)]
+pub struct RepoTagAction {
+    #[clap(subcommand)]
+    subcommand: RepoTagSubcommand,
+}
+
+#[derive(Subcommand, Handler, Debug, Clone)]
+pub enum RepoTagSubcommand {
+    #[clap(about = ll!("repo-tag-add"))]
+    Add(RepoTagAddAction),
+    #[clap(about = ll!("repo-tag-remove"))]
+    Remove(RepoTagRemoveAction),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoTagAddAction {
+    #[clap(help = ll!("repo-tag-album"))]
+    album: String,
+
+    #[clap(help = ll!("repo-tag-add-tag"))]
+    tag: String,
+}
+
+#[handler(RepoTagAddAction)]
+fn repo_tag_add(me: RepoTagAddAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    let manager = manager.into_owned_manager()?;
+    let tag = TagRef::from_cow_str(me.tag.as_str());
+    let candidates = manager
+        .tags_iter()
+        .filter(|t| {
+            t.name() == tag.name()
+                && (*tag.tag_type() == TagType::Unknown || t.tag_type() == tag.tag_type())
+        })
+        .count();
+    match candidates {
+        0 => ball!("repo-tag-not-found", tag = me.tag.as_str()),
+        1 => {}
+        _ => ball!("repo-tag-ambiguous", tag = me.tag.as_str()),
+    }
+
+    let path = album_path(&manager, &me.album)?;
+    let input = fs::read_to_string(&path)?;
+    match add_album_tag(&input, &tag)? {
+        Some(output) => {
+            fs::write(&path, output)?;
+            info!(
+                "{}",
+                fl!("repo-tag-modified", path = path.display().to_string())
+            );
+        }
+        None => warn!("{}", fl!("repo-tag-already-added", tag = me.tag.as_str())),
+    }
+    Ok(())
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoTagRemoveAction {
+    #[clap(help = ll!("repo-tag-album"))]
+    album: String,
+
+    #[clap(help = ll!("repo-tag-remove-tag"))]
+    tag: String,
+}
+
+#[handler(RepoTagRemoveAction)]
+fn repo_tag_remove(me: RepoTagRemoveAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    let manager = manager.into_owned_manager()?;
+    let tag = TagRef::from_cow_str(me.tag.as_str());
+
+    let path = album_path(&manager, &me.album)?;
+    let input = fs::read_to_string(&path)?;
+    match remove_album_tag(&input, &tag)? {
+        Some(output) => {
+            fs::write(&path, output)?;
+            info!(
+                "{}",
+                fl!("repo-tag-modified", path = path.display().to_string())
+            );
+        }
+        None => warn!("{}", fl!("repo-tag-not-in-album", tag = me.tag.as_str())),
+    }
+    Ok(())
+}
+
+/// Find path of album toml by album id or catalog.
+fn album_path(manager: &OwnedRepositoryManager, album: &str) -> anyhow::Result<PathBuf> {
+    let mut paths: Vec<PathBuf> = if let Ok(album_id) = Uuid::parse_str(album) {
+        manager
+            .album_path(&album_id)
+            .map(|path| manager.repo.root().join(path))
+            .into_iter()
+            .collect()
+    } else {
+        manager.repo.album_paths(album)?
+    };
+
+    match paths.len() {
+        0 => ball!("repo-album-not-found", catalog = album),
+        1 => Ok(paths.remove(0)),
+        _ => ball!("repo-tag-album-ambiguous", album = album),
+    }
+}
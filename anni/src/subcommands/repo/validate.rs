@@ -0,0 +1,118 @@
+use crate::{ball, fl, ll};
+use anni_repo::prelude::*;
+use anni_repo::validate::{ValidateDiagnostic, ValidateSeverity};
+use anni_repo::RepositoryManager;
+use chrono::{Datelike, Local};
+use clap::{Args, ValueEnum};
+use clap_handler::handler;
+use colored::Colorize;
+use serde::Serialize;
+use std::path::Path;
+use uuid::Uuid;
+
+#[derive(Args, Debug, Clone)]
+pub struct RepoValidateAction {
+    #[clap(short, long)]
+    #[clap(value_enum, default_value = "text")]
+    #[clap(help = ll!("repo-validate-format"))]
+    format: RepoValidateFormat,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum RepoValidateFormat {
+    Text,
+    Json,
+}
+
+/// Problems of an album, printed as a JSON object.
+#[derive(Serialize)]
+struct AlbumReport<'a> {
+    path: &'a Path,
+    album_id: Uuid,
+    catalog: &'a str,
+    diagnostics: &'a [ValidateDiagnostic],
+}
+
+/// Output of `--format json`.
+#[derive(Serialize)]
+struct ValidateReport<'a> {
+    albums: Vec<AlbumReport<'a>>,
+    /// Tag cycles like `["group:A", "group:B", "group:A"]`
+    tag_cycles: Vec<Vec<String>>,
+}
+
+#[handler(RepoValidateAction)]
+fn repo_validate(me: RepoValidateAction, manager: RepositoryManager) -> anyhow::Result<()> {
+    let manager = manager.into_owned_manager()?;
+    let now = Local::now();
+    let today = AnniDate::new(now.year() as u16, now.month() as u8, now.day() as u8);
+
+    let result = manager.validate(&today);
+    let tag_cycles: Vec<Vec<String>> = match manager.validate_tag_graph() {
+        Ok(()) => Vec::new(),
+        Err(cycles) => cycles
+            .iter()
+            .map(|cycle| cycle.iter().map(|tag| tag.to_string()).collect())
+            .collect(),
+    };
+    let errors = result
+        .iter()
+        .flat_map(|(_, _, diagnostics)| diagnostics.iter())
+        .filter(|d| d.is_error())
+        .count()
+        + tag_cycles.len();
+
+    match me.format {
+        RepoValidateFormat::Json => {
+            let report = ValidateReport {
+                albums: result
+                    .iter()
+                    .map(|(path, album, diagnostics)| AlbumReport {
+                        path,
+                        album_id: album.album_id(),
+                        catalog: album.catalog(),
+                        diagnostics,
+                    })
+                    .collect(),
+                tag_cycles,
+            };
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        RepoValidateFormat::Text => {
+            for (path, album, diagnostics) in result.iter() {
+                println!(
+                    "{} ({}, {})",
+                    path.display().to_string().bold(),
+                    album.catalog(),
+                    album.album_id()
+                );
+                for diagnostic in diagnostics.iter() {
+                    let location = match (diagnostic.disc_id, diagnostic.track_id) {
+                        (Some(disc_id), Some(track_id)) => {
+                            format!("disc {disc_id} track {track_id}: ")
+                        }
+                        (Some(disc_id), None) => format!("disc {disc_id}: "),
+                        _ => String::new(),
+                    };
+                    let severity = match diagnostic.severity {
+                        ValidateSeverity::Error => "error".red(),
+                        ValidateSeverity::Warning => "warning".yellow(),
+                    };
+                    println!("  {severity}: {location}{}", diagnostic.message);
+                }
+            }
+            for cycle in tag_cycles.iter() {
+                println!("{}: Tag cycle {}", "error".red(), cycle.join(" -> "));
+            }
+        }
+    }
+
+    if errors > 0 {
+        ball!("repo-validate-failed", count = errors);
+    }
+    info!(
+        "{}",
+        fl!("repo-validate-passed", count = manager.albums().len())
+    );
+    Ok(())
+}
@@ -1,23 +1,30 @@
+use std::collections::HashMap;
 use std::io::Read;
+use std::num::NonZeroU8;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use clap::{ArgAction, Args, ValueEnum};
 
 use anni_common::fs;
 
 use crate::{ball, ll};
-use anni_flac::blocks::{BlockPicture, PictureType, UserComment, UserCommentExt};
+use anni_flac::blocks::{BlockCueSheet, BlockPicture, PictureType, UserComment, UserCommentExt};
 use anni_flac::{FlacHeader, MetadataBlock, MetadataBlockData};
 use anni_split::codec::wav::{WavDecoder, WavEncoder};
 use anni_split::codec::{
-    ApeCommandDecoder, Decoder, Encoder, FlacCommandDecoder, FlacCommandEncoder, TakCommandDecoder,
-    TtaCommandDecoder,
+    ApeCommandDecoder, Decoder, Encoder, FlacCommandDecoder, FlacCommandEncoder, M4aCommandDecoder,
+    TakCommandDecoder, TtaCommandDecoder,
 };
 use anni_split::error::SplitError;
+use anni_split::split::{Breakpoint, SampleBreakpoint};
 use anni_split::{cue_breakpoints, split};
+use anni_workspace::AnniWorkspace;
 use clap_handler::handler;
 use cuna::Cuna;
+use serde::Deserialize;
 use std::fmt::{Display, Formatter};
+use uuid::Uuid;
 
 #[derive(Args, Debug, Clone)]
 #[clap(about = ll!("split"))]
@@ -40,6 +47,10 @@ pub struct SplitSubcommand {
     #[clap(help = ll!("split-no-import-cover"))]
     import_cover: bool,
 
+    #[clap(long, conflicts_with = "import_cover")]
+    #[clap(help = ll!("split-cover"))]
+    cover: Option<PathBuf>,
+
     #[clap(long = "keep", action = ArgAction::SetFalse, default_value_t = true)]
     remove_after_success: bool,
 
@@ -49,15 +60,145 @@ pub struct SplitSubcommand {
     #[clap(long = "dry-run")]
     dry_run: bool,
 
+    #[clap(long = "output-dir")]
+    #[clap(help = ll!("split-output-dir"))]
+    output_dir: Option<PathBuf>,
+
+    #[clap(value_enum)]
+    #[clap(long, default_value = "flat", requires = "output_dir")]
+    #[clap(help = ll!("split-layout"))]
+    layout: SplitLayout,
+
+    #[clap(long = "album-id", requires = "output_dir")]
+    #[clap(help = ll!("split-album-id"))]
+    album_id: Option<Uuid>,
+
+    #[clap(long = "disc-id", default_value = "1", requires = "output_dir")]
+    #[clap(help = ll!("split-disc-id"))]
+    disc_id: NonZeroU8,
+
+    #[clap(long)]
+    #[clap(help = ll!("split-force"))]
+    force: bool,
+
+    #[clap(long = "from-chapters")]
+    #[clap(help = ll!("split-from-chapters"))]
+    from_chapters: bool,
+
     directories: Vec<PathBuf>,
 }
 
+/// Layout of split tracks in `--output-dir`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum SplitLayout {
+    /// `{output_dir}/{index:02}. {title}.{ext}`
+    Flat,
+    /// `{output_dir}/{strict album path}/{disc_id}/{track_id}.{ext}`, the same as objects store
+    Strict,
+}
+
+/// Where track boundaries are read from.
+enum CueSource {
+    /// Sidecar cue file
+    File(PathBuf),
+    /// Cue embedded in audio file as tag
+    Embedded(String),
+    /// Cue generated from chapters embedded in audio file, with sample offsets of tracks
+    Chapters { cue: String, samples: Vec<u64> },
+}
+
+impl CueSource {
+    fn path(&self) -> Option<&Path> {
+        match self {
+            CueSource::File(path) => Some(path),
+            CueSource::Embedded(_) | CueSource::Chapters { .. } => None,
+        }
+    }
+
+    fn read(&self) -> anyhow::Result<String> {
+        match self {
+            CueSource::File(path) => Ok(fs::read_to_string(path)?),
+            CueSource::Embedded(cue) | CueSource::Chapters { cue, .. } => Ok(cue.clone()),
+        }
+    }
+}
+
+/// Where split tracks of a directory are written to.
+enum SplitTarget {
+    /// Tracks are named by index and title in the directory
+    Flat(PathBuf),
+    /// Tracks are named by track id in disc directory of an album
+    Strict { album_root: PathBuf, disc_id: u8 },
+}
+
+impl SplitTarget {
+    fn track_root(&self) -> PathBuf {
+        match self {
+            SplitTarget::Flat(root) => root.clone(),
+            SplitTarget::Strict {
+                album_root,
+                disc_id,
+            } => album_root.join(disc_id.to_string()),
+        }
+    }
+}
+
 impl SplitSubcommand {
     fn need_remove_after_success(&self) -> bool {
         !self.dry_run && self.remove_after_success
     }
 
-    fn split<P>(&self, audio_path: P, cue_path: P, cover: Option<P>) -> anyhow::Result<()>
+    /// Decide where tracks split from `directory` are written to, and prepare the output directory.
+    ///
+    /// Without `--output-dir`, tracks are written next to `cue_path`, which is the audio file
+    /// if chapters embedded in it are used.
+    fn target(&self, directory: &Path, cue_path: &Path) -> anyhow::Result<SplitTarget> {
+        let Some(output_dir) = &self.output_dir else {
+            let root = cue_path.parent().map(Path::to_path_buf).unwrap_or_default();
+            return Ok(SplitTarget::Flat(root));
+        };
+
+        let target = match self.layout {
+            SplitLayout::Flat => {
+                // keep tracks of different directories apart
+                let root = if self.directories.len() > 1 {
+                    let name = fs::canonicalize(directory)?;
+                    let name = name.file_name().ok_or_else(|| {
+                        anyhow!("Invalid directory name: {}", directory.display())
+                    })?;
+                    output_dir.join(name)
+                } else {
+                    output_dir.clone()
+                };
+                prepare_output_dir(&root, self.force, self.dry_run)?;
+                SplitTarget::Flat(root)
+            }
+            SplitLayout::Strict => {
+                if self.album_id.is_some() && self.directories.len() > 1 {
+                    ball!("split-album-id-multiple-directories");
+                }
+                let album_id = self.album_id.unwrap_or_else(Uuid::new_v4);
+                let album_root = AnniWorkspace::strict_album_path(output_dir.clone(), &album_id, 2);
+                info!(target: "split", "Album {album_id} => {}", album_root.display());
+
+                let target = SplitTarget::Strict {
+                    album_root,
+                    disc_id: self.disc_id.get(),
+                };
+                prepare_output_dir(&target.track_root(), self.force, self.dry_run)?;
+                target
+            }
+        };
+        Ok(target)
+    }
+
+    fn split<P>(
+        &self,
+        audio_path: P,
+        cue: CueSource,
+        cover: Option<P>,
+        target: SplitTarget,
+    ) -> anyhow::Result<()>
     where
         P: AsRef<Path>,
     {
@@ -66,22 +207,40 @@ impl SplitSubcommand {
         let input = self
             .input_format
             .get_decoder(audio_path.as_ref().to_path_buf());
-        let (breakpoints, cue) = cue_breakpoints(fs::read_to_string(cue_path.as_ref())?)?;
-        let tracks = cue_tracks(cue);
+        let cue_content = cue.read()?;
+        let date = cue_rem(&cue_content, "DATE");
+        let (breakpoints, cuna) = cue_breakpoints(&cue_content)?;
+        // cue time is in CD frames, so chapters are split at samples to keep accuracy
+        let breakpoints: Vec<Box<dyn Breakpoint>> = match &cue {
+            CueSource::Chapters { samples, .. } => samples
+                .iter()
+                .filter(|&&sample| sample != 0)
+                .map(|&sample| Box::new(SampleBreakpoint(sample)) as Box<dyn Breakpoint>)
+                .collect(),
+            _ => breakpoints
+                .into_iter()
+                .map(|b| Box::new(b) as Box<dyn Breakpoint>)
+                .collect(),
+        };
+        let tracks = cue_tracks(cuna, date.as_deref());
 
         // generate file names & check whether file exists before split
+        let track_root = target.track_root();
         let files = tracks
             .iter()
-            .map(|track| {
-                let filename =
-                    format!("{:02}. {}.{}", track.index, track.title, self.output_format)
-                        .replace("/", "／");
-                // file output path is relative to cue path
-                let output = cue_path.as_ref().with_file_name(&filename);
+            .enumerate()
+            .map(|(i, track)| {
+                let filename = match target {
+                    SplitTarget::Flat(_) => fs::sanitize_file_name(&format!(
+                        "{:02}. {}.{}",
+                        track.index, track.title, self.output_format
+                    ))
+                    .into_owned(),
+                    SplitTarget::Strict { .. } => format!("{}.{}", i + 1, self.output_format),
+                };
+                let output = track_root.join(&filename);
                 // check if file exists
-                if output.exists()
-                /* TODO: && !override_file */
-                {
+                if output.exists() && !self.force {
                     ball!("split-output-file-exist", filename = filename);
                 } else {
                     // save file path
@@ -124,17 +283,241 @@ impl SplitSubcommand {
                 }
             }
 
+            // objects store keeps covers of album and disc as files
+            if let (SplitTarget::Strict { album_root, .. }, Some(cover)) = (&target, &cover) {
+                fs::copy(cover, album_root.join("cover.jpg"))?;
+                fs::copy(cover, track_root.join("cover.jpg"))?;
+            }
+
             // Option to remove full track after successful split
             if self.need_remove_after_success() {
                 debug!(target: "split", "Removing audio file: {}", audio_path.as_ref().display());
                 fs::remove_file(audio_path, self.trashcan)?;
-                debug!(target: "split", "Removing cue file: {}", cue_path.as_ref().display());
-                fs::remove_file(cue_path, self.trashcan)?;
+                if let Some(cue_path) = cue.path() {
+                    debug!(target: "split", "Removing cue file: {}", cue_path.display());
+                    fs::remove_file(cue_path, self.trashcan)?;
+                }
             }
         }
 
         Ok(())
     }
+
+    /// Read track boundaries from chapters embedded in `audio`.
+    ///
+    /// For FLAC files, `CUESHEET` tag or CUESHEET block is used.
+    /// For M4A files, chapters are read by `ffprobe`.
+    fn embedded_cue(&self, audio: &Path) -> anyhow::Result<Option<CueSource>> {
+        match self.input_format {
+            SplitFormat::Flac => flac_chapters(audio),
+            SplitFormat::M4a => m4a_chapters(audio),
+            _ => {
+                warn!(target: "split", "Embedded chapters are only supported for FLAC and M4A input");
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn flac_chapters(audio: &Path) -> anyhow::Result<Option<CueSource>> {
+    let header = FlacHeader::from_file(audio)?;
+    let comments = header.comments().map(|c| c.to_map()).unwrap_or_default();
+    // cue in tag keeps titles of tracks, which is preferred
+    if let Some(cue) = comments.get("CUESHEET") {
+        return Ok(Some(CueSource::Embedded(cue.value().to_string())));
+    }
+
+    let cue_sheet = header.blocks.iter().find_map(|block| match &block.data {
+        MetadataBlockData::CueSheet(cue_sheet) => Some(cue_sheet),
+        _ => None,
+    });
+    Ok(cue_sheet.map(|cue_sheet| {
+        let file_name = audio.file_name().unwrap_or_default().to_string_lossy();
+        let album = comments.get("ALBUM").map(|c| c.value());
+        let artist = comments.get("ARTIST").map(|c| c.value());
+        CueSource::Chapters {
+            cue: cue_sheet_to_cue(
+                cue_sheet,
+                header.stream_info().sample_rate,
+                &file_name,
+                album,
+                artist,
+            ),
+            samples: cue_sheet_samples(cue_sheet),
+        }
+    }))
+}
+
+/// Output of `ffprobe -print_format json`.
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    #[serde(default)]
+    chapters: Vec<ProbeChapter>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    sample_rate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeChapter {
+    /// Start time in `time_base`
+    start: u64,
+    /// Time base like `1/1000`
+    time_base: String,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+fn m4a_chapters(audio: &Path) -> anyhow::Result<Option<CueSource>> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_chapters",
+            "-show_format",
+            "-show_streams",
+            "-select_streams",
+            "a:0",
+        ])
+        .arg(audio)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "ffprobe failed on {}: {}",
+            audio.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let probe: Probe = serde_json::from_slice(&output.stdout)?;
+    let file_name = audio.file_name().unwrap_or_default().to_string_lossy();
+    probe_to_cue(&probe, &file_name)
+}
+
+/// Convert chapters of `probe` to cue, with sample offsets of tracks.
+fn probe_to_cue(probe: &Probe, file_name: &str) -> anyhow::Result<Option<CueSource>> {
+    if probe.chapters.is_empty() {
+        return Ok(None);
+    }
+    let sample_rate: u64 = probe
+        .streams
+        .first()
+        .and_then(|stream| stream.sample_rate.as_deref())
+        .ok_or_else(|| anyhow!("No audio stream found in {file_name}"))?
+        .parse()?;
+
+    let album_tags = probe.format.as_ref().map(|format| &format.tags);
+    let album_tag = |key: &str| album_tags.and_then(|tags| tags.get(key)).map(|v| quote(v));
+
+    let mut cue = String::new();
+    if let Some(artist) = album_tag("artist") {
+        cue += &format!("PERFORMER \"{artist}\"\n");
+    }
+    if let Some(album) = album_tag("album") {
+        cue += &format!("TITLE \"{album}\"\n");
+    }
+    cue += &format!("FILE \"{}\" WAVE\n", quote(file_name));
+
+    let mut samples = Vec::with_capacity(probe.chapters.len());
+    for (i, chapter) in probe.chapters.iter().enumerate() {
+        let (num, den) = chapter
+            .time_base
+            .split_once('/')
+            .and_then(|(num, den)| Some((num.parse::<u64>().ok()?, den.parse::<u64>().ok()?)))
+            .filter(|(_, den)| *den != 0)
+            .ok_or_else(|| anyhow!("Invalid chapter time base: {}", chapter.time_base))?;
+        let sample = rounded_div(chapter.start * sample_rate * num, den);
+        samples.push(sample);
+
+        cue += &format!("  TRACK {:02} AUDIO\n", i + 1);
+        if let Some(title) = chapter.tags.get("title") {
+            cue += &format!("    TITLE \"{}\"\n", quote(title));
+        }
+        cue += &format!("    INDEX 01 {}\n", cue_time(sample, sample_rate));
+    }
+    Ok(Some(CueSource::Chapters { cue, samples }))
+}
+
+/// Cue does not support escaping quotes.
+fn quote(value: &str) -> String {
+    value.replace('"', "'")
+}
+
+fn rounded_div(a: u64, b: u64) -> u64 {
+    (a + b / 2) / b
+}
+
+/// Format `sample` as cue time `mm:ss:ff`, in which `ff` is CD frame of 1/75 second.
+fn cue_time(sample: u64, sample_rate: u64) -> String {
+    let frames = rounded_div(sample * 75, sample_rate);
+    format!(
+        "{:02}:{:02}:{:02}",
+        frames / 75 / 60,
+        frames / 75 % 60,
+        frames % 75
+    )
+}
+
+/// Sample offsets of tracks in FLAC CUESHEET block, in the same order as [cue_sheet_to_cue].
+fn cue_sheet_samples(cue_sheet: &BlockCueSheet) -> Vec<u64> {
+    cue_sheet
+        .tracks
+        .iter()
+        .flat_map(|track| {
+            track
+                .track_index
+                .iter()
+                .filter(|index| index.index_point == 1)
+                .map(|index| track.track_offset + index.sample_offset)
+        })
+        .collect()
+}
+
+/// Convert a FLAC CUESHEET block to cue. Tracks have no title, so they are named by number.
+fn cue_sheet_to_cue(
+    cue_sheet: &BlockCueSheet,
+    sample_rate: u32,
+    file_name: &str,
+    album: Option<&str>,
+    artist: Option<&str>,
+) -> String {
+    let mut cue = String::new();
+    if let Some(artist) = artist {
+        cue += &format!("PERFORMER \"{}\"\n", quote(artist));
+    }
+    if let Some(album) = album {
+        cue += &format!("TITLE \"{}\"\n", quote(album));
+    }
+    cue += &format!("FILE \"{}\" WAVE\n", quote(file_name));
+    // lead-out track has no index
+    for track in cue_sheet
+        .tracks
+        .iter()
+        .filter(|t| !t.track_index.is_empty())
+    {
+        cue += &format!("  TRACK {:02} AUDIO\n", track.track_number);
+        for index in track.track_index.iter() {
+            cue += &format!(
+                "    INDEX {:02} {}\n",
+                index.index_point,
+                cue_time(track.track_offset + index.sample_offset, sample_rate as u64)
+            );
+        }
+    }
+    cue
 }
 
 #[derive(ValueEnum, Debug, Clone)]
@@ -144,6 +527,7 @@ pub enum SplitFormat {
     Ape,
     Tak,
     Tta,
+    M4a,
 }
 
 pub enum SplitFormats<P>
@@ -155,6 +539,7 @@ where
     Ape(ApeCommandDecoder<P>),
     Tak(TakCommandDecoder<P>),
     Tta(TtaCommandDecoder<P>),
+    M4a(M4aCommandDecoder<P>),
 }
 
 impl<P> Decoder for SplitFormats<P>
@@ -170,6 +555,7 @@ where
             SplitFormats::Ape(decoder) => Box::new(decoder.decode()?),
             SplitFormats::Tak(decoder) => Box::new(decoder.decode()?),
             SplitFormats::Tta(decoder) => Box::new(decoder.decode()?),
+            SplitFormats::M4a(decoder) => Box::new(decoder.decode()?),
         })
     }
 }
@@ -182,6 +568,7 @@ impl SplitFormat {
             SplitFormat::Ape => "ape",
             SplitFormat::Tak => "tak",
             SplitFormat::Tta => "tta",
+            SplitFormat::M4a => "m4a",
         }
     }
 
@@ -195,6 +582,7 @@ impl SplitFormat {
             SplitFormat::Ape => SplitFormats::Ape(ApeCommandDecoder(path)),
             SplitFormat::Tak => SplitFormats::Tak(TakCommandDecoder(path)),
             SplitFormat::Tta => SplitFormats::Tta(TtaCommandDecoder(path)),
+            SplitFormat::M4a => SplitFormats::M4a(M4aCommandDecoder(path)),
         }
     }
 }
@@ -256,8 +644,54 @@ impl Display for SplitOutputFormat {
     }
 }
 
-fn get_cover(root: &PathBuf) -> anyhow::Result<Option<PathBuf>> {
-    if let Some(cover) = fs::get_ext_file(root.as_path(), "jpg", false)? {
+/// Make sure `path` is an empty and writable directory, creating it if necessary.
+///
+/// Non-empty directory is accepted if `force` is set.
+/// Nothing is written under `dry_run`, so whether the directory is writable is not checked.
+fn prepare_output_dir(path: &Path, force: bool, dry_run: bool) -> anyhow::Result<()> {
+    if path.exists() {
+        if !path.is_dir() {
+            ball!(
+                "split-output-dir-not-dir",
+                path = path.display().to_string()
+            );
+        }
+        if !force && fs::read_dir(path)?.next().is_some() {
+            ball!(
+                "split-output-dir-not-empty",
+                path = path.display().to_string()
+            );
+        }
+    } else if !dry_run {
+        fs::create_dir_all(path)?;
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    // metadata does not tell whether current user is able to write, so try to write a file
+    let probe = path.join(".anni-split");
+    if let Err(e) = fs::write(&probe, b"") {
+        ball!(
+            "split-output-dir-not-writable",
+            path = path.display().to_string(),
+            error = e.to_string()
+        );
+    }
+    fs::remove_file(&probe, false)?;
+    Ok(())
+}
+
+/// Find cover of `root`, which is `cover.jpg` if it exists, or any other jpg file.
+fn get_cover(root: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let cover = root.join("cover.jpg");
+    let cover = if cover.is_file() {
+        Some(cover)
+    } else {
+        fs::get_ext_file(root, "jpg", false)?
+    };
+    if let Some(cover) = cover {
         let mut file = fs::File::open(&cover)?;
         let mut buffer = [0u8; 3];
         file.read_exact(&mut buffer)?;
@@ -291,31 +725,57 @@ fn handle_split(me: &SplitSubcommand) -> anyhow::Result<()> {
                     directory.display()
                 )
             })?;
-        let cue = {
-            let audio_cue = audio.with_extension("cue");
-            if audio_cue.is_file() {
-                audio_cue
-            } else {
-                fs::get_ext_file(directory.as_path(), "cue", false)?.ok_or_else(|| {
-                    anyhow!(
-                        "Failed to find cue file from directory {}",
-                        directory.display()
+        let embedded = if me.from_chapters {
+            let cue = me.embedded_cue(&audio)?;
+            if cue.is_none() {
+                info!(
+                    target: "split",
+                    "No embedded chapters found in {}, falling back to cue file",
+                    audio.display()
+                );
+            }
+            cue
+        } else {
+            None
+        };
+        let cue = match embedded {
+            Some(cue) => cue,
+            None => {
+                let audio_cue = audio.with_extension("cue");
+                if audio_cue.is_file() {
+                    CueSource::File(audio_cue)
+                } else {
+                    CueSource::File(
+                        fs::get_ext_file(directory.as_path(), "cue", false)?.ok_or_else(|| {
+                            anyhow!(
+                                "Failed to find cue file from directory {}",
+                                directory.display()
+                            )
+                        })?,
                     )
-                })?
+                }
             }
         };
 
         // try to get cover
-        let cover = if me.import_cover {
-            get_cover(directory)?
-        } else {
-            None
+        let cover = match &me.cover {
+            Some(cover) if cover.is_file() => Some(cover.clone()),
+            Some(cover) => {
+                warn!(target: "split", "Cover file {} not found", cover.display());
+                None
+            }
+            None if me.import_cover => {
+                let cover = get_cover(directory)?;
+                if cover.is_none() {
+                    warn!(target: "split", "Cover not found in directory {}", directory.display());
+                }
+                cover
+            }
+            None => None,
         };
-        if me.import_cover && cover.is_none() {
-            warn!(target: "split", "Cover not found in directory {}", directory.display());
-        }
 
-        me.split(audio, cue, cover)?;
+        let target = me.target(directory, cue.path().unwrap_or(&audio))?;
+        me.split(audio, cue, cover, target)?;
     }
 
     // log 'Finished' after all tracks were split
@@ -329,7 +789,24 @@ struct CueTrack {
     pub tags: Vec<UserComment>,
 }
 
-fn cue_tracks(cue: Cuna) -> Vec<CueTrack> {
+/// Value of a `REM {key} value` line in the header of `cue`, e.g. `REM DATE 2020`.
+fn cue_rem(cue: &str, key: &str) -> Option<String> {
+    cue.lines()
+        .map(str::trim)
+        .take_while(|line| !line.starts_with("FILE "))
+        .find_map(|line| {
+            let value = line.strip_prefix("REM ")?.trim_start().strip_prefix(key)?;
+            // keys such as `DATE` and `DATETIME` should not match each other
+            if !value.starts_with(char::is_whitespace) {
+                return None;
+            }
+            let value = value.trim().trim_matches('"');
+            (!value.is_empty()).then(|| value.to_string())
+        })
+}
+
+/// Generate tracks with tags from cue. Album tags are written to every track.
+fn cue_tracks(cue: Cuna, date: Option<&str>) -> Vec<CueTrack> {
     let album = cue.title().get(0).map(String::as_str).unwrap_or("");
     let artist = cue.performer().get(0).map(String::as_str).unwrap_or("");
 
@@ -346,17 +823,24 @@ fn cue_tracks(cue: Cuna) -> Vec<CueTrack> {
                         .get(0)
                         .map(String::to_owned)
                         .unwrap_or(format!("Track {}", track_number));
-                    let artist = track.performer.get(0).map(String::as_str).unwrap_or(artist);
+                    let track_artist = track.performer.get(0).map(String::as_str).unwrap_or(artist);
+                    let mut tags = vec![
+                        UserComment::title(&title),
+                        UserComment::album(album),
+                        UserComment::artist(track_artist),
+                    ];
+                    if !artist.is_empty() {
+                        tags.push(UserComment::album_artist(artist));
+                    }
+                    if let Some(date) = date {
+                        tags.push(UserComment::date(date));
+                    }
+                    tags.push(UserComment::track_number(track_number));
+                    tags.push(UserComment::track_total(track_total));
                     result.push(CueTrack {
                         index: (i + 1) as u8,
-                        title: title.to_owned(),
-                        tags: vec![
-                            UserComment::title(title),
-                            UserComment::album(album),
-                            UserComment::artist(artist),
-                            UserComment::track_number(track_number),
-                            UserComment::track_total(track_total),
-                        ],
+                        title,
+                        tags,
                     });
                 }
             }
@@ -365,3 +849,135 @@ fn cue_tracks(cue: Cuna) -> Vec<CueTrack> {
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cue_sheet_to_cue() {
+        let header = FlacHeader::from_file("../assets/1s-cuesheet.flac").unwrap();
+        let cue_sheet = header
+            .blocks
+            .iter()
+            .find_map(|block| match &block.data {
+                MetadataBlockData::CueSheet(cue_sheet) => Some(cue_sheet),
+                _ => None,
+            })
+            .unwrap();
+        let cue = cue_sheet_to_cue(
+            cue_sheet,
+            header.stream_info().sample_rate,
+            "1s-cuesheet.flac",
+            Some("Album"),
+            None,
+        );
+        assert_eq!(
+            cue,
+            r#"TITLE "Album"
+FILE "1s-cuesheet.flac" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 00:00:40
+"#
+        );
+        assert_eq!(cue_sheet_samples(cue_sheet), [0, 23520]);
+
+        let (breakpoints, cuna) = cue_breakpoints(&cue).unwrap();
+        assert_eq!(breakpoints.into_iter().count(), 1);
+        let tracks = cue_tracks(cuna, None);
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[1].title, "Track 2");
+    }
+
+    #[test]
+    fn test_probe_to_cue() {
+        let probe: Probe = serde_json::from_str(
+            r#"{
+                "streams": [{ "index": 0, "codec_name": "aac", "sample_rate": "44100" }],
+                "chapters": [
+                    { "id": 0, "time_base": "1/1000", "start": 0, "tags": { "title": "First" } },
+                    { "id": 1, "time_base": "1/44100", "start": 441500, "tags": { "title": "Second \"Part\"" } },
+                    { "id": 2, "time_base": "1/1000", "start": 20011, "tags": {} }
+                ],
+                "format": { "tags": { "album": "Album", "artist": "Artist" } }
+            }"#,
+        )
+        .unwrap();
+        let Some(CueSource::Chapters { cue, samples }) = probe_to_cue(&probe, "album.m4a").unwrap()
+        else {
+            panic!("chapters not found");
+        };
+        assert_eq!(
+            cue,
+            r#"PERFORMER "Artist"
+TITLE "Album"
+FILE "album.m4a" WAVE
+  TRACK 01 AUDIO
+    TITLE "First"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second 'Part'"
+    INDEX 01 00:10:01
+  TRACK 03 AUDIO
+    INDEX 01 00:20:01
+"#
+        );
+        // sample offsets are kept, while cue time is rounded to CD frames
+        assert_eq!(samples, [0, 441500, 882485]);
+
+        let probe: Probe = serde_json::from_str(r#"{ "streams": [], "chapters": [] }"#).unwrap();
+        assert!(probe_to_cue(&probe, "album.m4a").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_prepare_output_dir_dry_run() {
+        let root = tempfile::tempdir().unwrap();
+        prepare_output_dir(root.path(), false, true).unwrap();
+        assert!(fs::read_dir(root.path()).unwrap().next().is_none());
+
+        let missing = root.path().join("missing");
+        prepare_output_dir(&missing, false, true).unwrap();
+        assert!(!missing.exists());
+
+        prepare_output_dir(&missing, false, false).unwrap();
+        assert!(fs::read_dir(&missing).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_cue_album_tags() {
+        let cue = r#"REM GENRE Soundtrack
+REM DATE "2020"
+PERFORMER "Album Artist"
+TITLE "Album"
+FILE "album.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "First"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second"
+    PERFORMER "Guest"
+    INDEX 01 00:01:00
+"#;
+        assert_eq!(cue_rem(cue, "DATE").as_deref(), Some("2020"));
+        assert_eq!(cue_rem(cue, "DATETIME"), None);
+        assert_eq!(cue_rem(cue, "COMMENT"), None);
+
+        let (_, cuna) = cue_breakpoints(cue).unwrap();
+        let tracks = cue_tracks(cuna, cue_rem(cue, "DATE").as_deref());
+        let tags: Vec<_> = tracks[1].tags.iter().map(|tag| tag.entry()).collect();
+        assert_eq!(
+            tags,
+            [
+                "TITLE=Second",
+                "ALBUM=Album",
+                "ARTIST=Guest",
+                "ALBUMARTIST=Album Artist",
+                "DATE=2020",
+                "TRACKNUMBER=2",
+                "TRACKTOTAL=2",
+            ]
+        );
+    }
+}
@@ -1,19 +1,31 @@
+use crate::ll;
+use anni_repo::library::AlbumFolderInfo;
+use anni_repo::prelude::{Album, AlbumInfo, Disc, DiscInfo, Track};
 use anni_workspace::AnniWorkspace;
 use clap::Args;
 use clap_handler::handler;
-use std::num::NonZeroU8;
-use std::path::PathBuf;
+use serde::Deserialize;
+use std::num::{NonZeroU8, NonZeroUsize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use uuid::Uuid;
 
 #[derive(Args, Debug, Clone)]
 pub struct WorkspaceCreateAction {
     #[clap(short = 'a', long)]
     album_id: Option<Uuid>,
-    #[clap(short = 'd', long, default_value = "1")]
-    disc_num: NonZeroU8,
+    #[clap(short = 'd', long)]
+    disc_num: Option<NonZeroU8>,
     #[clap(short = 'f', long)]
     force: bool,
 
+    #[clap(short = 't', long)]
+    #[clap(help = ll!("workspace-create-template"))]
+    template: Option<String>,
+    #[clap(long, requires = "template")]
+    #[clap(help = ll!("workspace-create-tracks"))]
+    tracks: Option<NonZeroUsize>,
+
     path: PathBuf,
 }
 
@@ -29,27 +41,165 @@ fn handle_workspace_create(me: WorkspaceCreateAction) -> anyhow::Result<()> {
         bail!("Target path already exists");
     }
 
-    workspace.create_album(&album_id, &user_album_path, me.disc_num)?;
+    let template = match me.template {
+        Some(name) => {
+            let template = AlbumTemplate::load(&workspace, &name)?;
+            Some(template.resize(me.disc_num, me.tracks))
+        }
+        None => None,
+    };
+    let disc_num = match &template {
+        Some(template) => u8::try_from(template.discs.len())
+            .ok()
+            .and_then(NonZeroU8::new)
+            .ok_or_else(|| anyhow!("Too many discs in template"))?,
+        None => me.disc_num.unwrap_or(NonZeroU8::MIN),
+    };
+
+    workspace.create_album(&album_id, &user_album_path, disc_num)?;
+
+    if let Some(template) = template {
+        let album = template.to_album(album_id, &user_album_path);
+        info!(
+            "Album {album_id} scaffolded with {} disc(s) and {} track(s)",
+            template.discs.len(),
+            template.discs.iter().map(|d| d.get()).sum::<usize>()
+        );
+        let allow_duplicate = album.catalog() == AlbumInfo::default().catalog;
+        workspace
+            .to_repository_manager()?
+            .add_album(album, allow_duplicate)?;
+    }
 
     Ok(())
 }
 
-// #[cfg(test)]
-// mod test {
-//     use super::{WorkspaceCreateAction};
-//     use clap_handler::Handler;
-//
-//     #[tokio::test]
-//     async fn test_create_album() -> anyhow::Result<()> {
-//         let path = tempfile::tempdir()?;
-//
-//         WorkspaceCreateAction {
-//             album_id: None,
-//             disc_num: 1.into(),
-//             path: path.path().to_path_buf(),
-//             name: None,
-//         }.run().await?;
-//
-//         Ok(())
-//     }
-// }
+/// Disc and track skeleton of an album.
+///
+/// Builtin templates are `single`, `ep` and `album`, which can be overridden by `.anni/templates/{name}.toml`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+struct AlbumTemplate {
+    /// Number of tracks in each disc
+    discs: Vec<NonZeroUsize>,
+}
+
+impl AlbumTemplate {
+    fn builtin(name: &str) -> Option<Self> {
+        let tracks = match name {
+            "single" => 4,
+            "ep" => 6,
+            "album" => 12,
+            _ => return None,
+        };
+        Some(Self {
+            discs: vec![NonZeroUsize::new(tracks).unwrap()],
+        })
+    }
+
+    fn load(workspace: &AnniWorkspace, name: &str) -> anyhow::Result<Self> {
+        let path = workspace
+            .workspace_root()
+            .join(".anni")
+            .join("templates")
+            .join(format!("{name}.toml"));
+        let template: Self = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            toml::from_str(&content)
+                .map_err(|e| anyhow!("Invalid template {}: {e}", path.display()))?
+        } else {
+            Self::builtin(name).ok_or_else(|| anyhow!("Template {name} not found"))?
+        };
+
+        if template.discs.is_empty() {
+            bail!("Template {name} does not have any disc");
+        }
+        Ok(template)
+    }
+
+    /// Override number of discs and tracks in each disc.
+    ///
+    /// New discs have the same number of tracks as the last disc in template.
+    fn resize(mut self, discs: Option<NonZeroU8>, tracks: Option<NonZeroUsize>) -> Self {
+        if let Some(discs) = discs {
+            let last = *self.discs.last().unwrap();
+            self.discs.resize(discs.get() as usize, last);
+        }
+        if let Some(tracks) = tracks {
+            self.discs.fill(tracks);
+        }
+        self
+    }
+
+    fn to_album(&self, album_id: Uuid, path: &Path) -> Album {
+        let mut info = AlbumInfo {
+            album_id,
+            ..Default::default()
+        };
+        let folder_name = path.file_name().map(|n| n.to_string_lossy());
+        if let Some(folder) = folder_name.and_then(|n| AlbumFolderInfo::from_str(&n).ok()) {
+            info.title = folder.title;
+            info.edition = folder.edition;
+            info.catalog = folder.catalog;
+            info.release_date = folder.release_date;
+        }
+
+        let discs = self
+            .discs
+            .iter()
+            .map(|tracks| {
+                Disc::new(
+                    DiscInfo::new(
+                        info.catalog.clone(),
+                        None,
+                        None,
+                        None,
+                        None,
+                        Default::default(),
+                    ),
+                    (0..tracks.get()).map(|_| Track::empty()).collect(),
+                )
+            })
+            .collect();
+        Album::new(info, discs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AlbumTemplate;
+    use std::num::{NonZeroU8, NonZeroUsize};
+    use std::path::Path;
+    use uuid::Uuid;
+
+    fn counts(template: &AlbumTemplate) -> Vec<usize> {
+        template.discs.iter().map(|d| d.get()).collect()
+    }
+
+    #[test]
+    fn test_template_resize() {
+        let template = AlbumTemplate::builtin("single").unwrap();
+        assert_eq!(counts(&template), [4]);
+        assert!(AlbumTemplate::builtin("unknown").is_none());
+
+        let resized = template.clone().resize(NonZeroU8::new(2), None);
+        assert_eq!(counts(&resized), [4, 4]);
+
+        let resized = template.resize(NonZeroU8::new(3), NonZeroUsize::new(2));
+        assert_eq!(counts(&resized), [2, 2, 2]);
+    }
+
+    #[test]
+    fn test_template_to_album() {
+        let template: AlbumTemplate = toml::from_str("discs = [3, 1]").unwrap();
+        let album = template.to_album(
+            Uuid::new_v4(),
+            Path::new("[220302][SMCL-753] 彩色硝子 [2 Discs]"),
+        );
+        assert_eq!(album.catalog(), "SMCL-753");
+        assert_eq!(album.title_raw(), "彩色硝子");
+        let tracks: Vec<_> = album.iter().map(|disc| disc.iter().count()).collect();
+        assert_eq!(tracks, [3, 1]);
+
+        assert!(toml::from_str::<AlbumTemplate>("discs = [0]").is_err());
+    }
+}
@@ -2,6 +2,9 @@ use anni_common::fs;
 use anni_workspace::{AnniWorkspace, WorkspaceAlbumState};
 use clap::Args;
 use clap_handler::handler;
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
 
 #[derive(Args, Debug, Clone)]
 pub struct WorkspaceFsckAction {
@@ -9,6 +12,13 @@ pub struct WorkspaceFsckAction {
     fix_dangling: bool,
     #[clap(long)]
     gc: bool,
+    /// Remove `.album` symlinks whose target no longer exists and whose album is not in the repository,
+    /// and empty directories under `objects`.
+    #[clap(long, conflicts_with = "fix_dangling")]
+    clean: bool,
+    /// Print what would be changed without touching any file.
+    #[clap(short = 'n', long)]
+    dry_run: bool,
 }
 
 #[handler(WorkspaceFsckAction)]
@@ -19,9 +29,18 @@ fn handle_workspace_fsck(me: WorkspaceFsckAction) -> anyhow::Result<()> {
         let albums = workspace.scan()?;
         for album in albums {
             if let WorkspaceAlbumState::Dangling(album_path) = album.state {
+                let real_path = workspace.controlled_album_path(&album.album_id, 2);
+                if me.dry_run {
+                    println!(
+                        "Would relink {} to {}",
+                        album_path.display(),
+                        real_path.display()
+                    );
+                    continue;
+                }
+
                 let result: anyhow::Result<()> = try {
                     let dot_album = album_path.join(".album");
-                    let real_path = workspace.controlled_album_path(&album.album_id, 2);
                     if !real_path.exists() {
                         fs::create_dir_all(&real_path)?;
                     }
@@ -44,6 +63,13 @@ fn handle_workspace_fsck(me: WorkspaceFsckAction) -> anyhow::Result<()> {
         let albums = workspace.scan()?;
         for album in albums {
             if let WorkspaceAlbumState::Garbage = album.state {
+                if me.dry_run {
+                    if let Ok(real_path) = workspace.get_album_controlled_path(&album.album_id) {
+                        println!("Would remove garbage album {}", real_path.display());
+                    }
+                    continue;
+                }
+
                 let result: anyhow::Result<()> = try {
                     if let Ok(real_path) = workspace.get_album_controlled_path(&album.album_id) {
                         // 1. remove garbage album directory
@@ -72,5 +98,73 @@ fn handle_workspace_fsck(me: WorkspaceFsckAction) -> anyhow::Result<()> {
         }
     }
 
+    if me.clean {
+        // 1. remove dangling album symlinks, which confuse fixing dangling albums later
+        let repo = workspace.to_repository_manager()?.into_owned_manager()?;
+        for album in workspace.scan()? {
+            if let WorkspaceAlbumState::Dangling(album_path) = album.state {
+                let dot_album = album_path.join(".album");
+                // target of the symlink no longer exists
+                if !dot_album.is_symlink() || dot_album.exists() {
+                    continue;
+                }
+                // links of albums in repository can be restored by --fix-dangling
+                if repo.album(&album.album_id).is_some() {
+                    log::warn!(
+                        "Keeping dangling album link {} of album {} in repository, use --fix-dangling to restore it",
+                        dot_album.display(),
+                        album.album_id
+                    );
+                    continue;
+                }
+
+                let target = fs::read_link(&dot_album)?;
+                if me.dry_run {
+                    println!(
+                        "Would remove dangling album link {} -> {}",
+                        dot_album.display(),
+                        target.display()
+                    );
+                } else if let Err(e) = fs::remove_file(&dot_album, false) {
+                    log::error!(
+                        "Error while removing album link at {}: {}",
+                        dot_album.display(),
+                        e
+                    );
+                } else {
+                    log::info!("Removed dangling album link {}", dot_album.display());
+                }
+            }
+        }
+
+        // 2. remove empty directories under objects
+        remove_empty_directories(&workspace.objects_root(), me.dry_run)?;
+    }
+
     Ok(())
 }
+
+/// Remove empty directories under `path` recursively, while `path` itself is kept.
+///
+/// Album directories are kept even if they are empty, as untracked albums link to them.
+/// Use `--gc` to remove those not linked by any album.
+///
+/// Returns whether `path` is empty after removal.
+fn remove_empty_directories(path: &Path, dry_run: bool) -> anyhow::Result<bool> {
+    let mut is_empty = true;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let child = entry.path();
+        let is_album = Uuid::from_str(&entry.file_name().to_string_lossy()).is_ok();
+        if entry.file_type()?.is_dir() && !is_album && remove_empty_directories(&child, dry_run)? {
+            if dry_run {
+                println!("Would remove empty directory {}", child.display());
+            } else {
+                fs::remove_dir(&child)?;
+            }
+        } else {
+            is_empty = false;
+        }
+    }
+    Ok(is_empty)
+}
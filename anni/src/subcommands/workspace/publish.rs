@@ -1,8 +1,12 @@
+use anni_common::parallel;
+use anni_repo::OwnedRepositoryManager;
 use anni_workspace::{AnniWorkspace, WorkspaceAlbumState};
 use clap::Args;
 use clap_handler::handler;
+use indicatif::{ProgressBar, ProgressStyle};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 #[derive(Args, Debug, Clone)]
 pub struct WorkspacePublishAction {
@@ -17,6 +21,16 @@ pub struct WorkspacePublishAction {
     #[clap(long)]
     soft: bool,
 
+    /// Number of albums to publish in parallel.
+    #[clap(short = 'j', long, default_value = "1")]
+    concurrency: usize,
+
+    /// Stop publishing remaining albums at the first failure.
+    ///
+    /// Albums published before the failure are kept.
+    #[clap(long)]
+    fail_fast: bool,
+
     // publish_to: Option<PathBuf>,
     path: Vec<PathBuf>,
 }
@@ -57,12 +71,78 @@ pub async fn handle_workspace_publish(mut me: WorkspacePublishAction) -> anyhow:
         })
         .collect();
 
-    for path in me.path {
-        if me.write {
-            workspace.apply_tags(&path)?;
+    let sizes: Vec<u64> = me.path.iter().map(|path| album_size(path)).collect();
+    let progress = ProgressBar::new(sizes.iter().sum());
+    progress.set_style(
+        ProgressStyle::with_template("{msg} [{bar:40}] {bytes}/{total_bytes} ({eta})")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    progress.set_message(format!("0/{} albums", me.path.len()));
+
+    // repository can only be loaded once, so it's shared by all workers
+    let repo = if me.write {
+        Some(workspace.to_repository_manager()?.into_owned_manager()?)
+    } else {
+        None
+    };
+    let published = AtomicUsize::new(0);
+    let aborted = AtomicBool::new(false);
+    let results = parallel::map(me.path.iter().zip(sizes), me.concurrency, |(path, size)| {
+        if aborted.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let result = publish_album(&workspace, path, repo.as_ref(), me.soft);
+        if result.is_err() && me.fail_fast {
+            aborted.store(true, Ordering::Relaxed);
         }
 
-        workspace.publish(path, me.soft)?;
+        let published = published.fetch_add(1, Ordering::Relaxed) + 1;
+        progress.set_message(format!("{published}/{} albums", me.path.len()));
+        progress.inc(size);
+        result.err().map(|e| (path, e))
+    });
+    progress.finish();
+
+    let failures: Vec<_> = results.into_iter().flatten().collect();
+    if failures.is_empty() {
+        return Ok(());
     }
+    for (path, e) in failures.iter() {
+        error!("Failed to publish {}: {e}", path.display());
+    }
+    if me.fail_fast {
+        bail!("Publish aborted due to failure");
+    }
+    bail!(
+        "{} of {} albums failed to publish",
+        failures.len(),
+        me.path.len()
+    );
+}
+
+/// Publish album at `path`, applying tags from `repo` first if it's provided.
+fn publish_album(
+    workspace: &AnniWorkspace,
+    path: &Path,
+    repo: Option<&OwnedRepositoryManager>,
+    soft: bool,
+) -> anyhow::Result<()> {
+    if let Some(repo) = repo {
+        workspace.apply_tags_with(path, repo)?;
+    }
+    workspace.publish(path, soft)?;
     Ok(())
 }
+
+/// Total size of files in an album, following symlinks to the controlled directory.
+fn album_size(path: &Path) -> u64 {
+    if !path.is_dir() {
+        return 0;
+    }
+    anni_common::fs::PathWalker::new(path, true, true, Default::default())
+        .filter_map(|file| std::fs::metadata(file).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
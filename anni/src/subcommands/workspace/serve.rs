@@ -43,6 +43,15 @@ pub async fn handle_workspace_serve(this: WorkspaceServeAction) -> anyhow::Resul
         last_update: Default::default(),
         etag: Default::default(),
         metadata: None,
+        database: Default::default(),
+        provider_timeout_secs: None,
+        max_response_size: None,
+        http_cache: None,
+        cover_format: None,
+        mime: Default::default(),
+        transcode: Default::default(),
+        covers: Default::default(),
+        caches: Default::default(),
     };
     let annil_provider = AnnilProvider::new(NoCacheStrictLocalProvider {
         root: audio_root,
@@ -12,6 +12,9 @@ pub struct WorkspaceStatusAction {
     album_id: bool,
     #[clap(short = 'j', long)]
     json: bool,
+    /// Print one album per line, prefixed by its state.
+    #[clap(short = 's', long, conflicts_with = "json")]
+    short: bool,
 }
 
 struct DisplayUuid<'uuid> {
@@ -46,6 +49,31 @@ pub async fn handle_workspace_status(me: WorkspaceStatusAction) -> anyhow::Resul
     if me.json {
         let json = serde_json::to_string(&albums)?;
         println!("{json}");
+        return Ok(());
+    }
+
+    // directories with flac files but not linked to any album
+    let unlinked = workspace.scan_unlinked()?;
+    if me.short {
+        for album in albums.iter() {
+            let album_id = DisplayUuid::new(&album.album_id, me.album_id);
+            let (state, path) = match album.state {
+                WorkspaceAlbumState::Untracked(ref p) => ("U".bright_red(), Some(p)),
+                WorkspaceAlbumState::Committed(ref p) => ("C".green(), Some(p)),
+                WorkspaceAlbumState::Dangling(ref p) => ("D".red(), Some(p)),
+                WorkspaceAlbumState::Published => ("P".white(), None),
+                WorkspaceAlbumState::Garbage => ("G".white(), None),
+            };
+            match path {
+                Some(path) => {
+                    println!("{state} {album_id} {}", path.strip_prefix(&root)?.display())
+                }
+                None => println!("{state} {album_id}"),
+            }
+        }
+        for path in unlinked.iter() {
+            println!("{} {}", "?".yellow(), path.strip_prefix(&root)?.display());
+        }
     } else {
         let mut untracked: Vec<(&Path, DisplayUuid)> = vec![];
         let mut committed: Vec<(&Path, DisplayUuid)> = vec![];
@@ -122,6 +150,15 @@ pub async fn handle_workspace_status(me: WorkspaceStatusAction) -> anyhow::Resul
             }
             println!();
         }
+
+        if !unlinked.is_empty() {
+            println!("Directories without album link:");
+            for path in unlinked.iter() {
+                let output = format!("{}", path.strip_prefix(&root)?.display()).yellow();
+                println!("\t{output}");
+            }
+            println!();
+        }
     }
 
     Ok(())
@@ -57,3 +57,14 @@ fn flac_export_cover() {
     file.read_to_end(&mut data).expect("Failed to read cover.");
     assert_eq!(cmd.stdout, data);
 }
+
+#[test]
+fn flac_tags_lint_default() {
+    // use an empty config root, so that default allowed tags are used
+    let root = tempfile::tempdir().unwrap();
+    let status = common::run(&["flac", "tags", "lint", FLAC_PATH])
+        .env("ANNI_ROOT", root.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
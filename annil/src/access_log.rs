@@ -0,0 +1,98 @@
+use axum::body::HttpBody;
+use axum::extract::MatchedPath;
+use axum::http::header::CONTENT_LENGTH;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Extension;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Access log options, configured by `[server.access-log]`.
+///
+/// Access log is disabled if it's not configured.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AccessLogConfig {
+    /// Route patterns or paths not logged
+    #[serde(default = "default_exclude")]
+    pub exclude: Vec<String>,
+}
+
+fn default_exclude() -> Vec<String> {
+    vec!["/info".to_string(), "/healthz".to_string()]
+}
+
+/// A line of access log, which is logged as JSON with target `annil::access`.
+#[derive(Serialize, Debug)]
+pub struct AccessLogEntry {
+    pub method: String,
+    /// Matched route pattern like `/:album_id/cover`, or the requested path if no route matches
+    pub path: String,
+    pub status: u16,
+    /// Size of response body, `null` if unknown, e.g. chunked responses
+    pub bytes: Option<u64>,
+    /// Time to produce the response, excluding time to send its body
+    pub elapsed_ms: u64,
+}
+
+/// Middleware which logs a JSON line for each request.
+///
+/// It requires [AccessLogConfig] in extensions.
+pub async fn access_log<B>(
+    Extension(config): Extension<Arc<AccessLogConfig>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    // route patterns keep the number of distinct paths low
+    let path = match request.extensions().get::<MatchedPath>() {
+        Some(path) => path.as_str().to_string(),
+        None => request.uri().path().to_string(),
+    };
+    if config.exclude.contains(&path) {
+        return next.run(request).await;
+    }
+
+    let method = request.method().to_string();
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let entry = AccessLogEntry {
+        method,
+        path,
+        status: response.status().as_u16(),
+        bytes: response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse().ok())
+            .or_else(|| response.body().size_hint().exact()),
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    };
+    match serde_json::to_string(&entry) {
+        Ok(line) => log::info!(target: "annil::access", "{line}"),
+        Err(e) => log::warn!("Failed to serialize access log: {e}"),
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    struct Server {
+        access_log: Option<AccessLogConfig>,
+    }
+
+    #[test]
+    fn test_access_log_config() {
+        let server: Server = toml::from_str("").unwrap();
+        assert!(server.access_log.is_none());
+
+        let server: Server = toml::from_str("[access-log]").unwrap();
+        assert_eq!(server.access_log.unwrap().exclude, ["/info", "/healthz"]);
+
+        let server: Server = toml::from_str("[access-log]\nexclude = []").unwrap();
+        assert!(server.access_log.unwrap().exclude.is_empty());
+    }
+}
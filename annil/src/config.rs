@@ -0,0 +1,622 @@
+use crate::access_log::AccessLogConfig;
+use crate::cors::CorsConfig;
+use crate::cover::CoverFormat;
+use crate::http_cache::HttpCacheConfig;
+use crate::metadata::MetadataConfig;
+use crate::mime::MimeConfig;
+use crate::transcode::TranscodeConfig;
+use anni_provider::cache::{CacheBackend, CacheMode, EvictionPolicy};
+use anni_provider::providers::drive::RetryPolicy;
+use anni_provider::providers::Routing;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub metadata: Option<MetadataConfig>,
+    #[serde(rename = "backends")]
+    pub providers: HashMap<String, ProviderConfig>,
+}
+
+impl Config {
+    pub fn from_file<P: AsRef<Path>>(config_path: P) -> anyhow::Result<Self> {
+        let string = fs::read_to_string(config_path)?;
+        let result: Self = toml::from_str(&string)?;
+        if result.server.cover_format.is_some() && !cfg!(feature = "cover-transcode") {
+            anyhow::bail!(
+                "server.cover-format requires annil to be built with `cover-transcode` feature"
+            );
+        }
+        check_cache_roots(
+            result
+                .providers
+                .iter()
+                .filter_map(|(name, provider)| Some((name.as_str(), provider.cache()?))),
+        )?;
+        Ok(result)
+    }
+
+    /// Resolve `max-size` of each cache pool.
+    ///
+    /// See [resolve_cache_pool_sizes] for how conflicts are handled.
+    pub fn cache_pool_sizes(&self) -> HashMap<String, usize> {
+        resolve_cache_pool_sizes(
+            self.providers
+                .iter()
+                .filter_map(|(name, provider)| Some((name.as_str(), provider.cache()?))),
+        )
+    }
+}
+
+/// Check that disk caches sharing a root use the same pool.
+///
+/// Full and sparse pools on the same root would race on index file and cached files of it.
+pub fn check_cache_roots<'a>(
+    caches: impl IntoIterator<Item = (&'a str, &'a CacheConfig)>,
+) -> anyhow::Result<()> {
+    let mut roots: HashMap<PathBuf, (&str, String)> = HashMap::new();
+    for (name, cache) in caches {
+        if !matches!(cache.backend, CacheBackendConfig::Disk) {
+            continue;
+        }
+        let key = cache.pool_key();
+        match roots.get(&cache.root_path()) {
+            Some((other, other_key)) if *other_key != key => {
+                let (a, b) = if *other < name {
+                    (*other, name)
+                } else {
+                    (name, *other)
+                };
+                anyhow::bail!(
+                    "cache root {} is used by providers {a} and {b} with different modes",
+                    cache.root
+                );
+            }
+            Some(_) => {}
+            None => {
+                roots.insert(cache.root_path(), (name, key));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolve `max-size` of cache pools shared by multiple providers.
+///
+/// If providers sharing the same pool configured different `max-size`, the largest one wins
+/// (`0` means unlimited, which is larger than any other value) and a warning is logged.
+/// The result does not depend on the order of providers.
+pub fn resolve_cache_pool_sizes<'a>(
+    caches: impl IntoIterator<Item = (&'a str, &'a CacheConfig)>,
+) -> HashMap<String, usize> {
+    let mut pools: HashMap<String, Vec<(&str, usize)>> = HashMap::new();
+    for (name, cache) in caches {
+        pools
+            .entry(cache.pool_key())
+            .or_default()
+            .push((name, cache.max_size));
+    }
+
+    pools
+        .into_iter()
+        .map(|(key, mut sizes)| {
+            sizes.sort();
+            let size = sizes
+                .iter()
+                .map(|(_, size)| *size)
+                .max_by_key(|size| if *size == 0 { usize::MAX } else { *size })
+                .unwrap_or_default();
+            if sizes.iter().any(|(_, s)| *s != size) {
+                let configured = sizes
+                    .iter()
+                    .map(|(name, size)| format!("{name} = {size}"))
+                    .collect::<Vec<_>>();
+                log::warn!(
+                    "Cache pool {key} is shared by providers with different max-size ({}), using {size}",
+                    configured.join(", ")
+                );
+            }
+            (key, size)
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ServerConfig {
+    /// Server name
+    pub name: String,
+    /// Address to listen on, or unix domain socket path prefixed by `unix:`
+    pub listen: String,
+    /// HMAC key for JWT
+    #[serde(rename = "hmac-key")]
+    pub sign_key: String,
+    pub share_key: String,
+    pub share_key_id: String,
+    /// Password to reload data
+    pub admin_token: String,
+    /// Maximum bytes of a single response with unknown or estimated size
+    #[serde(default)]
+    pub max_response_size: Option<u64>,
+    /// Reload providers automatically when config file changes
+    #[serde(default)]
+    pub watch_config: bool,
+    /// Cache directives of audio and cover responses
+    #[serde(default)]
+    pub http_cache: Option<HttpCacheConfig>,
+    /// Transcode covers to the given format before serving
+    #[serde(default)]
+    pub cover_format: Option<CoverFormat>,
+    /// How to choose a provider when multiple providers have the same album
+    #[serde(default)]
+    pub routing: RoutingConfig,
+    /// Default time limit in seconds for providers to fetch audio or covers
+    #[serde(default)]
+    pub provider_timeout_secs: Option<u64>,
+    /// `Content-Type` of audio extensions, which overrides built-in types
+    #[serde(default)]
+    pub mime: MimeConfig,
+    /// Codecs which audio can be transcoded to with ffmpeg by `transcode` query
+    #[serde(default)]
+    pub transcode: TranscodeConfig,
+    /// Origins allowed to access user routes, `"*"` or a list of origins
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// Time in seconds to wait for connections to close and cache fills to finish on shutdown
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    /// File to persist ids of revoked tokens, kept in memory only if not set
+    #[serde(default)]
+    pub revocation_file: Option<PathBuf>,
+    /// Requests per minute of audio allowed for each user or share token, unlimited if not set or 0
+    #[serde(default)]
+    pub rate_limit: Option<u32>,
+    /// Log a JSON line for each request, disabled if not set
+    #[serde(default)]
+    pub access_log: Option<AccessLogConfig>,
+    /// Require admin token to get `/metrics`
+    #[serde(default)]
+    pub metrics_admin_only: bool,
+}
+
+const fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoutingConfig {
+    /// Use the first provider in config
+    #[default]
+    Priority,
+    /// Take turns to use healthy providers
+    RoundRobin,
+}
+
+impl From<RoutingConfig> for Routing {
+    fn from(routing: RoutingConfig) -> Self {
+        match routing {
+            RoutingConfig::Priority => Routing::Priority,
+            RoutingConfig::RoundRobin => Routing::RoundRobin,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ProviderConfig {
+    #[serde(flatten)]
+    pub item: ProviderItem,
+    cache: Option<CacheConfig>,
+    /// Time limit in seconds for this provider, overrides `server.provider-timeout-secs`
+    #[serde(default, rename = "timeout-secs")]
+    pub timeout_secs: Option<u64>,
+}
+
+impl ProviderConfig {
+    #[inline]
+    pub fn cache(&self) -> Option<&CacheConfig> {
+        self.cache.as_ref()
+    }
+}
+
+/// Body of `POST /admin/providers/add`, which is a provider config with its name.
+#[derive(Deserialize)]
+pub struct NamedProviderConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: ProviderConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+pub enum ProviderItem {
+    #[serde(rename = "file")]
+    #[serde(rename_all = "kebab-case")]
+    File {
+        root: String,
+        strict: bool,
+        #[serde(default = "default_layer")]
+        layer: usize,
+    },
+    #[serde(rename = "drive")]
+    #[serde(rename_all = "kebab-case")]
+    Drive {
+        corpora: String,
+        drive_id: Option<String>,
+        initial_token_path: Option<PathBuf>,
+        token_path: Option<PathBuf>,
+        /// Tokens used in turn for requests, after `token_path`
+        #[serde(default)]
+        token_paths: Vec<PathBuf>,
+        #[serde(default)]
+        strict: bool,
+        /// Maximum retries of each request after the first failure
+        #[serde(default = "default_drive_retries")]
+        retries: u32,
+    },
+    #[serde(rename = "webdav")]
+    #[serde(rename_all = "kebab-case")]
+    WebDav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+        #[serde(default)]
+        strict: bool,
+        /// Layer of album folders in strict mode
+        #[serde(default = "default_layer")]
+        layer: usize,
+    },
+    #[serde(rename = "s3")]
+    #[serde(rename_all = "kebab-case")]
+    S3 {
+        endpoint: String,
+        #[serde(default = "default_region")]
+        region: String,
+        bucket: String,
+        #[serde(default)]
+        prefix: String,
+        access_key_id: String,
+        secret_access_key: String,
+        #[serde(default)]
+        strict: bool,
+        /// Layer of album folders in strict mode
+        #[serde(default = "default_layer")]
+        layer: usize,
+    },
+    #[serde(rename = "zip")]
+    Zip { root: String },
+}
+
+fn default_drive_retries() -> u32 {
+    RetryPolicy::default().retries
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+const fn default_layer() -> usize {
+    2
+}
+
+#[derive(Deserialize)]
+pub struct CacheConfig {
+    /// Root of cache folder, not used by memory backend
+    #[serde(default)]
+    pub root: String,
+    #[serde(default, rename = "max-size")]
+    pub max_size: usize,
+    #[serde(default)]
+    pub backend: CacheBackendConfig,
+    /// Cache whole files, or only requested ranges
+    #[serde(default)]
+    pub mode: CacheModeConfig,
+    /// Which items to remove when cache is full
+    ///
+    /// Providers sharing the same pool use the policy of the first one.
+    #[serde(default)]
+    pub eviction: CacheEvictionConfig,
+    /// Time in seconds after which cached audio is fetched again, never expires if not set
+    ///
+    /// Providers sharing the same pool use the value of the first one.
+    #[serde(default, rename = "ttl-secs")]
+    pub ttl_secs: Option<u64>,
+    /// Number of items filled at the same time, 0 means unlimited
+    ///
+    /// Providers sharing the same pool use the value of the first one.
+    #[serde(default = "default_fill_limit", rename = "fill-limit")]
+    pub fill_limit: usize,
+}
+
+const fn default_fill_limit() -> usize {
+    anni_provider::cache::DEFAULT_FILL_LIMIT
+}
+
+impl CacheConfig {
+    /// Providers with the same key share one cache pool.
+    ///
+    /// All memory caches share the same pool, while disk caches are distinguished by root.
+    /// Sparse caches use separated pools from full caches.
+    pub fn pool_key(&self) -> String {
+        let key = match self.backend {
+            CacheBackendConfig::Disk => self.root_path().display().to_string(),
+            CacheBackendConfig::Memory => ":memory:".to_string(),
+        };
+        match self.mode {
+            CacheModeConfig::Full => key,
+            CacheModeConfig::Sparse => format!("{key}#sparse"),
+        }
+    }
+
+    /// Root of cache folder, with trailing separators and `.` components removed.
+    fn root_path(&self) -> PathBuf {
+        Path::new(&self.root).components().collect()
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackendConfig {
+    #[default]
+    Disk,
+    Memory,
+}
+
+impl From<CacheBackendConfig> for CacheBackend {
+    fn from(backend: CacheBackendConfig) -> Self {
+        match backend {
+            CacheBackendConfig::Disk => CacheBackend::Disk,
+            CacheBackendConfig::Memory => CacheBackend::Memory,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheModeConfig {
+    #[default]
+    Full,
+    Sparse,
+}
+
+impl From<CacheModeConfig> for CacheMode {
+    fn from(mode: CacheModeConfig) -> Self {
+        match mode {
+            CacheModeConfig::Full => CacheMode::Full,
+            CacheModeConfig::Sparse => CacheMode::Sparse,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum CacheEvictionConfig {
+    #[default]
+    Lru,
+    Lfu,
+    SizeWeighted,
+}
+
+impl From<CacheEvictionConfig> for EvictionPolicy {
+    fn from(eviction: CacheEvictionConfig) -> Self {
+        match eviction {
+            CacheEvictionConfig::Lru => EvictionPolicy::Lru,
+            CacheEvictionConfig::Lfu => EvictionPolicy::Lfu,
+            CacheEvictionConfig::SizeWeighted => EvictionPolicy::SizeWeighted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_cache_roots, default_fill_limit, resolve_cache_pool_sizes, CacheBackendConfig,
+        CacheConfig, CacheEvictionConfig, CacheModeConfig, NamedProviderConfig, ProviderConfig,
+        ProviderItem,
+    };
+    use std::path::PathBuf;
+
+    fn cache(root: &str, max_size: usize) -> CacheConfig {
+        CacheConfig {
+            root: root.to_string(),
+            max_size,
+            backend: CacheBackendConfig::Disk,
+            mode: CacheModeConfig::Full,
+            eviction: CacheEvictionConfig::Lru,
+            ttl_secs: None,
+            fill_limit: default_fill_limit(),
+        }
+    }
+
+    #[test]
+    fn test_cache_pool_size_largest_wins() {
+        let a = cache("/cache", 1024);
+        let b = cache("/cache", 4096);
+        let c = cache("/other", 2048);
+        let sizes = resolve_cache_pool_sizes([("a", &a), ("b", &b), ("c", &c)]);
+        assert_eq!(sizes["/cache"], 4096);
+        assert_eq!(sizes["/other"], 2048);
+
+        // order of providers does not matter
+        let sizes = resolve_cache_pool_sizes([("b", &b), ("a", &a)]);
+        assert_eq!(sizes["/cache"], 4096);
+    }
+
+    #[test]
+    fn test_cache_pool_size_unlimited_wins() {
+        let a = cache("/cache", 1024);
+        let b = cache("/cache", 0);
+        assert_eq!(
+            resolve_cache_pool_sizes([("a", &a), ("b", &b)])["/cache"],
+            0
+        );
+        assert_eq!(
+            resolve_cache_pool_sizes([("b", &b), ("a", &a)])["/cache"],
+            0
+        );
+    }
+
+    #[test]
+    fn test_cache_roots() {
+        let full = cache("/cache", 0);
+        let same = cache("/cache/", 1024);
+        assert_eq!(full.pool_key(), same.pool_key());
+        assert!(check_cache_roots([("a", &full), ("b", &same)]).is_ok());
+
+        let sparse = CacheConfig {
+            mode: CacheModeConfig::Sparse,
+            ..cache("/cache", 0)
+        };
+        assert!(check_cache_roots([("a", &full), ("b", &sparse)]).is_err());
+
+        let memory = CacheConfig {
+            backend: CacheBackendConfig::Memory,
+            mode: CacheModeConfig::Sparse,
+            ..cache("/cache", 0)
+        };
+        assert!(check_cache_roots([("a", &full), ("b", &memory)]).is_ok());
+    }
+
+    #[test]
+    fn test_provider_timeout() {
+        let config: ProviderConfig =
+            toml::from_str("type = \"file\"\nroot = \"/music\"\nstrict = true\ntimeout-secs = 2")
+                .unwrap();
+        assert_eq!(config.timeout_secs, Some(2));
+
+        let config: ProviderConfig =
+            toml::from_str("type = \"file\"\nroot = \"/music\"\nstrict = true").unwrap();
+        assert_eq!(config.timeout_secs, None);
+    }
+
+    #[test]
+    fn test_drive_token_paths() {
+        let config: ProviderConfig = toml::from_str(
+            "type = \"drive\"\ncorpora = \"drive\"\ntoken-path = \"/a.json\"\ntoken-paths = [\"/b.json\", \"/c.json\"]",
+        )
+        .unwrap();
+        match config.item {
+            ProviderItem::Drive {
+                token_path,
+                token_paths,
+                retries,
+                ..
+            } => {
+                assert_eq!(token_path, Some(PathBuf::from("/a.json")));
+                assert_eq!(
+                    token_paths,
+                    vec![PathBuf::from("/b.json"), PathBuf::from("/c.json")]
+                );
+                assert_eq!(retries, 4);
+            }
+            _ => panic!("expected drive provider"),
+        }
+    }
+
+    #[test]
+    fn test_named_provider_config() {
+        let config: NamedProviderConfig = serde_json::from_str(
+            r#"{"name": "extra", "type": "file", "root": "/music", "strict": true, "timeout-secs": 2}"#,
+        )
+        .unwrap();
+        assert_eq!(config.name, "extra");
+        assert_eq!(config.config.timeout_secs, Some(2));
+        assert!(matches!(
+            config.config.item,
+            ProviderItem::File {
+                strict: true,
+                layer: 2,
+                ..
+            }
+        ));
+        assert!(config.config.cache().is_none());
+    }
+
+    #[test]
+    fn test_webdav_provider_config() {
+        let config: ProviderConfig = toml::from_str(
+            "type = \"webdav\"\nurl = \"https://dav.example.com/music\"\nusername = \"anni\"\npassword = \"secret\"",
+        )
+        .unwrap();
+        match config.item {
+            ProviderItem::WebDav {
+                url,
+                username,
+                password,
+                strict,
+                layer,
+            } => {
+                assert_eq!(url, "https://dav.example.com/music");
+                assert_eq!(username.as_deref(), Some("anni"));
+                assert_eq!(password.as_deref(), Some("secret"));
+                assert!(!strict);
+                assert_eq!(layer, 2);
+            }
+            _ => panic!("expected webdav provider"),
+        }
+    }
+
+    #[test]
+    fn test_s3_provider_config() {
+        let config: ProviderConfig = toml::from_str(
+            "type = \"s3\"\nendpoint = \"http://localhost:9000\"\nbucket = \"music\"\nprefix = \"anni\"\naccess-key-id = \"key\"\nsecret-access-key = \"secret\"\nstrict = true",
+        )
+        .unwrap();
+        match config.item {
+            ProviderItem::S3 {
+                region,
+                bucket,
+                prefix,
+                strict,
+                layer,
+                ..
+            } => {
+                assert_eq!(region, "us-east-1");
+                assert_eq!(bucket, "music");
+                assert_eq!(prefix, "anni");
+                assert!(strict);
+                assert_eq!(layer, 2);
+            }
+            _ => panic!("expected s3 provider"),
+        }
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let config: ProviderConfig = toml::from_str(
+            "type = \"file\"\nroot = \"/music\"\nstrict = true\n[cache]\nroot = \"/cache\"\neviction = \"size-weighted\"",
+        )
+        .unwrap();
+        assert!(matches!(
+            config.cache().unwrap().eviction,
+            CacheEvictionConfig::SizeWeighted
+        ));
+
+        let config: ProviderConfig = toml::from_str(
+            "type = \"file\"\nroot = \"/music\"\nstrict = true\n[cache]\nroot = \"/cache\"",
+        )
+        .unwrap();
+        assert!(matches!(
+            config.cache().unwrap().eviction,
+            CacheEvictionConfig::Lru
+        ));
+    }
+
+    #[test]
+    fn test_cache_fill_limit() {
+        let config: ProviderConfig = toml::from_str(
+            "type = \"file\"\nroot = \"/music\"\nstrict = true\n[cache]\nroot = \"/cache\"\nfill-limit = 1",
+        )
+        .unwrap();
+        assert_eq!(config.cache().unwrap().fill_limit, 1);
+
+        let config: ProviderConfig = toml::from_str(
+            "type = \"file\"\nroot = \"/music\"\nstrict = true\n[cache]\nroot = \"/cache\"",
+        )
+        .unwrap();
+        assert_eq!(config.cache().unwrap().fill_limit, default_fill_limit());
+    }
+}
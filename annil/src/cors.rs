@@ -0,0 +1,87 @@
+use axum::http::{HeaderValue, Method};
+use serde::Deserialize;
+use tower_http::cors::{self, AllowOrigin, CorsLayer};
+
+/// Origins allowed to access user routes cross-origin, configured by `server.cors`.
+///
+/// It's either `"*"` to allow any origin, or a list of origins like `["https://example.com"]`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(try_from = "CorsValue")]
+pub enum CorsConfig {
+    /// Allow any origin
+    #[default]
+    Any,
+    /// Only allow listed origins
+    Origins(Vec<HeaderValue>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CorsValue {
+    Wildcard(String),
+    Origins(Vec<String>),
+}
+
+impl TryFrom<CorsValue> for CorsConfig {
+    type Error = String;
+
+    fn try_from(value: CorsValue) -> Result<Self, Self::Error> {
+        match value {
+            CorsValue::Wildcard(origin) if origin == "*" => Ok(CorsConfig::Any),
+            CorsValue::Wildcard(origin) => Err(format!(
+                "invalid cors value {origin:?}, expected \"*\" or a list of origins"
+            )),
+            CorsValue::Origins(origins) => origins
+                .into_iter()
+                .map(|origin| {
+                    HeaderValue::from_str(&origin).map_err(|_| format!("invalid origin {origin:?}"))
+                })
+                .collect::<Result<_, _>>()
+                .map(CorsConfig::Origins),
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Build a layer which only allows `GET` and `HEAD` requests, and answers preflight requests.
+    pub fn layer(&self) -> CorsLayer {
+        let layer = CorsLayer::new()
+            .allow_methods([Method::GET, Method::HEAD])
+            .allow_headers(cors::Any);
+        match self {
+            CorsConfig::Any => layer.allow_origin(cors::Any),
+            CorsConfig::Origins(origins) => layer.allow_origin(AllowOrigin::list(origins.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Server {
+        #[serde(default)]
+        cors: CorsConfig,
+    }
+
+    #[test]
+    fn test_cors_config() {
+        let server: Server = toml::from_str("").unwrap();
+        assert!(matches!(server.cors, CorsConfig::Any));
+
+        let server: Server = toml::from_str("cors = \"*\"").unwrap();
+        assert!(matches!(server.cors, CorsConfig::Any));
+
+        let server: Server =
+            toml::from_str("cors = [\"https://a.example\", \"https://b.example\"]").unwrap();
+        match server.cors {
+            CorsConfig::Origins(origins) => {
+                assert_eq!(origins, ["https://a.example", "https://b.example"])
+            }
+            CorsConfig::Any => panic!("expected origin list"),
+        }
+
+        assert!(toml::from_str::<Server>("cors = \"https://a.example\"").is_err());
+    }
+}
@@ -0,0 +1,329 @@
+use anni_provider::{ProviderError, ResourceReader};
+use axum::body::Bytes;
+use lru::LruCache;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::sync::{Mutex, OnceCell};
+
+/// Target format of cover transcoding.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverFormat {
+    #[serde(alias = "jpg")]
+    Jpeg,
+    Png,
+}
+
+impl CoverFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoverFormat::Jpeg => "jpeg",
+            CoverFormat::Png => "png",
+        }
+    }
+}
+
+/// Largest `size` of cover thumbnails.
+pub const MAX_COVER_SIZE: u32 = 2048;
+
+/// Sizes covers are resized to, so that only a few thumbnails of a cover are cached.
+const COVER_SIZES: [u32; 5] = [128, 256, 512, 1024, MAX_COVER_SIZE];
+
+/// Round requested `size` of thumbnail up to one of [COVER_SIZES].
+pub fn cover_size(size: u32) -> u32 {
+    COVER_SIZES
+        .into_iter()
+        .find(|bucket| *bucket >= size)
+        .unwrap_or(MAX_COVER_SIZE)
+}
+
+/// Bytes of transcoded covers kept in [CoverCache], least recently used ones are dropped beyond it.
+pub const MAX_COVER_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+type CoverKey = (String, Option<u8>, CoverFormat, Option<u32>);
+
+/// Error of [CoverCache::get_or_transcode].
+#[derive(Debug, thiserror::Error)]
+pub enum CoverError {
+    /// The original cover can not be fetched from provider.
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+    /// The cover can not be transcoded. `original` is the cover read from provider, if any.
+    #[error("{error}")]
+    Transcode {
+        error: anyhow::Error,
+        original: Option<Bytes>,
+    },
+}
+
+/// In-memory cache of transcoded covers, keyed by (album_id, disc_id, format, size).
+///
+/// At most [MAX_COVER_CACHE_SIZE] bytes are kept. Cleared when providers are reloaded.
+pub struct CoverCache {
+    covers: Mutex<CachedCovers>,
+    /// Covers being transcoded, which are shared by concurrent requests of them
+    pending: Mutex<HashMap<CoverKey, Arc<OnceCell<Bytes>>>>,
+}
+
+struct CachedCovers {
+    covers: LruCache<CoverKey, Bytes>,
+    size: usize,
+}
+
+impl Default for CoverCache {
+    fn default() -> Self {
+        Self {
+            covers: Mutex::new(CachedCovers {
+                covers: LruCache::unbounded(),
+                size: 0,
+            }),
+            pending: Default::default(),
+        }
+    }
+}
+
+impl CoverCache {
+    /// Get transcoded cover from cache, or transcode the cover opened by `open` to `format` and
+    /// cache the result.
+    ///
+    /// `open` is only called on cache miss, and a cover is transcoded once by concurrent requests.
+    /// If `size` is given, covers larger than `size` are resized to fit in a `size`x`size` square,
+    /// preserving aspect ratio.
+    pub async fn get_or_transcode<F, Fut>(
+        &self,
+        album_id: &str,
+        disc_id: Option<u8>,
+        format: CoverFormat,
+        size: Option<u32>,
+        open: F,
+    ) -> Result<Bytes, CoverError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ResourceReader, ProviderError>>,
+    {
+        let key = (album_id.to_string(), disc_id, format, size);
+        if let Some(cover) = self.covers.lock().await.covers.get(&key) {
+            return Ok(cover.clone());
+        }
+
+        let cell = self
+            .pending
+            .lock()
+            .await
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let result = cell
+            .get_or_try_init(|| async {
+                let mut reader = open().await?;
+                let mut original = Vec::new();
+                if let Err(e) = reader.read_to_end(&mut original).await {
+                    return Err(CoverError::Transcode {
+                        error: e.into(),
+                        original: None,
+                    });
+                }
+                let original = Bytes::from(original);
+                let cover = transcode(original.clone(), format, size)
+                    .await
+                    .map_err(|error| CoverError::Transcode {
+                        error,
+                        original: Some(original),
+                    })?;
+                self.insert(key.clone(), cover.clone()).await;
+                Ok(cover)
+            })
+            .await
+            .cloned();
+
+        let mut pending = self.pending.lock().await;
+        if pending
+            .get(&key)
+            .is_some_and(|pending| Arc::ptr_eq(pending, &cell))
+        {
+            pending.remove(&key);
+        }
+        result
+    }
+
+    async fn insert(&self, key: CoverKey, cover: Bytes) {
+        if cover.len() > MAX_COVER_CACHE_SIZE {
+            return;
+        }
+        let mut cached = self.covers.lock().await;
+        cached.size += cover.len();
+        if let Some(old) = cached.covers.put(key, cover) {
+            cached.size -= old.len();
+        }
+        while cached.size > MAX_COVER_CACHE_SIZE {
+            match cached.covers.pop_lru() {
+                Some((_, cover)) => cached.size -= cover.len(),
+                None => break,
+            }
+        }
+    }
+
+    pub async fn clear(&self) {
+        let mut cached = self.covers.lock().await;
+        cached.covers.clear();
+        cached.size = 0;
+    }
+}
+
+#[cfg(feature = "cover-transcode")]
+async fn transcode(data: Bytes, format: CoverFormat, size: Option<u32>) -> anyhow::Result<Bytes> {
+    use image::imageops::FilterType;
+    use image::ImageFormat;
+    use std::io::Cursor;
+
+    let result = tokio::task::spawn_blocking(move || -> anyhow::Result<Vec<u8>> {
+        let image = image::load_from_memory(&data)?;
+        let image = match size {
+            Some(size) if image.width() > size || image.height() > size => {
+                image.resize(size, size, FilterType::CatmullRom)
+            }
+            _ => image,
+        };
+        let mut result = Cursor::new(Vec::new());
+        match format {
+            // jpeg does not support alpha channel
+            CoverFormat::Jpeg => image::DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_to(&mut result, ImageFormat::Jpeg)?,
+            CoverFormat::Png => image.write_to(&mut result, ImageFormat::Png)?,
+        }
+        Ok(result.into_inner())
+    })
+    .await??;
+    Ok(Bytes::from(result))
+}
+
+#[cfg(not(feature = "cover-transcode"))]
+async fn transcode(
+    _data: Bytes,
+    _format: CoverFormat,
+    _size: Option<u32>,
+) -> anyhow::Result<Bytes> {
+    anyhow::bail!("cover transcoding is not enabled, rebuild annil with `cover-transcode` feature")
+}
+
+#[cfg(all(test, feature = "cover-transcode"))]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn open(
+        data: Vec<u8>,
+    ) -> impl FnOnce() -> std::future::Ready<Result<ResourceReader, ProviderError>> {
+        move || std::future::ready(Ok(Box::pin(Cursor::new(data)) as ResourceReader))
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Cursor::new(Vec::new());
+        image::DynamicImage::new_rgba8(width, height)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        png.into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_transcode_cover() {
+        let cache = CoverCache::default();
+        let jpeg = cache
+            .get_or_transcode("album", None, CoverFormat::Jpeg, None, open(png(2, 2)))
+            .await
+            .unwrap();
+        assert_eq!(&jpeg[..2], &[0xff, 0xd8]);
+
+        // cached result is returned without opening the cover
+        let cached = cache
+            .get_or_transcode("album", None, CoverFormat::Jpeg, None, || async {
+                unreachable!("cover should not be opened on cache hit")
+            })
+            .await
+            .unwrap();
+        assert_eq!(jpeg, cached);
+
+        let png = cache
+            .get_or_transcode("album", Some(1), CoverFormat::Png, None, open(png(2, 2)))
+            .await
+            .unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+
+    #[tokio::test]
+    async fn test_resize_cover() {
+        let png = png(600, 300);
+        let cache = CoverCache::default();
+
+        let thumbnail = cache
+            .get_or_transcode(
+                "album",
+                None,
+                CoverFormat::Png,
+                Some(300),
+                open(png.clone()),
+            )
+            .await
+            .unwrap();
+        let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (300, 150));
+
+        // smaller covers are not enlarged
+        let cover = cache
+            .get_or_transcode("album", None, CoverFormat::Png, Some(1000), open(png))
+            .await
+            .unwrap();
+        let cover = image::load_from_memory(&cover).unwrap();
+        assert_eq!((cover.width(), cover.height()), (600, 300));
+
+        // resized covers are cached by size
+        let open = open(b"not an image".to_vec());
+        assert!(cache
+            .get_or_transcode("album", None, CoverFormat::Png, Some(300), open)
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn test_cover_size() {
+        assert_eq!(cover_size(1), 128);
+        assert_eq!(cover_size(128), 128);
+        assert_eq!(cover_size(300), 512);
+        assert_eq!(cover_size(1025), MAX_COVER_SIZE);
+        assert_eq!(cover_size(u32::MAX), MAX_COVER_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_transcode_once() {
+        let cache = CoverCache::default();
+        let opened = AtomicUsize::new(0);
+        let get = || {
+            cache.get_or_transcode("album", None, CoverFormat::Jpeg, None, || {
+                opened.fetch_add(1, Ordering::SeqCst);
+                open(png(64, 64))()
+            })
+        };
+        let (a, b) = tokio::join!(get(), get());
+        assert_eq!(a.unwrap(), b.unwrap());
+        assert_eq!(opened.load(Ordering::SeqCst), 1);
+        assert!(cache.pending.lock().await.is_empty());
+
+        // failures are not cached
+        let cache = CoverCache::default();
+        let result = cache
+            .get_or_transcode("album", None, CoverFormat::Jpeg, None, open(Vec::new()))
+            .await;
+        assert!(matches!(
+            result,
+            Err(CoverError::Transcode { original: Some(original), .. }) if original.is_empty()
+        ));
+        assert!(cache
+            .get_or_transcode("album", None, CoverFormat::Jpeg, None, open(png(2, 2)))
+            .await
+            .is_ok());
+    }
+}
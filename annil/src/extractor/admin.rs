@@ -0,0 +1,35 @@
+use crate::state::AnnilKeys;
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::Extension;
+use std::convert::Infallible;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+
+/// Whether the request is made by admin.
+///
+/// Admin is told by `X-Admin-Token` header matching `admin-token` in config.
+/// Unlike other extractors, requests without a valid admin token are not rejected.
+pub struct IsAdmin(pub bool);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IsAdmin
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let keys = Extension::<Arc<AnnilKeys>>::from_request_parts(parts, state)
+            .await
+            .expect("Failed to extract keys from extension. Please re-check your code first.");
+        let is_admin = !keys.admin_token.is_empty()
+            && parts
+                .headers
+                .get("X-Admin-Token")
+                // compared in constant time so the token can not be guessed by response time
+                .is_some_and(|token| token.as_bytes().ct_eq(keys.admin_token.as_bytes()).into());
+        Ok(Self(is_admin))
+    }
+}
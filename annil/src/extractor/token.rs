@@ -31,6 +31,9 @@ pub struct UserClaim {
     /// Optional `share` field, contains properties required to create [Share Token]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) share: Option<ShareToken>,
+    /// Resources the token can access. Defaults to both covers and audios.
+    #[serde(default, skip_serializing_if = "TokenScope::is_both")]
+    pub(crate) scope: TokenScope,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -48,6 +51,36 @@ pub struct ShareToken {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ShareClaim {
     pub(crate) audios: HashMap<String, HashMap<String, Vec<NonZeroU8>>>,
+    /// Resources the token can access. Defaults to both covers and audios.
+    #[serde(default, skip_serializing_if = "TokenScope::is_both")]
+    pub(crate) scope: TokenScope,
+}
+
+/// Scope of resources an Annil token can access.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    /// Covers only, e.g. for previews
+    Cover,
+    /// Audios only
+    Audio,
+    /// Both covers and audios
+    #[default]
+    Both,
+}
+
+impl TokenScope {
+    pub fn allows_cover(&self) -> bool {
+        matches!(self, TokenScope::Cover | TokenScope::Both)
+    }
+
+    pub fn allows_audio(&self) -> bool {
+        matches!(self, TokenScope::Audio | TokenScope::Both)
+    }
+
+    fn is_both(&self) -> bool {
+        *self == TokenScope::Both
+    }
 }
 
 #[async_trait]
@@ -64,35 +97,42 @@ where
             .expect("Failed to extract keys from extension. Please re-check your code first.");
 
         let metadata = Token::decode_metadata(&auth).map_err(|_| AnnilError::Unauthorized)?;
-        match metadata.key_id() {
+        let token = match metadata.key_id() {
             None => {
                 // no key_id, verify with normal token
-                if let Ok(token) = keys.sign_key.verify_token::<AnnilClaim>(&auth, None) {
-                    // if the token is signed with sign_key, it's always valid
-                    return Ok(token.custom);
-                }
+                keys.sign_key.verify_token::<AnnilClaim>(&auth, None).ok()
             }
-            Some(_) => {
+            Some(key_id) => {
                 // got key_id, verify with share token
-                if let Ok(token) = keys.share_key.verify_token::<AnnilClaim>(
-                    &auth,
-                    Some(VerificationOptions {
-                        required_key_id: Some(
-                            keys.share_key.key_id().as_deref().unwrap().to_string(),
-                        ),
-                        ..Default::default()
-                    }),
-                ) {
+                keys.share_key(key_id)
+                    .verify_token::<AnnilClaim>(
+                        &auth,
+                        Some(VerificationOptions {
+                            required_key_id: Some(key_id.to_string()),
+                            ..Default::default()
+                        }),
+                    )
+                    .ok()
                     // We MUST check whether it's a share token here
                     // otherwise, we may get a user token signed by share key
-                    if token.custom.is_guest() {
-                        return Ok(token.custom);
-                    }
-                }
+                    .filter(|token| token.custom.is_guest())
+                    // key id is the id of user token which signed it
+                    .filter(|_| !keys.revoked.is_revoked(key_id))
             }
-        }
+        };
 
-        Err(AnnilError::Unauthorized)
+        match token {
+            // tokens revoked before expiry are rejected by their ids
+            Some(token)
+                if !token
+                    .jwt_id
+                    .as_deref()
+                    .is_some_and(|jti| keys.revoked.is_revoked(jti)) =>
+            {
+                Ok(token.custom)
+            }
+            _ => Err(AnnilError::Unauthorized),
+        }
     }
 }
 
@@ -119,6 +159,13 @@ impl AnnilClaim {
         }
     }
 
+    pub(crate) fn scope(&self) -> TokenScope {
+        match self {
+            AnnilClaim::User(u) => u.scope,
+            AnnilClaim::Share(s) => s.scope,
+        }
+    }
+
     #[inline]
     pub(crate) fn is_guest(&self) -> bool {
         matches!(self, AnnilClaim::Share(_))
@@ -141,6 +188,7 @@ fn test_sign() {
             custom: AnnilClaim::User(UserClaim {
                 user_id: "test".to_string(),
                 share: None,
+                scope: TokenScope::Both,
             }),
         })
         .expect("failed to sign jwt");
@@ -0,0 +1,136 @@
+use axum::http::header::IF_NONE_MATCH;
+use axum::http::HeaderMap;
+use serde::Deserialize;
+
+/// `Cache-Control` of covers when `[server.http-cache]` is not configured.
+pub const DEFAULT_COVER_CACHE_CONTROL: &str = "public, max-age=31536000";
+
+/// Cache directives of audio and cover responses, for annil fronted by CDN.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct HttpCacheConfig {
+    /// Allow shared caches(e.g. CDN) to store audio responses
+    #[serde(default = "default_true")]
+    pub public: bool,
+    /// `max-age` of audio responses in seconds
+    #[serde(default = "default_max_age")]
+    pub audio_max_age: u64,
+    /// Mark audio responses as `immutable`.
+    ///
+    /// Audio content is stable for each (album, disc, track), so it's safe to enable it.
+    #[serde(default = "default_true")]
+    pub audio_immutable: bool,
+    /// `max-age` of cover responses in seconds
+    #[serde(default = "default_max_age")]
+    pub cover_max_age: u64,
+}
+
+impl Default for HttpCacheConfig {
+    fn default() -> Self {
+        Self {
+            public: true,
+            audio_max_age: default_max_age(),
+            audio_immutable: true,
+            cover_max_age: default_max_age(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_age() -> u64 {
+    31536000
+}
+
+impl HttpCacheConfig {
+    pub fn audio_cache_control(&self) -> String {
+        let visibility = if self.public { "public" } else { "private" };
+        let mut result = format!("{visibility}, max-age={}", self.audio_max_age);
+        if self.audio_immutable {
+            result += ", immutable";
+        }
+        result
+    }
+
+    pub fn cover_cache_control(&self) -> String {
+        format!("public, max-age={}", self.cover_max_age)
+    }
+}
+
+/// Strong `ETag` of an audio file, or a weak one if the audio would be transcoded.
+pub fn audio_etag(
+    album_id: &str,
+    disc_id: u8,
+    track_id: u8,
+    size: usize,
+    transcoded: Option<&str>,
+) -> String {
+    match transcoded {
+        Some(quality) => format!(r#"W/"{album_id}-{disc_id}-{track_id}-{size}-{quality}""#),
+        None => format!(r#""{album_id}-{disc_id}-{track_id}-{size}""#),
+    }
+}
+
+/// `ETag` of a cover, derived from its content.
+pub fn cover_etag(cover: &[u8]) -> String {
+    let hash = hmac_sha256::Hash::hash(cover);
+    let hash: String = hash[..8].iter().map(|b| format!("{b:02x}")).collect();
+    format!(r#""cover-{hash}""#)
+}
+
+/// Whether `If-None-Match` in request matches `etag`.
+///
+/// Weak comparison is used as required by RFC 9110.
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_cache_control() {
+        let config = HttpCacheConfig::default();
+        assert_eq!(
+            config.audio_cache_control(),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(config.cover_cache_control(), DEFAULT_COVER_CACHE_CONTROL);
+
+        let config: HttpCacheConfig =
+            toml::from_str("public = false\naudio-max-age = 60\naudio-immutable = false").unwrap();
+        assert_eq!(config.audio_cache_control(), "private, max-age=60");
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let etag = audio_etag("album", 1, 2, 1024, None);
+        let weak = audio_etag("album", 1, 2, 1024, Some("low"));
+        assert_eq!(etag, r#""album-1-2-1024""#);
+        assert_eq!(weak, r#"W/"album-1-2-1024-low""#);
+
+        let mut headers = HeaderMap::new();
+        assert!(!if_none_match(&headers, &etag));
+
+        headers.insert(
+            IF_NONE_MATCH,
+            HeaderValue::from_static(r#""other", W/"album-1-2-1024""#),
+        );
+        assert!(if_none_match(&headers, &etag));
+        assert!(!if_none_match(&headers, &weak));
+
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert!(if_none_match(&headers, &weak));
+    }
+}
@@ -1,37 +1,113 @@
 #![feature(int_roundings)]
 
+pub mod access_log;
+pub mod config;
+pub mod cover;
+pub mod cors;
 pub mod extractor;
+pub mod http_cache;
+pub mod listen;
+pub mod metrics;
+pub mod mime;
 pub mod provider;
+pub mod range;
+pub mod rate_limit;
+pub mod revoke;
 pub mod route;
 pub mod state;
 pub mod utils;
 
 pub mod metadata;
-mod transcode;
+pub mod transcode;
 
 pub mod error {
+    use anni_provider::ProviderError;
+    use axum::http::header::RETRY_AFTER;
     use axum::http::StatusCode;
     use axum::response::{IntoResponse, Response};
+    use axum::Json;
     use thiserror::Error;
 
     #[derive(Error, Debug)]
     pub enum AnnilError {
         #[error("unauthorized")]
         Unauthorized,
+        /// Token is valid, but its scope does not cover the requested resource.
+        #[error("forbidden")]
+        Forbidden,
         #[error("unknown path")]
         UnknownPath,
         #[error("not found")]
         NotFound,
+        /// A catalog was given where an album is expected, but it maps to multiple albums.
+        /// Contains candidate album ids, and clients should retry with one of them.
+        #[error("catalog matches multiple albums")]
+        AmbiguousCatalog(Vec<String>),
+        /// Provider did not respond in time.
+        #[error("provider timed out")]
+        Timeout,
+        /// Provider requested by admin does not exist.
+        #[error("unknown provider")]
+        UnknownProvider,
+        /// Metadata database can not be opened or read.
+        #[error("metadata database is unavailable")]
+        DatabaseUnavailable,
+        /// Requested transcoding codec is unknown or not enabled.
+        #[error("unsupported codec")]
+        UnsupportedCodec,
+        /// Requested transcoding bitrate can not be parsed or is out of range.
+        #[error("invalid bitrate")]
+        InvalidBitrate,
+        /// Share secret is requested for a token limited to covers or audios.
+        /// Share tokens are signed by clients, so their scope can not be limited by the parent token.
+        #[error("share is only allowed for tokens with both scope")]
+        ScopedShare,
+        /// Too many requests are made by the token.
+        /// Contains seconds to wait before the next request.
+        #[error("rate limited")]
+        RateLimited(u64),
+    }
+
+    impl From<ProviderError> for AnnilError {
+        fn from(error: ProviderError) -> Self {
+            match error {
+                ProviderError::Timeout { .. } => AnnilError::Timeout,
+                ProviderError::DatabaseUnavailable(_) => AnnilError::DatabaseUnavailable,
+                _ => AnnilError::NotFound,
+            }
+        }
     }
 
     impl IntoResponse for AnnilError {
         fn into_response(self) -> Response {
             match self {
-                AnnilError::Unauthorized => StatusCode::UNAUTHORIZED,
-                AnnilError::UnknownPath => StatusCode::FORBIDDEN,
-                AnnilError::NotFound => StatusCode::NOT_FOUND,
+                AnnilError::Unauthorized => StatusCode::UNAUTHORIZED.into_response(),
+                AnnilError::Forbidden => StatusCode::FORBIDDEN.into_response(),
+                AnnilError::UnknownPath => StatusCode::FORBIDDEN.into_response(),
+                AnnilError::NotFound => StatusCode::NOT_FOUND.into_response(),
+                AnnilError::AmbiguousCatalog(candidates) => {
+                    (StatusCode::CONFLICT, Json(candidates)).into_response()
+                }
+                AnnilError::Timeout => StatusCode::GATEWAY_TIMEOUT.into_response(),
+                AnnilError::UnknownProvider => StatusCode::BAD_REQUEST.into_response(),
+                AnnilError::DatabaseUnavailable => (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "metadata database is unavailable, please retry later",
+                )
+                    .into_response(),
+                AnnilError::UnsupportedCodec => StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response(),
+                AnnilError::InvalidBitrate => StatusCode::BAD_REQUEST.into_response(),
+                AnnilError::ScopedShare => (
+                    StatusCode::BAD_REQUEST,
+                    "share is only allowed for tokens with both scope",
+                )
+                    .into_response(),
+                AnnilError::RateLimited(retry_after) => (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(RETRY_AFTER, retry_after.to_string())],
+                )
+                    .into_response(),
             }
-            .into_response()
         }
     }
 }
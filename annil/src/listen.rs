@@ -0,0 +1,201 @@
+use axum::{Router, Server};
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Address to listen on, configured by `server.listen`.
+///
+/// It's either a socket address like `127.0.0.1:3614`, or a unix domain socket path prefixed
+/// by `unix:`, like `unix:/run/annil.sock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("unix:") {
+            #[cfg(unix)]
+            Some(path) if !path.is_empty() => Ok(ListenAddr::Unix(PathBuf::from(path))),
+            #[cfg(unix)]
+            Some(_) => anyhow::bail!("empty unix socket path in listen address"),
+            #[cfg(not(unix))]
+            Some(_) => anyhow::bail!("unix domain sockets are not supported on this platform"),
+            None => Ok(ListenAddr::Tcp(s.parse()?)),
+        }
+    }
+}
+
+impl Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{addr}"),
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl ListenAddr {
+    /// Serve `app` on this address until `shutdown` completes or an error occurs.
+    ///
+    /// After `shutdown` completes, no more connections are accepted,
+    /// and it returns after existing connections are closed.
+    pub async fn serve<F>(self, app: Router, shutdown: F) -> anyhow::Result<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        log::info!("Listening on {self}");
+        match self {
+            ListenAddr::Tcp(addr) => {
+                Server::bind(&addr)
+                    .serve(app.into_make_service())
+                    .with_graceful_shutdown(shutdown)
+                    .await?
+            }
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => unix::serve(&path, app, shutdown).await?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use axum::Router;
+    use hyper::server::conn::Http;
+    use std::fs::{self, Permissions};
+    use std::future::Future;
+    use std::io;
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::path::Path;
+    use std::time::Duration;
+    use tokio::net::UnixListener;
+    use tokio::sync::watch;
+
+    /// Permission of the socket file, which allows the owner and its group(e.g. reverse proxy)
+    /// to connect.
+    const SOCKET_MODE: u32 = 0o660;
+
+    /// Time to wait before accepting again after a failed accept.
+    const ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+    pub(super) async fn serve<F>(path: &Path, app: Router, shutdown: F) -> io::Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        let listener = bind(path)?;
+        // connections are told to shut down by sending to this channel,
+        // and it's closed after all connections are closed
+        let (shutdown_tx, shutdown_rx) = watch::channel(());
+        tokio::pin!(shutdown);
+        loop {
+            let stream = tokio::select! {
+                result = listener.accept() => match result {
+                    Ok((stream, _)) => stream,
+                    Err(e) if is_connection_error(&e) => continue,
+                    Err(e) => {
+                        // errors like EMFILE would be returned again immediately,
+                        // so wait for some connections to close, like hyper does for tcp
+                        log::warn!("Failed to accept connection: {e}");
+                        tokio::select! {
+                            _ = tokio::time::sleep(ACCEPT_BACKOFF) => continue,
+                            _ = &mut shutdown => break,
+                        }
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+            let app = app.clone();
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                let conn = Http::new().serve_connection(stream, app);
+                tokio::pin!(conn);
+                let result = tokio::select! {
+                    result = conn.as_mut() => result,
+                    _ = shutdown_rx.changed() => {
+                        conn.as_mut().graceful_shutdown();
+                        conn.await
+                    }
+                };
+                if let Err(e) = result {
+                    log::debug!("Failed to serve connection: {e}");
+                }
+            });
+        }
+
+        drop(listener);
+        drop(shutdown_rx);
+        let _ = shutdown_tx.send(());
+        shutdown_tx.closed().await;
+        fs::remove_file(path)
+    }
+
+    /// Errors of a single connection, after which the next one can be accepted immediately.
+    fn is_connection_error(e: &io::Error) -> bool {
+        matches!(
+            e.kind(),
+            io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::ConnectionReset
+        )
+    }
+
+    /// Bind to `path`, removing stale socket left by previous runs.
+    fn bind(path: &Path) -> io::Result<UnixListener> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => {
+                if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("socket {} is in use", path.display()),
+                    ));
+                }
+                log::info!("Removing stale socket {}", path.display());
+                fs::remove_file(path)?;
+            }
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, Permissions::from_mode(SOCKET_MODE))?;
+        Ok(listener)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listen_addr() {
+        assert_eq!(
+            "127.0.0.1:3614".parse::<ListenAddr>().unwrap(),
+            ListenAddr::Tcp("127.0.0.1:3614".parse().unwrap())
+        );
+        assert!("localhost".parse::<ListenAddr>().is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_parse_unix_listen_addr() {
+        assert_eq!(
+            "unix:/run/annil.sock".parse::<ListenAddr>().unwrap(),
+            ListenAddr::Unix(PathBuf::from("/run/annil.sock"))
+        );
+        assert!("unix:".parse::<ListenAddr>().is_err());
+    }
+}
@@ -1,166 +1,129 @@
-use config::{Config, ProviderItem};
-
-use anni_provider::cache::{Cache, CachePool};
-use anni_provider::fs::LocalFileSystemProvider;
-use anni_provider::providers::drive::DriveProviderSettings;
-use anni_provider::providers::{
-    CommonConventionProvider, CommonStrictProvider, DriveProvider, MultipleProviders,
-};
-use anni_provider::AnniProvider;
-use annil::metadata::MetadataConfig;
-use annil::provider::AnnilProvider;
+use anni_provider::cache::CachePool;
+use anni_provider::providers::MultipleProviders;
+use annil::config::Config;
+use annil::listen::ListenAddr;
+use annil::metadata::{lazy_db, LazyDb};
+use annil::metrics::{track_audio_stream, track_requests, Metrics};
+use annil::provider::{init_provider, AnnilProvider};
+use annil::rate_limit::{self, RateLimiter};
+use annil::revoke::RevocationList;
 use annil::route::admin;
+use annil::route::health;
+use annil::route::metrics;
 use annil::route::user;
 use annil::state::{AnnilKeys, AnnilState};
-use axum::http::Method;
+use axum::middleware;
 use axum::routing::{get, post};
-use axum::{Extension, Router, Server};
+use axum::{Extension, Router};
+use futures::future::join_all;
 use jwt_simple::prelude::HS256Key;
+use notify::{RecursiveMode, Watcher};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
 use std::collections::HashMap;
-use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::RwLock;
-use tower_http::cors;
-use tower_http::cors::CorsLayer;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{oneshot, RwLock};
 
-async fn init_state(
-    config: Config,
-) -> anyhow::Result<(AnnilState, AnnilProvider<MultipleProviders>, AnnilKeys)> {
-    #[cfg(feature = "metadata")]
-    let mut db = config.metadata.clone().map(MetadataConfig::into_db);
+/// Initialize providers, with cache pools and metadata database used by them.
+///
+/// Pools in `pools` which are still configured are reused, so that cached items are kept on reload
+/// and two pools never work on the same root.
+async fn init_providers(
+    config: &Config,
+    pools: &HashMap<String, Arc<CachePool>>,
+) -> anyhow::Result<(
+    MultipleProviders,
+    HashMap<String, Arc<CachePool>>,
+    Option<LazyDb>,
+)> {
+    let mut db = lazy_db(&config.metadata);
 
     log::info!("Start initializing providers...");
     let now = SystemTime::now();
     let mut providers = Vec::with_capacity(config.providers.len());
-    let mut caches = HashMap::new();
+    let mut names = Vec::with_capacity(config.providers.len());
+    let cache_sizes = config.cache_pool_sizes();
+    let mut caches: HashMap<_, _> = pools
+        .iter()
+        .filter(|(key, _)| cache_sizes.contains_key(*key))
+        .map(|(key, pool)| (key.clone(), pool.clone()))
+        .collect();
+    let mut timings = Vec::with_capacity(config.providers.len());
 
     for (provider_name, provider_config) in config.providers.iter() {
         log::debug!("Initializing provider: {}", provider_name);
-        let mut provider: Box<dyn AnniProvider + Send + Sync> =
-            match (&provider_config.item, &mut db) {
-                (
-                    ProviderItem::File {
-                        root,
-                        strict: false,
-                        ..
-                    },
-                    Some(db),
-                ) => Box::new(
-                    CommonConventionProvider::new(
-                        PathBuf::from(root),
-                        db.open()?,
-                        Box::new(LocalFileSystemProvider),
-                    )
-                    .await?,
-                ),
-                (
-                    ProviderItem::File {
-                        root,
-                        strict: true,
-                        layer,
-                    },
-                    _,
-                ) => Box::new(
-                    CommonStrictProvider::new(
-                        PathBuf::from(root),
-                        *layer,
-                        Box::new(LocalFileSystemProvider),
-                    )
-                    .await?,
-                ),
-                (
-                    ProviderItem::Drive {
-                        drive_id,
-                        corpora,
-                        initial_token_path,
-                        token_path,
-                        strict: false,
-                    },
-                    Some(db),
-                ) => {
-                    if let Some(initial_token_path) = initial_token_path {
-                        if initial_token_path.exists() && !token_path.exists() {
-                            let _ = std::fs::copy(initial_token_path, token_path.clone());
-                        }
-                    }
-                    Box::new(
-                        DriveProvider::new(
-                            Default::default(),
-                            DriveProviderSettings {
-                                corpora: corpora.to_string(),
-                                drive_id: drive_id.clone(),
-                            },
-                            Some(db.open()?),
-                            token_path.clone(),
-                        )
-                        .await?,
-                    )
-                }
-                (
-                    ProviderItem::Drive {
-                        drive_id,
-                        corpora,
-                        initial_token_path,
-                        token_path,
-                        strict: true,
-                    },
-                    _,
-                ) => {
-                    if let Some(initial_token_path) = initial_token_path {
-                        if initial_token_path.exists() && !token_path.exists() {
-                            let _ = std::fs::copy(initial_token_path, token_path.clone());
-                        }
-                    }
-                    Box::new(
-                        DriveProvider::new(
-                            Default::default(),
-                            DriveProviderSettings {
-                                corpora: corpora.to_string(),
-                                drive_id: drive_id.clone(),
-                            },
-                            None,
-                            token_path.clone(),
-                        )
-                        .await?,
-                    )
-                }
-                (_, None) => {
-                    log::error!(
-                        "Metadata is not configured, but provider {} requires it.",
-                        provider_name
-                    );
-                    continue;
-                }
-            };
-        if let Some(cache) = provider_config.cache() {
-            log::debug!(
-                "Cache configuration detected: root = {}, max-size = {}",
-                cache.root,
-                cache.max_size
-            );
-            if !caches.contains_key(&cache.root) {
-                // new cache pool
-                let pool = CachePool::new(&cache.root, cache.max_size);
-                caches.insert(cache.root.to_string(), Arc::new(pool));
-            }
-            provider = Box::new(Cache::new(provider, caches[&cache.root].clone()));
-        }
+        let provider_now = Instant::now();
+        let provider = match init_provider(
+            provider_name,
+            provider_config,
+            config.server.provider_timeout_secs,
+            &mut db,
+            &mut caches,
+            &cache_sizes,
+        )
+        .await?
+        {
+            Some(provider) => provider,
+            None => continue,
+        };
         providers.push(provider);
+        names.push(provider_name.to_string());
+
+        let elapsed = provider_now.elapsed();
+        log::info!(
+            "Provider {} initialized in {}ms",
+            provider_name,
+            elapsed.as_millis()
+        );
+        timings.push((provider_name, elapsed));
     }
     log::info!(
         "Provider initialization finished, used {:?}",
         now.elapsed().unwrap()
     );
+    if timings.len() > 1 {
+        timings.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let slowest = timings
+            .iter()
+            .take(5)
+            .map(|(name, elapsed)| format!("{name} ({}ms)", elapsed.as_millis()))
+            .collect::<Vec<_>>();
+        log::info!("Slowest providers: {}", slowest.join(", "));
+    }
+
+    let providers = MultipleProviders::new(providers)
+        .with_names(names)
+        .with_routing(config.server.routing.into());
+    Ok((providers, caches, db))
+}
+
+/// Open metadata database shared by requests of album metadata.
+async fn open_database(db: Option<LazyDb>) -> Option<anni_provider::RepoDatabaseRead> {
+    match db?.open().await {
+        Ok(db) => Some(db),
+        Err(e) => {
+            log::error!("Failed to open metadata database: {e}");
+            None
+        }
+    }
+}
 
-    let providers = AnnilProvider::new(MultipleProviders::new(providers));
+async fn init_state(
+    config: Config,
+) -> anyhow::Result<(AnnilState, AnnilProvider<MultipleProviders>, AnnilKeys)> {
+    let (providers, caches, db) = init_providers(&config, &HashMap::new()).await?;
+    let providers = AnnilProvider::new(providers);
     let etag = providers.compute_etag().await?;
 
     // key
     let sign_key = HS256Key::from_bytes(config.server.sign_key.as_ref());
     let share_key = HS256Key::from_bytes(config.server.share_key.as_ref())
         .with_key_id(&config.server.share_key_id);
+    let revoked = match config.server.revocation_file {
+        Some(path) => RevocationList::load(path)?,
+        None => Default::default(),
+    };
     let version = format!("Annil v{}", env!("CARGO_PKG_VERSION"));
     let last_update = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -170,18 +133,100 @@ async fn init_state(
         AnnilState {
             version,
             metadata: config.metadata,
+            database: std::sync::Mutex::new(open_database(db).await),
+            provider_timeout_secs: config.server.provider_timeout_secs,
             last_update: RwLock::new(last_update),
             etag: RwLock::new(etag),
+            max_response_size: config.server.max_response_size.filter(|size| *size > 0),
+            http_cache: config.server.http_cache,
+            cover_format: config.server.cover_format,
+            mime: config.server.mime,
+            transcode: config.server.transcode,
+            covers: Default::default(),
+            caches: RwLock::new(caches),
         },
         providers,
         AnnilKeys {
             sign_key,
             share_key,
             admin_token: config.server.admin_token,
+            revoked,
         },
     ))
 }
 
+/// Watch config file and reload providers on change.
+///
+/// If the new config fails to parse or initialize, the old providers are kept.
+async fn watch_config(
+    config_path: PathBuf,
+    state: Arc<AnnilState>,
+    provider: Arc<AnnilProvider<MultipleProviders>>,
+) -> anyhow::Result<()> {
+    let config_path = config_path.canonicalize()?;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut debouncer = new_debouncer(
+        Duration::from_secs(2),
+        None,
+        move |result: DebounceEventResult| {
+            let _ = tx.send(result);
+        },
+    )?;
+    // watch parent directory, as editors may replace the file on save
+    let watch_root = config_path.parent().unwrap_or(&config_path);
+    debouncer
+        .watcher()
+        .watch(watch_root, RecursiveMode::NonRecursive)?;
+    log::info!("Watching config file {}", config_path.display());
+
+    while let Some(result) = rx.recv().await {
+        let events = match result {
+            Ok(events) => events,
+            Err(errors) => {
+                log::error!("Failed to watch config file: {:?}", errors);
+                continue;
+            }
+        };
+        if !events.iter().any(|event| event.path == config_path) {
+            continue;
+        }
+
+        log::info!("Config file changed, reloading providers...");
+        let config = match Config::from_file(&config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                log::error!("Failed to parse config file, keeping old config: {}", e);
+                continue;
+            }
+        };
+        let pools = state.caches.read().await.clone();
+        let (providers, caches, db) = match init_providers(&config, &pools).await {
+            Ok(result) => result,
+            Err(e) => {
+                log::error!("Failed to initialize providers, keeping old config: {}", e);
+                continue;
+            }
+        };
+
+        *provider.write().await = providers;
+        *state.caches.write().await = caches;
+        let database = open_database(db).await;
+        *state.database.lock().unwrap() = database;
+        state.covers.clear().await;
+        match provider.compute_etag().await {
+            Ok(etag) => *state.etag.write().await = etag,
+            Err(e) => log::error!("Failed to compute etag: {:?}", e),
+        }
+        *state.last_update.write().await = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        log::info!("Providers reloaded.");
+    }
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::builder()
@@ -189,128 +234,135 @@ async fn main() -> anyhow::Result<()> {
         .parse_env("ANNI_LOG")
         .filter_module("sqlx::query", log::LevelFilter::Warn)
         .init();
-    let config = Config::from_file(
+    let config_path = PathBuf::from(
         std::env::args()
             .nth(1)
             .unwrap_or_else(|| "config.toml".to_owned()),
-    )?;
-    let listen: SocketAddr = config.server.listen.parse()?;
+    );
+    let config = Config::from_file(&config_path)?;
+    let listen: ListenAddr = config.server.listen.parse()?;
+    let watch = config.server.watch_config;
+    let cors = config.server.cors.layer();
+    let shutdown_timeout = Duration::from_secs(config.server.shutdown_timeout_secs);
+    let requests_per_minute = config.server.rate_limit;
+    let access_log = config.server.access_log.clone();
+    let request_metrics = Arc::new(Metrics::new(config.server.metrics_admin_only));
     let (state, provider, keys) = init_state(config).await?;
+    let state = Arc::new(state);
+    let provider = Arc::new(provider);
+
+    if watch {
+        let state = state.clone();
+        let provider = provider.clone();
+        tokio::spawn(async move {
+            if let Err(e) = watch_config(config_path, state, provider).await {
+                log::error!("Failed to watch config file: {}", e);
+            }
+        });
+    }
 
     type Provider = MultipleProviders;
+    let audio = get(user::audio::<Provider>).head(user::audio_head::<Provider>);
+    // limiter runs after extensions are added, and before audio handlers
+    let audio = match requests_per_minute.filter(|limit| *limit > 0) {
+        Some(limit) => audio
+            .route_layer(middleware::from_fn(rate_limit::rate_limit))
+            .layer(Extension(Arc::new(RateLimiter::new(limit)))),
+        None => audio,
+    };
+    let audio = audio.route_layer(middleware::from_fn(track_audio_stream));
     let app = Router::new()
         .route("/info", get(user::info))
         .route("/albums", get(user::albums::<Provider>))
-        .route(
-            "/:album_id/:disc_id/:track_id",
-            get(user::audio::<Provider>).head(user::audio_head::<Provider>),
-        )
+        .route("/:album_id/:disc_id/:track_id", audio)
         .route("/:album_id/cover", get(user::cover::<Provider>))
-        .route("/:album_id/:disc_id/cover", get(user::cover::<Provider>))
-        .layer(
-            CorsLayer::new()
-                .allow_methods([Method::GET])
-                .allow_origin(cors::Any)
-                .allow_headers(cors::Any),
-        )
+        .route("/:album_id/:disc_id/cover", get(user::cover::<Provider>));
+    #[cfg(feature = "metadata")]
+    let app = app.route("/album/:album_id", get(user::album));
+    let app = app
+        .layer(cors)
         .route("/admin/sign", post(admin::sign))
         .route("/admin/reload", post(admin::reload::<Provider>))
-        .layer(Extension(Arc::new(state)))
-        .layer(Extension(Arc::new(provider)))
-        .layer(Extension(Arc::new(keys)));
-
-    Server::bind(&listen)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
-
-    Ok(())
-}
-
-mod config {
-    use annil::metadata::MetadataConfig;
-    use serde::Deserialize;
-    use std::collections::HashMap;
-    use std::fs;
-    use std::path::{Path, PathBuf};
-
-    #[derive(Deserialize)]
-    pub struct Config {
-        pub server: ServerConfig,
-        pub metadata: Option<MetadataConfig>,
-        #[serde(rename = "backends")]
-        pub providers: HashMap<String, ProviderConfig>,
-    }
+        .route("/admin/revoke", post(admin::revoke))
+        .route(
+            "/admin/etag/recompute",
+            post(admin::recompute_etag::<Provider>),
+        )
+        .route("/admin/albums", get(admin::albums::<Provider>))
+        .route("/admin/providers", post(admin::providers))
+        .route("/admin/health", get(admin::health))
+        .route("/admin/providers/add", post(admin::add_provider))
+        .route("/admin/cache/stats", post(admin::cache_stats))
+        .route("/admin/cache/verify", post(admin::cache_verify))
+        .route("/healthz", get(health::healthz))
+        .route("/metrics", get(metrics::metrics));
+    // layers only wrap routes added before them, so they come after all routes
+    let app = app.layer(middleware::from_fn(track_requests));
+    let app = match access_log {
+        Some(config) => app
+            .layer(middleware::from_fn(annil::access_log::access_log))
+            .layer(Extension(Arc::new(config))),
+        None => app,
+    };
+    let app = app
+        .layer(Extension(state.clone()))
+        .layer(Extension(provider))
+        .layer(Extension(Arc::new(keys)))
+        .layer(Extension(request_metrics));
 
-    impl Config {
-        pub fn from_file<P: AsRef<Path>>(config_path: P) -> anyhow::Result<Self> {
-            let string = fs::read_to_string(config_path)?;
-            let result = toml::from_str(&string)?;
-            Ok(result)
-        }
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let mut server = tokio::spawn(listen.serve(app, async move {
+        let _ = shutdown_rx.await;
+    }));
+    tokio::select! {
+        result = &mut server => return result?,
+        _ = shutdown_signal() => {}
     }
 
-    #[derive(Deserialize)]
-    #[serde(rename_all = "kebab-case")]
-    pub struct ServerConfig {
-        /// Server name
-        pub name: String,
-        /// Port to listen on
-        pub listen: String,
-        /// HMAC key for JWT
-        #[serde(rename = "hmac-key")]
-        pub sign_key: String,
-        pub share_key: String,
-        pub share_key_id: String,
-        /// Password to reload data
-        pub admin_token: String,
+    log::info!(
+        "Shutting down, waiting at most {}s for connections and cache fills",
+        shutdown_timeout.as_secs()
+    );
+    let _ = shutdown_tx.send(());
+    let caches: Vec<_> = state.caches.read().await.values().cloned().collect();
+    let fills = join_all(caches.iter().map(|pool| pool.wait_fills(shutdown_timeout)));
+    let (server, fills) = tokio::join!(tokio::time::timeout(shutdown_timeout, server), fills);
+    match server {
+        Ok(result) => result??,
+        Err(_) => log::warn!("Connections were not closed in time"),
     }
-
-    #[derive(Deserialize)]
-    pub struct ProviderConfig {
-        #[serde(flatten)]
-        pub item: ProviderItem,
-        cache: Option<CacheConfig>,
+    if fills.contains(&false) {
+        log::warn!("Cache fills were not finished in time, unfinished items are dropped");
     }
+    Ok(())
+}
 
-    impl ProviderConfig {
-        #[inline]
-        pub fn cache(&self) -> Option<&CacheConfig> {
-            self.cache.as_ref()
+/// Wait for Ctrl-C, or SIGTERM on unix.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
         }
-    }
-
-    #[derive(Deserialize)]
-    #[serde(tag = "type")]
-    pub enum ProviderItem {
-        #[serde(rename = "file")]
-        #[serde(rename_all = "kebab-case")]
-        File {
-            root: String,
-            strict: bool,
-            #[serde(default = "default_layer")]
-            layer: usize,
-        },
-        #[serde(rename = "drive")]
-        #[serde(rename_all = "kebab-case")]
-        Drive {
-            corpora: String,
-            drive_id: Option<String>,
-            initial_token_path: Option<PathBuf>,
-            token_path: PathBuf,
-            #[serde(default)]
-            strict: bool,
-        },
-    }
-
-    const fn default_layer() -> usize {
-        2
-    }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                log::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-    #[derive(Deserialize)]
-    pub struct CacheConfig {
-        pub root: String,
-        #[serde(default, rename = "max-size")]
-        pub max_size: usize,
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
     }
 }
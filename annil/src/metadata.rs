@@ -9,26 +9,58 @@ pub struct MetadataConfig {
     #[serde(default = "default_true")]
     pub pull: bool,
     pub proxy: Option<String>,
+    /// Maximum retries of clone and pull on network errors.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Seconds to wait before the first retry, doubled after each retry.
+    #[serde(default = "default_retry_backoff")]
+    pub retry_backoff: u64,
+    /// Load the whole metadata database into memory at startup.
+    #[serde(default)]
+    pub in_memory: bool,
+}
+
+impl MetadataConfig {
+    /// Path of metadata database generated from the repository.
+    pub fn database_path(&self) -> PathBuf {
+        self.base.join("repo.db")
+    }
 }
 
 fn default_true() -> bool {
     true
 }
 
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_retry_backoff() -> u64 {
+    1
+}
+
 #[cfg(feature = "metadata")]
 impl MetadataConfig {
+    pub fn retry_policy(&self) -> anni_repo::RetryPolicy {
+        anni_repo::RetryPolicy {
+            retries: self.retries,
+            backoff: std::time::Duration::from_secs(self.retry_backoff),
+        }
+    }
+
     pub fn init(&self) -> anyhow::Result<PathBuf> {
         use anni_repo::RepositoryManager;
 
         log::info!("Fetching metadata repository...");
 
         let repo_root = self.base.join("repo");
-        let repo = if !repo_root.exists() {
+        // repo.toml is missing if a previous clone was interrupted, resume it in this case
+        let repo = if !repo_root.join("repo.toml").exists() {
             log::debug!("Cloning metadata repository from {}", self.repo);
-            RepositoryManager::clone(&self.repo, repo_root)?
+            RepositoryManager::clone_with_retry(&self.repo, repo_root, self.retry_policy())?
         } else if self.pull {
             log::debug!("Updating metadata repository at branch: {}", self.branch);
-            RepositoryManager::pull(repo_root, &self.branch)?
+            RepositoryManager::pull_with_retry(repo_root, &self.branch, self.retry_policy())?
         } else {
             log::debug!("Loading metadata repository at {}", repo_root.display());
             RepositoryManager::new(repo_root)?
@@ -36,7 +68,7 @@ impl MetadataConfig {
 
         log::debug!("Generating metadata database...");
         let repo = repo.into_owned_manager()?;
-        let database_path = self.base.join("repo.db");
+        let database_path = self.database_path();
         repo.to_database(&database_path)?;
 
         log::info!("Metadata repository fetched.");
@@ -64,6 +96,28 @@ impl MetadataConfig {
     }
 }
 
+/// Metadata database of `metadata`, which is opened on first use.
+///
+/// It's always `None` without `metadata` feature, so providers requiring metadata are not built.
+pub fn lazy_db(metadata: &Option<MetadataConfig>) -> Option<LazyDb> {
+    #[cfg(feature = "metadata")]
+    let db = metadata.clone().map(MetadataConfig::into_db);
+    #[cfg(not(feature = "metadata"))]
+    let db = metadata.as_ref().and(None);
+    db
+}
+
+/// Placeholder of metadata database without `metadata` feature, which can not be created.
+#[cfg(not(feature = "metadata"))]
+pub enum LazyDb {}
+
+#[cfg(not(feature = "metadata"))]
+impl LazyDb {
+    pub async fn open(&mut self) -> anyhow::Result<anni_provider::RepoDatabaseRead> {
+        match *self {}
+    }
+}
+
 #[cfg(feature = "metadata")]
 pub struct LazyDb {
     metadata: MetadataConfig,
@@ -72,14 +126,25 @@ pub struct LazyDb {
 
 #[cfg(feature = "metadata")]
 impl LazyDb {
-    pub fn open(&mut self) -> anyhow::Result<anni_repo::db::RepoDatabaseRead> {
+    pub async fn open(&mut self) -> anyhow::Result<anni_repo::db::RepoDatabaseRead> {
         let db = match self.db_path {
             Some(ref p) => p,
             None => {
-                let p = self.metadata.init()?;
+                // git operations and waits between their retries block
+                let metadata = self.metadata.clone();
+                let p = tokio::task::spawn_blocking(move || metadata.init()).await??;
                 self.db_path.insert(p)
             }
         };
-        Ok(anni_repo::db::RepoDatabaseRead::new(db)?)
+        if !self.metadata.in_memory {
+            return Ok(anni_repo::db::RepoDatabaseRead::new(db)?);
+        }
+
+        let size = std::fs::metadata(&db).map(|m| m.len()).unwrap_or(0);
+        log::info!(
+            "Loading metadata database into memory, which takes about {:.2} MiB of RAM",
+            size as f64 / 1024.0 / 1024.0
+        );
+        Ok(anni_repo::db::RepoDatabaseRead::new_in_memory(db)?)
     }
 }
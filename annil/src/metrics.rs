@@ -0,0 +1,247 @@
+use axum::body::{boxed, BoxBody, Bytes, HttpBody};
+use axum::extract::MatchedPath;
+use axum::http::{HeaderMap, Method, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Extension;
+use hyper::body::SizeHint;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Upper bounds in seconds of buckets of audio stream durations.
+const STREAM_DURATION_BUCKETS: [f64; 9] = [0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 600.0];
+
+/// Route label of requests which match no route, so that unknown paths do not add new labels.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Counters exposed by `GET /metrics` in Prometheus text format.
+///
+/// Cache counters are read from cache pools when rendering, others are recorded by
+/// [track_requests] and [track_audio_stream].
+#[derive(Default)]
+pub struct Metrics {
+    /// Whether `GET /metrics` requires admin token
+    pub admin_only: bool,
+    /// Number of responses by route pattern and status
+    requests: RwLock<HashMap<String, HashMap<u16, AtomicU64>>>,
+    /// Number of audio streams by bucket in [STREAM_DURATION_BUCKETS], and the last one is `+Inf`
+    stream_buckets: [AtomicU64; STREAM_DURATION_BUCKETS.len() + 1],
+    /// Total duration of audio streams in microseconds
+    stream_micros: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new(admin_only: bool) -> Self {
+        Self {
+            admin_only,
+            ..Default::default()
+        }
+    }
+
+    /// Count a response of `route` with `status`.
+    ///
+    /// Maps are only allocated the first time a route or status is seen.
+    pub fn record_request(&self, route: &str, status: u16) {
+        if let Some(count) = self
+            .requests
+            .read()
+            .unwrap()
+            .get(route)
+            .and_then(|statuses| statuses.get(&status))
+        {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.requests
+            .write()
+            .unwrap()
+            .entry(route.to_string())
+            .or_default()
+            .entry(status)
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the time an audio stream took, from request to the end of response body.
+    pub fn record_audio_stream(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = STREAM_DURATION_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(STREAM_DURATION_BUCKETS.len());
+        self.stream_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.stream_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Render counters recorded here in Prometheus text format.
+    pub fn render(&self, out: &mut String) {
+        let _ = writeln!(
+            out,
+            "# HELP annil_http_requests_total Number of responses by route and status."
+        );
+        let _ = writeln!(out, "# TYPE annil_http_requests_total counter");
+        let requests = self.requests.read().unwrap();
+        let mut routes: Vec<_> = requests.iter().collect();
+        routes.sort_by_key(|(route, _)| *route);
+        for (route, statuses) in routes {
+            let mut statuses: Vec<_> = statuses.iter().collect();
+            statuses.sort_by_key(|(status, _)| **status);
+            for (status, count) in statuses {
+                let _ = writeln!(
+                    out,
+                    "annil_http_requests_total{{route=\"{}\",status=\"{status}\"}} {}",
+                    escape_label(route),
+                    count.load(Ordering::Relaxed)
+                );
+            }
+        }
+        drop(requests);
+
+        let _ = writeln!(
+            out,
+            "# HELP annil_audio_stream_duration_seconds Time from audio request to the end of its response."
+        );
+        let _ = writeln!(out, "# TYPE annil_audio_stream_duration_seconds histogram");
+        let mut total = 0;
+        for (index, count) in self.stream_buckets.iter().enumerate() {
+            total += count.load(Ordering::Relaxed);
+            let bound = match STREAM_DURATION_BUCKETS.get(index) {
+                Some(bound) => bound.to_string(),
+                None => "+Inf".to_string(),
+            };
+            let _ = writeln!(
+                out,
+                "annil_audio_stream_duration_seconds_bucket{{le=\"{bound}\"}} {total}"
+            );
+        }
+        let sum = self.stream_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "annil_audio_stream_duration_seconds_sum {sum}");
+        let _ = writeln!(out, "annil_audio_stream_duration_seconds_count {total}");
+    }
+}
+
+/// Escape a label value in Prometheus text format.
+pub fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Middleware which counts responses by route pattern and status.
+///
+/// It requires [Metrics] in extensions.
+pub async fn track_requests<B>(
+    Extension(metrics): Extension<Arc<Metrics>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let route = request.extensions().get::<MatchedPath>().cloned();
+    let response = next.run(request).await;
+    let route = route.as_ref().map_or(UNMATCHED_ROUTE, MatchedPath::as_str);
+    metrics.record_request(route, response.status().as_u16());
+    response
+}
+
+/// Middleware which records durations of successful audio streams by `GET`, until response bodies end.
+///
+/// It requires [Metrics] in extensions.
+pub async fn track_audio_stream<B>(
+    Extension(metrics): Extension<Arc<Metrics>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    // responses of HEAD requests have no body
+    let is_get = request.method() == Method::GET;
+    let start = Instant::now();
+    let response = next.run(request).await;
+    if !is_get || !response.status().is_success() {
+        return response;
+    }
+    response.map(|body| {
+        boxed(TimedBody {
+            inner: body,
+            start,
+            metrics,
+        })
+    })
+}
+
+/// Response body which records the time since `start` when it's dropped,
+/// either after being sent or when the client disconnects.
+struct TimedBody {
+    inner: BoxBody,
+    start: Instant,
+    metrics: Arc<Metrics>,
+}
+
+impl HttpBody for TimedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_data(cx)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for TimedBody {
+    fn drop(&mut self) {
+        self.metrics.record_audio_stream(self.start.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::new(false);
+        metrics.record_request("/info", 200);
+        metrics.record_request("/info", 200);
+        metrics.record_request("/:album_id/cover", 404);
+        metrics.record_audio_stream(Duration::from_millis(300));
+        metrics.record_audio_stream(Duration::from_secs(1000));
+
+        let mut out = String::new();
+        metrics.render(&mut out);
+        assert!(out.contains("annil_http_requests_total{route=\"/info\",status=\"200\"} 2\n"));
+        assert!(out
+            .contains("annil_http_requests_total{route=\"/:album_id/cover\",status=\"404\"} 1\n"));
+        assert!(out.contains("annil_audio_stream_duration_seconds_bucket{le=\"0.1\"} 0\n"));
+        assert!(out.contains("annil_audio_stream_duration_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(out.contains("annil_audio_stream_duration_seconds_bucket{le=\"600\"} 1\n"));
+        assert!(out.contains("annil_audio_stream_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(out.contains("annil_audio_stream_duration_seconds_sum 1000.3\n"));
+        assert!(out.contains("annil_audio_stream_duration_seconds_count 2\n"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,62 @@
+use anni_provider::format::known_content_type;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// `Content-Type` of files whose extension is unknown.
+pub const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Extension to `Content-Type` map in `[server.mime]`, which overrides built-in types.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(from = "HashMap<String, String>")]
+pub struct MimeConfig(HashMap<String, String>);
+
+/// Extensions are case insensitive.
+impl From<HashMap<String, String>> for MimeConfig {
+    fn from(types: HashMap<String, String>) -> Self {
+        Self(
+            types
+                .into_iter()
+                .map(|(ext, content_type)| (ext.to_ascii_lowercase(), content_type))
+                .collect(),
+        )
+    }
+}
+
+impl MimeConfig {
+    /// Get `Content-Type` of a file by its extension.
+    pub fn content_type(&self, extension: &str) -> &str {
+        match self.0.get(&extension.to_ascii_lowercase()) {
+            Some(content_type) => content_type,
+            None => known_content_type(extension)
+                .or_else(|| image_content_type(extension))
+                .unwrap_or(DEFAULT_CONTENT_TYPE),
+        }
+    }
+}
+
+/// `Content-Type` of cover images.
+fn image_content_type(extension: &str) -> Option<&'static str> {
+    match extension.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_type() {
+        let config: MimeConfig =
+            toml::from_str("OPUS = \"audio/opus\"\ndsf = \"audio/dsf\"").unwrap();
+        assert_eq!(config.content_type("opus"), "audio/opus");
+        assert_eq!(config.content_type("dsf"), "audio/dsf");
+        // built-in types
+        assert_eq!(config.content_type("FLAC"), "audio/flac");
+        assert_eq!(config.content_type("ape"), DEFAULT_CONTENT_TYPE);
+        assert_eq!(config.content_type("jpg"), "image/jpeg");
+        assert_eq!(config.content_type("png"), "image/png");
+    }
+}
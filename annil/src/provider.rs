@@ -1,43 +1,395 @@
+use crate::config::{ProviderConfig, ProviderItem};
+use crate::metadata::LazyDb;
+use anni_provider::cache::{Cache, CachePool};
+use anni_provider::fs::LocalFileSystemProvider;
+use anni_provider::providers::drive::{DriveProviderSettings, RetryPolicy, TokenStorage};
+use anni_provider::providers::{
+    CommonConventionProvider, CommonStrictProvider, DriveProvider, S3Provider, S3Settings,
+    TimeoutProvider, WebDavProvider, WebDavSettings, ZipProvider,
+};
 use anni_provider::{AnniProvider, ProviderError};
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
-use tokio::sync::RwLock;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
 
-pub struct AnnilProvider<T: AnniProvider + Send + Sync>(RwLock<T>);
+pub struct AnnilProvider<T: AnniProvider + Send + Sync> {
+    provider: RwLock<T>,
+    /// Album ids of inner providers by their names, or of the provider itself under an empty
+    /// name if it's not combined
+    ///
+    /// Etag is computed from the union of them, which is the same as the etag of all albums.
+    albums: Mutex<HashMap<String, HashSet<u128>>>,
+}
 
 impl<T: AnniProvider + Send + Sync> AnnilProvider<T> {
     pub fn new(provider: T) -> Self {
-        Self(RwLock::new(provider))
+        Self {
+            provider: RwLock::new(provider),
+            albums: Default::default(),
+        }
     }
 
+    /// List albums of all inner providers, and compute etag from them.
     pub async fn compute_etag(&self) -> Result<String, ProviderError> {
-        use base64::engine::general_purpose::STANDARD;
-        use base64::Engine;
+        let provider = self.provider.read().await;
+        // held while listing, so that albums added concurrently are not overwritten
+        let mut albums = self.albums.lock().await;
 
-        let provider = self.0.read().await;
-
-        let mut etag = 0;
-        for album in provider.albums().await? {
-            if let Ok(uuid) = uuid::Uuid::parse_str(album.as_ref()) {
-                etag ^= uuid.as_u128();
-            } else {
-                log::error!("Failed to parse uuid: {album}");
+        let inner = provider.inner_providers();
+        let mut listed = HashMap::with_capacity(inner.len().max(1));
+        if inner.is_empty() {
+            listed.insert(String::new(), album_ids(&*provider).await?);
+        } else {
+            for (name, provider) in inner {
+                listed.insert(name.to_string(), album_ids(provider).await?);
             }
         }
 
-        Ok(format!(r#""{}""#, STANDARD.encode(etag.to_be_bytes())))
+        *albums = listed;
+        Ok(etag(&albums))
+    }
+
+    /// List albums of inner provider `name` added after others, and compute etag again.
+    ///
+    /// Albums of other providers are not listed again.
+    pub async fn add_albums<P>(&self, name: &str, inner: &P) -> Result<String, ProviderError>
+    where
+        P: AnniProvider + ?Sized,
+    {
+        let mut albums = self.albums.lock().await;
+        albums.insert(name.to_string(), album_ids(inner).await?);
+        Ok(etag(&albums))
+    }
+}
+
+/// Ids of albums of a provider.
+async fn album_ids<P>(provider: &P) -> Result<HashSet<u128>, ProviderError>
+where
+    P: AnniProvider + ?Sized,
+{
+    let mut ids = HashSet::new();
+    for album in provider.albums().await? {
+        if let Ok(uuid) = uuid::Uuid::parse_str(album.as_ref()) {
+            ids.insert(uuid.as_u128());
+        } else {
+            log::error!("Failed to parse uuid: {album}");
+        }
     }
+    Ok(ids)
+}
+
+/// Etag of albums, which is the xor of ids of all distinct albums.
+///
+/// Adding an album and then removing it results in the same etag.
+fn etag(albums: &HashMap<String, HashSet<u128>>) -> String {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    let all: HashSet<u128> = albums.values().flatten().copied().collect();
+    let etag = all.into_iter().fold(0u128, |etag, id| etag ^ id);
+    format!(r#""{}""#, STANDARD.encode(etag.to_be_bytes()))
 }
 
 impl<T: AnniProvider + Send + Sync> Deref for AnnilProvider<T> {
     type Target = RwLock<T>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.provider
     }
 }
 
 impl<T: AnniProvider + Send + Sync> DerefMut for AnnilProvider<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.provider
+    }
+}
+
+/// Build a provider from its config, without timeout or cache.
+///
+/// Returns `None` if the provider requires metadata, but metadata is not configured.
+async fn build_provider(
+    item: &ProviderItem,
+    db: &mut Option<LazyDb>,
+) -> anyhow::Result<Option<Box<dyn AnniProvider + Send + Sync>>> {
+    let provider: Box<dyn AnniProvider + Send + Sync> = match (item, db) {
+        (
+            ProviderItem::File {
+                root,
+                strict: false,
+                ..
+            },
+            Some(db),
+        ) => Box::new(
+            CommonConventionProvider::new(
+                PathBuf::from(root),
+                db.open().await?,
+                Box::new(LocalFileSystemProvider),
+            )
+            .await?,
+        ),
+        (
+            ProviderItem::File {
+                root,
+                strict: true,
+                layer,
+            },
+            _,
+        ) => Box::new(
+            CommonStrictProvider::new(
+                PathBuf::from(root),
+                *layer,
+                Box::new(LocalFileSystemProvider),
+            )
+            .await?,
+        ),
+        (
+            ProviderItem::Drive {
+                drive_id,
+                corpora,
+                initial_token_path,
+                token_path,
+                token_paths,
+                strict: false,
+                retries,
+            },
+            Some(db),
+        ) => {
+            let token_storages = drive_token_storages(initial_token_path, token_path, token_paths)?;
+            Box::new(
+                DriveProvider::with_token_storages(
+                    Default::default(),
+                    DriveProviderSettings {
+                        corpora: corpora.to_string(),
+                        drive_id: drive_id.clone(),
+                        retry: RetryPolicy {
+                            retries: *retries,
+                            ..Default::default()
+                        },
+                    },
+                    Some(db.open().await?),
+                    token_storages,
+                )
+                .await?,
+            )
+        }
+        (
+            ProviderItem::Drive {
+                drive_id,
+                corpora,
+                initial_token_path,
+                token_path,
+                token_paths,
+                strict: true,
+                retries,
+            },
+            _,
+        ) => {
+            let token_storages = drive_token_storages(initial_token_path, token_path, token_paths)?;
+            Box::new(
+                DriveProvider::with_token_storages(
+                    Default::default(),
+                    DriveProviderSettings {
+                        corpora: corpora.to_string(),
+                        drive_id: drive_id.clone(),
+                        retry: RetryPolicy {
+                            retries: *retries,
+                            ..Default::default()
+                        },
+                    },
+                    None,
+                    token_storages,
+                )
+                .await?,
+            )
+        }
+        (
+            ProviderItem::WebDav {
+                url,
+                username,
+                password,
+                strict: false,
+                ..
+            },
+            Some(db),
+        ) => Box::new(
+            CommonConventionProvider::new(
+                PathBuf::from("/"),
+                db.open().await?,
+                Box::new(WebDavProvider::new(WebDavSettings {
+                    url: url.clone(),
+                    username: username.clone(),
+                    password: password.clone(),
+                })),
+            )
+            .await?,
+        ),
+        (
+            ProviderItem::WebDav {
+                url,
+                username,
+                password,
+                strict: true,
+                layer,
+            },
+            _,
+        ) => Box::new(
+            CommonStrictProvider::new(
+                PathBuf::from("/"),
+                *layer,
+                Box::new(WebDavProvider::new(WebDavSettings {
+                    url: url.clone(),
+                    username: username.clone(),
+                    password: password.clone(),
+                })),
+            )
+            .await?,
+        ),
+        (
+            ProviderItem::S3 {
+                endpoint,
+                region,
+                bucket,
+                prefix,
+                access_key_id,
+                secret_access_key,
+                strict: false,
+                ..
+            },
+            Some(db),
+        ) => Box::new(
+            CommonConventionProvider::new(
+                PathBuf::from("/"),
+                db.open().await?,
+                Box::new(S3Provider::new(S3Settings {
+                    endpoint: endpoint.clone(),
+                    region: region.clone(),
+                    bucket: bucket.clone(),
+                    prefix: prefix.clone(),
+                    access_key_id: access_key_id.clone(),
+                    secret_access_key: secret_access_key.clone(),
+                })),
+            )
+            .await?,
+        ),
+        (
+            ProviderItem::S3 {
+                endpoint,
+                region,
+                bucket,
+                prefix,
+                access_key_id,
+                secret_access_key,
+                strict: true,
+                layer,
+            },
+            _,
+        ) => Box::new(
+            CommonStrictProvider::new(
+                PathBuf::from("/"),
+                *layer,
+                Box::new(S3Provider::new(S3Settings {
+                    endpoint: endpoint.clone(),
+                    region: region.clone(),
+                    bucket: bucket.clone(),
+                    prefix: prefix.clone(),
+                    access_key_id: access_key_id.clone(),
+                    secret_access_key: secret_access_key.clone(),
+                })),
+            )
+            .await?,
+        ),
+        (ProviderItem::Zip { root }, _) => Box::new(ZipProvider::new(PathBuf::from(root)).await?),
+        (_, None) => return Ok(None),
+    };
+    Ok(Some(provider))
+}
+
+/// Token storages of a Drive provider, `token_path` followed by `token_paths`.
+///
+/// `initial_token_path` is copied to `token_path` if the latter does not exist.
+fn drive_token_storages(
+    initial_token_path: &Option<PathBuf>,
+    token_path: &Option<PathBuf>,
+    token_paths: &[PathBuf],
+) -> anyhow::Result<Vec<TokenStorage>> {
+    if let (Some(initial_token_path), Some(token_path)) = (initial_token_path, token_path) {
+        if initial_token_path.exists() && !token_path.exists() {
+            let _ = std::fs::copy(initial_token_path, token_path);
+        }
+    }
+
+    let token_storages: Vec<_> = token_path
+        .iter()
+        .chain(token_paths)
+        .cloned()
+        .map(TokenStorage::from)
+        .collect();
+    if token_storages.is_empty() {
+        anyhow::bail!("token-path or token-paths is required for drive provider");
+    }
+    Ok(token_storages)
+}
+
+/// Build a provider named `provider_name`, wrapped with timeout and cache if configured.
+///
+/// Cache pools not in `caches` are created with sizes in `cache_sizes`, or the configured
+/// `max-size` if the pool is not there.
+pub async fn init_provider(
+    provider_name: &str,
+    provider_config: &ProviderConfig,
+    default_timeout_secs: Option<u64>,
+    db: &mut Option<LazyDb>,
+    caches: &mut HashMap<String, Arc<CachePool>>,
+    cache_sizes: &HashMap<String, usize>,
+) -> anyhow::Result<Option<Box<dyn AnniProvider + Send + Sync>>> {
+    let mut provider = match build_provider(&provider_config.item, db).await? {
+        Some(provider) => provider,
+        None => {
+            log::error!(
+                "Metadata is not configured, but provider {} requires it.",
+                provider_name
+            );
+            return Ok(None);
+        }
+    };
+    if let Some(timeout) = provider_config
+        .timeout_secs
+        .or(default_timeout_secs)
+        .filter(|secs| *secs > 0)
+    {
+        log::debug!("Timeout of provider {provider_name}: {timeout}s");
+        provider = Box::new(TimeoutProvider::new(
+            provider,
+            provider_name.to_string(),
+            Duration::from_secs(timeout),
+        ));
+    }
+    if let Some(cache) = provider_config.cache() {
+        log::debug!(
+            "Cache configuration detected: root = {}, max-size = {}, backend = {:?}, mode = {:?}, eviction = {:?}, ttl-secs = {:?}, fill-limit = {}",
+            cache.root,
+            cache.max_size,
+            cache.backend,
+            cache.mode,
+            cache.eviction,
+            cache.ttl_secs,
+            cache.fill_limit
+        );
+        let key = cache.pool_key();
+        if !caches.contains_key(&key) {
+            // new cache pool
+            let max_size = cache_sizes.get(&key).copied().unwrap_or(cache.max_size);
+            let pool = CachePool::with_backend(&cache.root, max_size, cache.backend.into())
+                .with_mode(cache.mode.into())
+                .with_eviction(cache.eviction.into())
+                .with_ttl(cache.ttl_secs.map(Duration::from_secs))
+                .with_fill_limit(cache.fill_limit);
+            caches.insert(key.clone(), Arc::new(pool));
+        }
+        provider = Box::new(Cache::new(provider, caches[&key].clone()));
     }
+    Ok(Some(provider))
 }
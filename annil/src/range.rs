@@ -0,0 +1,456 @@
+//! HTTP range request handling.
+use crate::utils::{limit_stream, slice_stream};
+use anni_provider::{Range, ResourceReader};
+use axum::body::{Bytes, StreamBody};
+use axum::http::header::{CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use std::future::Future;
+use tokio_util::io::ReaderStream;
+
+/// Maximum number of ranges served in a `multipart/byteranges` response.
+///
+/// Requests with more ranges are served in full.
+pub const MAX_RANGES: usize = 16;
+
+/// Result of a `Range` header parsed against the size of the requested resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// No range is requested, or the header is ignored. Whole resource should be sent with `200 OK`.
+    Full,
+    /// A satisfiable range, clamped to the resource size. Should be sent with `206 Partial Content`.
+    ///
+    /// `total` of the range is set to resource size.
+    Partial(Range),
+    /// Multiple satisfiable ranges in requested order, clamped to the resource size.
+    /// Should be sent by [multipart_byteranges].
+    Multiple(Vec<Range>),
+    /// No byte of the range is within the resource. Should be responded by [unsatisfiable].
+    Unsatisfiable,
+}
+
+impl RangeRequest {
+    /// Parse `Range` header in `headers`.
+    pub fn from_headers(headers: &HeaderMap, size: u64) -> Self {
+        match headers.get(RANGE).and_then(|r| r.to_str().ok()) {
+            Some(range) => Self::parse(range, size),
+            None => RangeRequest::Full,
+        }
+    }
+
+    /// Parse value of `Range` header against resource with `size` bytes.
+    ///
+    /// Following RFC 9110, syntactically invalid ranges are ignored. Unsatisfiable ranges in
+    /// a multi-range request are dropped. Overlapping ranges, or more than [MAX_RANGES] ranges,
+    /// are ignored as well.
+    pub fn parse(range: &str, size: u64) -> Self {
+        let Some(specs) = parse_specs(range) else {
+            return RangeRequest::Full;
+        };
+
+        let mut ranges = Vec::with_capacity(specs.len());
+        for spec in specs {
+            let (start, end) = match spec {
+                // bytes=start-end
+                (Some(start), Some(end)) if start <= end => (start, end),
+                // bytes=start-
+                (Some(start), None) => (start, u64::MAX),
+                // bytes=-suffix
+                (None, Some(suffix)) if suffix > 0 => (size.saturating_sub(suffix), u64::MAX),
+                (None, Some(_)) => continue,
+                // bytes=- or end < start
+                _ => return RangeRequest::Full,
+            };
+            if start < size {
+                ranges.push(Range::new(start, Some(end)).end_with(size));
+            }
+        }
+
+        match ranges.len() {
+            0 => RangeRequest::Unsatisfiable,
+            1 => RangeRequest::Partial(ranges[0]),
+            n if n > MAX_RANGES => RangeRequest::Full,
+            _ => {
+                let mut sorted = ranges.clone();
+                sorted.sort_by_key(|range| range.start);
+                let overlapped = sorted
+                    .windows(2)
+                    .any(|pair| Some(pair[1].start) <= pair[0].end);
+                if overlapped {
+                    RangeRequest::Full
+                } else {
+                    RangeRequest::Multiple(ranges)
+                }
+            }
+        }
+    }
+
+    /// Parse `Range` header in `headers` before size of the resource is known.
+    ///
+    /// Returns `None` if a suffix range like `bytes=-500` is requested, which needs the size.
+    /// Otherwise ranges are parsed like [RangeRequest::from_headers], and should be parsed
+    /// again with the size to be validated.
+    pub fn from_headers_without_size(headers: &HeaderMap) -> Option<Self> {
+        let Some(range) = headers.get(RANGE).and_then(|r| r.to_str().ok()) else {
+            return Some(RangeRequest::Full);
+        };
+        match parse_specs(range) {
+            Some(specs) if specs.iter().any(|(start, _)| start.is_none()) => None,
+            _ => Some(Self::parse(range, u64::MAX)),
+        }
+    }
+
+    /// Parse `Range` header in `headers` for a resource whose size is unknown.
+    pub fn from_headers_unknown_size(headers: &HeaderMap) -> Self {
+        match headers.get(RANGE).and_then(|r| r.to_str().ok()) {
+            Some(range) => Self::parse_unknown_size(range),
+            None => RangeRequest::Full,
+        }
+    }
+
+    /// Parse value of `Range` header against resource whose size is unknown, e.g. transcoded audio.
+    ///
+    /// Only a single `bytes=start-end` can be served without size, other ranges are ignored.
+    /// `total` of the returned range is `None`.
+    pub fn parse_unknown_size(range: &str) -> Self {
+        match parse_specs(range).as_deref() {
+            Some([(Some(start), Some(end))]) if start <= end => {
+                RangeRequest::Partial(Range::new(*start, Some(*end)))
+            }
+            _ => RangeRequest::Full,
+        }
+    }
+}
+
+/// Parse first and last byte positions of each byte range. Returns `None` if it should be ignored.
+fn parse_specs(range: &str) -> Option<Vec<(Option<u64>, Option<u64>)>> {
+    let (unit, ranges) = range.split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+    ranges
+        .split(',')
+        // empty elements are allowed in lists
+        .filter(|spec| !spec.trim().is_empty())
+        .map(parse_spec)
+        .collect::<Option<Vec<_>>>()
+        .filter(|specs| !specs.is_empty())
+}
+
+/// Parse first and last byte positions of a single byte range spec, like `0-99`.
+fn parse_spec(spec: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let (first, last) = spec.trim().split_once('-')?;
+    let parse = |s: &str| -> Option<Option<u64>> {
+        let s = s.trim();
+        if s.is_empty() {
+            Some(None)
+        } else if s.bytes().all(|b| b.is_ascii_digit()) {
+            s.parse().ok().map(Some)
+        } else {
+            None
+        }
+    };
+    Some((parse(first)?, parse(last)?))
+}
+
+/// `416 Range Not Satisfiable` response for resource with `size` bytes.
+pub fn unsatisfiable(size: u64) -> Response {
+    (
+        StatusCode::RANGE_NOT_SATISFIABLE,
+        [(CONTENT_RANGE, format!("bytes */{size}"))],
+    )
+        .into_response()
+}
+
+/// `multipart/byteranges` response of `first` part with its reader, followed by `others`.
+///
+/// Readers of `others` are opened by `open` in the body stream, each after the previous part
+/// is sent, and the body is limited to `limit` bytes.
+/// Ranges must have both `end` and `total` set, like ranges in [RangeRequest::Multiple].
+/// Status code and other headers should be set by the caller.
+pub fn multipart_byteranges<F, Fut>(
+    first: (Range, ResourceReader),
+    others: Vec<Range>,
+    open: F,
+    content_type: &str,
+    limit: Option<u64>,
+) -> Response
+where
+    F: Fn(Range) -> Fut + Send + 'static,
+    Fut: Future<Output = std::io::Result<ResourceReader>> + Send + 'static,
+{
+    let boundary = uuid::Uuid::new_v4().simple().to_string();
+
+    let (first_range, first_reader) = first;
+    let mut first_reader = Some(first_reader);
+    let mut length = 0;
+    let mut body: Vec<BoxStream<'static, std::io::Result<Bytes>>> = Vec::new();
+    for (index, range) in std::iter::once(first_range).chain(others).enumerate() {
+        let part_length = range.length().unwrap_or_default();
+        let header = format!(
+            "{}--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: {}\r\n\r\n",
+            if index == 0 { "" } else { "\r\n" },
+            range.to_content_range_header(),
+        );
+        length += header.len() as u64 + part_length;
+        body.push(stream::once(async move { Ok(Bytes::from(header)) }).boxed());
+        let reader = match first_reader.take() {
+            Some(reader) => stream::once(async move { Ok(reader) }).boxed(),
+            None => stream::once(open(range)).boxed(),
+        };
+        body.push(
+            reader
+                .map(move |reader| match reader {
+                    Ok(reader) => {
+                        slice_stream(ReaderStream::new(reader), 0, Some(part_length)).boxed()
+                    }
+                    Err(e) => stream::once(async move { Err(e) }).boxed(),
+                })
+                .flatten()
+                .boxed(),
+        );
+    }
+    let end = format!("\r\n--{boundary}--\r\n");
+    length += end.len() as u64;
+    body.push(stream::once(async move { Ok(Bytes::from(end)) }).boxed());
+
+    (
+        [
+            (
+                CONTENT_TYPE,
+                format!("multipart/byteranges; boundary={boundary}"),
+            ),
+            (CONTENT_LENGTH, length.to_string()),
+        ],
+        StreamBody::new(limit_stream(stream::iter(body).flatten(), limit)),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(start: u64, end: u64, total: u64) -> RangeRequest {
+        RangeRequest::Partial(Range::new(start, Some(end)).end_with(total))
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            RangeRequest::parse("bytes=0-99", 1000),
+            partial(0, 99, 1000)
+        );
+        assert_eq!(
+            RangeRequest::parse("bytes=500-", 1000),
+            partial(500, 999, 1000)
+        );
+        assert_eq!(RangeRequest::parse("bytes=0-", 1000), partial(0, 999, 1000));
+        assert_eq!(
+            RangeRequest::parse("bytes=-100", 1000),
+            partial(900, 999, 1000)
+        );
+        assert_eq!(
+            RangeRequest::parse("bytes=-2000", 1000),
+            partial(0, 999, 1000)
+        );
+        // end is clamped to size
+        assert_eq!(
+            RangeRequest::parse("bytes=900-2000", 1000),
+            RangeRequest::Partial(Range {
+                start: 900,
+                end: Some(999),
+                total: Some(1000),
+            })
+        );
+    }
+
+    #[test]
+    fn test_unsatisfiable_range() {
+        assert_eq!(
+            RangeRequest::parse("bytes=99999999-", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            RangeRequest::parse("bytes=1000-1001", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            RangeRequest::parse("bytes=-0", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            RangeRequest::parse("bytes=0-", 0),
+            RangeRequest::Unsatisfiable
+        );
+
+        let response = unsatisfiable(1000);
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes */1000");
+    }
+
+    #[test]
+    fn test_ignored_range() {
+        for range in [
+            "",
+            "bytes",
+            "items=0-1",
+            "bytes=1-0",
+            "bytes=-",
+            "bytes=a-b",
+            "bytes=+1-2",
+            "bytes=0-1,a-b",
+            "bytes=0-99,50-149",
+            "bytes=,",
+        ] {
+            assert_eq!(
+                RangeRequest::parse(range, 1000),
+                RangeRequest::Full,
+                "{range}"
+            );
+        }
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            RangeRequest::from_headers(&headers, 1000),
+            RangeRequest::Full
+        );
+        headers.insert(RANGE, "bytes=10-19".parse().unwrap());
+        assert_eq!(
+            RangeRequest::from_headers(&headers, 1000),
+            partial(10, 19, 1000)
+        );
+    }
+
+    #[test]
+    fn test_parse_multiple_ranges() {
+        assert_eq!(
+            RangeRequest::parse("bytes=0-1023,2048-4095", 10000),
+            RangeRequest::Multiple(vec![
+                Range::new(0, Some(1023)).end_with(10000),
+                Range::new(2048, Some(4095)).end_with(10000),
+            ])
+        );
+        // requested order is kept, and open-ended ranges are clamped
+        assert_eq!(
+            RangeRequest::parse("bytes=500-, 0-9", 1000),
+            RangeRequest::Multiple(vec![
+                Range::new(500, Some(999)).end_with(1000),
+                Range::new(0, Some(9)).end_with(1000),
+            ])
+        );
+        // overlapping ranges are ignored
+        assert_eq!(
+            RangeRequest::parse("bytes=900-, 0-9, -50", 1000),
+            RangeRequest::Full
+        );
+        // unsatisfiable ranges are dropped
+        assert_eq!(
+            RangeRequest::parse("bytes=0-9,5000-6000", 1000),
+            partial(0, 9, 1000)
+        );
+        assert_eq!(
+            RangeRequest::parse("bytes=5000-6000,7000-", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        // empty list elements are allowed
+        assert_eq!(RangeRequest::parse("bytes=0-9,", 1000), partial(0, 9, 1000));
+
+        let too_many = (0..=MAX_RANGES)
+            .map(|i| format!("{}-{}", i * 10, i * 10 + 1))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(
+            RangeRequest::parse(&format!("bytes={too_many}"), 1000),
+            RangeRequest::Full
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multipart_byteranges() {
+        let first: (Range, ResourceReader) = (
+            Range::new(0, Some(2)).end_with(10),
+            Box::pin(std::io::Cursor::new(b"0123".to_vec())),
+        );
+        let open = |range: Range| async move {
+            let data = b"0123456789"[range.start as usize..].to_vec();
+            Ok::<ResourceReader, _>(Box::pin(std::io::Cursor::new(data)))
+        };
+        let response = multipart_byteranges(
+            first,
+            vec![Range::new(7, Some(9)).end_with(10)],
+            open,
+            "audio/flac",
+            None,
+        );
+        let content_type = response.headers()[CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+        let length: usize = response.headers()[CONTENT_LENGTH]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body.len(), length);
+        assert_eq!(
+            String::from_utf8(body.to_vec()).unwrap(),
+            format!(
+                "--{boundary}\r\nContent-Type: audio/flac\r\nContent-Range: bytes 0-2/10\r\n\r\n012\r\n--{boundary}\r\nContent-Type: audio/flac\r\nContent-Range: bytes 7-9/10\r\n\r\n789\r\n--{boundary}--\r\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_from_headers_without_size() {
+        let headers = |range: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RANGE, range.parse().unwrap());
+            headers
+        };
+        assert_eq!(
+            RangeRequest::from_headers_without_size(&HeaderMap::new()),
+            Some(RangeRequest::Full)
+        );
+        assert_eq!(
+            RangeRequest::from_headers_without_size(&headers("bytes=100-")),
+            Some(partial(100, u64::MAX - 1, u64::MAX))
+        );
+        assert_eq!(
+            RangeRequest::from_headers_without_size(&headers("bytes=0-1,-100")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_size() {
+        assert_eq!(
+            RangeRequest::parse_unknown_size("bytes=100-199"),
+            RangeRequest::Partial(Range {
+                start: 100,
+                end: Some(199),
+                total: None,
+            })
+        );
+        for range in [
+            "bytes=100-",
+            "bytes=-100",
+            "bytes=1-0",
+            "bytes=0-1,5-6",
+            "items=0-1",
+        ] {
+            assert_eq!(
+                RangeRequest::parse_unknown_size(range),
+                RangeRequest::Full,
+                "{range}"
+            );
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::extractor::auth::AuthExtractor;
+use crate::extractor::token::AnnilClaim;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use jwt_simple::prelude::Token;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Number of buckets kept before the least recently seen half of them are dropped.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// Token bucket rate limiter, configured by `server.rate-limit` in requests per minute.
+///
+/// Each key has a bucket of `requests_per_minute` requests, which is refilled continuously.
+pub struct RateLimiter {
+    /// Maximum number of requests in a bucket
+    capacity: f64,
+    /// Requests refilled per second
+    rate: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    /// Requests left, may be fractional while refilling
+    tokens: f64,
+    /// Time of the last request, when the bucket was refilled
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limiter: &RateLimiter, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limiter.rate).min(limiter.capacity);
+        self.updated = now;
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `requests_per_minute` requests of each key, which must not be 0.
+    pub fn new(requests_per_minute: u32) -> Self {
+        assert!(requests_per_minute > 0, "rate limit must not be 0");
+        Self {
+            capacity: requests_per_minute as f64,
+            rate: requests_per_minute as f64 / 60.0,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Take a request from the bucket of `key`.
+    ///
+    /// Returns the time to wait before the next request is allowed if the bucket is empty.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= PRUNE_THRESHOLD && !buckets.contains_key(key) {
+            // keys seen recently are more likely to be limited, and are kept
+            let mut updated: Vec<_> = buckets.values().map(|bucket| bucket.updated).collect();
+            let (_, &mut cutoff, _) = updated.select_nth_unstable(PRUNE_THRESHOLD / 2);
+            buckets.retain(|_, bucket| bucket.updated > cutoff);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+        bucket.refill(self, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+/// Middleware which limits requests of each user, or share tokens of each user token.
+///
+/// Requests made by admin are not limited. It requires [RateLimiter] in extensions.
+pub async fn rate_limit<B>(
+    IsAdmin(is_admin): IsAdmin,
+    AuthExtractor(auth): AuthExtractor,
+    claim: AnnilClaim,
+    Extension(limiter): Extension<Arc<RateLimiter>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if !is_admin {
+        let key = match &claim {
+            AnnilClaim::User(user) => format!("user:{}", user.user_id),
+            // share tokens do not have user ids, but their key id is the id of user token signing them
+            AnnilClaim::Share(_) => match Token::decode_metadata(&auth)
+                .ok()
+                .and_then(|metadata| metadata.key_id().map(str::to_string))
+            {
+                Some(key_id) => format!("share:{key_id}"),
+                None => return AnnilError::Unauthorized.into_response(),
+            },
+        };
+        if let Err(retry_after) = limiter.check(&key) {
+            log::debug!("Rate limit exceeded by {key}");
+            return AnnilError::RateLimited(retry_after.as_secs_f64().ceil().max(1.0) as u64)
+                .into_response();
+        }
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(2);
+        assert!(limiter.check("a").is_ok());
+        assert!(limiter.check("a").is_ok());
+        let retry_after = limiter.check("a").unwrap_err();
+        assert!(retry_after > Duration::from_secs(29) && retry_after <= Duration::from_secs(30));
+        // buckets are separated by key
+        assert!(limiter.check("b").is_ok());
+    }
+
+    #[test]
+    fn test_rate_limiter_prune() {
+        let limiter = RateLimiter::new(1);
+        assert!(limiter.check("limited").is_ok());
+        for i in 0..PRUNE_THRESHOLD * 2 {
+            limiter.check(&i.to_string()).unwrap();
+            if i % (PRUNE_THRESHOLD / 4) == 0 {
+                // keys being used are kept
+                assert!(limiter.check("limited").is_err());
+            }
+        }
+        assert!(limiter.buckets.lock().unwrap().len() <= PRUNE_THRESHOLD);
+        assert!(limiter.check("limited").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Revoked token ids(`jti` claim), persisted to `server.revocation-file` if configured.
+///
+/// Each id is kept with the expiry of its token, and dropped lazily after that,
+/// as expired tokens are rejected anyway.
+#[derive(Default)]
+pub struct RevocationList {
+    /// File to persist revoked ids, `None` keeps them in memory only
+    path: Option<PathBuf>,
+    /// Revoked ids with expiry of their tokens in seconds since epoch, `None` means never expires
+    revoked: RwLock<HashMap<String, Option<u64>>>,
+    /// Held while persisting, so that an older list never overwrites a newer one
+    persist: tokio::sync::Mutex<()>,
+}
+
+impl RevocationList {
+    /// Load revoked ids from `path`, which is created on the first revocation if it doesn't exist.
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let mut revoked: HashMap<String, Option<u64>> = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        prune(&mut revoked);
+        Ok(Self {
+            path: Some(path),
+            revoked: RwLock::new(revoked),
+            persist: Default::default(),
+        })
+    }
+
+    /// Whether `jti` has been revoked, and its token has not expired.
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        let expires_at = match self.revoked.read().unwrap().get(jti) {
+            Some(expires_at) => *expires_at,
+            None => return false,
+        };
+        if expires_at.is_some_and(|expires_at| expires_at <= now()) {
+            self.revoked.write().unwrap().remove(jti);
+            return false;
+        }
+        true
+    }
+
+    /// Revoke `jti`, whose token expires at `expires_at`, and persist the list.
+    ///
+    /// Ids of tokens without expiry are kept forever, as such tokens are valid forever.
+    pub async fn revoke(&self, jti: String, expires_at: Option<u64>) -> io::Result<()> {
+        let _persist = self.persist.lock().await;
+        let data = {
+            let mut revoked = self.revoked.write().unwrap();
+            revoked.insert(jti, expires_at);
+            prune(&mut revoked);
+            serde_json::to_vec(&*revoked)?
+        };
+
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        tokio::task::spawn_blocking(move || {
+            // write to a temporary file first, so that the list would not be truncated if interrupted
+            let temp = path.with_extension("tmp");
+            fs::write(&temp, data)?;
+            fs::rename(temp, path)
+        })
+        .await?
+    }
+
+    /// Number of revoked ids, including expired ones not dropped yet.
+    pub fn len(&self) -> usize {
+        self.revoked.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Drop ids whose tokens have expired.
+fn prune(revoked: &mut HashMap<String, Option<u64>>) {
+    let now = now();
+    revoked.retain(|_, expires_at| expires_at.map_or(true, |expires_at| expires_at > now));
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
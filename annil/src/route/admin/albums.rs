@@ -0,0 +1,57 @@
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::provider::AnnilProvider;
+use anni_provider::AnniProvider;
+use axum::body::StreamBody;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use std::borrow::Cow;
+use std::io;
+use std::sync::Arc;
+
+/// List albums of all providers as a JSON array.
+///
+/// Album ids are collected before the response is sent, so that providers are not locked
+/// while a slow client reads the array.
+pub async fn albums<P>(
+    IsAdmin(is_admin): IsAdmin,
+    Extension(provider): Extension<Arc<AnnilProvider<P>>>,
+) -> Response
+where
+    P: AnniProvider + Send + Sync,
+{
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+
+    let albums: Vec<String> = {
+        let provider = provider.read().await;
+        match provider.albums().await {
+            Ok(albums) => albums.into_iter().map(Cow::into_owned).collect(),
+            Err(e) => {
+                log::error!("Failed to list albums: {e}");
+                return AnnilError::from(e).into_response();
+            }
+        }
+    };
+
+    let items = albums.into_iter().enumerate().map(|(i, album)| {
+        let album = serde_json::to_string(&album).unwrap();
+        if i == 0 {
+            album
+        } else {
+            format!(",{album}")
+        }
+    });
+    let chunks = std::iter::once("[".to_string())
+        .chain(items)
+        .chain(std::iter::once("]".to_string()))
+        .map(Ok::<_, io::Error>);
+
+    (
+        [(CONTENT_TYPE, "application/json")],
+        StreamBody::new(futures::stream::iter(chunks)),
+    )
+        .into_response()
+}
@@ -0,0 +1,63 @@
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::state::AnnilState;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use jwt_simple::reexports::serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+/// Statistics of cache pools, keyed by cache root.
+pub async fn cache_stats(
+    IsAdmin(is_admin): IsAdmin,
+    Extension(data): Extension<Arc<AnnilState>>,
+) -> Response {
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+
+    let pools: Map<String, Value> = data
+        .caches
+        .read()
+        .await
+        .iter()
+        .map(|(key, pool)| {
+            let stats = pool.stats();
+            let value = json!({
+                "entries": stats.entries,
+                "bytes_used": stats.space_used,
+                "max_size": stats.max_size,
+                "filling": stats.filling,
+                "hits": stats.hits,
+                "misses": stats.misses,
+                "evictions": stats.evictions,
+            });
+            (key.clone(), value)
+        })
+        .collect();
+    Json(Value::Object(pools)).into_response()
+}
+
+/// Check cached data of all cache pools, and remove items whose size does not match.
+///
+/// Responds with the number of removed items, keyed by cache root.
+pub async fn cache_verify(
+    IsAdmin(is_admin): IsAdmin,
+    Extension(data): Extension<Arc<AnnilState>>,
+) -> Response {
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+
+    let pools: Vec<_> = data
+        .caches
+        .read()
+        .await
+        .iter()
+        .map(|(key, pool)| (key.clone(), pool.clone()))
+        .collect();
+    let mut removed = Map::new();
+    for (key, pool) in pools {
+        removed.insert(key, pool.verify().await.into());
+    }
+    Json(Value::Object(removed)).into_response()
+}
@@ -0,0 +1,41 @@
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::provider::AnnilProvider;
+use crate::state::AnnilState;
+use anni_provider::AnniProvider;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Recompute etag over current providers, without reloading providers or metadata.
+///
+/// Responds with the new etag.
+pub async fn recompute_etag<P>(
+    IsAdmin(is_admin): IsAdmin,
+    Extension(data): Extension<Arc<AnnilState>>,
+    Extension(provider): Extension<Arc<AnnilProvider<P>>>,
+) -> Response
+where
+    P: AnniProvider + Send + Sync,
+{
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+
+    let etag = match provider.compute_etag().await {
+        Ok(etag) => etag,
+        Err(e) => {
+            log::error!("Failed to compute etag: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    *data.etag.write().await = etag.clone();
+    *data.last_update.write().await = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    etag.into_response()
+}
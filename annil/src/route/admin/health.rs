@@ -0,0 +1,40 @@
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::provider::AnnilProvider;
+use anni_provider::providers::MultipleProviders;
+use anni_provider::Capabilities;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct ProviderHealth {
+    name: String,
+    /// `false` while the provider is marked as unhealthy
+    healthy: bool,
+    capabilities: Capabilities,
+}
+
+/// Report health and capabilities of loaded providers in order.
+///
+/// Unlike `/admin/providers`, albums are not listed, so it's cheap to poll.
+pub async fn health(
+    IsAdmin(is_admin): IsAdmin,
+    Extension(provider): Extension<Arc<AnnilProvider<MultipleProviders>>>,
+) -> Response {
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+
+    let provider = provider.read().await;
+    let result: Vec<_> = provider
+        .providers()
+        .map(|(name, inner, healthy)| ProviderHealth {
+            name: name.to_string(),
+            healthy,
+            capabilities: inner.capabilities(),
+        })
+        .collect();
+    Json(result).into_response()
+}
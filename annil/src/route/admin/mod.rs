@@ -1,5 +1,17 @@
+mod albums;
+mod cache;
+mod etag;
+mod health;
+mod providers;
 mod reload;
+mod revoke;
 mod sign;
 
+pub use albums::*;
+pub use cache::*;
+pub use etag::*;
+pub use health::*;
+pub use providers::*;
 pub use reload::*;
+pub use revoke::*;
 pub use sign::*;
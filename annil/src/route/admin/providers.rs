@@ -0,0 +1,136 @@
+use crate::config::NamedProviderConfig;
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::metadata::lazy_db;
+use crate::provider::{init_provider, AnnilProvider};
+use crate::state::AnnilState;
+use anni_provider::providers::MultipleProviders;
+use anni_provider::AnniProvider;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize)]
+struct ProviderInfo {
+    name: String,
+    /// Whether the provider is in rotation, `false` while it's marked as unhealthy
+    enabled: bool,
+    /// Number of albums, `null` if the provider failed to list them
+    album_count: Option<usize>,
+}
+
+/// List loaded providers in order, with their album counts.
+pub async fn providers(
+    IsAdmin(is_admin): IsAdmin,
+    Extension(provider): Extension<Arc<AnnilProvider<MultipleProviders>>>,
+) -> Response {
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+
+    let provider = provider.read().await;
+    let mut result = Vec::with_capacity(provider.len());
+    for (name, inner, enabled) in provider.providers() {
+        let album_count = match inner.albums().await {
+            Ok(albums) => Some(albums.len()),
+            Err(e) => {
+                log::warn!("Failed to list albums of provider {name}: {e}");
+                None
+            }
+        };
+        result.push(ProviderInfo {
+            name: name.to_string(),
+            enabled,
+            album_count,
+        });
+    }
+    Json(result).into_response()
+}
+
+/// Build a provider from request body and add it after loaded providers, then update etag
+/// by its albums.
+///
+/// Added providers are not written to config file, and are dropped when config file is reloaded.
+/// Responds with the new etag.
+pub async fn add_provider(
+    IsAdmin(is_admin): IsAdmin,
+    Extension(state): Extension<Arc<AnnilState>>,
+    Extension(provider): Extension<Arc<AnnilProvider<MultipleProviders>>>,
+    Json(NamedProviderConfig { name, config }): Json<NamedProviderConfig>,
+) -> Response {
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+    let exists = |providers: &MultipleProviders| providers.providers().any(|(n, _, _)| n == name);
+    if exists(&*provider.read().await) {
+        return (StatusCode::CONFLICT, "provider already exists").into_response();
+    }
+
+    log::info!("Adding provider: {}", name);
+    let mut db = lazy_db(&state.metadata);
+    // build without holding the lock, new pools are added after the provider is added
+    let mut caches = state.caches.read().await.clone();
+    let inner = match init_provider(
+        &name,
+        &config,
+        state.provider_timeout_secs,
+        &mut db,
+        &mut caches,
+        &HashMap::new(),
+    )
+    .await
+    {
+        Ok(Some(inner)) => inner,
+        Ok(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "metadata is not configured, but the provider requires it",
+            )
+                .into_response()
+        }
+        Err(e) => {
+            log::error!("Failed to initialize provider {}: {:?}", name, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to initialize provider: {e}"),
+            )
+                .into_response();
+        }
+    };
+
+    let providers = {
+        let mut providers = provider.write().await;
+        if exists(&*providers) {
+            return (StatusCode::CONFLICT, "provider already exists").into_response();
+        }
+        providers.push(name.clone(), inner);
+        providers.downgrade()
+    };
+    let mut pools = state.caches.write().await;
+    for (key, pool) in caches {
+        pools.entry(key).or_insert(pool);
+    }
+    drop(pools);
+
+    // only albums of the added provider are listed
+    let added = providers.named_provider(&name).unwrap();
+    let etag = match provider.add_albums(&name, added).await {
+        Ok(etag) => etag,
+        Err(e) => {
+            log::error!("Failed to compute etag: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    drop(providers);
+    *state.etag.write().await = etag.clone();
+    *state.last_update.write().await = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    log::info!("Provider {} added.", name);
+    etag.into_response()
+}
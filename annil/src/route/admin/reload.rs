@@ -1,37 +1,67 @@
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
 use crate::provider::AnnilProvider;
 use crate::state::AnnilState;
 use anni_provider::AnniProvider;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::Extension;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub async fn reload<P>(
+    IsAdmin(is_admin): IsAdmin,
     Extension(data): Extension<Arc<AnnilState>>,
     Extension(provider): Extension<Arc<AnnilProvider<P>>>,
-) where
+) -> Response
+where
     P: AnniProvider + Send + Sync,
 {
-    #[cfg(feature = "metadata")]
-    if let Some(metadata) = &data.metadata {
-        use anni_repo::RepositoryManager;
-
-        if metadata.pull {
-            let repo =
-                RepositoryManager::pull(metadata.base.join("repo"), &metadata.branch).unwrap();
-            let repo = repo.into_owned_manager().unwrap();
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
 
-            let database_path = metadata.base.join("repo.db");
-            repo.to_database(&database_path).unwrap();
+    #[cfg(feature = "metadata")]
+    if data.metadata.as_ref().is_some_and(|metadata| metadata.pull) {
+        // git operations and waits between their retries block
+        let state = data.clone();
+        match tokio::task::spawn_blocking(move || update_metadata(&state)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::error!("Failed to update metadata: {e}"),
+            Err(e) => log::error!("Failed to update metadata: {e}"),
         }
     }
 
     if let Err(e) = provider.write().await.reload().await {
         log::error!("Failed to reload provider: {:?}", e);
     }
+    data.covers.clear().await;
 
     *data.etag.write().await = provider.compute_etag().await.unwrap();
     *data.last_update.write().await = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
+    StatusCode::OK.into_response()
+}
+
+/// Pull metadata repository, and regenerate metadata database from it.
+#[cfg(feature = "metadata")]
+fn update_metadata(data: &AnnilState) -> anyhow::Result<()> {
+    use anni_repo::RepositoryManager;
+
+    let Some(metadata) = &data.metadata else {
+        return Ok(());
+    };
+    let repo = RepositoryManager::pull_with_retry(
+        metadata.base.join("repo"),
+        &metadata.branch,
+        metadata.retry_policy(),
+    )?;
+    let repo = repo.into_owned_manager()?;
+    repo.to_database(metadata.database_path())?;
+    if let Some(db) = data.database.lock().unwrap().as_mut() {
+        db.reload()?;
+    }
+    Ok(())
 }
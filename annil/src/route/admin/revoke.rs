@@ -0,0 +1,57 @@
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::extractor::token::AnnilClaim;
+use crate::state::AnnilKeys;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use jwt_simple::prelude::MACLike;
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct RevokePayload {
+    /// `jti` claim of the token to revoke
+    #[serde(default)]
+    jti: Option<String>,
+    /// `exp` claim of the token, after which the id is dropped from the revocation list
+    #[serde(default)]
+    exp: Option<u64>,
+    /// The token to revoke, whose `jti` and `exp` claims are used instead of the fields above
+    #[serde(default)]
+    token: Option<String>,
+}
+
+/// Revoke a token by its id, so that it's rejected before expiry.
+///
+/// Share tokens signed with the share secret of a user token are revoked along with it.
+pub async fn revoke(
+    IsAdmin(is_admin): IsAdmin,
+    Extension(keys): Extension<Arc<AnnilKeys>>,
+    Json(payload): Json<RevokePayload>,
+) -> Response {
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+
+    let (jti, exp) = match payload.token {
+        Some(token) => match keys.sign_key.verify_token::<AnnilClaim>(&token, None) {
+            Ok(claims) => (
+                claims.jwt_id,
+                claims.expires_at.map(|expires_at| expires_at.as_secs()),
+            ),
+            Err(_) => return (StatusCode::BAD_REQUEST, "invalid token").into_response(),
+        },
+        None => (payload.jti, payload.exp),
+    };
+    let Some(jti) = jti else {
+        return (StatusCode::BAD_REQUEST, "token id is missing").into_response();
+    };
+
+    log::info!("Revoking token {jti}");
+    if let Err(e) = keys.revoked.revoke(jti, exp).await {
+        log::error!("Failed to save revocation list: {:?}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
@@ -1,46 +1,69 @@
-use crate::extractor::token::{AnnilClaim, ShareToken, UserClaim};
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::extractor::token::{AnnilClaim, ShareToken, TokenScope, UserClaim};
 use crate::state::AnnilKeys;
+use axum::response::{IntoResponse, Response};
 use axum::{Extension, Json};
 use jwt_simple::prelude::*;
 use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Deserialize, Clone)]
 pub struct SignPayload {
     user_id: String,
     #[serde(default)]
     share: bool,
+    /// Resources the signed token can access, defaults to both covers and audios
+    #[serde(default)]
+    scope: TokenScope,
+    /// Seconds before the signed token expires, never expires if not set
+    #[serde(default)]
+    expires_in: Option<u64>,
 }
 
 pub async fn sign(
+    IsAdmin(is_admin): IsAdmin,
     Extension(keys): Extension<Arc<AnnilKeys>>,
     Json(info): Json<SignPayload>,
-) -> String {
+) -> Response {
+    if !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+    if info.share && info.scope != TokenScope::Both {
+        return AnnilError::ScopedShare.into_response();
+    }
+
+    // random id, so that the token can be revoked individually
+    let jwt_id = Uuid::new_v4().to_string();
     let custom = AnnilClaim::User(UserClaim {
         user_id: info.user_id,
+        // share tokens are signed with a secret of this token, and revoked along with it
         share: if info.share {
             Some(ShareToken {
-                key_id: keys.share_key.key_id().as_deref().unwrap().to_string(),
-                secret: unsafe { String::from_utf8_unchecked(keys.share_key.to_bytes().to_vec()) },
+                key_id: jwt_id.clone(),
+                secret: keys.share_secret(&jwt_id),
                 allowed: None,
             })
         } else {
             None
         },
+        scope: info.scope,
     });
 
-    let now = Some(Clock::now_since_epoch());
+    let now = Clock::now_since_epoch();
     let claim = JWTClaims {
-        issued_at: now,
-        expires_at: None,
+        issued_at: Some(now),
+        expires_at: info.expires_in.map(|secs| now + Duration::from_secs(secs)),
         invalid_before: None,
         issuer: None,
         subject: None,
         audiences: None,
-        jwt_id: None,
+        jwt_id: Some(jwt_id),
         nonce: None,
         custom,
     };
     keys.sign_key
         .authenticate(claim)
         .expect("Failed to sign user token")
+        .into_response()
 }
@@ -0,0 +1,17 @@
+use crate::provider::AnnilProvider;
+use anni_provider::providers::MultipleProviders;
+use axum::{Extension, Json};
+use jwt_simple::reexports::serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Liveness check for load balancers, which requires no token.
+///
+/// Inner providers are not touched so that it responds quickly.
+pub async fn healthz(
+    Extension(provider): Extension<Arc<AnnilProvider<MultipleProviders>>>,
+) -> Json<Value> {
+    Json(json!({
+        "status": "ok",
+        "providers": provider.read().await.len(),
+    }))
+}
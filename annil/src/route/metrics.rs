@@ -0,0 +1,64 @@
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::metrics::{escape_label, Metrics};
+use crate::state::AnnilState;
+use anni_provider::cache::CacheStats;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Metrics in Prometheus text format.
+///
+/// It requires no token, unless `server.metrics-admin-only` is set.
+pub async fn metrics(
+    IsAdmin(is_admin): IsAdmin,
+    Extension(data): Extension<Arc<AnnilState>>,
+    Extension(metrics): Extension<Arc<Metrics>>,
+) -> Response {
+    if metrics.admin_only && !is_admin {
+        return AnnilError::Unauthorized.into_response();
+    }
+
+    let mut pools: Vec<_> = data
+        .caches
+        .read()
+        .await
+        .iter()
+        .map(|(key, pool)| (escape_label(key), pool.stats()))
+        .collect();
+    pools.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut out = String::new();
+    let cache_metrics: [(&str, &str, &str, fn(&CacheStats) -> u64); 3] = [
+        (
+            "annil_cache_hits_total",
+            "counter",
+            "Number of requests served by cached items.",
+            |stats| stats.hits,
+        ),
+        (
+            "annil_cache_misses_total",
+            "counter",
+            "Number of requests which fetched items to cache.",
+            |stats| stats.misses,
+        ),
+        (
+            "annil_cache_bytes",
+            "gauge",
+            "Bytes used by cached items.",
+            |stats| stats.space_used as u64,
+        ),
+    ];
+    for (name, kind, help, value) in cache_metrics {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        for (pool, stats) in pools.iter() {
+            let _ = writeln!(out, "{name}{{pool=\"{pool}\"}} {}", value(stats));
+        }
+    }
+    metrics.render(&mut out);
+
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], out).into_response()
+}
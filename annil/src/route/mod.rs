@@ -1,2 +1,4 @@
 pub mod admin;
+pub mod health;
+pub mod metrics;
 pub mod user;
@@ -0,0 +1,87 @@
+use crate::error::AnnilError;
+use crate::extractor::token::AnnilClaim;
+use crate::state::AnnilState;
+use anni_repo::db::RepoDatabaseRead;
+use anni_repo::models::JsonAlbum;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Get metadata of album with {album_id}, in the same format as `anni repo print --type json`
+///
+/// A catalog can be given instead of album id. If it maps to multiple albums, candidate
+/// album ids are returned with `409 Conflict`, and clients should retry with one of them.
+///
+/// Tokens limited to covers are rejected, and share tokens can only get metadata of albums
+/// shared by them.
+pub async fn album(
+    claim: AnnilClaim,
+    Path(album): Path<String>,
+    Extension(state): Extension<Arc<AnnilState>>,
+) -> Response {
+    if !claim.scope().allows_audio() {
+        return AnnilError::Forbidden.into_response();
+    }
+
+    if state.metadata.is_none() {
+        return AnnilError::NotFound.into_response();
+    }
+    let result = tokio::task::spawn_blocking(move || {
+        let database = state.database.lock().unwrap();
+        let database = database.as_ref().ok_or(AnnilError::DatabaseUnavailable)?;
+        let album_id = match Uuid::parse_str(&album) {
+            Ok(album_id) => album_id,
+            Err(_) => resolve_catalog(database, &album, &claim)?,
+        };
+        if let AnnilClaim::Share(share) = &claim {
+            if !share.audios.contains_key(&album_id.to_string()) {
+                return Err(AnnilError::Forbidden);
+            }
+        }
+        database
+            .read_album(album_id)
+            .map_err(|e| {
+                log::error!("Failed to read metadata of album {album_id}: {e}");
+                AnnilError::DatabaseUnavailable
+            })?
+            .ok_or(AnnilError::NotFound)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(album)) => Json(JsonAlbum::from(album)).into_response(),
+        Ok(Err(e)) => e.into_response(),
+        Err(e) => {
+            log::error!("Failed to read metadata of album: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Resolve `catalog` to a single album id.
+///
+/// Albums not shared by share tokens are not considered.
+fn resolve_catalog(
+    database: &RepoDatabaseRead,
+    catalog: &str,
+    claim: &AnnilClaim,
+) -> Result<Uuid, AnnilError> {
+    let mut album_ids = database.get_album_ids_by_catalog(catalog).map_err(|e| {
+        log::error!("Failed to resolve catalog {catalog}: {e}");
+        AnnilError::DatabaseUnavailable
+    })?;
+    if let AnnilClaim::Share(share) = claim {
+        album_ids.retain(|album_id| share.audios.contains_key(&album_id.to_string()));
+    }
+
+    match album_ids.len() {
+        0 => Err(AnnilError::NotFound),
+        1 => Ok(album_ids[0]),
+        _ => Err(AnnilError::AmbiguousCatalog(
+            album_ids.iter().map(Uuid::to_string).collect(),
+        )),
+    }
+}
@@ -1,21 +1,29 @@
+use super::select_provider;
 use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
 use crate::extractor::token::AnnilClaim;
 use crate::extractor::track::TrackIdentifier;
+use crate::http_cache::{audio_etag, if_none_match};
 use crate::provider::AnnilProvider;
+use crate::range::{multipart_byteranges, unsatisfiable, RangeRequest};
+use crate::state::AnnilState;
 use crate::transcode::*;
-use crate::utils::Either;
-use anni_provider::{AnniProvider, Range};
+#[cfg(feature = "transcode")]
+use crate::utils::slice_stream;
+use crate::utils::{limit_stream, Either};
+use anni_provider::{AnniProvider, AudioResourceReader, Range};
 use axum::body::StreamBody;
 use axum::extract::Query;
 use axum::http::header::{
     ACCEPT_RANGES, ACCESS_CONTROL_EXPOSE_HEADERS, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE,
-    CONTENT_TYPE,
+    CONTENT_TYPE, ETAG, IF_NONE_MATCH, RANGE, VARY,
 };
-use axum::http::{HeaderMap, StatusCode};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Extension;
 use futures::StreamExt;
 use serde::Deserialize;
+use std::io;
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio_util::io::ReaderStream;
@@ -68,12 +76,42 @@ pub struct AudioQuery {
 
     #[serde(default)]
     opus: bool,
+
+    /// Codec to transcode audio to with ffmpeg, which must be enabled in `server.transcode`
+    transcode: Option<String>,
+    /// Bitrate of transcoded audio like `128k`, defaults to the one of requested quality
+    bitrate: Option<String>,
+
+    /// Name of provider to serve the request, only respected for admin
+    provider: Option<String>,
 }
 
 impl AudioQuery {
-    pub fn get_transcoder(&self, is_guest: bool) -> Box<dyn Transcode + Send + Sync> {
+    pub fn get_transcoder(
+        &self,
+        is_guest: bool,
+        config: &TranscodeConfig,
+    ) -> Result<Box<dyn Transcode + Send + Sync>, AnnilError> {
         let quality = self.quality(is_guest);
-        if quality.need_transcode() {
+        if let Some(codec) = &self.transcode {
+            let codec = Codec::from_str(codec)
+                .ok()
+                .filter(|codec| config.enabled(*codec))
+                .ok_or(AnnilError::UnsupportedCodec)?;
+            let bitrate = match &self.bitrate {
+                Some(bitrate) => parse_bitrate(bitrate).ok_or(AnnilError::InvalidBitrate)?,
+                None => quality_bitrate(quality),
+            };
+            // guests are limited to low quality
+            let bitrate = if is_guest {
+                bitrate.min(quality_bitrate(AudioQuality::Low))
+            } else {
+                bitrate
+            };
+            return Ok(Box::new(FfmpegTranscoder::new(codec, bitrate, quality)));
+        }
+
+        Ok(if quality.need_transcode() {
             if self.opus {
                 Box::new(OpusTranscoder::new(quality))
             } else {
@@ -81,7 +119,7 @@ impl AudioQuery {
             }
         } else {
             Box::new(FlacTranscoder::new(quality))
-        }
+        })
     }
 
     fn quality(&self, is_guest: bool) -> AudioQuality {
@@ -92,20 +130,43 @@ impl AudioQuery {
     }
 }
 
+/// `ETag`, `Vary` and `Cache-Control` of audio responses.
+fn cache_headers(state: &AnnilState, etag: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, etag.parse().unwrap());
+    headers.insert(VARY, HeaderValue::from_static("Range"));
+    if let Some(config) = &state.http_cache {
+        headers.insert(CACHE_CONTROL, config.audio_cache_control().parse().unwrap());
+    }
+    headers
+}
+
 pub async fn audio_head<P>(
     claim: AnnilClaim,
+    IsAdmin(is_admin): IsAdmin,
     track: TrackIdentifier,
+    Extension(state): Extension<Arc<AnnilState>>,
     Extension(provider): Extension<Arc<AnnilProvider<P>>>,
     query: Query<AudioQuery>,
+    request_headers: HeaderMap,
 ) -> Response
 where
     P: AnniProvider + Send + Sync,
 {
+    if !claim.scope().allows_audio() {
+        return AnnilError::Forbidden.into_response();
+    }
     if !claim.can_fetch(&track) {
         return AnnilError::Unauthorized.into_response();
     }
 
     let provider = provider.read().await;
+    let provider = match select_provider(&*provider, query.provider.as_deref(), is_admin) {
+        Ok(provider) => provider,
+        Err(e) => return e.into_response(),
+    };
+    // responses of a forced provider should not be taken from client cache
+    let forced = is_admin && query.provider.is_some();
     let album_id = track.album_id.to_string();
     if !provider.has_album(&album_id).await {
         return (StatusCode::NOT_FOUND, [(CACHE_CONTROL, "private")]).into_response();
@@ -114,29 +175,47 @@ where
     let audio = provider
         .get_audio_info(&album_id, track.disc_id, track.track_id)
         .await
-        .map_err(|_| AnnilError::NotFound);
+        .map_err(AnnilError::from);
 
-    let transcoder = query.get_transcoder(claim.is_guest());
+    let transcoder = match query.get_transcoder(claim.is_guest(), &state.transcode) {
+        Ok(transcoder) => transcoder,
+        Err(e) => return e.into_response(),
+    };
     let need_transcode = transcoder.need_transcode();
 
     return match audio {
         Ok(info) => {
+            let etag = audio_etag(
+                &album_id,
+                track.disc_id.get(),
+                track.track_id.get(),
+                info.size,
+                need_transcode.then(|| transcoder.variant()).as_deref(),
+            );
+            let cache_headers = cache_headers(&state, &etag);
+            if !forced && if_none_match(&request_headers, &etag) {
+                return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+            }
+
             let headers = [
                         (
                             CONTENT_TYPE,
                             if need_transcode {
                                 transcoder.content_type().to_string()
                             } else {
-                                format!("audio/{}", info.extension)
+                                state.mime.content_type(&info.extension).to_string()
                             },
                         ),
                         (
                             ACCESS_CONTROL_EXPOSE_HEADERS,
-                            "X-Origin-Type, X-Origin-Size, X-Duration-Seconds, X-Audio-Quality, Accept-Ranges".to_string(),
+                            "X-Origin-Type, X-Origin-Size, X-Duration-Seconds, X-Audio-Quality, X-Estimated-Size, Accept-Ranges".to_string(),
                         ),
                     ];
             let custom_headers = [
-                ("X-Origin-Type", format!("audio/{}", info.extension)),
+                (
+                    "X-Origin-Type",
+                    state.mime.content_type(&info.extension).to_string(),
+                ),
                 ("X-Origin-Size", format!("{}", info.size)),
                 ("X-Duration-Seconds", format!("{}", info.duration / 1000)),
                 (
@@ -150,77 +229,235 @@ where
             if let Some(length) = transcoder.content_length(&info) {
                 transcode_headers.insert(CONTENT_LENGTH, length.into());
             }
+            if need_transcode {
+                if let Some(size) = transcoder.estimated_size(&info) {
+                    transcode_headers.insert("X-Estimated-Size", size.into());
+                }
+            }
 
-            // TODO: support range for all formats with CONTENT_LENGTH
-            if !need_transcode {
+            // transcoded output supports range if its size is known
+            let supports_range = if need_transcode {
+                cfg!(feature = "transcode") && transcoder.content_length(&info).is_some()
+            } else {
+                provider.album_capabilities(&album_id).await.supports_range
+                    && info.known_size().is_some()
+            };
+            if supports_range {
                 transcode_headers.insert(ACCEPT_RANGES, "bytes".parse().unwrap());
             }
 
-            (headers, custom_headers, transcode_headers).into_response()
+            (headers, custom_headers, transcode_headers, cache_headers).into_response()
         }
         Err(e) => e.into_response(),
     };
 }
 
+/// Length of response body, clamped to the actual size of audio.
+fn body_length(audio: &AudioResourceReader) -> u64 {
+    let size = audio.info.size as u64;
+    if audio.range.start >= size {
+        0
+    } else {
+        audio.range.length_limit(size - 1)
+    }
+}
+
+/// Body of untranscoded audio, with `Content-Length` if its size is known.
+///
+/// Audio of unknown size is sent with chunked transfer encoding.
+fn audio_body(audio: AudioResourceReader, state: &AnnilState) -> impl IntoResponse {
+    let content_type = [(
+        CONTENT_TYPE,
+        state.mime.content_type(&audio.info.extension).to_string(),
+    )];
+    match audio.info.known_size() {
+        Some(_) => {
+            let size = body_length(&audio);
+            Either::Left((
+                content_type,
+                [(CONTENT_LENGTH, format!("{size}"))],
+                StreamBody::new(ReaderStream::new(audio.reader).take(size as usize)),
+            ))
+        }
+        None => Either::Right((
+            content_type,
+            StreamBody::new(limit_stream(
+                ReaderStream::new(audio.reader),
+                state.max_response_size,
+            )),
+        )),
+    }
+}
+
+/// Split ranges into whether range is requested, the first range and other ranges of a multi-range
+/// request, which are fetched after the first one.
+fn split_ranges(range: RangeRequest) -> (bool, Range, Vec<Range>) {
+    match range {
+        RangeRequest::Partial(range) => (true, range, Vec::new()),
+        RangeRequest::Multiple(mut ranges) => {
+            let first = ranges.remove(0);
+            (true, first, ranges)
+        }
+        _ => (false, Range::FULL, Vec::new()),
+    }
+}
+
 /// Get audio in an album with `album_id`, `disc_id` and `track_id`
+#[cfg_attr(not(feature = "transcode"), allow(unused_variables))]
 pub async fn audio<P>(
     claim: AnnilClaim,
+    IsAdmin(is_admin): IsAdmin,
     track: TrackIdentifier,
-    Extension(provider): Extension<Arc<AnnilProvider<P>>>,
+    Extension(state): Extension<Arc<AnnilState>>,
+    Extension(providers): Extension<Arc<AnnilProvider<P>>>,
     query: Query<AudioQuery>,
     headers: HeaderMap,
 ) -> Response
 where
-    P: AnniProvider + Send + Sync,
+    P: AnniProvider + Send + Sync + 'static,
 {
+    if !claim.scope().allows_audio() {
+        return AnnilError::Forbidden.into_response();
+    }
     if !claim.can_fetch(&track) {
         return AnnilError::Unauthorized.into_response();
     }
 
-    let provider = provider.read().await;
+    let provider = providers.read().await;
+    let provider = match select_provider(&*provider, query.provider.as_deref(), is_admin) {
+        Ok(provider) => provider,
+        Err(e) => return e.into_response(),
+    };
+    // responses of a forced provider should not be taken from client cache
+    let forced = is_admin && query.provider.is_some();
     let album_id = track.album_id.to_string();
 
-    let range = headers.get("Range").and_then(|r| {
-        let range = r.to_str().ok()?;
-        let (_, right) = range.split_once('=')?;
-        let (from, to) = right.split_once('-')?;
-        let range = Range::new(from.parse().ok()?, to.parse().ok());
-        Some(if range.is_full() {
-            Range::new(0, Some(1023))
-        } else {
-            range
-        })
-    });
-    let need_range = range.is_some();
-    let range = range.unwrap_or(Range::FULL);
-
     if !provider.has_album(&album_id).await {
         return (StatusCode::NOT_FOUND, [(CACHE_CONTROL, "private")]).into_response();
     }
 
-    let transcoder = query.get_transcoder(claim.is_guest());
-    let need_range = need_range && !transcoder.need_transcode(); // Only support range if transcode is not performed
+    let transcoder = match query.get_transcoder(claim.is_guest(), &state.transcode) {
+        Ok(transcoder) => transcoder,
+        Err(e) => return e.into_response(),
+    };
+    let variant = transcoder.need_transcode().then(|| transcoder.variant());
+    let etag = |size| {
+        audio_etag(
+            &album_id,
+            track.disc_id.get(),
+            track.track_id.get(),
+            size,
+            variant.as_deref(),
+        )
+    };
+    // etag is checked by audio info, before audio is opened
+    if !forced && headers.contains_key(IF_NONE_MATCH) {
+        match provider
+            .get_audio_info(&album_id, track.disc_id, track.track_id)
+            .await
+        {
+            Ok(info) => {
+                let etag = etag(info.size);
+                if if_none_match(&headers, &etag) {
+                    return (StatusCode::NOT_MODIFIED, cache_headers(&state, &etag))
+                        .into_response();
+                }
+            }
+            Err(e) => return AnnilError::from(e).into_response(),
+        }
+    }
 
-    // range is only supported on lossless
-    #[cfg(feature = "transcode")]
-    let range = if transcoder.need_transcode() {
-        Range::FULL
+    // Only support range if transcode is not performed, and provider can handle range.
+    // Otherwise the whole file is served.
+    let range = if headers.contains_key(RANGE)
+        && !transcoder.need_transcode()
+        && provider.album_capabilities(&album_id).await.supports_range
+    {
+        match RangeRequest::from_headers_without_size(&headers) {
+            Some(range) => range,
+            // size is required to parse suffix ranges
+            None => match provider
+                .get_audio_info(&album_id, track.disc_id, track.track_id)
+                .await
+            {
+                Ok(info) => match info.known_size().map(|size| size as u64) {
+                    Some(size) => match RangeRequest::from_headers(&headers, size) {
+                        RangeRequest::Unsatisfiable => return unsatisfiable(size),
+                        range => range,
+                    },
+                    // audio of unknown size is always served in full
+                    None => RangeRequest::Full,
+                },
+                Err(e) => return AnnilError::from(e).into_response(),
+            },
+        }
     } else {
-        range
+        RangeRequest::Full
     };
+    let (mut need_range, mut range, mut other_ranges) = split_ranges(range);
 
-    let audio = provider
+    let mut audio = provider
         .get_audio(&album_id, track.disc_id, track.track_id, range)
-        .await
-        .map_err(|_| AnnilError::NotFound);
+        .await;
+    if need_range {
+        // Ranges are validated by size of the fetched audio. Info is fetched separately only if
+        // provider rejected the range, which may start beyond the end.
+        let size = match &audio {
+            Ok(audio) => audio.info.known_size(),
+            Err(_) => provider
+                .get_audio_info(&album_id, track.disc_id, track.track_id)
+                .await
+                .ok()
+                .and_then(|info| info.known_size()),
+        };
+        let validated = size.map(|size| RangeRequest::from_headers(&headers, size as u64));
+        match (size, validated) {
+            (Some(size), Some(RangeRequest::Unsatisfiable)) => return unsatisfiable(size as u64),
+            // keep the error if size is still unknown
+            (None, _) if audio.is_err() => {}
+            (_, validated) => {
+                // audio of unknown size is always served in full
+                let (validated_need, first, others) =
+                    split_ranges(validated.unwrap_or(RangeRequest::Full));
+                // first range is dropped if it starts beyond the end
+                if audio.is_err() || !validated_need || first.start != range.start {
+                    audio = provider
+                        .get_audio(&album_id, track.disc_id, track.track_id, first)
+                        .await;
+                }
+                (need_range, range, other_ranges) = (validated_need, first, others);
+            }
+        }
+    }
+    let audio = audio.map_err(AnnilError::from);
 
     return match audio {
         Ok(audio) => {
-            let (status, range) = if need_range && !audio.range.is_full() {
+            let cache_headers = cache_headers(&state, &etag(audio.info.size));
+
+            // Transcoded output can not be seeked. Ranges of it are served by transcoding from
+            // the start and dropping bytes before the range, so the cost grows with range start.
+            // If size of the output is unknown, only ranges with both start and end are served.
+            #[cfg(feature = "transcode")]
+            let transcode_range = if transcoder.need_transcode() {
+                match transcoder.content_length(&audio.info) {
+                    Some(length) => match RangeRequest::from_headers(&headers, length as u64) {
+                        RangeRequest::Unsatisfiable => return unsatisfiable(length as u64),
+                        range => range,
+                    },
+                    None => RangeRequest::from_headers_unknown_size(&headers),
+                }
+            } else {
+                RangeRequest::Full
+            };
+
+            let (status, range_headers) = if need_range {
+                // range served by provider, which may end beyond the end if not clamped by it
+                let served = audio.range.end_with(audio.info.size as u64);
                 (
                     StatusCode::PARTIAL_CONTENT,
                     Some([
-                        (CONTENT_RANGE, audio.range.to_content_range_header()),
+                        (CONTENT_RANGE, served.to_content_range_header()),
                         (ACCEPT_RANGES, "bytes".to_string()),
                     ]),
                 )
@@ -230,11 +467,15 @@ where
 
             let header = [(
                 ACCESS_CONTROL_EXPOSE_HEADERS,
-                "X-Origin-Type, X-Origin-Size, X-Duration-Seconds, X-Audio-Quality".to_string(),
+                "X-Origin-Type, X-Origin-Size, X-Duration-Seconds, X-Audio-Quality, X-Estimated-Size"
+                    .to_string(),
             )];
 
             let headers = [
-                ("X-Origin-Type", format!("audio/{}", audio.info.extension)),
+                (
+                    "X-Origin-Type",
+                    state.mime.content_type(&audio.info.extension).to_string(),
+                ),
                 ("X-Origin-Size", format!("{}", audio.info.size)),
                 (
                     "X-Duration-Seconds",
@@ -246,11 +487,60 @@ where
                 ),
             ];
 
+            if !other_ranges.is_empty() {
+                let content_type = state.mime.content_type(&audio.info.extension).to_string();
+                // other parts are fetched in the body stream, after the previous part is sent
+                let open = {
+                    let providers = providers.clone();
+                    let name = query.provider.clone();
+                    let album_id = album_id.clone();
+                    move |range| {
+                        let providers = providers.clone();
+                        let name = name.clone();
+                        let album_id = album_id.clone();
+                        async move {
+                            let providers = providers.read().await;
+                            let provider = select_provider(&*providers, name.as_deref(), is_admin)
+                                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                            provider
+                                .get_audio(&album_id, track.disc_id, track.track_id, range)
+                                .await
+                                .map(|audio| audio.reader)
+                                .map_err(|e| {
+                                    log::error!("Failed to fetch range of audio: {e}");
+                                    io::Error::new(io::ErrorKind::Other, e)
+                                })
+                        }
+                    }
+                };
+                return (
+                    StatusCode::PARTIAL_CONTENT,
+                    [(ACCEPT_RANGES, "bytes")],
+                    header,
+                    headers,
+                    cache_headers,
+                    multipart_byteranges(
+                        (range, audio.reader),
+                        other_ranges,
+                        open,
+                        &content_type,
+                        state.max_response_size,
+                    ),
+                )
+                    .into_response();
+            }
+
             #[cfg(feature = "transcode")]
             let body = if transcoder.quality().need_transcode() {
                 let mut transcode_headers = HeaderMap::new();
                 let info = audio.info.clone();
-                let mut process = transcoder.spawn();
+                let mut process = match transcoder.spawn() {
+                    Ok(process) => process,
+                    Err(e) => {
+                        log::error!("Failed to spawn transcoder: {e}");
+                        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                    }
+                };
                 let stdout = process.stdout.take().unwrap();
                 tokio::spawn(async move {
                     let mut stdin = process.stdin.as_mut().unwrap();
@@ -261,6 +551,32 @@ where
                     CONTENT_TYPE,
                     transcoder.content_type().to_string().parse().unwrap(),
                 );
+                if let Some(size) = transcoder.estimated_size(&info) {
+                    transcode_headers.insert("X-Estimated-Size", size.into());
+                }
+                if let RangeRequest::Partial(range) = transcode_range {
+                    // end of range is always known
+                    let length = range.length().unwrap();
+                    transcode_headers.insert(CONTENT_LENGTH, length.into());
+                    // length of the output is estimated, so the same limit as other responses applies
+                    let body = StreamBody::new(limit_stream(
+                        slice_stream(ReaderStream::new(stdout), range.start, Some(length)),
+                        state.max_response_size,
+                    ));
+                    return (
+                        StatusCode::PARTIAL_CONTENT,
+                        [
+                            (CONTENT_RANGE, range.to_content_range_header()),
+                            (ACCEPT_RANGES, "bytes".to_string()),
+                        ],
+                        header,
+                        headers,
+                        cache_headers,
+                        transcode_headers,
+                        body,
+                    )
+                        .into_response();
+                }
                 if let Some(length) = transcoder.content_length(&info) {
                     transcode_headers.insert(CONTENT_LENGTH, length.to_string().parse().unwrap());
                 }
@@ -273,33 +589,20 @@ where
                 } else {
                     Either::Left((
                         transcode_headers,
-                        Either::Right(StreamBody::new(ReaderStream::new(stdout))),
+                        Either::Right(StreamBody::new(limit_stream(
+                            ReaderStream::new(stdout),
+                            state.max_response_size,
+                        ))),
                     ))
                 }
             } else {
-                let size = audio.range.length().unwrap_or(audio.info.size as u64);
-                Either::Right((
-                    [
-                        (CONTENT_TYPE, format!("audio/{}", audio.info.extension)),
-                        (CONTENT_LENGTH, format!("{size}")),
-                    ],
-                    StreamBody::new(ReaderStream::new(audio.reader).take(size as usize)),
-                ))
+                Either::Right(audio_body(audio, &state))
             };
 
             #[cfg(not(feature = "transcode"))]
-            let body = {
-                let size = audio.range.length().unwrap_or(audio.info.size as u64);
-                (
-                    [
-                        (CONTENT_LENGTH, format!("{size}")),
-                        (CONTENT_TYPE, format!("audio/{}", audio.info.extension)),
-                    ],
-                    StreamBody::new(ReaderStream::new(audio.reader).take(size as usize)),
-                )
-            };
+            let body = audio_body(audio, &state);
 
-            (status, range, header, headers, body).into_response()
+            (status, range_headers, header, headers, cache_headers, body).into_response()
         }
         Err(e) => e.into_response(),
     };
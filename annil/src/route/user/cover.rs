@@ -1,16 +1,23 @@
-use axum::body::StreamBody;
-use axum::extract::Path;
-use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
-use axum::http::StatusCode;
+use axum::body::Bytes;
+use axum::extract::{Path, Query};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG};
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Extension;
 use std::num::NonZeroU8;
 use std::sync::Arc;
 
+use super::select_provider;
+use crate::cover::{cover_size, CoverError, CoverFormat};
+use crate::error::AnnilError;
+use crate::extractor::admin::IsAdmin;
+use crate::extractor::token::AnnilClaim;
+use crate::http_cache::{cover_etag, if_none_match, DEFAULT_COVER_CACHE_CONTROL};
 use crate::provider::AnnilProvider;
-use anni_provider::AnniProvider;
+use crate::state::AnnilState;
+use anni_provider::{AnniProvider, ProviderError};
 use serde::Deserialize;
-use tokio_util::io::ReaderStream;
+use tokio::io::AsyncReadExt;
 use uuid::Uuid;
 
 #[derive(Deserialize)]
@@ -19,30 +26,138 @@ pub struct CoverPath {
     disc_id: Option<NonZeroU8>,
 }
 
+#[derive(Deserialize)]
+pub struct CoverQuery {
+    /// Name of provider to serve the request, only respected for admin
+    provider: Option<String>,
+    /// Resize cover to fit in a square of `size` rounded up by [cover_size]
+    size: Option<u32>,
+}
+
 /// Get audio cover of an album with {album_id} and optional {disc_id}
+///
+/// Covers are public, but a token without cover scope is rejected.
 pub async fn cover<P>(
+    claim: Option<AnnilClaim>,
+    IsAdmin(is_admin): IsAdmin,
     Path(CoverPath { album_id, disc_id }): Path<CoverPath>,
+    Query(query): Query<CoverQuery>,
+    Extension(state): Extension<Arc<AnnilState>>,
     Extension(provider): Extension<Arc<AnnilProvider<P>>>,
+    headers: HeaderMap,
 ) -> Response
 where
     P: AnniProvider + Send + Sync,
 {
+    if claim.is_some_and(|claim| !claim.scope().allows_cover()) {
+        return AnnilError::Forbidden.into_response();
+    }
+
     let provider = provider.read().await;
+    let provider = match select_provider(&*provider, query.provider.as_deref(), is_admin) {
+        Ok(provider) => provider,
+        Err(e) => return e.into_response(),
+    };
+    // responses of a forced provider should not be taken from client cache
+    let forced = is_admin && query.provider.is_some();
     let album_id = album_id.to_string();
 
-    if !provider.has_album(&album_id).await {
+    if !provider.has_album(&album_id).await
+        || !provider.album_capabilities(&album_id).await.has_covers
+    {
         return (StatusCode::NOT_FOUND, [(CACHE_CONTROL, "private")]).into_response();
     }
 
-    match provider.get_cover(&album_id, disc_id).await {
-        Ok(cover) => (
-            ([
-                (CONTENT_TYPE, "image/jpeg"),
-                (CACHE_CONTROL, "public, max-age=31536000"),
-            ]),
-            StreamBody::new(ReaderStream::new(cover)),
+    // resized covers are encoded in configured format, or jpeg by default
+    let size = query.size.filter(|size| *size > 0).map(cover_size);
+    let format = match size {
+        Some(_) => Some(state.cover_format.unwrap_or(CoverFormat::Jpeg)),
+        None => state.cover_format,
+    };
+
+    // covers are small, so they are read before sending to compute etag from their content
+    let (cover, content_type) = match format {
+        None => match read_cover(provider, &album_id, disc_id).await {
+            Ok(cover) => (cover, "jpg"),
+            Err(e) => return e,
+        },
+        Some(format) => {
+            let cover = state
+                .covers
+                .get_or_transcode(&album_id, disc_id.map(|d| d.get()), format, size, || {
+                    provider.get_cover(&album_id, disc_id)
+                })
+                .await;
+            match cover {
+                Ok(cover) => (cover, format.as_str()),
+                Err(CoverError::Provider(e)) => return cover_error(e),
+                // serve the original cover if it can not be resized
+                Err(CoverError::Transcode {
+                    error,
+                    original: Some(cover),
+                }) if size.is_some() => {
+                    log::warn!(
+                        "Failed to resize cover of {album_id}, serving the original one: {error}"
+                    );
+                    (cover, "jpg")
+                }
+                Err(CoverError::Transcode { error, .. }) => {
+                    log::error!("Failed to transcode cover of {album_id}: {error}");
+                    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            }
+        }
+    };
+
+    let etag = cover_etag(&cover);
+    let cache_control = match &state.http_cache {
+        Some(config) => config.cover_cache_control(),
+        None => DEFAULT_COVER_CACHE_CONTROL.to_string(),
+    };
+    if !forced && if_none_match(&headers, &etag) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(ETAG, etag), (CACHE_CONTROL, cache_control)],
         )
-            .into_response(),
-        Err(_) => (StatusCode::NOT_FOUND, [(CACHE_CONTROL, "private")]).into_response(),
+            .into_response();
+    }
+    (
+        [
+            (
+                CONTENT_TYPE,
+                state.mime.content_type(content_type).to_string(),
+            ),
+            (CACHE_CONTROL, cache_control),
+            (ETAG, etag),
+        ],
+        cover,
+    )
+        .into_response()
+}
+
+/// Read the original cover into memory.
+async fn read_cover(
+    provider: &(dyn AnniProvider + Send + Sync),
+    album_id: &str,
+    disc_id: Option<NonZeroU8>,
+) -> Result<Bytes, Response> {
+    let mut reader = provider
+        .get_cover(album_id, disc_id)
+        .await
+        .map_err(cover_error)?;
+    let mut cover = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut cover).await {
+        log::error!("Failed to read cover of {album_id}: {e}");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+    }
+    Ok(Bytes::from(cover))
+}
+
+fn cover_error(error: ProviderError) -> Response {
+    match error {
+        e @ (ProviderError::Timeout { .. } | ProviderError::DatabaseUnavailable(_)) => {
+            AnnilError::from(e).into_response()
+        }
+        _ => (StatusCode::NOT_FOUND, [(CACHE_CONTROL, "private")]).into_response(),
     }
 }
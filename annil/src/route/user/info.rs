@@ -1,12 +1,26 @@
+use crate::http_cache::if_none_match;
 use crate::state::AnnilState;
+use axum::http::header::ETAG;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::{Extension, Json};
-use jwt_simple::reexports::serde_json::{json, Value};
+use jwt_simple::reexports::serde_json::json;
 use std::sync::Arc;
 
-pub async fn info(Extension(data): Extension<Arc<AnnilState>>) -> Json<Value> {
-    Json(json!({
-        "version": data.version,
-        "protocol_version": "0.4.1",
-        "last_update": *data.last_update.read().await,
-    }))
+pub async fn info(Extension(data): Extension<Arc<AnnilState>>, headers: HeaderMap) -> Response {
+    // etag changes when albums of providers change
+    let etag = data.etag.read().await.clone();
+    if if_none_match(&headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+    }
+
+    (
+        [(ETAG, etag)],
+        Json(json!({
+            "version": data.version,
+            "protocol_version": "0.4.1",
+            "last_update": *data.last_update.read().await,
+        })),
+    )
+        .into_response()
 }
@@ -1,9 +1,35 @@
+#[cfg(feature = "metadata")]
+mod album;
 mod albums;
 mod audio;
 mod cover;
 mod info;
 
+#[cfg(feature = "metadata")]
+pub use album::*;
 pub use albums::*;
 pub use audio::*;
 pub use cover::*;
 pub use info::*;
+
+use crate::error::AnnilError;
+use anni_provider::AnniProvider;
+
+/// Select provider named `name` for admin requests, bypassing the normal routing.
+///
+/// `name` is ignored for non-admin requests.
+fn select_provider<'a, P>(
+    provider: &'a P,
+    name: Option<&str>,
+    is_admin: bool,
+) -> Result<&'a (dyn AnniProvider + Send + Sync), AnnilError>
+where
+    P: AnniProvider + Send + Sync,
+{
+    match name {
+        Some(name) if is_admin => provider
+            .named_provider(name)
+            .ok_or(AnnilError::UnknownProvider),
+        _ => Ok(provider),
+    }
+}
@@ -1,11 +1,18 @@
-use jwt_simple::prelude::HS256Key;
+use crate::revoke::RevocationList;
+use anni_provider::cache::CachePool;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jwt_simple::prelude::{HS256Key, MACLike};
+use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::RwLock;
 
-/// Readonly keys
+/// Keys to verify tokens, and tokens revoked before expiry
 pub struct AnnilKeys {
     pub sign_key: HS256Key,
     pub share_key: HS256Key,
     pub admin_token: String,
+    pub revoked: RevocationList,
 }
 
 impl AnnilKeys {
@@ -14,6 +21,28 @@ impl AnnilKeys {
             sign_key: HS256Key::from_bytes(sign_key),
             share_key: HS256Key::from_bytes(share_key),
             admin_token,
+            revoked: Default::default(),
+        }
+    }
+
+    /// Secret to sign share tokens of the user token with id `jti`.
+    ///
+    /// Share tokens signed by it have `jti` as key id, so that they are rejected
+    /// once the user token is revoked.
+    pub fn share_secret(&self, jti: &str) -> String {
+        let mac = hmac_sha256::HMAC::mac(jti.as_bytes(), self.share_key.to_bytes());
+        URL_SAFE_NO_PAD.encode(mac)
+    }
+
+    /// Key to verify share tokens with key id `key_id`.
+    ///
+    /// Tokens with the configured `share-key-id` are verified by the share key itself,
+    /// as they are signed with secrets given out by older versions.
+    pub(crate) fn share_key(&self, key_id: &str) -> HS256Key {
+        if self.share_key.key_id().as_deref() == Some(key_id) {
+            self.share_key.clone()
+        } else {
+            HS256Key::from_bytes(self.share_secret(key_id).as_bytes()).with_key_id(key_id)
         }
     }
 }
@@ -24,4 +53,24 @@ pub struct AnnilState {
     pub etag: RwLock<String>,
 
     pub metadata: Option<crate::metadata::MetadataConfig>,
+    /// Metadata database shared by requests of album metadata, `None` if it's not opened.
+    ///
+    /// It's always `None` without `metadata` feature.
+    pub database: std::sync::Mutex<Option<anni_provider::RepoDatabaseRead>>,
+    /// Timeout of providers without their own `timeout-secs`, used by providers added at runtime.
+    pub provider_timeout_secs: Option<u64>,
+    /// Maximum bytes sent in a single response whose size is unknown.
+    /// `None` means unlimited.
+    pub max_response_size: Option<u64>,
+    /// Cache directives for CDN. `None` means audio responses do not have `Cache-Control`.
+    pub http_cache: Option<crate::http_cache::HttpCacheConfig>,
+    /// Transcode covers to this format before serving. `None` means covers are served untouched.
+    pub cover_format: Option<crate::cover::CoverFormat>,
+    /// `Content-Type` overrides of audio extensions.
+    pub mime: crate::mime::MimeConfig,
+    /// Codecs which audio can be transcoded to on request.
+    pub transcode: crate::transcode::TranscodeConfig,
+    pub covers: crate::cover::CoverCache,
+    /// Cache pools used by providers, keyed by cache root.
+    pub caches: RwLock<HashMap<String, Arc<CachePool>>>,
 }
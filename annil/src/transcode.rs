@@ -1,6 +1,9 @@
 use crate::{route::user::AudioQuality, utils::opus_file_size};
 use anni_provider::AudioInfo;
+use serde::Deserialize;
+use std::borrow::Cow;
 use std::process::Stdio;
+use std::str::FromStr;
 use tokio::process::Child;
 
 pub trait Transcode {
@@ -12,9 +15,20 @@ pub trait Transcode {
         self.quality().need_transcode()
     }
 
+    /// Distinguishes outputs of the same audio, used in `ETag` of transcoded audio.
+    fn variant(&self) -> Cow<'static, str> {
+        Cow::Borrowed(self.quality().as_str())
+    }
+
     fn content_length(&self, info: &AudioInfo) -> Option<usize>;
 
-    fn spawn(&self) -> Child;
+    /// Approximate size of the output, for clients to show progress when `Content-Length` is not sent.
+    fn estimated_size(&self, info: &AudioInfo) -> Option<u64> {
+        self.content_length(info).map(|length| length as u64)
+    }
+
+    /// Spawn the transcoder process, which fails if the encoder is not installed.
+    fn spawn(&self) -> std::io::Result<Child>;
 }
 
 pub struct AacTranscoder(AudioQuality);
@@ -38,7 +52,7 @@ impl Transcode for AacTranscoder {
         self.0
     }
 
-    fn spawn(&self) -> Child {
+    fn spawn(&self) -> std::io::Result<Child> {
         let bitrate = match self.quality() {
             AudioQuality::Low => "128k",
             AudioQuality::Medium => "192k",
@@ -54,7 +68,6 @@ impl Transcode for AacTranscoder {
             .stdout(Stdio::piped())
             .stderr(Stdio::null())
             .spawn()
-            .unwrap()
     }
 
     fn content_length(&self, _: &AudioInfo) -> Option<usize> {
@@ -92,7 +105,7 @@ impl Transcode for OpusTranscoder {
         self.0
     }
 
-    fn spawn(&self) -> Child {
+    fn spawn(&self) -> std::io::Result<Child> {
         #[rustfmt::skip]
         let args = &[
             "--bitrate", &self.bit_rate().to_string(),
@@ -112,7 +125,6 @@ impl Transcode for OpusTranscoder {
             .stdout(Stdio::piped())
             .stderr(Stdio::null())
             .spawn()
-            .unwrap()
     }
 
     fn content_length(&self, info: &AudioInfo) -> Option<usize> {
@@ -141,11 +153,182 @@ impl Transcode for FlacTranscoder {
         AudioQuality::Lossless
     }
 
-    fn spawn(&self) -> Child {
-        panic!("FlacTranscoder cannot transcode")
+    fn spawn(&self) -> std::io::Result<Child> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "FlacTranscoder cannot transcode",
+        ))
     }
 
     fn content_length(&self, info: &AudioInfo) -> Option<usize> {
-        Some(info.size)
+        info.known_size()
+    }
+}
+
+/// Codecs which audio can be transcoded to with ffmpeg, requested by `transcode` query.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    Opus,
+    Aac,
+    Mp3,
+}
+
+impl FromStr for Codec {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "opus" => Ok(Codec::Opus),
+            "aac" => Ok(Codec::Aac),
+            "mp3" => Ok(Codec::Mp3),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Codec {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Codec::Opus => "opus",
+            Codec::Aac => "aac",
+            Codec::Mp3 => "mp3",
+        }
+    }
+
+    /// Encoder and output format used by ffmpeg.
+    fn ffmpeg_args(&self) -> [&'static str; 4] {
+        match self {
+            Codec::Opus => ["-c:a", "libopus", "-f", "ogg"],
+            Codec::Aac => ["-c:a", "aac", "-f", "adts"],
+            Codec::Mp3 => ["-c:a", "libmp3lame", "-f", "mp3"],
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            Codec::Opus => "audio/ogg",
+            Codec::Aac => "audio/aac",
+            Codec::Mp3 => "audio/mpeg",
+        }
+    }
+}
+
+/// Codecs enabled for `transcode` query in `server.transcode`, which requires ffmpeg.
+///
+/// No codec is enabled by default.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct TranscodeConfig(Vec<Codec>);
+
+impl TranscodeConfig {
+    pub fn new(codecs: Vec<Codec>) -> Self {
+        Self(codecs)
+    }
+
+    pub fn enabled(&self, codec: Codec) -> bool {
+        cfg!(feature = "transcode") && self.0.contains(&codec)
+    }
+}
+
+/// Parse bitrate like `128k` or `128000` to kbps.
+///
+/// Bitrates out of `8k..=512k` are rejected.
+pub fn parse_bitrate(bitrate: &str) -> Option<u32> {
+    let kbps = match bitrate.strip_suffix(['k', 'K']) {
+        Some(kbps) => kbps.parse().ok()?,
+        None => bitrate.parse::<u32>().ok()? / 1000,
+    };
+    (8..=512).contains(&kbps).then_some(kbps)
+}
+
+/// Default bitrate in kbps of `quality`.
+pub fn quality_bitrate(quality: AudioQuality) -> u32 {
+    match quality {
+        AudioQuality::Low => 128,
+        AudioQuality::Medium => 192,
+        AudioQuality::High | AudioQuality::Lossless => 256,
+    }
+}
+
+/// Transcode audio to `codec` at constant `bitrate` with ffmpeg.
+pub struct FfmpegTranscoder {
+    codec: Codec,
+    /// Bitrate in kbps
+    bitrate: u32,
+    quality: AudioQuality,
+}
+
+impl FfmpegTranscoder {
+    pub fn new(codec: Codec, bitrate: u32, quality: AudioQuality) -> Self {
+        Self {
+            codec,
+            bitrate,
+            quality,
+        }
+    }
+}
+
+impl Transcode for FfmpegTranscoder {
+    fn content_type(&self) -> &'static str {
+        self.codec.content_type()
+    }
+
+    fn quality(&self) -> AudioQuality {
+        self.quality
+    }
+
+    fn need_transcode(&self) -> bool {
+        true
+    }
+
+    fn variant(&self) -> Cow<'static, str> {
+        Cow::Owned(format!("{}-{}k", self.codec.as_str(), self.bitrate))
+    }
+
+    fn spawn(&self) -> std::io::Result<Child> {
+        let bitrate = format!("{}k", self.bitrate);
+        tokio::process::Command::new("ffmpeg")
+            .args(["-hide_banner", "-loglevel", "error", "-i", "pipe:0"])
+            .args(["-map", "0:a:0", "-b:a", &bitrate])
+            .args(self.codec.ffmpeg_args())
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+    }
+
+    /// Size of the output is not exact, so it's sent with chunked transfer encoding.
+    fn content_length(&self, _: &AudioInfo) -> Option<usize> {
+        None
+    }
+
+    /// Size of audio stream at `bitrate`, without container overhead.
+    fn estimated_size(&self, info: &AudioInfo) -> Option<u64> {
+        Some(info.duration * self.bitrate as u64 / 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bitrate() {
+        assert_eq!(parse_bitrate("128k"), Some(128));
+        assert_eq!(parse_bitrate("96K"), Some(96));
+        assert_eq!(parse_bitrate("320000"), Some(320));
+        assert_eq!(parse_bitrate("0k"), None);
+        assert_eq!(parse_bitrate("1024k"), None);
+        assert_eq!(parse_bitrate("fast"), None);
+    }
+
+    #[test]
+    fn test_transcode_config() {
+        let config: TranscodeConfig = serde_json::from_str(r#"["opus", "mp3"]"#).unwrap();
+        assert_eq!(config.enabled(Codec::Opus), cfg!(feature = "transcode"));
+        assert!(!config.enabled(Codec::Aac));
+        assert!(!TranscodeConfig::default().enabled(Codec::Opus));
     }
 }
@@ -1,4 +1,6 @@
+use axum::body::Bytes;
 use axum::response::{IntoResponse, IntoResponseParts};
+use futures::{Stream, StreamExt};
 
 pub(crate) enum Either<L, R> {
     Left(L),
@@ -36,6 +38,78 @@ where
     }
 }
 
+/// Limit the total bytes yielded by `stream`.
+///
+/// If `limit` is exceeded, an error is yielded and the stream ends, which aborts the response.
+pub fn limit_stream<S>(
+    stream: S,
+    limit: Option<u64>,
+) -> impl Stream<Item = std::io::Result<Bytes>>
+where
+    S: Stream<Item = std::io::Result<Bytes>>,
+{
+    stream.scan(Some(0u64), move |sent, chunk| {
+        let result = match (sent.as_mut(), limit) {
+            // limit exceeded before, end the stream
+            (None, _) => None,
+            (Some(sent), Some(limit)) => match chunk {
+                Ok(chunk) => {
+                    *sent += chunk.len() as u64;
+                    if *sent > limit {
+                        log::warn!("Response exceeded size limit of {limit} bytes, aborting");
+                        Some(Err(std::io::Error::new(
+                            std::io::ErrorKind::Other,
+                            "response size limit exceeded",
+                        )))
+                    } else {
+                        Some(Ok(chunk))
+                    }
+                }
+                Err(e) => Some(Err(e)),
+            },
+            (Some(_), None) => Some(chunk),
+        };
+        if let Some(Err(_)) = result {
+            *sent = None;
+        }
+        futures::future::ready(result)
+    })
+}
+
+/// Yield `length` bytes of `stream` starting from byte `start`, or all bytes after `start` if `length` is `None`.
+///
+/// Bytes before `start` are still read from `stream` and dropped, so the cost is proportional to `start`.
+pub fn slice_stream<S>(
+    stream: S,
+    start: u64,
+    length: Option<u64>,
+) -> impl Stream<Item = std::io::Result<Bytes>>
+where
+    S: Stream<Item = std::io::Result<Bytes>>,
+{
+    stream
+        .scan((start, length), |(skip, remaining), chunk| {
+            let result = match chunk {
+                // all requested bytes were sent, end the stream
+                Ok(_) if *remaining == Some(0) => None,
+                Ok(chunk) => {
+                    let skipped = (*skip).min(chunk.len() as u64) as usize;
+                    *skip -= skipped as u64;
+                    let mut chunk = chunk.slice(skipped..);
+                    if let Some(remaining) = remaining {
+                        let taken = (*remaining).min(chunk.len() as u64) as usize;
+                        *remaining -= taken as u64;
+                        chunk.truncate(taken);
+                    }
+                    Some(Ok(chunk))
+                }
+                Err(e) => Some(Err(e)),
+            };
+            futures::future::ready(result)
+        })
+        .filter(|chunk| futures::future::ready(!matches!(chunk, Ok(chunk) if chunk.is_empty())))
+}
+
 /// Calculate output size of opus file
 pub fn opus_file_size(milliseconds: u64, bit_rate: u16, frame_size: u8) -> u64 {
     const OGG_PREFIX_PAGES_SIZE: u64 = 0x2f + 0x31a;
@@ -56,7 +130,9 @@ pub fn opus_file_size(milliseconds: u64, bit_rate: u16, frame_size: u8) -> u64 {
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::opus_file_size;
+    use crate::utils::{opus_file_size, slice_stream};
+    use axum::body::Bytes;
+    use futures::StreamExt;
 
     #[test]
     fn test_sparkle_opus_size() {
@@ -71,4 +147,25 @@ mod tests {
     fn test_sakuranotoki_opus_size() {
         assert_eq!(opus_file_size(80361372 * 1000 / 44100, 64, 20), 14719255);
     }
+
+    #[tokio::test]
+    async fn test_slice_stream() {
+        let chunks = || {
+            futures::stream::iter(
+                ["0123", "4567", "89"].map(|c| Ok(Bytes::from_static(c.as_bytes()))),
+            )
+        };
+        let collect = |start, length| async move {
+            slice_stream(chunks(), start, length)
+                .map(|chunk| chunk.unwrap())
+                .collect::<Vec<_>>()
+                .await
+                .concat()
+        };
+        assert_eq!(collect(0, None).await, b"0123456789");
+        assert_eq!(collect(2, Some(4)).await, b"2345");
+        assert_eq!(collect(5, None).await, b"56789");
+        assert_eq!(collect(8, Some(10)).await, b"89");
+        assert!(collect(20, None).await.is_empty());
+    }
 }
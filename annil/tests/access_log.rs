@@ -0,0 +1,60 @@
+use annil::access_log::{access_log, AccessLogConfig};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::middleware;
+use axum::routing::get;
+use axum::{Extension, Router};
+use log::{Log, Metadata, Record};
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+
+/// Logger which keeps access log lines.
+struct AccessLogger(Mutex<Vec<serde_json::Value>>);
+
+impl Log for AccessLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == "annil::access"
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let line = serde_json::from_str(&record.args().to_string()).unwrap();
+            self.0.lock().unwrap().push(line);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: AccessLogger = AccessLogger(Mutex::new(Vec::new()));
+
+#[tokio::test]
+async fn access_log_matched_route() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+
+    let config: AccessLogConfig = toml::from_str("").unwrap();
+    let app = Router::new()
+        .route("/:album_id/cover", get(|| async { "cover" }))
+        .route("/info", get(|| async { "info" }))
+        .layer(middleware::from_fn(access_log))
+        .layer(Extension(Arc::new(config)));
+
+    for uri in ["/b8fdf8c6-1f27-4ab4-a3c7-4c4a3ff4d6e5/cover", "/info"] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    // `/info` is excluded by default
+    let lines = LOGGER.0.lock().unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["method"], "GET");
+    assert_eq!(lines[0]["path"], "/:album_id/cover");
+    assert_eq!(lines[0]["status"], 200);
+    assert_eq!(lines[0]["bytes"], 5);
+    assert!(lines[0]["elapsed_ms"].is_u64());
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use anni_provider::providers::MultipleProviders;
+use anni_provider::Capabilities;
+use annil::extractor::token::AnnilClaim;
+use annil::route::admin;
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::Router;
+use common::{StubProvider, ADMIN_TOKEN, ALBUM_ID};
+use jwt_simple::prelude::MACLike;
+use tower::ServiceExt;
+
+fn app() -> Router {
+    let routes = Router::new()
+        .route("/admin/albums", get(admin::albums::<StubProvider>))
+        .route(
+            "/admin/etag/recompute",
+            post(admin::recompute_etag::<StubProvider>),
+        )
+        .route("/admin/reload", post(admin::reload::<StubProvider>))
+        .route("/admin/sign", post(admin::sign));
+    common::app(
+        routes,
+        common::state(),
+        StubProvider::default(),
+        common::keys(),
+    )
+}
+
+async fn request(app: &Router, method: Method, uri: &str, admin_token: Option<&str>) -> Response {
+    // payload of `/admin/sign`, which is ignored by other routes
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json");
+    if let Some(admin_token) = admin_token {
+        request = request.header("X-Admin-Token", admin_token);
+    }
+    app.clone()
+        .oneshot(request.body(Body::from(r#"{"user_id":"test"}"#)).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn albums() {
+    let app = app();
+    let response = request(&app, Method::GET, "/admin/albums", Some(ADMIN_TOKEN)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value =
+        serde_json::from_slice(&common::body(response.into_body()).await).unwrap();
+    assert_eq!(body, serde_json::json!([ALBUM_ID]));
+}
+
+#[tokio::test]
+async fn sign() {
+    let keys = common::keys();
+    let app = common::app(
+        Router::new().route("/admin/sign", post(admin::sign)),
+        common::state(),
+        StubProvider::default(),
+        keys.clone(),
+    );
+    let response = request(&app, Method::POST, "/admin/sign", Some(ADMIN_TOKEN)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let token = String::from_utf8(common::body(response.into_body()).await).unwrap();
+    assert!(keys
+        .sign_key
+        .verify_token::<AnnilClaim>(&token, None)
+        .is_ok());
+}
+
+#[tokio::test]
+async fn admin_routes_require_admin_token() {
+    let app = app();
+    for (method, uri) in [
+        (Method::GET, "/admin/albums"),
+        (Method::POST, "/admin/etag/recompute"),
+        (Method::POST, "/admin/reload"),
+        (Method::POST, "/admin/sign"),
+    ] {
+        let response = request(&app, method.clone(), uri, None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+        let response = request(&app, method, uri, Some("wrong")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn health() {
+    let ranged = StubProvider {
+        capabilities: Capabilities {
+            supports_range: true,
+            ..Capabilities::CONSERVATIVE
+        },
+        ..Default::default()
+    };
+    let provider =
+        MultipleProviders::new(vec![Box::new(StubProvider::default()), Box::new(ranged)])
+            .with_names(vec!["covers".to_string(), "ranged".to_string()]);
+    let app = common::app(
+        Router::new().route("/admin/health", get(admin::health)),
+        common::state(),
+        provider,
+        common::keys(),
+    );
+
+    let response = request(&app, Method::GET, "/admin/health", None).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = request(&app, Method::GET, "/admin/health", Some(ADMIN_TOKEN)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value =
+        serde_json::from_slice(&common::body(response.into_body()).await).unwrap();
+    assert_eq!(body[0]["name"], "covers");
+    assert_eq!(body[0]["healthy"], true);
+    assert_eq!(body[0]["capabilities"]["has_covers"], true);
+    assert_eq!(body[0]["capabilities"]["supports_range"], false);
+    assert_eq!(body[1]["name"], "ranged");
+    assert_eq!(body[1]["capabilities"]["has_covers"], false);
+    assert_eq!(body[1]["capabilities"]["supports_range"], true);
+}
+
+#[tokio::test]
+async fn add_provider() {
+    let provider = MultipleProviders::new(vec![Box::new(StubProvider::default())])
+        .with_names(vec!["stub".to_string()]);
+    let app = common::app(
+        Router::new().route("/admin/providers/add", post(admin::add_provider)),
+        common::state(),
+        provider,
+        common::keys(),
+    );
+    let root = std::env::temp_dir().join(format!("annil-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir(&root).unwrap();
+    let add = |name: &str, admin_token: Option<&str>| {
+        let body = serde_json::json!({ "name": name, "type": "zip", "root": root });
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/providers/add")
+            .header("Content-Type", "application/json");
+        if let Some(admin_token) = admin_token {
+            request = request.header("X-Admin-Token", admin_token);
+        }
+        app.clone()
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+    };
+
+    let response = add("zip", None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = add("stub", Some(ADMIN_TOKEN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = add("zip", Some(ADMIN_TOKEN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = add("zip", Some(ADMIN_TOKEN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    std::fs::remove_dir(&root).unwrap();
+}
@@ -0,0 +1,134 @@
+#![cfg(feature = "metadata")]
+
+mod common;
+
+use anni_repo::db::RepoDatabaseRead;
+use anni_repo::RepositoryManager;
+use annil::metadata::MetadataConfig;
+use annil::route::user;
+use annil::state::{AnnilKeys, AnnilState};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::get;
+use axum::{Extension, Router};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+const ALBUM_ID: &str = "3e5ff166-f800-4433-a413-6cfa3c2b3cdd";
+
+/// Generate metadata database from test repository `repo` of anni-repo.
+fn metadata(base: &Path, repo: &str) -> MetadataConfig {
+    let repo = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../anni-repo/tests/repos")
+        .join(repo);
+    let metadata = MetadataConfig {
+        repo: String::new(),
+        branch: String::new(),
+        base: base.to_path_buf(),
+        pull: false,
+        proxy: None,
+        retries: 0,
+        retry_backoff: 0,
+        in_memory: false,
+    };
+    RepositoryManager::new(repo)
+        .unwrap()
+        .into_owned_manager()
+        .unwrap()
+        .to_database(&metadata.database_path())
+        .unwrap();
+    metadata
+}
+
+fn app(keys: Arc<AnnilKeys>, metadata: MetadataConfig) -> Router {
+    let database = RepoDatabaseRead::new(metadata.database_path()).unwrap();
+    let state = AnnilState {
+        metadata: Some(metadata),
+        database: Mutex::new(Some(database)),
+        ..common::state()
+    };
+    Router::new()
+        .route("/album/:album_id", get(user::album))
+        .layer(Extension(Arc::new(state)))
+        .layer(Extension(keys))
+}
+
+async fn get_album(app: &Router, album_id: &str, token: Option<&str>) -> (StatusCode, Vec<u8>) {
+    let mut request = Request::builder().uri(format!("/album/{album_id}"));
+    if let Some(token) = token {
+        request = request.header("Authorization", token);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    (status, common::body(response.into_body()).await)
+}
+
+#[tokio::test]
+async fn album_metadata() {
+    let base: PathBuf = std::env::temp_dir().join(format!("annil-album-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&base).unwrap();
+
+    let keys = common::keys();
+    let app = app(keys.clone(), metadata(&base, "album-tags"));
+    let token = common::sign(&keys, serde_json::json!({ "user_id": "test" })).await;
+
+    let (status, _) = get_album(&app, ALBUM_ID, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = get_album(&app, ALBUM_ID, Some(&token)).await;
+    assert_eq!(status, StatusCode::OK);
+    let album: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(album["album_id"], ALBUM_ID);
+    assert_eq!(album["title"], "Title");
+    assert_eq!(album["discs"][0]["tracks"][0]["title"], "Track 1");
+
+    let (status, _) = get_album(&app, &Uuid::nil().to_string(), Some(&token)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = get_album(&app, "album", Some(&token)).await;
+    assert_eq!(status, StatusCode::OK);
+    let album: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(album["album_id"], ALBUM_ID);
+
+    let (status, _) = get_album(&app, "unknown", Some(&token)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let cover_only = common::sign(
+        &keys,
+        serde_json::json!({ "user_id": "test", "scope": "cover" }),
+    )
+    .await;
+    let (status, _) = get_album(&app, ALBUM_ID, Some(&cover_only)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    std::fs::remove_dir_all(base).unwrap();
+}
+
+#[tokio::test]
+async fn ambiguous_catalog() {
+    let base: PathBuf = std::env::temp_dir().join(format!("annil-album-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&base).unwrap();
+
+    let keys = common::keys();
+    let app = app(keys.clone(), metadata(&base, "duplicate-catalogs"));
+    let token = common::sign(&keys, serde_json::json!({ "user_id": "test" })).await;
+
+    let (status, body) = get_album(&app, "TEST-0001", Some(&token)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let candidates: Vec<String> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        candidates,
+        [
+            "3d5f7a9c-1b2e-4d4f-a6b8-c0e2f4a6b8d1",
+            "9b1f3c5e-2a4d-4c6b-8e0f-1a3c5e7b9d20"
+        ]
+    );
+
+    std::fs::remove_dir_all(base).unwrap();
+}
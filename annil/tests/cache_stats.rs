@@ -0,0 +1,88 @@
+mod common;
+
+use anni_provider::cache::CachePool;
+use annil::route::admin;
+use annil::state::AnnilState;
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::routing::post;
+use axum::{Extension, Router};
+use common::ADMIN_TOKEN;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+fn app() -> Router {
+    let caches = HashMap::from([("/cache".to_string(), Arc::new(CachePool::new_memory(1024)))]);
+    let state = AnnilState {
+        caches: RwLock::new(caches),
+        ..common::state()
+    };
+    Router::new()
+        .route("/admin/cache/stats", post(admin::cache_stats))
+        .route("/admin/cache/verify", post(admin::cache_verify))
+        .layer(Extension(Arc::new(state)))
+        .layer(Extension(common::keys()))
+}
+
+#[tokio::test]
+async fn cache_stats() {
+    let app = app();
+    let request = |token: Option<&str>| {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/cache/stats");
+        if let Some(token) = token {
+            request = request.header("X-Admin-Token", token);
+        }
+        request.body(Body::empty()).unwrap()
+    };
+
+    let response = app.clone().oneshot(request(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app.clone().oneshot(request(Some("wrong"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.oneshot(request(Some(ADMIN_TOKEN))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let data = common::body(response.into_body()).await;
+    let body: serde_json::Value = serde_json::from_slice(&data).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "/cache": {
+                "entries": 0,
+                "bytes_used": 0,
+                "max_size": 1024,
+                "filling": 0,
+                "hits": 0,
+                "misses": 0,
+                "evictions": 0,
+            }
+        })
+    );
+}
+
+#[tokio::test]
+async fn cache_verify() {
+    let app = app();
+    let request = |token: Option<&str>| {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/cache/verify");
+        if let Some(token) = token {
+            request = request.header("X-Admin-Token", token);
+        }
+        request.body(Body::empty()).unwrap()
+    };
+
+    let response = app.clone().oneshot(request(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.oneshot(request(Some(ADMIN_TOKEN))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let data = common::body(response.into_body()).await;
+    let body: serde_json::Value = serde_json::from_slice(&data).unwrap();
+    assert_eq!(body, serde_json::json!({ "/cache": 0 }));
+}
@@ -0,0 +1,183 @@
+#![allow(dead_code)]
+
+use anni_provider::{
+    AnniProvider, AudioInfo, AudioResourceReader, Capabilities, Range, ResourceReader, Result,
+};
+use annil::extractor::admin::IsAdmin;
+use annil::provider::AnnilProvider;
+use annil::route::admin;
+use annil::state::{AnnilKeys, AnnilState};
+use async_trait::async_trait;
+use axum::body::HttpBody;
+use axum::{Extension, Json, Router};
+use jwt_simple::prelude::{Claims, Duration, HS256Key, MACLike};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::io::Cursor;
+use std::num::NonZeroU8;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+pub const ALBUM_ID: &str = "b8fdf8c6-1f27-4ab4-a3c7-4c4a3ff4d6e5";
+pub const ADMIN_TOKEN: &str = "admin token";
+
+/// Provider which serves the same audio and cover for every track of its albums.
+pub struct StubProvider {
+    /// Albums of the provider, which can be changed out-of-band.
+    pub albums: Arc<Mutex<Vec<String>>>,
+    pub audio: Vec<u8>,
+    /// Whether size of audio is reported. Requested range is served only if it is.
+    pub sized: bool,
+    pub duration: u64,
+    pub cover: Vec<u8>,
+    pub capabilities: Capabilities,
+}
+
+impl Default for StubProvider {
+    fn default() -> Self {
+        Self {
+            albums: Arc::new(Mutex::new(vec![ALBUM_ID.to_string()])),
+            audio: b"fLaC".to_vec(),
+            sized: true,
+            duration: 0,
+            cover: Vec::new(),
+            capabilities: Capabilities {
+                has_covers: true,
+                ..Capabilities::CONSERVATIVE
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl AnniProvider for StubProvider {
+    async fn albums(&self) -> Result<HashSet<Cow<str>>> {
+        let albums = self.albums.lock().unwrap();
+        Ok(albums.iter().cloned().map(Cow::Owned).collect())
+    }
+
+    async fn get_audio(
+        &self,
+        _album_id: &str,
+        _disc_id: NonZeroU8,
+        _track_id: NonZeroU8,
+        range: Range,
+    ) -> Result<AudioResourceReader> {
+        if !self.sized {
+            return Ok(AudioResourceReader {
+                info: AudioInfo {
+                    extension: "flac".to_string(),
+                    size: 0,
+                    duration: self.duration,
+                },
+                range,
+                reader: Box::pin(Cursor::new(self.audio.clone())),
+            });
+        }
+
+        let size = self.audio.len() as u64;
+        let range = range.end_with(size);
+        let audio = self
+            .audio
+            .get(range.start as usize..=range.end.unwrap() as usize)
+            .unwrap_or_default();
+        Ok(AudioResourceReader {
+            info: AudioInfo {
+                extension: "flac".to_string(),
+                size: size as usize,
+                duration: self.duration,
+            },
+            range,
+            reader: Box::pin(Cursor::new(audio.to_vec())),
+        })
+    }
+
+    async fn get_cover(
+        &self,
+        _album_id: &str,
+        _disc_id: Option<NonZeroU8>,
+    ) -> Result<ResourceReader> {
+        Ok(Box::pin(Cursor::new(self.cover.clone())))
+    }
+
+    async fn reload(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+}
+
+/// State with everything optional disabled. Tests override fields with struct update syntax.
+pub fn state() -> AnnilState {
+    AnnilState {
+        version: "test".to_string(),
+        last_update: RwLock::new(0),
+        etag: RwLock::new(String::new()),
+        metadata: None,
+        database: Default::default(),
+        provider_timeout_secs: None,
+        max_response_size: None,
+        http_cache: None,
+        cover_format: None,
+        mime: Default::default(),
+        transcode: Default::default(),
+        covers: Default::default(),
+        caches: Default::default(),
+    }
+}
+
+pub fn keys() -> Arc<AnnilKeys> {
+    let keys = AnnilKeys::new(b"sign key", b"share key", ADMIN_TOKEN.to_string());
+    Arc::new(AnnilKeys {
+        share_key: keys.share_key.with_key_id("share key id"),
+        ..keys
+    })
+}
+
+/// Add extensions needed by handlers to `routes`.
+pub fn app<P>(routes: Router, state: AnnilState, provider: P, keys: Arc<AnnilKeys>) -> Router
+where
+    P: AnniProvider + Send + Sync + 'static,
+{
+    routes
+        .layer(Extension(Arc::new(state)))
+        .layer(Extension(Arc::new(AnnilProvider::new(provider))))
+        .layer(Extension(keys))
+}
+
+pub async fn sign(keys: &Arc<AnnilKeys>, payload: serde_json::Value) -> String {
+    let response = admin::sign(
+        IsAdmin(true),
+        Extension(keys.clone()),
+        Json(serde_json::from_value(payload).unwrap()),
+    )
+    .await;
+    String::from_utf8(body(response.into_body()).await).unwrap()
+}
+
+/// Sign a share token of the only track of [ALBUM_ID] with the share secret in `user_token`, like clients do.
+pub fn share(keys: &AnnilKeys, user_token: &str) -> String {
+    let claims = keys
+        .sign_key
+        .verify_token::<serde_json::Value>(user_token, None)
+        .unwrap();
+    let share = &claims.custom["share"];
+    HS256Key::from_bytes(share["secret"].as_str().unwrap().as_bytes())
+        .with_key_id(share["key_id"].as_str().unwrap())
+        .authenticate(Claims::with_custom_claims(
+            serde_json::json!({ "type": "share", "audios": { ALBUM_ID: { "1": [1] } } }),
+            Duration::from_hours(1),
+        ))
+        .unwrap()
+}
+
+pub async fn body<B>(body: B) -> Vec<u8>
+where
+    B: HttpBody,
+    B::Error: Debug,
+{
+    hyper::body::to_bytes(body).await.unwrap().to_vec()
+}
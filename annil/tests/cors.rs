@@ -0,0 +1,74 @@
+use anni_provider::providers::MultipleProviders;
+use annil::cors::CorsConfig;
+use annil::route::user;
+use axum::body::Body;
+use axum::http::header::{
+    ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_REQUEST_METHOD,
+    ORIGIN,
+};
+use axum::http::{Method, Request, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use serde::Deserialize;
+use tower::ServiceExt;
+
+const TRACK: &str = "/b8fdf8c6-1f27-4ab4-a3c7-4c4a3ff4d6e5/1/1";
+
+#[derive(Deserialize)]
+struct ServerConfig {
+    cors: CorsConfig,
+}
+
+fn app(cors: &str) -> Router {
+    let config: ServerConfig = toml::from_str(&format!("cors = {cors}")).unwrap();
+    Router::new()
+        .route(
+            "/:album_id/:disc_id/:track_id",
+            get(user::audio::<MultipleProviders>).head(user::audio_head::<MultipleProviders>),
+        )
+        .layer(config.cors.layer())
+}
+
+async fn preflight(app: Router, origin: &str) -> axum::response::Response {
+    app.oneshot(
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri(TRACK)
+            .header(ORIGIN, origin)
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn cors_any() {
+    let response = preflight(app("\"*\""), "https://player.example").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    let methods = response.headers()[ACCESS_CONTROL_ALLOW_METHODS]
+        .to_str()
+        .unwrap();
+    assert!(methods.contains("GET") && methods.contains("HEAD"));
+    assert!(!methods.contains("POST"));
+}
+
+#[tokio::test]
+async fn cors_allowlist() {
+    let app = app("[\"https://player.example\"]");
+
+    let response = preflight(app.clone(), "https://player.example").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+        "https://player.example"
+    );
+
+    let response = preflight(app, "https://other.example").await;
+    assert!(response
+        .headers()
+        .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+        .is_none());
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use anni_provider::providers::MultipleProviders;
+use anni_provider::AnniProvider;
+use annil::provider::AnnilProvider;
+use annil::route::{admin, user};
+use annil::state::AnnilState;
+use axum::body::Body;
+use axum::http::header::{ETAG, IF_NONE_MATCH};
+use axum::http::{Method, Request, StatusCode};
+use axum::routing::{get, post};
+use axum::{Extension, Router};
+use common::{StubProvider, ADMIN_TOKEN, ALBUM_ID};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn recompute_etag() {
+    let albums = Arc::new(Mutex::new(vec![ALBUM_ID.to_string()]));
+    let provider = Arc::new(AnnilProvider::new(StubProvider {
+        albums: albums.clone(),
+        ..Default::default()
+    }));
+    let state = Arc::new(AnnilState {
+        etag: RwLock::new(provider.compute_etag().await.unwrap()),
+        ..common::state()
+    });
+    let app = Router::new()
+        .route(
+            "/admin/etag/recompute",
+            post(admin::recompute_etag::<StubProvider>),
+        )
+        .layer(Extension(state.clone()))
+        .layer(Extension(provider.clone()))
+        .layer(Extension(common::keys()));
+    let old_etag = state.etag.read().await.clone();
+    let recompute = |admin_token: Option<&str>| {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/etag/recompute");
+        if let Some(admin_token) = admin_token {
+            request = request.header("X-Admin-Token", admin_token);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    albums
+        .lock()
+        .unwrap()
+        .push("6ba9a4c6-5b35-4e8d-a5f6-8d4c0e6bd0b2".to_string());
+    let response = recompute(None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(*state.etag.read().await, old_etag);
+
+    let response = recompute(Some(ADMIN_TOKEN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let new_etag = state.etag.read().await.clone();
+    assert_ne!(old_etag, new_etag);
+    assert_eq!(new_etag, provider.compute_etag().await.unwrap());
+    assert!(*state.last_update.read().await > 0);
+}
+
+#[tokio::test]
+async fn info_if_none_match() {
+    let state = Arc::new(AnnilState {
+        etag: RwLock::new(r#""etag""#.to_string()),
+        ..common::state()
+    });
+    let app = Router::new()
+        .route("/info", get(user::info))
+        .layer(Extension(state));
+    let info = |etag: Option<&str>| {
+        let mut request = Request::builder().uri("/info");
+        if let Some(etag) = etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    let response = info(None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[ETAG], r#""etag""#);
+
+    for etag in [r#""etag""#, r#"W/"etag""#, r#""other", "etag""#] {
+        let response = info(Some(etag)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[ETAG], r#""etag""#);
+    }
+    assert_eq!(
+        info(Some(r#""other""#)).await.unwrap().status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn etag_restored_after_album_removed() {
+    let albums = Arc::new(Mutex::new(vec![ALBUM_ID.to_string()]));
+    let provider = AnnilProvider::new(StubProvider {
+        albums: albums.clone(),
+        ..Default::default()
+    });
+    let old_etag = provider.compute_etag().await.unwrap();
+
+    albums
+        .lock()
+        .unwrap()
+        .push("6ba9a4c6-5b35-4e8d-a5f6-8d4c0e6bd0b2".to_string());
+    assert_ne!(provider.compute_etag().await.unwrap(), old_etag);
+
+    albums.lock().unwrap().pop();
+    assert_eq!(provider.compute_etag().await.unwrap(), old_etag);
+}
+
+#[tokio::test]
+async fn etag_of_added_provider() {
+    let stub = |album: &str| -> Box<dyn AnniProvider + Send + Sync> {
+        Box::new(StubProvider {
+            albums: Arc::new(Mutex::new(vec![album.to_string()])),
+            ..Default::default()
+        })
+    };
+    let provider = AnnilProvider::new(MultipleProviders::new(vec![stub(ALBUM_ID)]));
+    let old_etag = provider.compute_etag().await.unwrap();
+
+    // etag is of distinct albums, so a mirror does not change it
+    let mirror = stub(ALBUM_ID);
+    let etag = provider.add_albums("mirror", &*mirror).await.unwrap();
+    provider.write().await.push("mirror".to_string(), mirror);
+    assert_eq!(etag, old_etag);
+
+    let added = stub("6ba9a4c6-5b35-4e8d-a5f6-8d4c0e6bd0b2");
+    let etag = provider.add_albums("added", &*added).await.unwrap();
+    provider.write().await.push("added".to_string(), added);
+    assert_ne!(etag, old_etag);
+    assert_eq!(etag, provider.compute_etag().await.unwrap());
+
+    // same as etag computed without combining providers
+    let albums: Vec<String> = {
+        let provider = provider.read().await;
+        let albums = provider.albums().await.unwrap();
+        albums.into_iter().map(|a| a.into_owned()).collect()
+    };
+    let single = AnnilProvider::new(StubProvider {
+        albums: Arc::new(Mutex::new(albums)),
+        ..Default::default()
+    });
+    assert_eq!(etag, single.compute_etag().await.unwrap());
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use annil::metrics::{track_audio_stream, track_requests, Metrics};
+use annil::route::metrics;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::middleware;
+use axum::routing::get;
+use axum::{Extension, Router};
+use common::ADMIN_TOKEN;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn app(admin_only: bool) -> Router {
+    Router::new()
+        .route(
+            "/:album_id/:disc_id/:track_id",
+            get(|| async { "audio" }).route_layer(middleware::from_fn(track_audio_stream)),
+        )
+        .route("/metrics", get(metrics::metrics))
+        .layer(middleware::from_fn(track_requests))
+        .layer(Extension(Arc::new(common::state())))
+        .layer(Extension(common::keys()))
+        .layer(Extension(Arc::new(Metrics::new(admin_only))))
+}
+
+async fn get_text(app: &Router, uri: &str, admin_token: Option<&str>) -> (StatusCode, String) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(admin_token) = admin_token {
+        request = request.header("X-Admin-Token", admin_token);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn metrics_text() {
+    let app = app(false);
+    for _ in 0..2 {
+        let (status, body) =
+            get_text(&app, "/b8fdf8c6-1f27-4ab4-a3c7-4c4a3ff4d6e5/1/1", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "audio");
+    }
+    get_text(&app, "/unknown", None).await;
+
+    let (status, body) = get_text(&app, "/metrics", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains(
+        "annil_http_requests_total{route=\"/:album_id/:disc_id/:track_id\",status=\"200\"} 2\n"
+    ));
+    assert!(body.contains("annil_http_requests_total{route=\"unmatched\",status=\"404\"} 1\n"));
+    assert!(body.contains("annil_audio_stream_duration_seconds_count 2\n"));
+    assert!(body.contains("# TYPE annil_cache_hits_total counter\n"));
+}
+
+#[tokio::test]
+async fn metrics_admin_only() {
+    let app = app(true);
+    let (status, _) = get_text(&app, "/metrics", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = get_text(&app, "/metrics", Some(ADMIN_TOKEN)).await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,255 @@
+mod common;
+
+use anni_provider::providers::MultipleProviders;
+use anni_provider::{
+    AnniProvider, AudioResourceReader, Capabilities, ProviderError, Range, ResourceReader, Result,
+};
+use annil::http_cache::cover_etag;
+use annil::route::{admin, health, user};
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::header::{ETAG, IF_NONE_MATCH};
+use axum::http::{Method, Request, StatusCode};
+use axum::routing::{get, post};
+use axum::Router;
+use common::{StubProvider, ADMIN_TOKEN, ALBUM_ID};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::num::NonZeroU8;
+use tower::ServiceExt;
+
+/// Provider whose cover of the only album is `cover`.
+fn cover_provider(cover: &str) -> Box<StubProvider> {
+    Box::new(StubProvider {
+        cover: cover.as_bytes().to_vec(),
+        ..Default::default()
+    })
+}
+
+/// Provider whose metadata database is unavailable.
+struct BrokenDatabaseProvider;
+
+#[async_trait]
+impl AnniProvider for BrokenDatabaseProvider {
+    async fn albums(&self) -> Result<HashSet<Cow<str>>> {
+        Ok(HashSet::from([Cow::Borrowed(ALBUM_ID)]))
+    }
+
+    async fn get_audio(
+        &self,
+        _album_id: &str,
+        _disc_id: NonZeroU8,
+        _track_id: NonZeroU8,
+        _range: Range,
+    ) -> Result<AudioResourceReader> {
+        Err(ProviderError::DatabaseUnavailable(
+            "file is not a database".to_string(),
+        ))
+    }
+
+    async fn get_cover(
+        &self,
+        _album_id: &str,
+        _disc_id: Option<NonZeroU8>,
+    ) -> Result<ResourceReader> {
+        Err(ProviderError::DatabaseUnavailable(
+            "file is not a database".to_string(),
+        ))
+    }
+
+    async fn reload(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            has_covers: true,
+            ..Capabilities::CONSERVATIVE
+        }
+    }
+}
+
+fn app() -> Router {
+    let provider = MultipleProviders::new(vec![cover_provider("a"), cover_provider("b")])
+        .with_names(vec!["a".to_string(), "b".to_string()]);
+    app_with(provider)
+}
+
+fn app_with(provider: MultipleProviders) -> Router {
+    let routes = Router::new()
+        .route("/:album_id/cover", get(user::cover::<MultipleProviders>))
+        .route("/healthz", get(health::healthz))
+        .route("/admin/providers", post(admin::providers));
+    common::app(routes, common::state(), provider, common::keys())
+}
+
+async fn cover(app: &Router, provider: Option<&str>, admin: bool) -> (StatusCode, String) {
+    let uri = match provider {
+        Some(provider) => format!("/{ALBUM_ID}/cover?provider={provider}"),
+        None => format!("/{ALBUM_ID}/cover"),
+    };
+    let mut request = Request::builder().uri(uri);
+    if admin {
+        request = request.header("X-Admin-Token", ADMIN_TOKEN);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let data = common::body(response.into_body()).await;
+    (status, String::from_utf8(data).unwrap())
+}
+
+#[tokio::test]
+async fn admin_selects_provider() {
+    let app = app();
+    assert_eq!(
+        cover(&app, None, true).await,
+        (StatusCode::OK, "a".to_string())
+    );
+    assert_eq!(
+        cover(&app, Some("b"), true).await,
+        (StatusCode::OK, "b".to_string())
+    );
+    assert_eq!(
+        cover(&app, Some("c"), true).await.0,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn non_admin_ignores_provider() {
+    let app = app();
+    assert_eq!(
+        cover(&app, Some("b"), false).await,
+        (StatusCode::OK, "a".to_string())
+    );
+    assert_eq!(
+        cover(&app, Some("c"), false).await,
+        (StatusCode::OK, "a".to_string())
+    );
+}
+
+#[tokio::test]
+async fn cover_size_falls_back_to_original() {
+    let response = app()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/{ALBUM_ID}/cover?size=300"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // etag of the original cover
+    assert_eq!(response.headers()[ETAG], cover_etag(b"a").as_str());
+    assert_eq!(common::body(response.into_body()).await, b"a");
+}
+
+#[tokio::test]
+async fn healthz() {
+    let response = app()
+        .oneshot(
+            Request::builder()
+                .uri("/healthz")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let data = common::body(response.into_body()).await;
+    let body: serde_json::Value = serde_json::from_slice(&data).unwrap();
+    assert_eq!(body, serde_json::json!({ "status": "ok", "providers": 2 }));
+}
+
+#[tokio::test]
+async fn database_unavailable() {
+    let app = app_with(MultipleProviders::new(vec![Box::new(
+        BrokenDatabaseProvider,
+    )]));
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/{ALBUM_ID}/cover"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn cover_if_none_match() {
+    let request = |app: Router, etag: &str| {
+        app.oneshot(
+            Request::builder()
+                .uri(format!("/{ALBUM_ID}/cover"))
+                .header(IF_NONE_MATCH, etag)
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+    let response = request(app(), &cover_etag(b"a")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    let response = request(app(), &cover_etag(b"b")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = request(app(), "*").await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // `*` does not match a cover which can not be fetched
+    let app = app_with(MultipleProviders::new(vec![Box::new(
+        BrokenDatabaseProvider,
+    )]));
+    let response = request(app, "*").await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn admin_providers() {
+    let provider =
+        MultipleProviders::new(vec![Box::new(BrokenDatabaseProvider), cover_provider("a")])
+            .with_names(vec!["broken".to_string(), "a".to_string()]);
+    let app = app_with(provider);
+    let providers = |admin: bool| {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/providers");
+        if admin {
+            request = request.header("X-Admin-Token", ADMIN_TOKEN);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    let response = providers(false).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = providers(true).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!([
+            { "name": "broken", "enabled": true, "album_count": 1 },
+            { "name": "a", "enabled": true, "album_count": 1 },
+        ])
+    );
+
+    // the failed provider is taken out of rotation
+    assert_eq!(
+        cover(&app, None, false).await,
+        (StatusCode::OK, "a".to_string())
+    );
+    let response = providers(true).await.unwrap();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body[0]["enabled"], false);
+    assert_eq!(body[1]["enabled"], true);
+}
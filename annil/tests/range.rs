@@ -0,0 +1,133 @@
+mod common;
+
+use anni_provider::Capabilities;
+use annil::route::user;
+use axum::body::Body;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use axum::http::{Request, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use common::{StubProvider, ALBUM_ID};
+use tower::ServiceExt;
+
+const AUDIO: &[u8] = b"fLaC audio of known size";
+
+async fn app() -> (Router, String) {
+    let keys = common::keys();
+    let token = common::sign(&keys, serde_json::json!({ "user_id": "test" })).await;
+    let routes = Router::new().route(
+        "/:album_id/:disc_id/:track_id",
+        get(user::audio::<StubProvider>).head(user::audio_head::<StubProvider>),
+    );
+    let provider = StubProvider {
+        audio: AUDIO.to_vec(),
+        capabilities: Capabilities {
+            supports_range: true,
+            ..Capabilities::CONSERVATIVE
+        },
+        ..Default::default()
+    };
+    (common::app(routes, common::state(), provider, keys), token)
+}
+
+async fn request(app: Router, token: &str, range: &str) -> axum::response::Response {
+    app.oneshot(
+        Request::builder()
+            .uri(format!("/{ALBUM_ID}/1/1?quality=lossless"))
+            .header("Authorization", token)
+            .header(RANGE, range)
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn range_beyond_end_is_unsatisfiable() {
+    let (app, token) = app().await;
+    let response = request(app, &token, "bytes=99999999-").await;
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        response.headers()[CONTENT_RANGE],
+        format!("bytes */{}", AUDIO.len())
+    );
+}
+
+#[tokio::test]
+async fn range_end_is_clamped() {
+    let (app, token) = app().await;
+    let response = request(app, &token, "bytes=5-99999999").await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        response.headers()[CONTENT_RANGE],
+        format!("bytes 5-{}/{}", AUDIO.len() - 1, AUDIO.len())
+    );
+    assert_eq!(
+        response.headers()[CONTENT_LENGTH],
+        (AUDIO.len() - 5).to_string()
+    );
+
+    assert_eq!(common::body(response.into_body()).await, &AUDIO[5..]);
+}
+
+#[tokio::test]
+async fn multiple_ranges() {
+    let (app, token) = app().await;
+    let response = request(app, &token, "bytes=0-3,5-9").await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let content_type = response.headers()[CONTENT_TYPE].to_str().unwrap();
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .unwrap()
+        .to_string();
+    let length: usize = response.headers()[CONTENT_LENGTH]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    let body = String::from_utf8(common::body(response.into_body()).await).unwrap();
+    assert_eq!(body.len(), length);
+    let size = AUDIO.len();
+    assert_eq!(
+        body,
+        format!(
+            "--{boundary}\r\nContent-Type: audio/flac\r\nContent-Range: bytes 0-3/{size}\r\n\r\nfLaC\
+             \r\n--{boundary}\r\nContent-Type: audio/flac\r\nContent-Range: bytes 5-9/{size}\r\n\r\naudio\
+             \r\n--{boundary}--\r\n"
+        )
+    );
+}
+
+#[tokio::test]
+async fn range_beyond_end_is_dropped() {
+    let (app, token) = app().await;
+    let response = request(app, &token, "bytes=99999999-,0-3").await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        response.headers()[CONTENT_RANGE],
+        format!("bytes 0-3/{}", AUDIO.len())
+    );
+    assert_eq!(common::body(response.into_body()).await, &AUDIO[..4]);
+}
+
+#[tokio::test]
+async fn suffix_range() {
+    let (app, token) = app().await;
+    let response = request(app, &token, "bytes=-4").await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        response.headers()[CONTENT_RANGE],
+        format!(
+            "bytes {}-{}/{}",
+            AUDIO.len() - 4,
+            AUDIO.len() - 1,
+            AUDIO.len()
+        )
+    );
+    assert_eq!(
+        common::body(response.into_body()).await,
+        &AUDIO[AUDIO.len() - 4..]
+    );
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use annil::rate_limit::{rate_limit, RateLimiter};
+use annil::state::AnnilKeys;
+use axum::body::Body;
+use axum::http::header::RETRY_AFTER;
+use axum::http::{Request, StatusCode};
+use axum::middleware;
+use axum::response::Response;
+use axum::routing::get;
+use axum::{Extension, Router};
+use common::ADMIN_TOKEN;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+async fn sign(keys: &Arc<AnnilKeys>, user_id: &str) -> String {
+    common::sign(keys, serde_json::json!({ "user_id": user_id })).await
+}
+
+async fn request(app: &Router, token: &str, admin_token: Option<&str>) -> Response {
+    let mut request = Request::builder().uri("/").header("Authorization", token);
+    if let Some(admin_token) = admin_token {
+        request = request.header("X-Admin-Token", admin_token);
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn rate_limit_per_user() {
+    let keys = common::keys();
+    let app = Router::new()
+        .route(
+            "/",
+            get(|| async { "ok" }).route_layer(middleware::from_fn(rate_limit)),
+        )
+        .layer(Extension(Arc::new(RateLimiter::new(2))))
+        .layer(Extension(keys.clone()));
+
+    let token = sign(&keys, "a").await;
+    for _ in 0..2 {
+        assert_eq!(request(&app, &token, None).await.status(), StatusCode::OK);
+    }
+    let response = request(&app, &token, None).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[RETRY_AFTER], "30");
+
+    // another token of the same user shares the limit
+    let other = sign(&keys, "a").await;
+    let response = request(&app, &other, None).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // other users are not affected
+    let token = sign(&keys, "b").await;
+    assert_eq!(request(&app, &token, None).await.status(), StatusCode::OK);
+
+    // admin is not limited
+    let response = request(&app, &other, Some(ADMIN_TOKEN)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // share tokens of a user token share the limit
+    let token = common::sign(&keys, serde_json::json!({ "user_id": "c", "share": true })).await;
+    let share = common::share(&keys, &token);
+    for _ in 0..2 {
+        assert_eq!(request(&app, &share, None).await.status(), StatusCode::OK);
+    }
+    let share = common::share(&keys, &token);
+    let response = request(&app, &share, None).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // unauthorized requests are rejected before counting
+    let response = request(&app, "invalid", None).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use annil::extractor::token::AnnilClaim;
+use annil::revoke::RevocationList;
+use annil::route::admin;
+use annil::state::AnnilKeys;
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::routing::{get, post};
+use axum::{Extension, Router};
+use common::ADMIN_TOKEN;
+use jwt_simple::prelude::MACLike;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tower::ServiceExt;
+
+fn app(keys: Arc<AnnilKeys>) -> Router {
+    Router::new()
+        .route("/", get(|_: AnnilClaim| async { "ok" }))
+        .route("/admin/revoke", post(admin::revoke))
+        .layer(Extension(keys))
+}
+
+async fn get_with(app: &Router, token: &str) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri("/")
+                .header("Authorization", token)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn revoke(app: &Router, admin_token: &str, payload: serde_json::Value) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/admin/revoke")
+                .header("X-Admin-Token", admin_token)
+                .header("Content-Type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn revoke_token() {
+    let keys = common::keys();
+    let app = app(keys.clone());
+    let token = common::sign(&keys, serde_json::json!({ "user_id": "test" })).await;
+    let other = common::sign(&keys, serde_json::json!({ "user_id": "test" })).await;
+    assert_eq!(get_with(&app, &token).await, StatusCode::OK);
+
+    let jti = keys
+        .sign_key
+        .verify_token::<AnnilClaim>(&token, None)
+        .unwrap()
+        .jwt_id
+        .unwrap();
+    assert_eq!(
+        revoke(&app, "wrong", serde_json::json!({ "jti": jti })).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(get_with(&app, &token).await, StatusCode::OK);
+
+    assert_eq!(
+        revoke(&app, ADMIN_TOKEN, serde_json::json!({ "jti": jti })).await,
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(get_with(&app, &token).await, StatusCode::UNAUTHORIZED);
+    // other tokens of the same user are not affected
+    assert_eq!(get_with(&app, &other).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn token_with_expiry() {
+    let keys = common::keys();
+    let token = common::sign(
+        &keys,
+        serde_json::json!({ "user_id": "test", "expires_in": 3600 }),
+    )
+    .await;
+    let claims = keys
+        .sign_key
+        .verify_token::<AnnilClaim>(&token, None)
+        .unwrap();
+    assert!(claims.expires_at.is_some());
+    assert_eq!(get_with(&app(keys), &token).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn share_token_revoked_with_user_token() {
+    let keys = common::keys();
+    let app = app(keys.clone());
+    let token = common::sign(
+        &keys,
+        serde_json::json!({ "user_id": "test", "share": true, "expires_in": 3600 }),
+    )
+    .await;
+
+    let share_token = common::share(&keys, &token);
+    assert_eq!(get_with(&app, &share_token).await, StatusCode::OK);
+
+    // exp of the revoked id is taken from the token
+    assert_eq!(
+        revoke(&app, ADMIN_TOKEN, serde_json::json!({ "token": token })).await,
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(get_with(&app, &token).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(get_with(&app, &share_token).await, StatusCode::UNAUTHORIZED);
+
+    assert_eq!(
+        revoke(&app, ADMIN_TOKEN, serde_json::json!({ "token": "invalid" })).await,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn revocation_list_persisted() {
+    let path = std::env::temp_dir().join(format!("annil-{}-revoked.json", std::process::id()));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let list = RevocationList::load(path.clone()).unwrap();
+    assert!(list.is_empty());
+    list.revoke("forever".to_string(), None).await.unwrap();
+    list.revoke("later".to_string(), Some(now + 3600))
+        .await
+        .unwrap();
+    list.revoke("expired".to_string(), Some(now - 1))
+        .await
+        .unwrap();
+    assert!(list.is_revoked("forever"));
+    assert!(list.is_revoked("later"));
+    // ids of expired tokens are dropped
+    assert!(!list.is_revoked("expired"));
+
+    let list = RevocationList::load(path.clone()).unwrap();
+    assert_eq!(list.len(), 2);
+    assert!(list.is_revoked("forever"));
+    assert!(list.is_revoked("later"));
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use annil::extractor::admin::IsAdmin;
+use annil::route::{admin, user};
+use annil::state::AnnilKeys;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use common::{StubProvider, ALBUM_ID};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn app(keys: Arc<AnnilKeys>) -> Router {
+    let routes = Router::new()
+        .route(
+            "/:album_id/:disc_id/:track_id",
+            get(user::audio::<StubProvider>),
+        )
+        .route("/:album_id/cover", get(user::cover::<StubProvider>));
+    common::app(routes, common::state(), StubProvider::default(), keys)
+}
+
+async fn sign(keys: &Arc<AnnilKeys>, scope: Option<&str>) -> String {
+    let mut payload = serde_json::json!({ "user_id": "test" });
+    if let Some(scope) = scope {
+        payload["scope"] = scope.into();
+    }
+    common::sign(keys, payload).await
+}
+
+async fn status(app: &Router, uri: &str, token: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = token {
+        request = request.header("Authorization", token);
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+fn audio_uri() -> String {
+    format!("/{ALBUM_ID}/1/1?quality=lossless")
+}
+
+fn cover_uri() -> String {
+    format!("/{ALBUM_ID}/cover")
+}
+
+#[tokio::test]
+async fn cover_only_token() {
+    let keys = common::keys();
+    let app = app(keys.clone());
+    let token = sign(&keys, Some("cover")).await;
+
+    assert_eq!(
+        status(&app, &audio_uri(), Some(&token)).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status(&app, &cover_uri(), Some(&token)).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn audio_only_token() {
+    let keys = common::keys();
+    let app = app(keys.clone());
+    let token = sign(&keys, Some("audio")).await;
+
+    assert_eq!(
+        status(&app, &audio_uri(), Some(&token)).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(&app, &cover_uri(), Some(&token)).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn default_scope_token() {
+    let keys = common::keys();
+    let app = app(keys.clone());
+    let token = sign(&keys, None).await;
+
+    assert_eq!(
+        status(&app, &audio_uri(), Some(&token)).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(&app, &cover_uri(), Some(&token)).await,
+        StatusCode::OK
+    );
+    // covers stay public
+    assert_eq!(status(&app, &cover_uri(), None).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn scoped_token_can_not_share() {
+    let keys = common::keys();
+    let sign = |payload: serde_json::Value| {
+        admin::sign(
+            IsAdmin(true),
+            Extension(keys.clone()),
+            Json(serde_json::from_value(payload).unwrap()),
+        )
+    };
+
+    // a cover-only token would be able to sign share tokens of audios with the share secret
+    for scope in ["cover", "audio"] {
+        let response =
+            sign(serde_json::json!({ "user_id": "test", "share": true, "scope": scope })).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    let response = sign(serde_json::json!({ "user_id": "test", "share": true })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
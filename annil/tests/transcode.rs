@@ -0,0 +1,96 @@
+mod common;
+
+use annil::route::user;
+use annil::state::AnnilState;
+use annil::transcode::{Codec, TranscodeConfig};
+use axum::body::Body;
+use axum::http::header::{ACCEPT_RANGES, CONTENT_TYPE, ETAG};
+use axum::http::{Method, Request, StatusCode};
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use common::{StubProvider, ALBUM_ID};
+use tower::ServiceExt;
+
+const DURATION: u64 = 10_000;
+
+async fn transcode_app(transcode: TranscodeConfig) -> (Router, String) {
+    let keys = common::keys();
+    let token = common::sign(&keys, serde_json::json!({ "user_id": "test" })).await;
+    let routes = Router::new().route(
+        "/:album_id/:disc_id/:track_id",
+        get(user::audio::<StubProvider>).head(user::audio_head::<StubProvider>),
+    );
+    let state = AnnilState {
+        transcode,
+        ..common::state()
+    };
+    let provider = StubProvider {
+        duration: DURATION,
+        ..Default::default()
+    };
+    (common::app(routes, state, provider, keys), token)
+}
+
+async fn head(app: &Router, token: &str, query: &str) -> Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::HEAD)
+                .uri(format!("/{ALBUM_ID}/1/1?{query}"))
+                .header("Authorization", token)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn transcode_codec_not_enabled() {
+    let (app, token) = transcode_app(TranscodeConfig::default()).await;
+    for method in [Method::GET, Method::HEAD] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(format!("/{ALBUM_ID}/1/1?transcode=opus"))
+                    .header("Authorization", &token)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    let (app, token) = transcode_app(TranscodeConfig::new(vec![Codec::Opus])).await;
+    let response = head(&app, &token, "transcode=aac").await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let response = head(&app, &token, "transcode=vorbis").await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[cfg(feature = "transcode")]
+#[tokio::test]
+async fn transcode_headers() {
+    let (app, token) = transcode_app(TranscodeConfig::new(vec![Codec::Opus, Codec::Mp3])).await;
+
+    let response = head(&app, &token, "transcode=opus&bitrate=128k").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "audio/ogg");
+    // size of the output is unknown, so ranges are not served
+    assert!(!response.headers().contains_key(ACCEPT_RANGES));
+    let estimated = (DURATION * 128 / 8).to_string();
+    assert_eq!(response.headers()["X-Estimated-Size"], estimated.as_str());
+    let opus_etag = response.headers()[ETAG].clone();
+
+    let response = head(&app, &token, "transcode=mp3&bitrate=128k").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "audio/mpeg");
+    assert_ne!(response.headers()[ETAG], opus_etag);
+
+    let response = head(&app, &token, "transcode=opus&bitrate=fast").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,82 @@
+#![cfg(unix)]
+
+use annil::listen::ListenAddr;
+use axum::routing::get;
+use axum::Router;
+use std::future::pending;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::sync::oneshot;
+
+fn socket_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("annil-{}-{name}.sock", std::process::id()))
+}
+
+async fn get_healthz(path: &Path) -> String {
+    let mut stream = UnixStream::connect(path).await.unwrap();
+    stream
+        .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+async fn wait_for(path: &Path) {
+    for _ in 0..50 {
+        if UnixStream::connect(path).await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("server did not listen on {}", path.display());
+}
+
+#[tokio::test]
+async fn serve_unix_socket() {
+    let path = socket_path("serve");
+    // stale socket left by a previous run
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+    let app = Router::new().route("/healthz", get(|| async { "ok" }));
+    let listen: ListenAddr = format!("unix:{}", path.display()).parse().unwrap();
+    let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(listen.serve(app, async move {
+        let _ = shutdown_rx.await;
+    }));
+    wait_for(&path).await;
+
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o660);
+
+    let response = get_healthz(&path).await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.ends_with("ok"), "{response}");
+
+    // socket in use is not removed
+    let listen: ListenAddr = format!("unix:{}", path.display()).parse().unwrap();
+    assert!(listen.serve(Router::new(), pending()).await.is_err());
+    assert!(get_healthz(&path).await.starts_with("HTTP/1.1 200"));
+
+    // socket is removed after shutdown
+    shutdown.send(()).unwrap();
+    server.await.unwrap().unwrap();
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn unix_socket_path_not_socket() {
+    let path = socket_path("file");
+    std::fs::write(&path, b"").unwrap();
+
+    let listen: ListenAddr = format!("unix:{}", path.display()).parse().unwrap();
+    assert!(listen.serve(Router::new(), pending()).await.is_err());
+    // regular files are kept
+    assert!(path.exists());
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use anni_provider::Capabilities;
+use annil::route::user;
+use axum::body::Body;
+use axum::http::header::{ACCEPT_RANGES, CONTENT_LENGTH, RANGE};
+use axum::http::{Method, Request, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use common::{StubProvider, ALBUM_ID};
+use tower::ServiceExt;
+
+const AUDIO: &[u8] = b"fLaC streamed audio";
+
+async fn app() -> (Router, String) {
+    let keys = common::keys();
+    let token = common::sign(&keys, serde_json::json!({ "user_id": "test" })).await;
+    let routes = Router::new().route(
+        "/:album_id/:disc_id/:track_id",
+        get(user::audio::<StubProvider>).head(user::audio_head::<StubProvider>),
+    );
+    // audio is streamed without knowing its size
+    let provider = StubProvider {
+        audio: AUDIO.to_vec(),
+        sized: false,
+        capabilities: Capabilities {
+            supports_range: true,
+            ..Capabilities::CONSERVATIVE
+        },
+        ..Default::default()
+    };
+    (common::app(routes, common::state(), provider, keys), token)
+}
+
+#[tokio::test]
+async fn unknown_size_is_chunked() {
+    let (app, token) = app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/{ALBUM_ID}/1/1?quality=lossless"))
+                .header("Authorization", &token)
+                // range can not be served without size
+                .header(RANGE, "bytes=0-3")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(CONTENT_LENGTH));
+
+    assert_eq!(common::body(response.into_body()).await, AUDIO);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::HEAD)
+                .uri(format!("/{ALBUM_ID}/1/1?quality=lossless"))
+                .header("Authorization", &token)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // `Content-Length` of the empty body is filled by axum, so only check that ranges are not offered
+    assert_eq!(response.headers()["X-Origin-Size"], "0");
+    assert!(!response.headers().contains_key(ACCEPT_RANGES));
+}
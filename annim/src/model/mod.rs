@@ -37,7 +37,7 @@ impl<'album> AlbumInfo<'album> {
     }
 
     async fn artists(&self) -> Option<&HashMap<String, String>> {
-        self.0.artists.as_ref()
+        self.0.detailed_artists()
     }
 
     #[graphql(name = "date")]
@@ -73,7 +73,7 @@ impl<'album> DiscInfo<'album> {
     }
 
     async fn artists(&self) -> Option<&HashMap<String, String>> {
-        self.0.artists()
+        self.0.detailed_artists()
     }
 
     async fn tags(&self) -> Vec<TagInfo> {
@@ -105,7 +105,7 @@ impl TrackInfo<'_, '_> {
     }
 
     async fn artists(&self) -> Option<&HashMap<String, String>> {
-        self.0.artists()
+        self.0.detailed_artists()
     }
 
     async fn tags(&self) -> Vec<TagInfo> {